@@ -0,0 +1,260 @@
+// Benchmarks of the sequence loop and the writer of the sequence file.
+//
+// Criterion is not used to keep the dependency tree small; this harness samples each case until a
+// time budget is spent and reports median, mean and standard deviation per iteration.
+//
+//     cargo bench                                  run all cases
+//     cargo bench -- medium                        run cases whose name contains "medium"
+//     cargo bench -- --save-baseline main          store the medians in target/collatz-bench/main.txt
+//     cargo bench -- --baseline main               compare against a stored baseline, exit code 1 on
+//                                                  regressions above --threshold percent (default 10)
+//
+// Set COLLATZ_BENCH_HUGE=1 to include the huge seed, which takes seconds per iteration.
+use collatz_bigint_file::digits::DigitWriter;
+use collatz_bigint_file::sequence::{collatz, Generated, Limits};
+use num_bigint::BigInt;
+use num_traits::One;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::hint::black_box;
+use std::io::{self, BufWriter, Write};
+use std::path::PathBuf;
+use std::process;
+use std::time::{Duration, Instant};
+
+// Time spent sampling each case (after one warm-up iteration)
+const BUDGET: Duration = Duration::from_secs(3);
+const MAX_SAMPLES: usize = 100;
+
+// Writer that discards its input but keeps the compiler from optimizing the writes away
+struct Sink;
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        black_box(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct Case {
+    name: String,
+    run: Box<dyn Fn()>,
+}
+
+fn seeds() -> Vec<(&'static str, BigInt)> {
+    let mersenne = |exponent: u32| (BigInt::one() << exponent) - BigInt::one();
+    let mut seeds = vec![
+        ("small", BigInt::from(27)),
+        ("medium", mersenne(199)),
+        ("large", mersenne(5000)),
+    ];
+    if env::var_os("COLLATZ_BENCH_HUGE").is_some() {
+        seeds.push(("huge", mersenne(100_000)));
+    }
+    seeds
+}
+
+fn cases() -> Vec<Case> {
+    let mut cases = Vec::new();
+    for (size, seed) in seeds() {
+        // the arithmetic and digit formatting without any I/O
+        let n = seed.clone();
+        cases.push(Case {
+            name: format!("collatz/{}/io-sink", size),
+            run: Box::new(move || {
+                black_box(collatz(
+                    n.clone(),
+                    &mut io::sink(),
+                    Generated::default(),
+                    &Limits::default(),
+                ));
+            }),
+        });
+        // through the default and a small write buffer
+        for (label, capacity) in [("buffer-1M", 1 << 20), ("buffer-8K", 8 << 10)] {
+            let n = seed.clone();
+            cases.push(Case {
+                name: format!("collatz/{}/{}", size, label),
+                run: Box::new(move || {
+                    let mut output = BufWriter::with_capacity(capacity, Sink);
+                    black_box(collatz(
+                        n.clone(),
+                        &mut output,
+                        Generated::default(),
+                        &Limits::default(),
+                    ));
+                    output.flush().unwrap();
+                }),
+            });
+        }
+        // digit formatting alone: DigitWriter against `writeln!`
+        let n = seed.clone();
+        let digits = RefCell::new(DigitWriter::default());
+        cases.push(Case {
+            name: format!("format/{}/digit-writer", size),
+            run: Box::new(move || {
+                let mut output = BufWriter::new(Sink);
+                digits.borrow_mut().write_line(&n, &mut output).unwrap();
+            }),
+        });
+        let n = seed.clone();
+        cases.push(Case {
+            name: format!("format/{}/writeln", size),
+            run: Box::new(move || {
+                let mut output = BufWriter::new(Sink);
+                writeln!(output, "{}", n).unwrap();
+            }),
+        });
+    }
+    cases
+}
+
+// Per-iteration statistics of a case in seconds
+struct Measurement {
+    median: f64,
+    mean: f64,
+    stddev: f64,
+    samples: usize,
+}
+
+fn measure(case: &Case) -> Measurement {
+    (case.run)();
+    // batch fast cases so each sample is long enough to time reliably
+    let start = Instant::now();
+    (case.run)();
+    let single = start.elapsed().max(Duration::from_nanos(1));
+    let batch = (Duration::from_millis(1).as_nanos() / single.as_nanos()).max(1) as u32;
+
+    let mut samples = Vec::new();
+    let started = Instant::now();
+    while samples.len() < MAX_SAMPLES && (started.elapsed() < BUDGET || samples.len() < 3) {
+        let start = Instant::now();
+        for _ in 0..batch {
+            (case.run)();
+        }
+        samples.push(start.elapsed().as_secs_f64() / f64::from(batch));
+    }
+    samples.sort_by(f64::total_cmp);
+    let count = samples.len() as f64;
+    let mean = samples.iter().sum::<f64>() / count;
+    let variance = samples.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / (count - 1.0);
+    Measurement {
+        median: samples[samples.len() / 2],
+        mean,
+        stddev: variance.sqrt(),
+        samples: samples.len(),
+    }
+}
+
+fn format_time(seconds: f64) -> String {
+    match seconds {
+        s if s < 1e-6 => format!("{:.1} ns", s * 1e9),
+        s if s < 1e-3 => format!("{:.2} µs", s * 1e6),
+        s if s < 1.0 => format!("{:.2} ms", s * 1e3),
+        s => format!("{:.3} s", s),
+    }
+}
+
+fn baseline_path(name: &str) -> PathBuf {
+    let target =
+        env::var_os("CARGO_TARGET_DIR").map_or_else(|| PathBuf::from("target"), PathBuf::from);
+    target.join("collatz-bench").join(format!("{}.txt", name))
+}
+
+// Baselines are stored as "<case> <median seconds>" lines
+fn load_baseline(name: &str) -> BTreeMap<String, f64> {
+    let path = baseline_path(name);
+    let content = fs::read_to_string(&path).unwrap_or_else(|err| {
+        eprintln!("Failed to read baseline {}: {}", path.display(), err);
+        process::exit(2);
+    });
+    content
+        .lines()
+        .filter_map(|line| {
+            let (case, median) = line.rsplit_once(' ')?;
+            Some((case.to_string(), median.parse().ok()?))
+        })
+        .collect()
+}
+
+fn main() {
+    let mut filter = None;
+    let mut save_baseline = None;
+    let mut baseline = None;
+    let mut threshold = 10.0;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            // passed by `cargo bench`
+            "--bench" => {}
+            "--save-baseline" => save_baseline = args.next(),
+            "--baseline" => baseline = args.next(),
+            "--threshold" => {
+                threshold = args
+                    .next()
+                    .and_then(|value| value.parse().ok())
+                    .expect("--threshold expects a percentage")
+            }
+            _ => filter = Some(arg),
+        }
+    }
+    let baseline = baseline.map(|name| load_baseline(&name));
+
+    let mut medians = Vec::new();
+    let mut regressions = 0;
+    for case in cases() {
+        if filter
+            .as_ref()
+            .is_some_and(|filter| !case.name.contains(filter.as_str()))
+        {
+            continue;
+        }
+        let measurement = measure(&case);
+        let mut line = format!(
+            "{:<36} median {:>11}   mean {:>11} ± {:>11}   ({} samples)",
+            case.name,
+            format_time(measurement.median),
+            format_time(measurement.mean),
+            format_time(measurement.stddev),
+            measurement.samples
+        );
+        if let Some(previous) = baseline
+            .as_ref()
+            .and_then(|baseline| baseline.get(&case.name))
+        {
+            let change = (measurement.median / previous - 1.0) * 100.0;
+            line.push_str(&format!("   {:+.1}%", change));
+            if change > threshold {
+                line.push_str(" REGRESSION");
+                regressions += 1;
+            }
+        }
+        println!("{}", line);
+        medians.push((case.name, measurement.median));
+    }
+
+    if let Some(name) = save_baseline {
+        let path = baseline_path(&name);
+        let content: String = medians
+            .iter()
+            .map(|(case, median)| format!("{} {}\n", case, median))
+            .collect();
+        fs::create_dir_all(path.parent().unwrap())
+            .and_then(|_| fs::write(&path, content))
+            .unwrap_or_else(|err| panic!("Failed to write {}: {}", path.display(), err));
+        println!("Saved baseline {}", path.display());
+    }
+    if regressions > 0 {
+        eprintln!(
+            "{} cases regressed by more than {}%",
+            regressions, threshold
+        );
+        process::exit(1);
+    }
+}
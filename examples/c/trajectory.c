@@ -0,0 +1,36 @@
+/*
+ * Prints the trajectory and statistics of a seed through the C API.
+ *
+ *   cargo build --release
+ *   cc -Iinclude examples/c/trajectory.c -Ltarget/release -lcollatz_bigint_file -o trajectory
+ *   LD_LIBRARY_PATH=target/release ./trajectory 27
+ */
+
+#include <stdio.h>
+
+#include "collatz_bigint_file.h"
+
+static int print_value(void *user_data, uint64_t step, const char *digits, size_t len) {
+    (void)user_data;
+    printf("%llu: %.*s\n", (unsigned long long)step, (int)len, digits);
+    return 0;
+}
+
+int main(int argc, char **argv) {
+    const char *seed = argc > 1 ? argv[1] : "27";
+    CollatzLimits limits = {.max_steps = 100000, .timeout_ms = 0, .max_bits = 0};
+    CollatzCallbacks callbacks = {.on_value = print_value, .user_data = NULL};
+    CollatzStats stats;
+
+    int status = collatz_compute(seed, &limits, &callbacks, &stats);
+    if (status != COLLATZ_OK) {
+        fprintf(stderr, "collatz_compute failed: %d\n", status);
+        return 1;
+    }
+    printf("stopping time %llu, glide %lld, max value %s at step %llu, odd %llu%s\n",
+           (unsigned long long)stats.stopping_time, (long long)stats.glide, stats.max_value,
+           (unsigned long long)stats.max_index, (unsigned long long)stats.odd,
+           stats.truncated != COLLATZ_TRUNCATED_NONE ? " (truncated)" : "");
+    collatz_stats_free(&stats);
+    return 0;
+}
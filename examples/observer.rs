@@ -0,0 +1,120 @@
+// Embeds the sequence loop with `stats::observe_trajectory`: a stop condition of its own (the
+// first value above a threshold), a sink of its own (the odd values, written to a file or
+// stdout) and a live display (a progress line on stderr). The results are checked against a
+// plain loop and the statistics of the run; the example panics at the first difference.
+//
+// Run with `cargo run --release --example observer -- [SEED] [ODD_VALUES_FILE]`, e.g.
+// `cargo run --release --example observer -- 2^1000-1 odd.txt`.
+use collatz_bigint_file::input;
+use collatz_bigint_file::sequence::{Limits, Map};
+use collatz_bigint_file::stats::{self, Parity};
+use num_bigint::BigInt;
+use num_traits::One;
+use std::env;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::ControlFlow;
+use std::time::{Duration, Instant};
+
+// Pause between two updates of the progress line
+const REFRESH: Duration = Duration::from_millis(100);
+
+// The next value of the standard map
+fn step(n: &BigInt) -> BigInt {
+    if n.bit(0) {
+        BigInt::from(3) * n + 1
+    } else {
+        n >> 1
+    }
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let seed = args.next().unwrap_or_else(|| "2^1000-1".to_string());
+    let start = input::parse_seed(&seed, u64::MAX).expect("a positive integer or 2^e-s");
+    let limits = Limits::default();
+
+    // stop condition: the first value above 4 times the seed, if the trajectory gets there
+    let threshold = &start << 2;
+    let row = stats::observe_trajectory(seed.clone(), &start, &limits, Map::Standard, |_, n, _| {
+        if *n > threshold {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    });
+    let (mut n, mut steps) = (start.clone(), 0);
+    while !n.is_one() && n <= threshold {
+        n = step(&n);
+        steps += 1;
+    }
+    assert_eq!(row.stopping_time, steps, "steps to the threshold");
+    assert_eq!(
+        row.truncated.is_some(),
+        n > threshold,
+        "stopped at the threshold"
+    );
+    println!(
+        "{}: {} steps until {}",
+        seed,
+        steps,
+        if n > threshold {
+            "the first value above 4 times the seed"
+        } else {
+            "1, without rising above 4 times the seed"
+        }
+    );
+
+    // sink and live display: the odd values go to the file, the progress to stderr
+    let output: Box<dyn Write> = match args.next() {
+        Some(path) => Box::new(File::create(&path).expect("the file can be created")),
+        None => Box::new(io::sink()),
+    };
+    let mut output = BufWriter::new(output);
+    let mut odd_steps = 0;
+    let mut previous = start.clone();
+    let mut refreshed = Instant::now();
+    let mut shown = false;
+    let row = stats::observe_trajectory(
+        seed.clone(),
+        &start,
+        &limits,
+        Map::Standard,
+        |step_number, n, parity| {
+            assert_eq!(*n, step(&previous), "value of step {}", step_number);
+            assert_eq!(
+                parity == Parity::Odd,
+                previous.bit(0),
+                "parity of step {}",
+                step_number
+            );
+            previous = n.clone();
+            if parity == Parity::Odd {
+                odd_steps += 1;
+            }
+            if n.bit(0) && writeln!(output, "{}", n).is_err() {
+                return ControlFlow::Break(());
+            }
+            if refreshed.elapsed() >= REFRESH {
+                eprint!("\rstep {}: {} bits", step_number, n.bits());
+                refreshed = Instant::now();
+                shown = true;
+            }
+            ControlFlow::Continue(())
+        },
+    );
+    output.flush().expect("the odd values can be written");
+    if shown {
+        eprint!("\r\x1b[K");
+    }
+    assert!(row.truncated.is_none(), "the trajectory reaches 1");
+    assert_eq!(
+        odd_steps,
+        row.valuations.counts().iter().sum::<u64>() as usize,
+        "3n+1 steps"
+    );
+    println!(
+        "{}: total stopping time {}, {} steps with 3n+1, {} odd values after the seed",
+        seed, row.stopping_time, odd_steps, row.odd
+    );
+}
@@ -0,0 +1,121 @@
+// Compares the raw digit formatting of the sequence file against `writeln!(.. "{}", n)`, and
+// the digits updated step by step of --fast-io against both. The --fast-io lines are first
+// checked against those of `DigitWriter`, value by value; the example panics at the first
+// difference.
+//
+// Run with `cargo run --release --example write_throughput -- [BITS] [BUFFER_SIZE]`, e.g.
+// `cargo run --release --example write_throughput -- 20000 8388608`.
+use collatz_bigint_file::digits::{DecimalValue, DigitWriter};
+use num_bigint::BigInt;
+use num_traits::One;
+use std::env;
+use std::io::{self, BufWriter, Write};
+use std::time::{Duration, Instant};
+
+// Writer that only counts bytes, so the benchmark measures formatting and not the disk
+#[derive(Default)]
+struct Sink {
+    bytes: u64,
+}
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.bytes += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// Run the sequence of 2^bits-1 and write every value with `write_value`
+fn run(
+    bits: u32,
+    buffer_size: usize,
+    mut write_value: impl FnMut(&BigInt, &mut BufWriter<Sink>) -> io::Result<()>,
+) -> (Duration, u64) {
+    let mut n = (BigInt::one() << bits) - BigInt::one();
+    let mut output = BufWriter::with_capacity(buffer_size, Sink::default());
+    let start = Instant::now();
+    while !n.is_one() {
+        if n.bit(0) {
+            n = BigInt::from(3) * n + BigInt::one();
+        } else {
+            n >>= 1;
+        }
+        write_value(&n, &mut output).expect("Failed to write");
+    }
+    output.flush().expect("Failed to write");
+    (start.elapsed(), output.get_ref().bytes)
+}
+
+// Check that the digits updated by --fast-io match a fresh conversion at every step
+fn check_fast_io(bits: u32) {
+    let mut n = (BigInt::one() << bits) - BigInt::one();
+    let mut decimal = DecimalValue::new(&n);
+    let mut digits = DigitWriter::default();
+    let (mut expected, mut actual) = (Vec::new(), Vec::new());
+    let mut step = 0;
+    while !n.is_one() {
+        if n.bit(0) {
+            n = BigInt::from(3) * n + BigInt::one();
+        } else {
+            n >>= 1;
+        }
+        decimal.step();
+        step += 1;
+        expected.clear();
+        actual.clear();
+        digits
+            .write_line(&n, &mut expected)
+            .expect("Failed to write");
+        decimal.write_line(&mut actual).expect("Failed to write");
+        assert!(
+            expected == actual,
+            "--fast-io differs at step {} of 2^{}-1",
+            step,
+            bits
+        );
+    }
+    println!("--fast-io: all {} values of 2^{}-1 match", step, bits);
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let bits = args.next().map_or(5000, |bits| bits.parse().expect("BITS"));
+    let buffer_size = args
+        .next()
+        .map_or(1 << 20, |size| size.parse().expect("BUFFER_SIZE"));
+
+    let (display, bytes) = run(bits, buffer_size, |n, output| writeln!(output, "{}", n));
+    let mut digits = DigitWriter::default();
+    let (raw, _) = run(bits, buffer_size, |n, output| {
+        digits.write_line(n, output).map(|_| ())
+    });
+
+    check_fast_io(bits);
+    let mut decimal = DecimalValue::new(&((BigInt::one() << bits) - BigInt::one()));
+    let (fast_io, _) = run(bits, buffer_size, |_, output| {
+        decimal.step();
+        decimal.write_line(output).map(|_| ())
+    });
+
+    let mb = bytes as f64 / (1 << 20) as f64;
+    println!("2^{}-1: {:.1} MiB of output", bits, mb);
+    println!(
+        "writeln!:   {:>8.2?} ({:.1} MiB/s)",
+        display,
+        mb / display.as_secs_f64()
+    );
+    println!(
+        "DigitWriter: {:>8.2?} ({:.1} MiB/s)",
+        raw,
+        mb / raw.as_secs_f64()
+    );
+    println!(
+        "--fast-io:  {:>8.2?} ({:.1} MiB/s)",
+        fast_io,
+        mb / fast_io.as_secs_f64()
+    );
+}
@@ -0,0 +1,23 @@
+//! Any text given as a start value: the parser may reject it, but must not panic, and what it
+//! accepts is a positive integer within the size limit.
+
+#![no_main]
+
+use collatz_bigint_file::input::{parse_seed, SeedError};
+use libfuzzer_sys::fuzz_target;
+use num_bigint::BigInt;
+use num_traits::Zero;
+
+// small enough that accepted expressions are computed quickly
+const MAX_BITS: u64 = 4096;
+
+fuzz_target!(|input: &str| {
+    match parse_seed(input, MAX_BITS) {
+        Ok(value) => {
+            assert!(value > BigInt::zero(), "{:?} parsed to {}", input, value);
+            assert!(value.bits() <= MAX_BITS, "{:?} exceeds the limit", input);
+        }
+        Err(SeedError::TooLarge { max_bits, .. }) => assert_eq!(max_bits, MAX_BITS),
+        Err(SeedError::Invalid) => {}
+    }
+});
@@ -0,0 +1,44 @@
+//! Writes the trajectory of an arbitrary seed as the value lines of a sequence file and reads
+//! them back: every line must parse, follow from the one before by the Collatz map, end at 1
+//! (or at the step limit) and agree with the in-memory statistics.
+
+#![no_main]
+
+use collatz_bigint_file::sequence::{collatz, Generated, Limits, Map};
+use collatz_bigint_file::stats;
+use libfuzzer_sys::fuzz_target;
+use num_bigint::{BigInt, Sign};
+use num_traits::{One, Zero};
+
+fuzz_target!(|bytes: &[u8]| {
+    let seed = BigInt::from_bytes_le(Sign::Plus, bytes) + BigInt::one();
+    let limits = Limits {
+        max_steps: Some(100_000),
+        ..Limits::default()
+    };
+    let mut file = Vec::new();
+    let generated = collatz(seed.clone(), &mut file, Generated::default(), &limits);
+
+    let text = std::str::from_utf8(&file).expect("sequence files are ASCII");
+    let mut previous = seed.clone();
+    let mut steps = 0;
+    let mut max_value = seed.clone();
+    for line in text.lines() {
+        let value: BigInt = line.parse().expect("every line is a decimal value");
+        let expected = if (&previous % 2u32).is_zero() {
+            &previous >> 1
+        } else {
+            &previous * 3u32 + 1u32
+        };
+        assert_eq!(value, expected, "step {} of {}", steps + 1, seed);
+        max_value = max_value.max(value.clone());
+        previous = value;
+        steps += 1;
+    }
+    assert_eq!(steps, generated.steps);
+    assert!(previous.is_one() || generated.truncated.is_some());
+
+    let row = stats::trajectory(seed.to_string(), &seed, &limits, Map::Standard);
+    assert_eq!(row.stopping_time, steps);
+    assert_eq!(row.max_value, max_value);
+});
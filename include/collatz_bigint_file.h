@@ -0,0 +1,76 @@
+/*
+ * C API of collatz_bigint_file, implemented in src/ffi.rs.
+ *
+ * Build the library with `cargo build --release` and link against
+ * target/release/libcollatz_bigint_file.so (or .a, plus -lpthread -ldl -lm).
+ *
+ * Functions return COLLATZ_OK or a negative COLLATZ_ERROR_* code. Seeds are
+ * NUL-terminated decimal strings.
+ */
+
+#ifndef COLLATZ_BIGINT_FILE_H
+#define COLLATZ_BIGINT_FILE_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define COLLATZ_OK 0
+#define COLLATZ_ERROR_NULL -1
+#define COLLATZ_ERROR_INVALID_SEED -2
+
+#define COLLATZ_TRUNCATED_NONE 0
+#define COLLATZ_TRUNCATED_MAX_STEPS 1
+#define COLLATZ_TRUNCATED_TIMEOUT 2
+#define COLLATZ_TRUNCATED_MAX_BITS 3
+#define COLLATZ_TRUNCATED_CANCELLED 4
+
+/* Limits of a computation, 0 means no limit. */
+typedef struct CollatzLimits {
+    uint64_t max_steps;
+    uint64_t timeout_ms;
+    uint64_t max_bits;
+} CollatzLimits;
+
+/* Receives the values of a trajectory. */
+typedef struct CollatzCallbacks {
+    /* Called with each value after the seed as decimal digits (not
+     * NUL-terminated), valid only during the call. A non-zero return value
+     * stops the computation. */
+    int (*on_value)(void *user_data, uint64_t step, const char *digits, size_t len);
+    void *user_data;
+} CollatzCallbacks;
+
+/* Statistics of a trajectory, release with collatz_stats_free. */
+typedef struct CollatzStats {
+    uint64_t stopping_time;
+    /* steps until the trajectory first drops below the seed, -1 if it never does */
+    int64_t glide;
+    uint64_t max_index;
+    uint64_t odd;
+    /* decimal, NUL-terminated, owned by the library */
+    char *max_value;
+    /* one of the COLLATZ_TRUNCATED_* values */
+    int truncated;
+} CollatzStats;
+
+/* Computes the trajectory of seed with the 3n+1 map, passes every value to
+ * callbacks->on_value and stores the statistics in stats. limits, callbacks
+ * and stats may be NULL. */
+int collatz_compute(const char *seed, const CollatzLimits *limits,
+                    const CollatzCallbacks *callbacks, CollatzStats *stats);
+
+/* Computes only the statistics of the trajectory of seed. */
+int collatz_stats(const char *seed, const CollatzLimits *limits, CollatzStats *stats);
+
+/* Releases the memory owned by stats; the struct itself belongs to the caller. */
+void collatz_stats_free(CollatzStats *stats);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
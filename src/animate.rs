@@ -0,0 +1,91 @@
+use crate::format::NumberFormat;
+use collatz_bigint_file::sequence::{Limits, Map};
+use collatz_bigint_file::stats;
+use colored::{Color, Colorize};
+use num_bigint::BigInt;
+use std::io::{self, Write};
+use std::ops::ControlFlow;
+use std::thread;
+use std::time::Duration;
+
+/// Prints a trajectory one step at a time with the rule that produced each value and the
+/// running maximum, waiting `delay` between the steps, e.g. `1  27 → odd → 3·27+1 = 82`.
+///
+/// The trajectory is computed in memory, nothing is written to a file. The limits apply as for
+/// a regular run.
+pub fn run(
+    seed: &BigInt,
+    limits: &Limits,
+    delay: Duration,
+    number_format: &NumberFormat,
+    even: Color,
+    odd: Color,
+) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "{:>5}  {}", 0, number_format.format(seed).bold())?;
+    stdout.flush()?;
+
+    let mut previous = seed.clone();
+    let mut max = seed.clone();
+    let mut step = 0;
+    let mut result = Ok(());
+    let row = stats::visit_trajectory(String::new(), seed, limits, Map::Standard, |n| {
+        step += 1;
+        let from = number_format.format(&previous);
+        let rule = if previous.bit(0) {
+            format!(
+                "{} → {} → {} =",
+                from,
+                "odd".color(odd),
+                format!("3·{}+1", from).color(odd)
+            )
+        } else {
+            format!(
+                "{} → {} → {} =",
+                from,
+                "even".color(even),
+                format!("{}/2", from).color(even)
+            )
+        };
+        // a new maximum is marked with an arrow
+        let running_max = if *n > max {
+            max = n.clone();
+            format!("max {} ↑", number_format.format(&max)).bold()
+        } else {
+            format!("max {}", number_format.format(&max)).normal()
+        };
+        let line = writeln!(
+            stdout,
+            "{:>5}  {} {}   {}",
+            step,
+            rule,
+            number_format.format(n).bold(),
+            running_max
+        )
+        .and_then(|()| stdout.flush());
+        if let Err(err) = line {
+            result = Err(err);
+            return ControlFlow::Break(());
+        }
+        previous = n.clone();
+        thread::sleep(delay);
+        ControlFlow::Continue(())
+    });
+    result?;
+
+    writeln!(stdout)?;
+    match row.truncated {
+        Some(truncation) => writeln!(
+            stdout,
+            "stopped after {} steps: {}",
+            row.stopping_time, truncation
+        ),
+        None => writeln!(
+            stdout,
+            "reached 1 after {} steps, max {} at step {}",
+            row.stopping_time,
+            number_format.format(&row.max_value),
+            row.max_index
+        ),
+    }
+}
@@ -0,0 +1,113 @@
+use crate::output;
+use crate::work;
+use collatz_bigint_file::sequence::{Limits, Map};
+use collatz_bigint_file::stats;
+use collatz_bigint_file::warn;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::ops::ControlFlow;
+use std::path::Path;
+
+// Seeds drawn at most, the SVG grows by a path per seed
+const MAX_SEEDS: usize = 100_000;
+// Margin around the drawing, in segment lengths
+const MARGIN: f64 = 4.0;
+
+/// Draws the trajectories of the seeds as the "Collatz coral" and writes it as SVG.
+///
+/// Every trajectory of the shortcut map (3n+1)/2 is walked backwards, from 1 up to its seed: a
+/// path starts at the origin heading up and turns by `even_angle` degrees (counterclockwise) at
+/// every even value and by `odd_angle` at every odd one, with segments of equal length. The
+/// trajectories share their tails, so the paths grow from a common stem into branches.
+/// Trajectories stopped by a limit are left out.
+pub fn run(
+    args: &[String],
+    path: &Path,
+    even_angle: f64,
+    odd_angle: f64,
+    limits: &Limits,
+    force: bool,
+) -> Result<(), String> {
+    let seeds = work::expand_seeds(args, limits.max_bits.unwrap_or(u64::MAX), MAX_SEEDS)?;
+    let (even_turn, odd_turn) = (even_angle.to_radians(), odd_angle.to_radians());
+
+    let mut paths = Vec::with_capacity(seeds.len());
+    let (mut min_x, mut min_y, mut max_x, mut max_y) = (0.0f64, 0.0f64, 0.0f64, 0.0f64);
+    let mut truncated = 0;
+    for (_, seed) in &seeds {
+        // parities from the seed down to 1
+        let mut parities = vec![!seed.bit(0)];
+        let row = stats::visit_trajectory(String::new(), seed, limits, Map::Shortcut, |n| {
+            parities.push(!n.bit(0));
+            ControlFlow::Continue(())
+        });
+        if row.truncated.is_some() {
+            truncated += 1;
+            continue;
+        }
+        let (mut x, mut y, mut heading) = (0.0f64, 0.0f64, std::f64::consts::FRAC_PI_2);
+        let mut data = String::from("M0 0");
+        // walk up from the value after 1
+        for &is_even in parities.iter().rev().skip(1) {
+            heading += if is_even { even_turn } else { odd_turn };
+            x += heading.cos();
+            // SVG coordinates grow downwards
+            y -= heading.sin();
+            min_x = min_x.min(x);
+            max_x = max_x.max(x);
+            min_y = min_y.min(y);
+            max_y = max_y.max(y);
+            write!(data, "L{:.2} {:.2}", x, y).expect("writing to a string does not fail");
+        }
+        paths.push(data);
+    }
+    if truncated > 0 {
+        warn!("Left out {} trajectories stopped by a limit", truncated);
+    }
+
+    let mut svg = String::new();
+    let (width, height) = (max_x - min_x + 2.0 * MARGIN, max_y - min_y + 2.0 * MARGIN);
+    writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{:.2} {:.2} {:.2} {:.2}\" width=\"1000\" height=\"{:.0}\">",
+        min_x - MARGIN,
+        min_y - MARGIN,
+        width,
+        height,
+        1000.0 * height / width
+    )
+    .expect("writing to a string does not fail");
+    writeln!(
+        svg,
+        "<rect x=\"{:.2}\" y=\"{:.2}\" width=\"{:.2}\" height=\"{:.2}\" fill=\"#fdfaf3\"/>",
+        min_x - MARGIN,
+        min_y - MARGIN,
+        width,
+        height
+    )
+    .expect("writing to a string does not fail");
+    writeln!(
+        svg,
+        "<g fill=\"none\" stroke-width=\"0.6\" stroke-opacity=\"0.35\" stroke-linecap=\"round\">"
+    )
+    .expect("writing to a string does not fail");
+    let count = paths.len().max(1) as f64;
+    for (i, data) in paths.iter().enumerate() {
+        // hue from teal to coral across the seeds
+        let hue = 170.0 + 190.0 * i as f64 / count;
+        writeln!(
+            svg,
+            "<path d=\"{}\" stroke=\"hsl({:.0},70%,40%)\" vector-effect=\"non-scaling-stroke\"/>",
+            data,
+            hue % 360.0
+        )
+        .expect("writing to a string does not fail");
+    }
+    svg.push_str("</g>\n</svg>\n");
+
+    output::create(path, force)?
+        .write_all(svg.as_bytes())
+        .map_err(|err| format!("Failed to write {}: {}", path.display(), err))?;
+    println!("Drew {} trajectories into {}", paths.len(), path.display());
+    Ok(())
+}
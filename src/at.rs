@@ -0,0 +1,49 @@
+use crate::format::NumberFormat;
+use collatz_bigint_file::sequence::{advance, Limits};
+use std::time::Instant;
+
+/// Prints the values of the trajectory of `seed` at the given steps, without writing or keeping
+/// the values in between.
+///
+/// The steps are visited in increasing order, each one continuing from the last, and runs of
+/// halvings are skipped with a shift (see `sequence::advance`). Step 0 is the seed itself. Steps
+/// after the trajectory reached 1 are reported as beyond its end.
+pub fn run(
+    seed: &str,
+    steps: &[u64],
+    limits: &Limits,
+    number_format: &NumberFormat,
+) -> Result<(), String> {
+    let mut n = crate::parse_input(seed.to_string(), limits.max_bits.unwrap_or(u64::MAX))?;
+    let mut steps: Vec<usize> = steps.iter().map(|&step| step as usize).collect();
+    steps.sort_unstable();
+    steps.dedup();
+
+    let deadline = limits.timeout.map(|timeout| Instant::now() + timeout);
+    let mut position = 0;
+    // step at which the trajectory reached 1, once it did
+    let mut end = None;
+    for step in steps {
+        if end.is_none() {
+            let (applied, truncation) = advance(&mut n, step - position, limits, deadline);
+            position += applied;
+            if let Some(truncation) = truncation {
+                return Err(format!(
+                    "Stopped at step {} before step {}: {}",
+                    position, step, truncation
+                ));
+            }
+            if position < step {
+                end = Some(position);
+            }
+        }
+        match end {
+            Some(end) => println!(
+                "step {}: beyond the end, the trajectory reaches 1 at step {}",
+                step, end
+            ),
+            None => println!("step {}: {}", step, number_format.format(&n)),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,223 @@
+use crate::drift;
+use crate::output;
+use crate::plan;
+use crate::sink::Sink;
+use crate::Settings;
+use collatz_bigint_file::sequence::{expected_steps, Map};
+use num_bigint::BigInt;
+
+// Seeds from this size convert faster with --fast-io: converting a value to decimal takes time
+// quadratic in its digits, updating them linear
+pub const FAST_IO_MIN_BITS: u64 = 1024;
+
+// Seeds from this size, about 315,000 decimal digits, are not converted to decimal at all
+pub const DIGITS_ONLY_MIN_BITS: u64 = 1 << 20;
+
+// Estimated size of the sequence file up to which every value is written, without --max-disk
+pub const OUTPUT_BUDGET: u64 = 10 << 30;
+
+// Value lines the write buffer holds at the size of the seed, and its bounds
+const LINES_PER_BUFFER: usize = 64;
+const MIN_BUFFER_SIZE: usize = 64 << 10;
+const MAX_BUFFER_SIZE: usize = 64 << 20;
+
+/// Which choices of `--auto` the command line or the config file made already; those are kept.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Given {
+    /// --fast-io or --digits-only
+    pub backend: bool,
+    /// --radix
+    pub radix: bool,
+    /// --odd-only, --sample-every, --filter or --annotate
+    pub format: bool,
+    /// --buffer-size
+    pub buffer_size: bool,
+    /// an option --digits-only excludes, like --analyze, --share or a format
+    pub decimal: bool,
+}
+
+/// One setting of the plan with the reason it was chosen, for `--explain-plan`.
+pub struct Choice {
+    pub setting: &'static str,
+    pub value: String,
+    pub reason: String,
+}
+
+/// Picks the backend, the output format and the buffer size of the run of `seed` from its size
+/// and the expected length of its trajectory, leaving what `given` says was set, and returns
+/// the choices.
+///
+/// The backend converts every value to decimal below [`FAST_IO_MIN_BITS`], updates the digits
+/// with `--fast-io` from there and writes hexadecimal with `--digits-only` from
+/// [`DIGITS_ONLY_MIN_BITS`] if that file fits [`OUTPUT_BUDGET`] or goes to no file at all. A
+/// decimal file estimated above the budget holds only the odd terms or a sample, as with
+/// `--max-disk`, which decides itself if given. The buffer holds [`LINES_PER_BUFFER`] values of
+/// the seed's size.
+pub fn plan(settings: &mut Settings, seed: &BigInt, given: &Given) -> Result<Vec<Choice>, String> {
+    let steps = expected_steps(drift::log2(seed), Map::Standard);
+    Ok(vec![
+        Choice {
+            setting: "seed",
+            value: format!("{} bits", seed.bits()),
+            reason: format!("~{:.0} steps expected by the drift model", steps),
+        },
+        backend(settings, seed, given),
+        format(settings, seed, given)?,
+        buffer_size(settings, seed, given),
+    ])
+}
+
+const GIVEN: &str = "set by the command line or config file";
+
+fn backend(settings: &mut Settings, seed: &BigInt, given: &Given) -> Choice {
+    let bits = seed.bits();
+    let choice = |settings: &Settings, reason: String| Choice {
+        setting: "backend",
+        value: backend_name(settings).to_string(),
+        reason,
+    };
+    if given.backend {
+        return choice(settings, GIVEN.to_string());
+    }
+    if bits < FAST_IO_MIN_BITS {
+        return choice(
+            settings,
+            format!(
+                "below {} bits converting every value to decimal is cheap",
+                FAST_IO_MIN_BITS
+            ),
+        );
+    }
+    if bits < DIGITS_ONLY_MIN_BITS {
+        settings.fast_io = true;
+        return choice(
+            settings,
+            format!(
+                "from {} bits updating the digits is faster than converting every value",
+                FAST_IO_MIN_BITS
+            ),
+        );
+    }
+
+    let budget = settings.max_disk.unwrap_or(OUTPUT_BUDGET);
+    let reason = if given.decimal {
+        "an option needs decimal values, so no --digits-only".to_string()
+    } else if given.radix && !settings.number_format.radix.is_power_of_two() {
+        format!(
+            "--radix {} is no power of two, so no --digits-only",
+            settings.number_format.radix
+        )
+    } else if settings.max_disk.is_some() {
+        "--max-disk may need another format, so no --digits-only".to_string()
+    } else {
+        let radix = settings.number_format.radix;
+        settings.digits_only = true;
+        if !given.radix {
+            settings.number_format.radix = 16;
+        }
+        if settings.sink != Sink::File {
+            return choice(
+                settings,
+                format!(
+                    "from {} bits decimal digits take too long",
+                    DIGITS_ONLY_MIN_BITS
+                ),
+            );
+        }
+        let size = plan::output_size(settings, seed);
+        if size <= budget as f64 {
+            return choice(
+                settings,
+                format!(
+                    "from {} bits decimal digits take too long, and ~{} in base {} fit {}",
+                    DIGITS_ONLY_MIN_BITS,
+                    output::format_size(size),
+                    settings.number_format.radix,
+                    output::format_size(budget as f64)
+                ),
+            );
+        }
+        settings.digits_only = false;
+        settings.number_format.radix = radix;
+        format!(
+            "~{} even with --digits-only, more than {}",
+            output::format_size(size),
+            output::format_size(budget as f64)
+        )
+    };
+    settings.fast_io = true;
+    choice(settings, reason)
+}
+
+fn format(settings: &mut Settings, seed: &BigInt, given: &Given) -> Result<Choice, String> {
+    let estimate = plan::output_size(settings, seed);
+    let reason = if settings.sink != Sink::File {
+        format!("the sequence goes to {}, not to disk", settings.sink)
+    } else if given.format {
+        GIVEN.to_string()
+    } else if settings.digits_only {
+        "--digits-only writes every value".to_string()
+    } else if let Some(budget) = settings.max_disk {
+        format!(
+            "~{} estimated, --max-disk {} decides",
+            output::format_size(estimate),
+            output::format_size(budget as f64)
+        )
+    } else {
+        let fits = crate::fit_format(settings, seed, OUTPUT_BUDGET, "the --auto budget")?.is_none();
+        format!(
+            "~{} in full, {} {}",
+            output::format_size(estimate),
+            if fits { "within" } else { "more than" },
+            output::format_size(OUTPUT_BUDGET as f64)
+        )
+    };
+    Ok(Choice {
+        setting: "format",
+        value: format_name(settings),
+        reason,
+    })
+}
+
+// Size the write buffer for LINES_PER_BUFFER values of the seed's length, in powers of two
+fn buffer_size(settings: &mut Settings, seed: &BigInt, given: &Given) -> Choice {
+    let radix = f64::from(settings.number_format.radix);
+    let line = (seed.bits() as f64 / radix.log2()).ceil() as usize + 1;
+    let reason = if given.buffer_size {
+        GIVEN.to_string()
+    } else {
+        settings.buffer_size = (line * LINES_PER_BUFFER)
+            .next_power_of_two()
+            .clamp(MIN_BUFFER_SIZE, MAX_BUFFER_SIZE);
+        format!(
+            "{} lines of the seed's {} digits",
+            settings.buffer_size / line,
+            line - 1
+        )
+    };
+    Choice {
+        setting: "buffer size",
+        value: output::format_size(settings.buffer_size as f64),
+        reason,
+    }
+}
+
+fn backend_name(settings: &Settings) -> &'static str {
+    if settings.digits_only {
+        "--digits-only"
+    } else if settings.fast_io {
+        "--fast-io"
+    } else {
+        "decimal conversion of every value"
+    }
+}
+
+fn format_name(settings: &Settings) -> String {
+    match (settings.odd_only, settings.sample_every) {
+        (true, _) => "only the odd terms (--odd-only)".to_string(),
+        (false, Some(every)) => format!("every {}th value (--sample-every {})", every, every),
+        (false, None) if !settings.filters.is_empty() => "the values kept by --filter".to_string(),
+        (false, None) if settings.annotate => "every value, annotated".to_string(),
+        (false, None) => "every value".to_string(),
+    }
+}
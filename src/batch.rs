@@ -0,0 +1,362 @@
+use crate::distribution::Distribution;
+use crate::format::NumberFormat;
+use crate::leaderboard::Leaderboards;
+use crate::metrics::METRICS;
+use crate::output;
+use crate::parquet::ParquetWriter;
+use crate::store::Store;
+use clap::ValueEnum;
+use collatz_bigint_file::report::SequenceStats;
+use collatz_bigint_file::sequence::{Limits, Map};
+use collatz_bigint_file::stats::{self, Row, StoppingTime};
+use collatz_bigint_file::{info, warn};
+use num_bigint::BigInt;
+use num_traits::Zero;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Write};
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+
+// Format of the per-seed statistics lines in batch mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatsFormat {
+    /// One human readable line per seed
+    Text,
+    /// Comma separated values with a header line
+    Csv,
+    /// One JSON object per line
+    Json,
+    /// A Markdown table
+    Markdown,
+    /// An HTML table
+    Html,
+}
+
+// Normalization of the seeds of a batch, selected with --normalize
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Normalize {
+    /// Name each seed by its decimal value instead of the expression given, e.g. 1023 for 2^10-1
+    Canonical,
+    /// Skip seeds whose value came before, whatever the expression
+    Unique,
+    /// Skip even seeds, whose trajectory continues that of n/2
+    Odd,
+    /// Skip multiples of 3, which no trajectory passes through after its seed
+    #[value(name = "not-multiple-of-3")]
+    #[serde(rename = "not-multiple-of-3")]
+    NotMultipleOf3,
+}
+
+// Unresolved seeds named in the summary
+const UNRESOLVED_EXAMPLES: usize = 5;
+
+/// Reads one seed or expression per line and writes one statistics line per seed to `output`.
+///
+/// Trajectories are computed in memory, no sequence files are written. Invalid lines are
+/// reported on stderr and skipped, and so are the seeds the `normalizer` rules out. With a
+/// `store`, every row is also added to the database, with `parquet` to a Parquet file, and with
+/// `leaderboards` the best seeds are ranked. Seeds stopped by a limit are collected in
+/// `unresolved`. Returns the distribution of the stopping times by `definition`.
+#[allow(clippy::too_many_arguments)]
+pub fn run(
+    input: impl BufRead,
+    output: impl Write,
+    format: StatsFormat,
+    limits: &Limits,
+    number_format: &NumberFormat,
+    mut store: Option<Store>,
+    mut parquet: Option<ParquetWriter>,
+    definition: StoppingTime,
+    mut leaderboards: Option<&mut Leaderboards>,
+    unresolved: &mut Unresolved,
+    normalizer: &mut Normalizer,
+) -> io::Result<Distribution> {
+    let mut distribution = Distribution::new(definition);
+    let max_bits = limits.max_bits.unwrap_or(u64::MAX);
+    let mut output = BufWriter::new(output);
+    write_header(&mut output, format)?;
+    for (line_num, line) in input.lines().enumerate() {
+        let line = line?;
+        let seed = line.trim();
+        if seed.is_empty() || seed.starts_with('#') {
+            continue;
+        }
+        match crate::parse_input(seed.to_string(), max_bits) {
+            Ok(start) => {
+                if let Some(reason) = normalizer.skip(line_num + 1, &start) {
+                    info!("Skipping line {} '{}': {}", line_num + 1, seed, reason);
+                    continue;
+                }
+                let canonical;
+                let seed = if normalizer.canonical {
+                    canonical = start.to_string();
+                    &canonical
+                } else {
+                    seed
+                };
+                let row = match store.as_mut() {
+                    Some(store) => {
+                        stored_trajectory(store, seed, &start, limits).map_err(io::Error::other)?
+                    }
+                    None => stats::trajectory(seed.to_string(), &start, limits, Map::Standard),
+                };
+                METRICS.record(&row);
+                distribution.record(&row);
+                if let Some(leaderboards) = leaderboards.as_mut() {
+                    leaderboards.record(&row);
+                }
+                unresolved.record(&row)?;
+                write_row(&mut output, &row, format, number_format)?;
+                if let Some(parquet) = parquet.as_mut() {
+                    parquet.write_row(&row)?;
+                }
+            }
+            Err(err) => {
+                METRICS.record_invalid();
+                warn!("Skipping line {} '{}': {}", line_num + 1, seed, err);
+            }
+        }
+    }
+    write_footer(&mut output, format)?;
+    output.flush()?;
+    if let Some(parquet) = parquet {
+        parquet.finish()?;
+    }
+    if let Some(store) = store {
+        store.close().map_err(io::Error::other)?;
+    }
+    Ok(distribution)
+}
+
+/// Seeds of a batch stopped by `--max-steps`, `--timeout` or `--max-value-size` before they
+/// reached 1, so one slow seed cannot stall a scan.
+///
+/// They are counted for the summary and, with a file, written to it one per line, which the
+/// `retry` subcommand reads back to compute them again with a larger budget.
+pub struct Unresolved {
+    count: u64,
+    examples: Vec<(String, String)>,
+    output: Option<(PathBuf, BufWriter<File>)>,
+}
+
+impl Unresolved {
+    pub fn new(path: Option<&Path>, force: bool) -> Result<Self, String> {
+        let output = match path {
+            Some(path) => {
+                let mut output = BufWriter::new(output::create(path, force)?);
+                writeln!(
+                    output,
+                    "# seeds stopped by a limit, retry with a larger --max-steps or --timeout"
+                )
+                .map_err(|err| format!("Failed to write {}: {}", path.display(), err))?;
+                Some((path.to_path_buf(), output))
+            }
+            None => None,
+        };
+        Ok(Unresolved {
+            count: 0,
+            examples: Vec::new(),
+            output,
+        })
+    }
+
+    fn record(&mut self, row: &Row) -> io::Result<()> {
+        let Some(truncation) = row.truncated else {
+            return Ok(());
+        };
+        self.count += 1;
+        if self.examples.len() < UNRESOLVED_EXAMPLES {
+            self.examples
+                .push((row.seed.clone(), truncation.to_string()));
+        }
+        if let Some((_, output)) = self.output.as_mut() {
+            writeln!(output, "{}", row.seed)?;
+        }
+        Ok(())
+    }
+
+    pub fn finish(&mut self) -> Result<(), String> {
+        match self.output.as_mut() {
+            Some((path, output)) => output
+                .flush()
+                .map_err(|err| format!("Failed to write {}: {}", path.display(), err)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn write_summary(&self, output: &mut impl Write) -> io::Result<()> {
+        if self.count == 0 {
+            return Ok(());
+        }
+        writeln!(
+            output,
+            "unresolved: {} seeds stopped by a limit before reaching 1",
+            self.count
+        )?;
+        for (seed, reason) in &self.examples {
+            writeln!(output, "  {}: {}", seed, reason)?;
+        }
+        if self.count > self.examples.len() as u64 {
+            writeln!(output, "  ...")?;
+        }
+        match &self.output {
+            Some((path, _)) => writeln!(
+                output,
+                "retry them with a larger budget: collatz_bigint_file --max-steps N retry {}",
+                path.display()
+            ),
+            None => writeln!(
+                output,
+                "write them to a file for retry with --unresolved FILE"
+            ),
+        }
+    }
+}
+
+/// Rules of `--normalize` for the seeds of a batch, and the seeds they skipped.
+pub struct Normalizer {
+    // name rows by the decimal value of the seed
+    canonical: bool,
+    // line of the first occurrence of every value so far, with `unique`
+    seen: Option<HashMap<BigInt, usize>>,
+    odd: bool,
+    not_multiple_of_3: bool,
+    duplicates: u64,
+    even: u64,
+    multiples_of_3: u64,
+}
+
+impl Normalizer {
+    pub fn new(rules: &[Normalize]) -> Self {
+        Normalizer {
+            canonical: rules.contains(&Normalize::Canonical),
+            seen: rules.contains(&Normalize::Unique).then(HashMap::new),
+            odd: rules.contains(&Normalize::Odd),
+            not_multiple_of_3: rules.contains(&Normalize::NotMultipleOf3),
+            duplicates: 0,
+            even: 0,
+            multiples_of_3: 0,
+        }
+    }
+
+    // Why the seed on this line is skipped, None if it is computed
+    fn skip(&mut self, line_num: usize, seed: &BigInt) -> Option<String> {
+        if self.odd && !seed.bit(0) {
+            self.even += 1;
+            return Some("even".to_string());
+        }
+        if self.not_multiple_of_3 && (seed % 3u32).is_zero() {
+            self.multiples_of_3 += 1;
+            return Some("multiple of 3".to_string());
+        }
+        if let Some(seen) = self.seen.as_mut() {
+            if let Some(first) = seen.get(seed) {
+                self.duplicates += 1;
+                return Some(format!("same value as line {}", first));
+            }
+            seen.insert(seed.clone(), line_num);
+        }
+        None
+    }
+
+    pub fn write_summary(&self, output: &mut impl Write) -> io::Result<()> {
+        let skipped: Vec<String> = [
+            (self.duplicates, "duplicates"),
+            (self.even, "even"),
+            (self.multiples_of_3, "multiples of 3"),
+        ]
+        .iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, reason)| format!("{} {}", count, reason))
+        .collect();
+        if skipped.is_empty() {
+            return Ok(());
+        }
+        writeln!(output, "skipped seeds: {}", skipped.join(", "))
+    }
+}
+
+// Compute a trajectory and add it to the store, with every value if the store keeps them
+fn stored_trajectory(
+    store: &mut Store,
+    seed: &str,
+    start: &BigInt,
+    limits: &Limits,
+) -> Result<Row, String> {
+    let id = store.begin_result(seed)?;
+    let row = if store.store_values() {
+        let mut step = 0;
+        let mut failure = None;
+        let row = stats::visit_trajectory(seed.to_string(), start, limits, Map::Standard, |n| {
+            step += 1;
+            match store.insert_value(id, step, n) {
+                Ok(()) => ControlFlow::Continue(()),
+                Err(err) => {
+                    failure = Some(err);
+                    ControlFlow::Break(())
+                }
+            }
+        });
+        if let Some(err) = failure {
+            return Err(err);
+        }
+        row
+    } else {
+        stats::trajectory(seed.to_string(), start, limits, Map::Standard)
+    };
+    store.finish_result(id, &row)?;
+    Ok(row)
+}
+
+pub fn write_row(
+    output: &mut impl Write,
+    row: &Row,
+    format: StatsFormat,
+    number_format: &NumberFormat,
+) -> io::Result<()> {
+    let max_value = match format {
+        StatsFormat::Text | StatsFormat::Markdown | StatsFormat::Html => {
+            number_format.format(&row.max_value)
+        }
+        StatsFormat::Csv | StatsFormat::Json => row.max_value.to_string(),
+    };
+    write_stats(output, &SequenceStats::new(row, max_value), format)
+}
+
+/// Writes what precedes the statistics lines in `format`: the CSV header or the table head.
+pub fn write_header(output: &mut impl Write, format: StatsFormat) -> io::Result<()> {
+    match format {
+        StatsFormat::Text | StatsFormat::Json => Ok(()),
+        StatsFormat::Csv => writeln!(output, "{}", SequenceStats::csv_header()),
+        StatsFormat::Markdown => writeln!(output, "{}", SequenceStats::markdown_header()),
+        StatsFormat::Html => writeln!(output, "{}", SequenceStats::html_header()),
+    }
+}
+
+/// Writes what follows the statistics lines in `format`: the end of the HTML table.
+pub fn write_footer(output: &mut impl Write, format: StatsFormat) -> io::Result<()> {
+    match format {
+        StatsFormat::Html => writeln!(output, "{}", SequenceStats::html_footer()),
+        _ => Ok(()),
+    }
+}
+
+/// Writes one line of statistics in `format`; the max value is written as `stats` has it.
+pub fn write_stats(
+    output: &mut impl Write,
+    stats: &SequenceStats,
+    format: StatsFormat,
+) -> io::Result<()> {
+    let line = match format {
+        StatsFormat::Text => stats.text(),
+        StatsFormat::Csv => stats.csv(),
+        StatsFormat::Json => stats.json(),
+        StatsFormat::Markdown => stats.markdown(),
+        StatsFormat::Html => stats.html(),
+    };
+    writeln!(output, "{}", line)
+}
@@ -0,0 +1,236 @@
+use crate::output;
+use crate::work::{self, Range, Report};
+use collatz_bigint_file::sequence::{self, Limits};
+use num_bigint::BigInt;
+use num_traits::{One, Zero};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::Instant;
+
+// First line of a certificate, with the version of the format
+const CERTIFICATE_HEADER: &str = "# collatz_bigint_file certificate 1";
+
+/// Writes the certificate of a verified range: the residue classes of the sieve and the glide of
+/// every other seed, in order, so that `certify --check` can confirm the report without
+/// searching for the glides again.
+///
+/// The file is one line per computed seed, about a quarter of the range, with the number of
+/// steps after which its trajectory is below the seed; the seeds themselves are implied by the
+/// range and the sieve.
+pub fn write(path: &Path, report: &Report, force: bool) -> Result<(), String> {
+    if !report.unverified.is_empty() {
+        return Err(format!(
+            "No certificate for {}: {} seeds could not be verified",
+            report.range,
+            report.unverified.len()
+        ));
+    }
+    let write_error = |err: io::Error| format!("Failed to write {}: {}", path.display(), err);
+    let mut output = BufWriter::new(output::create(path, force)?);
+    writeln!(output, "{}", CERTIFICATE_HEADER).map_err(write_error)?;
+    writeln!(output, "range: {}", report.range).map_err(write_error)?;
+    for (residue, bits) in work::SIEVE {
+        writeln!(output, "sieve: {} mod 2^{}", residue, bits).map_err(write_error)?;
+    }
+    writeln!(output, "computed: {}", report.computed).map_err(write_error)?;
+    writeln!(
+        output,
+        "# glides of the seeds outside the sieve classes, except 1, in order"
+    )
+    .map_err(write_error)?;
+    for glide in &report.glides {
+        writeln!(output, "{}", glide).map_err(write_error)?;
+    }
+    output.flush().map_err(write_error)
+}
+
+/// Checks a certificate written by `worker --certificate` and prints what it proves.
+///
+/// Every sieve class `r mod 2^k` is checked once, symbolically: the first k steps of its seeds
+/// follow the same parities, so after them every seed n of the class is (3^a n + c) / 2^k, which
+/// is below n for all n > 1 of the class if it is for the smallest. Every other seed of the
+/// range is advanced by its glide with runs of halvings applied as one shift, and its value then
+/// has to be below the seed. No glide is searched for, so checking is faster than verifying.
+pub fn check(path: &Path, buffer_size: usize) -> Result<(), String> {
+    let started = Instant::now();
+    let read_error = |err: io::Error| format!("Failed to read {}: {}", path.display(), err);
+    let file =
+        File::open(path).map_err(|err| format!("Failed to open {}: {}", path.display(), err))?;
+    let mut lines = BufReader::with_capacity(buffer_size, file)
+        .lines()
+        .enumerate()
+        .map(|(i, line)| line.map(|line| (i + 1, line)));
+    let invalid = |line_num: usize, line: &str| {
+        format!(
+            "{} line {}: invalid certificate line '{}'",
+            path.display(),
+            line_num,
+            line
+        )
+    };
+
+    match lines.next().transpose().map_err(read_error)? {
+        Some((_, line)) if line.trim() == CERTIFICATE_HEADER => {}
+        _ => {
+            return Err(format!(
+                "{} is not a certificate, expected '{}'",
+                path.display(),
+                CERTIFICATE_HEADER
+            ))
+        }
+    }
+    let mut range = None;
+    let mut sieve = Vec::new();
+    let mut computed = None;
+    // the header ends with the count of the glides that follow
+    while computed.is_none() {
+        let (line_num, line) = lines
+            .next()
+            .transpose()
+            .map_err(read_error)?
+            .ok_or_else(|| format!("{} ends in its header", path.display()))?;
+        let Some((key, value)) = line.split_once(':') else {
+            return Err(invalid(line_num, &line));
+        };
+        let value = value.trim();
+        match key.trim() {
+            "range" => range = Some(work::parse_range(value)?),
+            "sieve" => {
+                let class = value
+                    .split_once(" mod 2^")
+                    .and_then(|(residue, bits)| Some((residue.parse().ok()?, bits.parse().ok()?)))
+                    .filter(|&(residue, bits): &(u64, u32)| {
+                        (1..64).contains(&bits) && residue < 1 << bits
+                    })
+                    .ok_or_else(|| invalid(line_num, &line))?;
+                sieve.push(class);
+            }
+            "computed" => {
+                computed = Some(value.parse::<u64>().map_err(|_| invalid(line_num, &line))?)
+            }
+            _ => return Err(invalid(line_num, &line)),
+        }
+    }
+    let range: Range = range.ok_or_else(|| format!("{} has no range", path.display()))?;
+    let computed = computed.unwrap_or_default();
+    println!("range: {}", range);
+    for &(residue, bits) in &sieve {
+        let steps = check_class(residue, bits)?;
+        println!(
+            "sieve: every n > 1 with n = {} mod 2^{} is below itself after {} steps",
+            residue, bits, steps
+        );
+    }
+
+    let limits = Limits::default();
+    let mut seed = range.start.clone();
+    let (mut sieved, mut checked) = (0u64, 0u64);
+    let mut max_glide: Option<(u32, BigInt)> = None;
+    while seed < range.end {
+        if seed.is_one()
+            || sieve
+                .iter()
+                .any(|&(residue, bits)| work::in_class(&seed, residue, bits))
+        {
+            sieved += 1;
+        } else {
+            let (line_num, line) = loop {
+                match lines.next().transpose().map_err(read_error)? {
+                    Some((_, line)) if line.starts_with('#') || line.trim().is_empty() => {}
+                    Some(numbered) => break numbered,
+                    None => {
+                        return Err(format!(
+                            "{} ends before seed {}, which is not in the sieve",
+                            path.display(),
+                            seed
+                        ))
+                    }
+                }
+            };
+            let glide: u32 = line.trim().parse().map_err(|_| invalid(line_num, &line))?;
+            let mut n = seed.clone();
+            sequence::advance(&mut n, glide as usize, &limits, None);
+            if n >= seed {
+                return Err(format!(
+                    "MISMATCH: seed {} is not below itself after {} steps (line {})",
+                    seed, glide, line_num
+                ));
+            }
+            checked += 1;
+            if max_glide.as_ref().is_none_or(|(max, _)| glide > *max) {
+                max_glide = Some((glide, seed.clone()));
+            }
+        }
+        seed += 1;
+    }
+    for line in lines {
+        let (line_num, line) = line.map_err(read_error)?;
+        if !line.starts_with('#') && !line.trim().is_empty() {
+            return Err(format!(
+                "{} line {}: more glides than seeds outside the sieve",
+                path.display(),
+                line_num
+            ));
+        }
+    }
+    if checked != computed {
+        return Err(format!(
+            "{} announces {} glides but has {}",
+            path.display(),
+            computed,
+            checked
+        ));
+    }
+
+    println!("sieved: {}", sieved);
+    println!("checked: {}", checked);
+    if let Some((glide, seed)) = max_glide {
+        println!("max glide: {} (seed {})", glide, seed);
+    }
+    println!("elapsed: {:.3}s", started.elapsed().as_secs_f64());
+    if range.start <= BigInt::from(2) {
+        println!(
+            "OK: every seed of {} falls below itself, so all of them reach 1",
+            range
+        );
+    } else {
+        println!(
+            "OK: every seed of {} falls below itself, so all of them reach 1 if every seed below {} does",
+            range, range.start
+        );
+    }
+    Ok(())
+}
+
+// Check that every n > 1 of the class `residue mod 2^bits` is below itself after its first
+// steps, and return their number. While fewer than `bits` halvings are done, the value of a
+// seed n = residue + 2^bits t is (3^a n + c) / 2^e and its parity does not depend on t.
+fn check_class(residue: u64, bits: u32) -> Result<usize, String> {
+    let residue = BigInt::from(residue);
+    let (mut power, mut c, mut halvings, mut steps) = (BigInt::one(), BigInt::zero(), 0, 0);
+    while halvings < bits {
+        if ((&power * &residue + &c) >> halvings).bit(0) {
+            // 3 (3^a n + c) / 2^e + 1 = (3^(a+1) n + 3c + 2^e) / 2^e
+            power *= 3;
+            c = c * 3 + (BigInt::one() << halvings);
+        } else {
+            halvings += 1;
+        }
+        steps += 1;
+    }
+    // (3^a n + c) / 2^k < n is c < (2^k - 3^a) n, hardest for the smallest n
+    let smallest = if residue > BigInt::one() {
+        residue.clone()
+    } else {
+        residue.clone() + (BigInt::one() << bits)
+    };
+    let room = (BigInt::one() << bits) - &power;
+    if room <= BigInt::zero() || c >= room * smallest {
+        return Err(format!(
+            "MISMATCH: the sieve class {} mod 2^{} does not fall below itself after {} steps",
+            residue, bits, steps
+        ));
+    }
+    Ok(steps)
+}
@@ -0,0 +1,64 @@
+// Render values as a bar chart of `width` columns; each column shows the largest value of its
+// part of the input. A model curve of the same length is drawn over the bars with '•', at the
+// mean of its part of the input. The input covers steps 0 to `last_step`.
+pub fn plot(
+    values: &[f64],
+    model: Option<&[f64]>,
+    last_step: usize,
+    width: usize,
+    height: usize,
+) -> String {
+    let per_column = values.len().div_ceil(width).max(1);
+    let columns: Vec<f64> = values
+        .chunks(per_column)
+        .map(|chunk| chunk.iter().copied().fold(0.0, f64::max))
+        .collect();
+    let model_columns: Option<Vec<f64>> = model.map(|model| {
+        model
+            .chunks(per_column)
+            .map(|chunk| chunk.iter().sum::<f64>() / chunk.len() as f64)
+            .collect()
+    });
+    let max = columns.iter().copied().fold(1.0, f64::max).ceil();
+    let label_width = max.to_string().len();
+
+    let mut chart = String::new();
+    for row in (1..=height).rev() {
+        let label = if row == height {
+            max.to_string()
+        } else if row == 1 {
+            "0".to_string()
+        } else {
+            String::new()
+        };
+        chart.push_str(&format!("{:>width$} |", label, width = label_width));
+        for (column, value) in columns.iter().enumerate() {
+            // a column reaches this row if its value covers the row's share of the maximum
+            let filled = value * height as f64 >= max * row as f64 - max / 2.0;
+            let on_model = model_columns.as_ref().is_some_and(|model| {
+                let model_row = (model[column] * height as f64 / max).round().max(1.0);
+                model_row as usize == row
+            });
+            chart.push(match (on_model, filled) {
+                (true, _) => '•',
+                (false, true) => '█',
+                (false, false) => ' ',
+            });
+        }
+        chart.push('\n');
+    }
+    chart.push_str(&format!(
+        "{:>width$} +{}\n",
+        "",
+        "-".repeat(columns.len()),
+        width = label_width
+    ));
+    chart.push_str(&format!(
+        "{:>width$}  step 0{:>pad$}\n",
+        "",
+        last_step,
+        width = label_width,
+        pad = columns.len().saturating_sub(6)
+    ));
+    chart
+}
@@ -0,0 +1,94 @@
+use std::io::{ErrorKind, Write};
+use std::process::{Command, Stdio};
+
+// Commands that print the clipboard and that replace it with their stdin, tried in order until
+// one is installed
+#[cfg(target_os = "macos")]
+const PASTE: &[&[&str]] = &[&["pbpaste"]];
+#[cfg(target_os = "macos")]
+const COPY: &[&[&str]] = &[&["pbcopy"]];
+#[cfg(windows)]
+const PASTE: &[&[&str]] = &[&["powershell", "-NoProfile", "-Command", "Get-Clipboard -Raw"]];
+#[cfg(windows)]
+const COPY: &[&[&str]] = &[&["clip"]];
+#[cfg(not(any(target_os = "macos", windows)))]
+const PASTE: &[&[&str]] = &[
+    &["wl-paste", "--no-newline"],
+    &["xclip", "-selection", "clipboard", "-out"],
+    &["xsel", "--clipboard", "--output"],
+];
+#[cfg(not(any(target_os = "macos", windows)))]
+const COPY: &[&[&str]] = &[
+    &["wl-copy"],
+    &["xclip", "-selection", "clipboard", "-in"],
+    &["xsel", "--clipboard", "--input"],
+];
+
+/// Reads the text of the system clipboard with the clipboard tool of the platform: `pbpaste`
+/// on macOS, PowerShell on Windows, and `wl-paste`, `xclip` or `xsel` elsewhere.
+pub fn read() -> Result<String, String> {
+    for command in PASTE {
+        match Command::new(command[0])
+            .args(&command[1..])
+            .stderr(Stdio::null())
+            .output()
+        {
+            Ok(output) if output.status.success() => {
+                return String::from_utf8(output.stdout)
+                    .map_err(|_| "The clipboard does not hold text".to_string())
+            }
+            Ok(output) => {
+                return Err(format!(
+                    "Failed to read the clipboard with {}: {}",
+                    command[0], output.status
+                ))
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound => continue,
+            Err(err) => return Err(format!("Failed to run {}: {}", command[0], err)),
+        }
+    }
+    Err(missing_tool(PASTE))
+}
+
+/// Replaces the text of the system clipboard, with the tools of [`read`].
+pub fn write(text: &str) -> Result<(), String> {
+    for command in COPY {
+        let mut child = match Command::new(command[0])
+            .args(&command[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(err) if err.kind() == ErrorKind::NotFound => continue,
+            Err(err) => return Err(format!("Failed to run {}: {}", command[0], err)),
+        };
+        let written = child
+            .stdin
+            .take()
+            .expect("stdin is piped")
+            .write_all(text.as_bytes());
+        let status = child.wait();
+        return match (written, status) {
+            (Ok(()), Ok(status)) if status.success() => Ok(()),
+            (Err(err), _) | (_, Err(err)) => Err(format!(
+                "Failed to write the clipboard with {}: {}",
+                command[0], err
+            )),
+            (_, Ok(status)) => Err(format!(
+                "Failed to write the clipboard with {}: {}",
+                command[0], status
+            )),
+        };
+    }
+    Err(missing_tool(COPY))
+}
+
+fn missing_tool(commands: &[&[&str]]) -> String {
+    let names: Vec<&str> = commands.iter().map(|command| command[0]).collect();
+    format!(
+        "No clipboard tool found, install one of: {}",
+        names.join(", ")
+    )
+}
@@ -0,0 +1,217 @@
+use crate::format::NumberFormat;
+use crate::output;
+use crate::visited::{self, VisitedSet};
+use crate::work;
+use collatz_bigint_file::sequence::{Limits, Map};
+use collatz_bigint_file::stats;
+use collatz_bigint_file::{info, warn};
+use colored::Colorize;
+use num_bigint::BigInt;
+use std::io::{BufWriter, Write};
+use std::ops::ControlFlow;
+use std::path::Path;
+use std::time::Instant;
+
+// Seeds between two progress messages
+const PROGRESS_INTERVAL: u64 = 1_000_000;
+
+// Where the walk of one seed ended
+enum Walk {
+    // joined the values of an earlier seed at this step and value
+    Joined(usize, BigInt),
+    // reached 1 without meeting an earlier value, only the first seeds of a scan
+    Reached(usize),
+    // stopped by a limit after this many steps
+    Stopped(usize, String),
+}
+
+/// Walks the trajectory of every seed only until it joins one of an earlier seed and prints how
+/// far the seeds got on their own.
+///
+/// Every value walked goes into a [`VisitedSet`] of about `memory` bytes that spills to
+/// `spill_dir`, so scans of billions of seeds keep stopping at the first visited value however
+/// many values they collected. Seeds are expressions or ranges with the end excluded and are
+/// streamed, never held in memory. `csv` receives one line per seed with the steps walked and
+/// the value where the seed joined.
+pub fn run(
+    args: &[String],
+    memory: u64,
+    spill_dir: &Path,
+    csv: Option<&Path>,
+    limits: &Limits,
+    number_format: &NumberFormat,
+    force: bool,
+) -> Result<(), String> {
+    let started = Instant::now();
+    let max_bits = limits.max_bits.unwrap_or(u64::MAX);
+    let mut set = VisitedSet::new(memory, spill_dir)?;
+    let mut csv = csv
+        .map(|path| output::create(path, force).map(|file| (path, BufWriter::new(file))))
+        .transpose()?;
+    if let Some((path, output)) = csv.as_mut() {
+        writeln!(output, "seed,steps,join_value")
+            .map_err(|err| format!("Failed to write {}: {}", path.display(), err))?;
+    }
+
+    let (mut seeds, mut walked, mut reached, mut stopped) = (0u64, 0u64, 0u64, 0u64);
+    let mut longest: Option<(String, usize)> = None;
+    // seeds by the bit length of their walk: 0 steps, 1, 2-3, 4-7, ...
+    let mut histogram = Vec::<u64>::new();
+    let mut walk_seed = |label: String, seed: BigInt| -> Result<(), String> {
+        let walk = walk(&mut set, &label, &seed, limits)?;
+        seeds += 1;
+        let steps = match &walk {
+            Walk::Joined(steps, _) => *steps,
+            Walk::Reached(steps) => {
+                reached += 1;
+                *steps
+            }
+            Walk::Stopped(steps, reason) => {
+                warn!("{} stopped after {} steps: {}", label, steps, reason);
+                stopped += 1;
+                *steps
+            }
+        };
+        walked += steps as u64;
+        let bucket = (usize::BITS - steps.leading_zeros()) as usize;
+        if histogram.len() <= bucket {
+            histogram.resize(bucket + 1, 0);
+        }
+        histogram[bucket] += 1;
+        if let Some((path, output)) = csv.as_mut() {
+            let join_value = match &walk {
+                Walk::Joined(_, value) => value.to_string(),
+                _ => String::new(),
+            };
+            writeln!(output, "{},{},{}", label, steps, join_value)
+                .map_err(|err| format!("Failed to write {}: {}", path.display(), err))?;
+        }
+        if longest.as_ref().is_none_or(|(_, max)| steps > *max) {
+            longest = Some((label, steps));
+        }
+        if seeds.is_multiple_of(PROGRESS_INTERVAL) {
+            let stats = set.stats();
+            info!(
+                "{} seeds, {} values visited, {} of them on disk",
+                seeds,
+                stats.in_memory + stats.on_disk,
+                stats.on_disk
+            );
+        }
+        Ok(())
+    };
+    for arg in args {
+        if arg.contains("..") {
+            let range = work::parse_range(arg)?;
+            let mut seed = range.start;
+            while seed < range.end {
+                walk_seed(seed.to_string(), seed.clone())?;
+                seed += 1;
+            }
+        } else {
+            let seed = crate::parse_input(arg.clone(), max_bits)
+                .map_err(|err| format!("{}: {}", arg, err))?;
+            walk_seed(arg.clone(), seed)?;
+        }
+    }
+    if let Some((path, mut output)) = csv {
+        output
+            .flush()
+            .map_err(|err| format!("Failed to write {}: {}", path.display(), err))?;
+    }
+
+    let stats = set.stats();
+    println!("seeds: {}", seeds);
+    println!(
+        "steps walked: {} ({:.2} per seed)",
+        walked,
+        walked as f64 / seeds.max(1) as f64
+    );
+    if let Some((label, steps)) = &longest {
+        println!("longest walk: {} steps (seed {})", steps, label);
+    }
+    if reached > 0 {
+        println!("reached 1 on their own: {}", reached);
+    }
+    if stopped > 0 {
+        println!("stopped by a limit: {}", stopped);
+    }
+    println!(
+        "values visited: {} ({} in memory, {} on disk in {} runs after {} spills)",
+        number_format.format(&BigInt::from(stats.in_memory + stats.on_disk)),
+        stats.in_memory,
+        stats.on_disk,
+        stats.runs,
+        stats.spills
+    );
+    if stats.disk_lookups > 0 {
+        println!(
+            "disk lookups: {} ({} false positives of the Bloom filter)",
+            stats.disk_lookups, stats.false_positives
+        );
+    }
+    println!("elapsed: {:.3}s", started.elapsed().as_secs_f64());
+    println!();
+    println!(
+        "{}",
+        format!("{:>13}  {:>12}  share", "steps", "seeds").bold()
+    );
+    for (bucket, &count) in histogram.iter().enumerate() {
+        let steps = match bucket {
+            0 => "0".to_string(),
+            1 => "1".to_string(),
+            bucket => format!("{}-{}", 1u64 << (bucket - 1), (1u64 << bucket) - 1),
+        };
+        println!(
+            "{:>13}  {:>12}  {:>5.1}%",
+            steps,
+            count,
+            100.0 * count as f64 / seeds.max(1) as f64
+        );
+    }
+    Ok(())
+}
+
+// Walk the trajectory of one seed until a value is in `set`, adding the values before it
+fn walk(set: &mut VisitedSet, label: &str, seed: &BigInt, limits: &Limits) -> Result<Walk, String> {
+    let key = visited::key(seed);
+    if set.contains(key)? {
+        return Ok(Walk::Joined(0, seed.clone()));
+    }
+    set.insert(key)?;
+    let mut joined = None;
+    let mut error = None;
+    let row = stats::observe_trajectory(
+        label.to_string(),
+        seed,
+        limits,
+        Map::Standard,
+        |step, n, _| {
+            let key = visited::key(n);
+            match set.contains(key).and_then(|found| {
+                if !found {
+                    set.insert(key)?;
+                }
+                Ok(found)
+            }) {
+                Ok(false) => ControlFlow::Continue(()),
+                Ok(true) => {
+                    joined = Some((step, n.clone()));
+                    ControlFlow::Break(())
+                }
+                Err(err) => {
+                    error = Some(err);
+                    ControlFlow::Break(())
+                }
+            }
+        },
+    );
+    if let Some(err) = error {
+        return Err(err);
+    }
+    Ok(match (joined, row.truncated) {
+        (Some((step, value)), _) => Walk::Joined(step, value),
+        (None, None) => Walk::Reached(row.stopping_time),
+        (None, Some(truncation)) => Walk::Stopped(row.stopping_time, truncation.to_string()),
+    })
+}
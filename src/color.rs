@@ -0,0 +1,86 @@
+use clap::ValueEnum;
+use colored::Color;
+use num_bigint::BigInt;
+use serde::Deserialize;
+
+// How values of the sequence are colored in the terminal dump
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    /// Color by parity (even/odd colors)
+    Parity,
+    /// Color by magnitude: hue goes from blue (small) to red (peak bit length)
+    Magnitude,
+}
+
+// Color scheme used by `line_read` to color each printed value
+pub struct ColorScheme {
+    pub mode: ColorMode,
+    pub even: Color,
+    pub odd: Color,
+    // bit length of the largest value in the sequence, used to scale the magnitude hue
+    pub max_bits: u64,
+}
+
+impl ColorScheme {
+    pub fn color_for(&self, num: &BigInt, is_even: bool) -> Color {
+        match self.mode {
+            ColorMode::Parity if is_even => self.even,
+            ColorMode::Parity => self.odd,
+            ColorMode::Magnitude => magnitude_color(num.bits(), self.max_bits),
+        }
+    }
+}
+
+// Helper function to parse a color name (e.g. "yellow", "bright blue") or a "#rrggbb" hex value
+pub fn parse_color(input: &str) -> Result<Color, String> {
+    if let Some(hex) = input.strip_prefix('#') {
+        if hex.len() == 6 {
+            if let Ok(rgb) = u32::from_str_radix(hex, 16) {
+                return Ok(Color::TrueColor {
+                    r: (rgb >> 16) as u8,
+                    g: (rgb >> 8) as u8,
+                    b: rgb as u8,
+                });
+            }
+        }
+        return Err(format!(
+            "Invalid hex color '{}'. Expected format is '#rrggbb'",
+            input
+        ));
+    }
+    input
+        .parse::<Color>()
+        .map_err(|_| format!("Unknown color '{}'", input))
+}
+
+// Map a bit length onto a hue between blue (240°) and red (0°) relative to the peak bit length
+fn magnitude_color(bits: u64, max_bits: u64) -> Color {
+    let ratio = if max_bits == 0 {
+        1.0
+    } else {
+        (bits as f64 / max_bits as f64).min(1.0)
+    };
+    hsv_to_rgb(240.0 * (1.0 - ratio), 1.0, 1.0)
+}
+
+// Convert a HSV color (hue in degrees, saturation and value in 0..=1) to a true color
+fn hsv_to_rgb(hue: f64, saturation: f64, value: f64) -> Color {
+    let chroma = value * saturation;
+    let sector = hue / 60.0;
+    let x = chroma * (1.0 - (sector % 2.0 - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = value - chroma;
+    Color::TrueColor {
+        r: ((r + m) * 255.0).round() as u8,
+        g: ((g + m) * 255.0).round() as u8,
+        b: ((b + m) * 255.0).round() as u8,
+    }
+}
@@ -0,0 +1,414 @@
+use crate::batch::{self, StatsFormat};
+use crate::format::NumberFormat;
+use crate::leaderboard::Leaderboards;
+use crate::output;
+use crate::parquet::ParquetWriter;
+use crate::work::Range;
+use collatz_bigint_file::report::SequenceStats;
+use collatz_bigint_file::sequence::Truncation;
+use collatz_bigint_file::stats::Row;
+use collatz_bigint_file::warn;
+use num_bigint::BigInt;
+use num_traits::One;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+// Gaps listed in the coverage report
+const GAP_EXAMPLES: usize = 10;
+
+// Statistics of one seed read back from a batch output
+struct Record {
+    // value of the seed, the key of the deduplication
+    value: BigInt,
+    row: Row,
+    // `Row` keeps the valuations as a distribution, the outputs only their mean
+    valuation_mean: Option<f64>,
+    // file the record was read from
+    source: usize,
+}
+
+impl Record {
+    fn same_results(&self, other: &Record) -> bool {
+        let results = |row: &Row| {
+            (
+                row.stopping_time,
+                row.glide,
+                row.max_index,
+                row.odd,
+                row.truncated,
+            )
+        };
+        results(&self.row) == results(&other.row) && self.row.max_value == other.row.max_value
+    }
+
+    // Whether this record knows more than `other` about the same seed: a resolved trajectory
+    // beats a truncated one, and of two truncated ones the longer
+    fn better_than(&self, other: &Record) -> bool {
+        match (self.row.truncated, other.row.truncated) {
+            (None, Some(_)) => true,
+            (Some(_), Some(_)) => self.row.stopping_time > other.row.stopping_time,
+            _ => false,
+        }
+    }
+}
+
+// Counts of the combination for the report
+#[derive(Default)]
+struct Counts {
+    rows: u64,
+    duplicates: u64,
+    conflicts: u64,
+    invalid: u64,
+}
+
+/// Combines the statistics written by batch runs (`--stats-format csv` or `json`, e.g. on several
+/// machines) into one set: every seed once, ordered by value, with the global records and the
+/// gaps between the covered seeds.
+///
+/// Seeds are deduplicated by value, so overlapping ranges and different spellings of a seed
+/// count once. Of two results for a seed the resolved one is kept over a truncated one; two
+/// resolved results that differ are reported as conflicts and the first is kept. With `out`
+/// the combined rows are written as Parquet, JSON lines or CSV, by the extension of the file.
+pub fn run(
+    inputs: &[PathBuf],
+    out: Option<&Path>,
+    top: usize,
+    number_format: &NumberFormat,
+    force: bool,
+) -> Result<(), String> {
+    let mut records: BTreeMap<BigInt, Record> = BTreeMap::new();
+    let mut counts = Counts::default();
+    for (source, path) in inputs.iter().enumerate() {
+        let file = File::open(path)
+            .map_err(|err| format!("Failed to open {}: {}", path.display(), err))?;
+        read(
+            BufReader::new(file),
+            path,
+            source,
+            &mut counts,
+            |counts, record| match records.get(&record.value) {
+                None => {
+                    records.insert(record.value.clone(), record);
+                }
+                Some(existing) => {
+                    counts.duplicates += 1;
+                    if existing.row.truncated.is_none()
+                        && record.row.truncated.is_none()
+                        && !existing.same_results(&record)
+                    {
+                        counts.conflicts += 1;
+                        warn!(
+                            "Conflicting results for {}: {} and {} differ, keeping the first",
+                            record.value,
+                            inputs[existing.source].display(),
+                            path.display()
+                        );
+                    } else if record.better_than(existing) {
+                        records.insert(record.value.clone(), record);
+                    }
+                }
+            },
+        )?;
+    }
+
+    println!(
+        "read {} rows from {} files: {} duplicates ({} conflicting), {} invalid lines",
+        counts.rows,
+        inputs.len(),
+        counts.duplicates,
+        counts.conflicts,
+        counts.invalid
+    );
+    let unresolved = records
+        .values()
+        .filter(|record| record.row.truncated.is_some())
+        .count();
+    println!(
+        "combined: {} seeds, {} unresolved",
+        records.len(),
+        unresolved
+    );
+    print_coverage(records.keys());
+
+    let mut leaderboards = Leaderboards::new(top);
+    for record in records.values() {
+        leaderboards.record(&record.row);
+    }
+    leaderboards
+        .write(&mut io::stdout(), number_format)
+        .map_err(|err| format!("Failed to print the records: {}", err))?;
+
+    if let Some(path) = out {
+        write(path, records.values(), force)?;
+        println!("wrote {} seeds to {}", records.len(), path.display());
+    }
+    Ok(())
+}
+
+// Read the rows of a CSV file with a header line or of JSON lines, detected from the first line
+fn read(
+    input: impl BufRead,
+    path: &Path,
+    source: usize,
+    counts: &mut Counts,
+    mut add: impl FnMut(&mut Counts, Record),
+) -> Result<(), String> {
+    let mut header: Option<Vec<String>> = None;
+    for (line_num, line) in input.lines().enumerate() {
+        let line = line.map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let fields = if line.starts_with('{') {
+            json_object(line)
+        } else if let Some(names) = &header {
+            let values = csv_fields(line);
+            (values.len() == names.len()).then(|| {
+                names
+                    .iter()
+                    .cloned()
+                    .zip(
+                        values
+                            .into_iter()
+                            .map(|value| Some(value).filter(|value| !value.is_empty())),
+                    )
+                    .collect()
+            })
+        } else {
+            header = Some(csv_fields(line));
+            continue;
+        };
+        match fields
+            .ok_or_else(|| "not a row of statistics".to_string())
+            .and_then(|fields| record(fields, source))
+        {
+            Ok(record) => {
+                counts.rows += 1;
+                add(counts, record);
+            }
+            Err(err) => {
+                counts.invalid += 1;
+                warn!(
+                    "Skipping line {} of {}: {}",
+                    line_num + 1,
+                    path.display(),
+                    err
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+// Build a record from the named fields of a row; unknown fields are ignored
+fn record(fields: HashMap<String, Option<String>>, source: usize) -> Result<Record, String> {
+    let text = |name: &str| fields.get(name).cloned().flatten();
+    let required = |name: &str| text(name).ok_or_else(|| format!("no {}", name));
+    let number = |name: &str| {
+        required(name)?
+            .parse::<usize>()
+            .map_err(|_| format!("invalid {}", name))
+    };
+    let seed = required("seed")?;
+    let value = crate::parse_input(seed.clone(), u64::MAX)
+        .map_err(|_| format!("invalid seed '{}'", seed))?;
+    let row = Row {
+        stopping_time: number("stopping_time")?,
+        glide: text("glide").map(|_| number("glide")).transpose()?,
+        max_value: required("max_value")?
+            .parse()
+            .map_err(|_| "invalid max_value".to_string())?,
+        max_index: number("max_index")?,
+        odd: number("odd")?,
+        valuations: Default::default(),
+        truncated: text("truncated")
+            .map(|truncated| truncated.parse::<Truncation>())
+            .transpose()?,
+        seed,
+    };
+    let valuation_mean = text("valuation_mean")
+        .map(|mean| {
+            mean.parse()
+                .map_err(|_| "invalid valuation_mean".to_string())
+        })
+        .transpose()?;
+    Ok(Record {
+        value,
+        row,
+        valuation_mean,
+        source,
+    })
+}
+
+// Fields of a CSV line; quoted fields may contain commas and doubled quotes
+fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        let field = fields.last_mut().expect("there is always a field");
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            c => field.push(c),
+        }
+    }
+    fields
+}
+
+// Members of a flat JSON object as written by `--stats-format json` or the history catalog:
+// strings, numbers and null. None if the line is not such an object.
+pub fn json_object(line: &str) -> Option<HashMap<String, Option<String>>> {
+    let mut chars = line
+        .trim()
+        .strip_prefix('{')?
+        .strip_suffix('}')?
+        .chars()
+        .peekable();
+    let mut members = HashMap::new();
+    let skip_spaces = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+    };
+    loop {
+        skip_spaces(&mut chars);
+        if chars.peek().is_none() {
+            return Some(members);
+        }
+        let name = json_string(&mut chars)?;
+        skip_spaces(&mut chars);
+        if chars.next()? != ':' {
+            return None;
+        }
+        skip_spaces(&mut chars);
+        let value = if chars.peek() == Some(&'"') {
+            Some(json_string(&mut chars)?)
+        } else {
+            let mut token = String::new();
+            while let Some(c) = chars.next_if(|c| *c != ',' && !c.is_whitespace()) {
+                token.push(c);
+            }
+            (token != "null").then_some(token)
+        };
+        members.insert(name, value);
+        skip_spaces(&mut chars);
+        match chars.next() {
+            Some(',') => {}
+            None => return Some(members),
+            Some(_) => return None,
+        }
+    }
+}
+
+// A JSON string starting at the opening quote, with its escapes resolved
+fn json_string(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<String> {
+    if chars.next()? != '"' {
+        return None;
+    }
+    let mut text = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(text),
+            '\\' => match chars.next()? {
+                'n' => text.push('\n'),
+                'r' => text.push('\r'),
+                't' => text.push('\t'),
+                'u' => {
+                    let code: String = (0..4).filter_map(|_| chars.next()).collect();
+                    text.push(char::from_u32(u32::from_str_radix(&code, 16).ok()?)?);
+                }
+                c => text.push(c),
+            },
+            c => text.push(c),
+        }
+    }
+}
+
+// Print the contiguous ranges of the seeds and the gaps between them
+fn print_coverage<'a>(seeds: impl Iterator<Item = &'a BigInt>) {
+    let mut covered: Vec<Range> = Vec::new();
+    for seed in seeds {
+        match covered.last_mut() {
+            Some(last) if last.end == *seed => last.end += 1,
+            _ => covered.push(Range {
+                start: seed.clone(),
+                end: seed + BigInt::one(),
+            }),
+        }
+    }
+    let (Some(first), Some(last)) = (covered.first(), covered.last()) else {
+        println!("coverage: no seeds");
+        return;
+    };
+    println!(
+        "coverage: {}..{} in {} contiguous ranges",
+        first.start,
+        last.end,
+        covered.len()
+    );
+    let gaps: Vec<Range> = covered
+        .windows(2)
+        .map(|pair| Range {
+            start: pair[0].end.clone(),
+            end: pair[1].start.clone(),
+        })
+        .collect();
+    if gaps.is_empty() {
+        return;
+    }
+    println!("gaps:");
+    for gap in gaps.iter().take(GAP_EXAMPLES) {
+        println!("  {} ({} seeds)", gap, &gap.end - &gap.start);
+    }
+    if gaps.len() > GAP_EXAMPLES {
+        println!("  ... and {} more", gaps.len() - GAP_EXAMPLES);
+    }
+}
+
+// Write the combined rows, in the format given by the extension of `path`
+fn write<'a>(
+    path: &Path,
+    records: impl Iterator<Item = &'a Record>,
+    force: bool,
+) -> Result<(), String> {
+    let write_error = |err: io::Error| format!("Failed to write {}: {}", path.display(), err);
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let stats = |record: &'a Record| SequenceStats {
+        valuation_mean: record.valuation_mean,
+        ..SequenceStats::new(&record.row, record.row.max_value.to_string())
+    };
+    if extension == "parquet" {
+        // checks that the file may be written
+        drop(output::create(path, force)?);
+        let mut parquet = ParquetWriter::create(path)?;
+        for record in records {
+            parquet.write_stats(&stats(record)).map_err(write_error)?;
+        }
+        return parquet.finish().map_err(write_error);
+    }
+    let format = match extension.as_str() {
+        "json" | "jsonl" | "ndjson" => StatsFormat::Json,
+        "md" | "markdown" => StatsFormat::Markdown,
+        "html" | "htm" => StatsFormat::Html,
+        _ => StatsFormat::Csv,
+    };
+    let mut output = BufWriter::new(output::create(path, force)?);
+    batch::write_header(&mut output, format).map_err(write_error)?;
+    for record in records {
+        batch::write_stats(&mut output, &stats(record), format).map_err(write_error)?;
+    }
+    batch::write_footer(&mut output, format).map_err(write_error)?;
+    output.flush().map_err(write_error)
+}
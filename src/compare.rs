@@ -0,0 +1,106 @@
+use crate::format::NumberFormat;
+use collatz_bigint_file::sequence::{Limits, Map};
+use collatz_bigint_file::stats::{trajectory, Row};
+use colored::Colorize;
+
+/// Computes the trajectories of several seeds and prints their statistics side by side.
+///
+/// The largest value of each column is highlighted. Trajectories stopped by `--max-steps`,
+/// `--timeout` or `--max-value-size` are marked with `*`, their numbers are lower bounds.
+pub fn run(seeds: &[String], limits: &Limits, number_format: &NumberFormat) -> Result<(), String> {
+    let max_bits = limits.max_bits.unwrap_or(u64::MAX);
+    let mut rows = Vec::with_capacity(seeds.len());
+    for seed in seeds {
+        let start = crate::parse_input(seed.clone(), max_bits)
+            .map_err(|err| format!("{}: {}", seed, err))?;
+        rows.push(trajectory(seed.clone(), &start, limits, Map::Standard));
+    }
+
+    // cells as text, the winner of each metric column is highlighted
+    let header = [
+        "seed",
+        "total stopping time",
+        "glide",
+        "max value",
+        "max pos",
+    ];
+    let cells: Vec<[String; 5]> = rows
+        .iter()
+        .map(|row| {
+            let mark = if row.truncated.is_some() { "*" } else { "" };
+            [
+                row.seed.clone(),
+                format!("{}{}", row.stopping_time, mark),
+                match row.glide {
+                    Some(glide) => glide.to_string(),
+                    None => format!("-{}", mark),
+                },
+                number_format.format(&row.max_value),
+                row.max_index.to_string(),
+            ]
+        })
+        .collect();
+    let winners = [
+        None,
+        best(&rows, |a, b| a.stopping_time.cmp(&b.stopping_time)),
+        best(&rows, |a, b| a.glide.cmp(&b.glide)),
+        best(&rows, |a, b| a.max_value.cmp(&b.max_value)),
+        best(&rows, |a, b| a.max_index.cmp(&b.max_index)),
+    ];
+
+    let mut widths = header.map(str::len);
+    for row in &cells {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let line: Vec<String> = header
+        .iter()
+        .zip(widths)
+        .map(|(title, width)| format!("{:<width$}", title, width = width))
+        .collect();
+    println!("{}", line.join("  ").bold());
+    for (i, row) in cells.iter().enumerate() {
+        let line: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .zip(winners)
+            .enumerate()
+            .map(|(column, ((cell, width), winner))| {
+                let padded = if column == 0 {
+                    format!("{:<width$}", cell, width = width)
+                } else {
+                    format!("{:>width$}", cell, width = width)
+                };
+                if winner == Some(i) && rows.len() > 1 {
+                    padded.green().bold().to_string()
+                } else {
+                    padded
+                }
+            })
+            .collect();
+        println!("{}", line.join("  "));
+    }
+
+    for row in rows.iter().filter(|row| row.truncated.is_some()) {
+        if let Some(truncation) = row.truncated {
+            println!(
+                "* {}: {} after {} steps",
+                row.seed, truncation, row.stopping_time
+            );
+        }
+    }
+    Ok(())
+}
+
+// Index of the row with the largest value of a metric (the first one on ties)
+fn best(rows: &[Row], compare: impl Fn(&Row, &Row) -> std::cmp::Ordering) -> Option<usize> {
+    (0..rows.len()).reduce(|best, i| {
+        if compare(&rows[i], &rows[best]).is_gt() {
+            i
+        } else {
+            best
+        }
+    })
+}
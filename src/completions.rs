@@ -0,0 +1,388 @@
+use clap::builder::PossibleValue;
+use clap::{Arg, ArgAction, Command, ValueEnum, ValueHint};
+use std::fmt::Write;
+
+// Shells supported by the `completions` subcommand
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+// Options of a command as shown to the user, without the positional arguments
+fn options(command: &Command) -> impl Iterator<Item = &Arg> {
+    command
+        .get_arguments()
+        .filter(|arg| !arg.is_positional() && !arg.is_hide_set())
+}
+
+fn takes_value(arg: &Arg) -> bool {
+    matches!(arg.get_action(), ArgAction::Set | ArgAction::Append)
+}
+
+// Options taking a path complete file names, other free-form values complete nothing
+fn takes_path(arg: &Arg) -> bool {
+    matches!(
+        arg.get_value_hint(),
+        ValueHint::AnyPath | ValueHint::FilePath | ValueHint::DirPath
+    )
+}
+
+fn possible_values(arg: &Arg) -> Vec<PossibleValue> {
+    arg.get_possible_values()
+        .into_iter()
+        .filter(|value| !value.is_hide_set())
+        .collect()
+}
+
+fn help(arg: &Arg) -> String {
+    arg.get_help()
+        .map(|help| help.to_string())
+        .unwrap_or_default()
+}
+
+// All spellings of an option, e.g. ["-v", "--verbose"]
+fn flags(arg: &Arg) -> Vec<String> {
+    let mut flags = Vec::new();
+    if let Some(short) = arg.get_short() {
+        flags.push(format!("-{}", short));
+    }
+    if let Some(long) = arg.get_long() {
+        flags.push(format!("--{}", long));
+    }
+    flags
+}
+
+/// Renders a completion script for `shell` from the clap definition of the command line.
+pub fn generate(command: &mut Command, shell: Shell) -> String {
+    command.build();
+    match shell {
+        Shell::Bash => bash(command),
+        Shell::Zsh => zsh(command),
+        Shell::Fish => fish(command),
+    }
+}
+
+fn bash(command: &Command) -> String {
+    let name = command.get_name();
+    let function = format!("_{}", name.replace('-', "_"));
+    let mut script = String::new();
+    writeln!(script, "{}() {{", function).unwrap();
+    script.push_str("    local cur prev subcommand i\n");
+    script.push_str("    cur=\"${COMP_WORDS[COMP_CWORD]}\"\n");
+    script.push_str("    prev=\"${COMP_WORDS[COMP_CWORD-1]}\"\n");
+    script.push_str("    subcommand=\"\"\n");
+    script.push_str("    for ((i = 1; i < COMP_CWORD; i++)); do\n");
+    script.push_str("        case \"${COMP_WORDS[i]}\" in\n");
+    let names: Vec<&str> = command.get_subcommands().map(Command::get_name).collect();
+    writeln!(
+        script,
+        "            {}) subcommand=\"${{COMP_WORDS[i]}}\"; break ;;",
+        names.join("|")
+    )
+    .unwrap();
+    script.push_str("        esac\n    done\n\n");
+
+    // values of options with a fixed set of choices, files for everything else
+    script.push_str("    case \"$prev\" in\n");
+    for arg in options(command).chain(command.get_subcommands().flat_map(options)) {
+        if !takes_value(arg) {
+            continue;
+        }
+        let values = possible_values(arg);
+        let words = if takes_path(arg) {
+            "-f -- \"$cur\"".to_string()
+        } else if values.is_empty() {
+            "-W \"\" -- \"$cur\"".to_string()
+        } else {
+            let names: Vec<&str> = values.iter().map(PossibleValue::get_name).collect();
+            format!("-W \"{}\" -- \"$cur\"", names.join(" "))
+        };
+        writeln!(
+            script,
+            "        {}) COMPREPLY=($(compgen {})); return ;;",
+            flags(arg).join("|"),
+            words
+        )
+        .unwrap();
+    }
+    script.push_str("    esac\n\n");
+
+    script.push_str("    case \"$subcommand\" in\n");
+    for subcommand in command.get_subcommands() {
+        let words: Vec<String> = options(subcommand).flat_map(flags).collect();
+        writeln!(
+            script,
+            "        {}) COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")) ;;",
+            subcommand.get_name(),
+            words.join(" ")
+        )
+        .unwrap();
+    }
+    let mut words: Vec<String> = options(command).flat_map(flags).collect();
+    words.extend(names.iter().map(|name| name.to_string()));
+    writeln!(
+        script,
+        "        *) COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")) ;;",
+        words.join(" ")
+    )
+    .unwrap();
+    script.push_str("    esac\n}\n\n");
+    writeln!(script, "complete -F {} -o default {}", function, name).unwrap();
+    script
+}
+
+// Escape text for a single-quoted zsh string inside an _arguments spec
+fn zsh_escape(text: &str) -> String {
+    text.replace('\'', "'\\''")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+        .replace(':', "\\:")
+}
+
+fn zsh_specs(command: &Command) -> Vec<String> {
+    let mut specs = Vec::new();
+    for arg in options(command) {
+        let action = if !takes_value(arg) {
+            String::new()
+        } else {
+            let values = possible_values(arg);
+            let value_name = arg
+                .get_value_names()
+                .and_then(|names| names.first())
+                .map_or_else(|| arg.get_id().to_string(), |name| name.to_string());
+            if takes_path(arg) {
+                format!(":{}:_files", zsh_escape(&value_name))
+            } else if values.is_empty() {
+                format!(":{}: ", zsh_escape(&value_name))
+            } else {
+                let names: Vec<&str> = values.iter().map(PossibleValue::get_name).collect();
+                format!(":{}:({})", zsh_escape(&value_name), names.join(" "))
+            }
+        };
+        let description = zsh_escape(&help(arg));
+        let flags = flags(arg);
+        if flags.len() > 1 {
+            specs.push(format!(
+                "'({})'{{{}}}'[{}]{}'",
+                flags.join(" "),
+                flags.join(","),
+                description,
+                action
+            ));
+        } else {
+            specs.push(format!("'{}[{}]{}'", flags[0], description, action));
+        }
+    }
+    specs
+}
+
+fn zsh(command: &Command) -> String {
+    let name = command.get_name();
+    let mut script = format!("#compdef {}\n\n_{}() {{\n", name, name);
+    script.push_str("    local line state\n    _arguments -C \\\n");
+    for spec in zsh_specs(command) {
+        writeln!(script, "        {} \\", spec).unwrap();
+    }
+    script.push_str("        '1: :->subcommand' \\\n        '*:: :->args'\n\n");
+    script.push_str("    case $state in\n    subcommand)\n        local -a subcommands\n        subcommands=(\n");
+    for subcommand in command.get_subcommands() {
+        let about = subcommand
+            .get_about()
+            .map(|about| about.to_string())
+            .unwrap_or_default();
+        writeln!(
+            script,
+            "            '{}:{}'",
+            subcommand.get_name(),
+            zsh_escape(&about)
+        )
+        .unwrap();
+    }
+    script.push_str("        )\n        _describe 'subcommand' subcommands\n        ;;\n    args)\n        case $line[1] in\n");
+    for subcommand in command.get_subcommands() {
+        let mut specs = zsh_specs(subcommand);
+        specs.push("'*:seed or file:_files'".to_string());
+        writeln!(
+            script,
+            "        {}) _arguments {} ;;",
+            subcommand.get_name(),
+            specs.join(" ")
+        )
+        .unwrap();
+    }
+    script.push_str("        esac\n        ;;\n    esac\n}\n\n");
+    writeln!(script, "_{} \"$@\"", name).unwrap();
+    script
+}
+
+fn fish_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\'', "\\'")
+}
+
+fn fish(command: &Command) -> String {
+    let name = command.get_name();
+    let mut script = String::new();
+    let mut complete = |condition: &str, arg: &Arg| {
+        let mut line = format!("complete -c {} -n '{}'", name, condition);
+        if let Some(short) = arg.get_short() {
+            write!(line, " -s {}", short).unwrap();
+        }
+        if let Some(long) = arg.get_long() {
+            write!(line, " -l {}", long).unwrap();
+        }
+        if takes_value(arg) {
+            let values = possible_values(arg);
+            if takes_path(arg) {
+                line.push_str(" -r -F");
+            } else if values.is_empty() {
+                line.push_str(" -x");
+            } else {
+                let names: Vec<&str> = values.iter().map(PossibleValue::get_name).collect();
+                write!(line, " -x -a '{}'", names.join(" ")).unwrap();
+            }
+        }
+        write!(line, " -d '{}'", fish_escape(&help(arg))).unwrap();
+        writeln!(script, "{}", line).unwrap();
+    };
+    for arg in options(command) {
+        complete("__fish_use_subcommand", arg);
+    }
+    for subcommand in command.get_subcommands() {
+        let condition = format!("__fish_seen_subcommand_from {}", subcommand.get_name());
+        for arg in options(subcommand) {
+            complete(&condition, arg);
+        }
+    }
+    for subcommand in command.get_subcommands() {
+        let about = subcommand
+            .get_about()
+            .map(|about| about.to_string())
+            .unwrap_or_default();
+        writeln!(
+            script,
+            "complete -c {} -n '__fish_use_subcommand' -f -a {} -d '{}'",
+            name,
+            subcommand.get_name(),
+            fish_escape(&about)
+        )
+        .unwrap();
+    }
+    script
+}
+
+// Escape text for roff: backslashes and leading dots or quotes
+fn roff_escape(text: &str) -> String {
+    let escaped = text.replace('\\', "\\e").replace('-', "\\-");
+    match escaped.chars().next() {
+        Some('.') | Some('\'') => format!("\\&{}", escaped),
+        _ => escaped,
+    }
+}
+
+fn roff_option(page: &mut String, arg: &Arg) {
+    page.push_str(".TP\n");
+    let mut flags: Vec<String> = flags(arg)
+        .iter()
+        .map(|flag| format!("\\fB{}\\fR", roff_escape(flag)))
+        .collect();
+    if takes_value(arg) {
+        let value_name = arg
+            .get_value_names()
+            .and_then(|names| names.first())
+            .map_or_else(
+                || arg.get_id().to_string().to_uppercase(),
+                |name| name.to_string(),
+            );
+        if let Some(last) = flags.last_mut() {
+            write!(last, " \\fI{}\\fR", roff_escape(&value_name)).unwrap();
+        }
+    }
+    writeln!(page, "{}", flags.join(", ")).unwrap();
+    writeln!(page, "{}", roff_escape(&help(arg))).unwrap();
+    let values = possible_values(arg);
+    if !values.is_empty() {
+        let names: Vec<String> = values
+            .iter()
+            .map(|value| roff_escape(value.get_name()))
+            .collect();
+        writeln!(page, "Possible values: {}.", names.join(", ")).unwrap();
+    }
+    if let Some(env) = arg.get_env() {
+        writeln!(
+            page,
+            "Environment variable: \\fB{}\\fR.",
+            roff_escape(&env.to_string_lossy())
+        )
+        .unwrap();
+    }
+}
+
+/// Renders a man page (roff) from the clap definition of the command line.
+pub fn manpage(command: &mut Command) -> String {
+    command.build();
+    let name = command.get_name().to_string();
+    let version = command.get_version().unwrap_or_default().to_string();
+    let about = command
+        .get_about()
+        .map(|about| about.to_string())
+        .unwrap_or_default();
+
+    let mut page = String::new();
+    writeln!(
+        page,
+        ".TH {} 1 \"\" \"{} {}\" \"User Commands\"",
+        roff_escape(&name.to_uppercase()),
+        roff_escape(&name),
+        version
+    )
+    .unwrap();
+    page.push_str(".SH NAME\n");
+    writeln!(page, "{} \\- {}", roff_escape(&name), roff_escape(&about)).unwrap();
+    page.push_str(".SH SYNOPSIS\n");
+    writeln!(
+        page,
+        "\\fB{}\\fR [\\fIOPTIONS\\fR] [\\fICOMMAND\\fR]",
+        roff_escape(&name)
+    )
+    .unwrap();
+    if let Some(long_about) = command.get_long_about() {
+        page.push_str(".SH DESCRIPTION\n");
+        for paragraph in long_about.to_string().split("\n\n") {
+            writeln!(page, "{}\n.PP", roff_escape(paragraph.trim())).unwrap();
+        }
+    }
+    page.push_str(".SH OPTIONS\n");
+    for arg in options(command) {
+        roff_option(&mut page, arg);
+    }
+    page.push_str(".SH COMMANDS\n");
+    for subcommand in command.get_subcommands() {
+        let arguments: Vec<String> = subcommand
+            .get_arguments()
+            .filter(|arg| arg.is_positional())
+            .map(|arg| format!("\\fI{}\\fR", arg.get_id().to_string().to_uppercase()))
+            .collect();
+        writeln!(
+            page,
+            ".TP\n\\fB{}\\fR {}",
+            roff_escape(subcommand.get_name()),
+            arguments.join(" ")
+        )
+        .unwrap();
+        if let Some(about) = subcommand.get_about() {
+            writeln!(page, "{}", roff_escape(&about.to_string())).unwrap();
+        }
+        for arg in options(subcommand) {
+            page.push_str(".RS\n");
+            roff_option(&mut page, arg);
+            page.push_str(".RE\n");
+        }
+    }
+    page.push_str(".SH FILES\n.TP\n\\fI~/.config/collatz/config.toml\\fR\n");
+    page.push_str(
+        "Defaults for the options (also \\fI$XDG_CONFIG_HOME/collatz/config.toml\\fR).\n",
+    );
+    page
+}
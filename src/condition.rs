@@ -0,0 +1,502 @@
+//! Stop conditions of `--stop-when`: expressions like `n < seed || step > 10^7 || bits > 100000`,
+//! parsed once and evaluated after every step of a trajectory.
+
+use num_bigint::BigInt;
+use num_traits::{Pow, ToPrimitive, Zero};
+use std::borrow::Cow;
+use std::fmt;
+
+// Bit length of a power at most, checked before it is computed: a number raised at parse time
+// above it is an error, a value of the trajectory raised above it fails the comparison
+const MAX_POWER_BITS: u64 = 1 << 24;
+
+/// Variables a [`Condition`] can refer to, the state of the trajectory after a step.
+pub struct Vars<'a> {
+    /// `n`, the current value
+    pub n: &'a BigInt,
+    /// `seed`, the start value
+    pub seed: &'a BigInt,
+    /// `step`, the steps so far
+    pub step: usize,
+    /// `max`, the largest value so far, the seed included
+    pub max: &'a BigInt,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Var {
+    N,
+    Seed,
+    Step,
+    Bits,
+    Max,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Cmp {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+// Arithmetic on integers; powers are only allowed with a constant exponent
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Term {
+    Number(BigInt),
+    Var(Var),
+    Binary(Op, Box<Term>, Box<Term>),
+    Pow(Box<Term>, u32),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Cmp, Term, Term),
+}
+
+/// A parsed stop condition, true when the computation should stop.
+///
+/// Conditions compare integer terms with `<`, `<=`, `>`, `>=`, `==` and `!=` and combine the
+/// comparisons with `&&`, `||`, `!` and parentheses. Terms are numbers and the variables `n`,
+/// `seed`, `step`, `bits` (the bit length of `n`) and `max`, joined by `+`, `-`, `*`, `/`
+/// (rounding towards zero), `%` and `^` with a number as exponent, e.g. `n % 3^5 == 0`. A
+/// comparison with a division by zero, or a power of more than 2^24 bits, is false.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Condition {
+    expr: Expr,
+    // as given, for the plan and the log
+    text: String,
+}
+
+impl Condition {
+    /// Whether the condition holds for the trajectory at `vars`.
+    pub fn holds(&self, vars: &Vars) -> bool {
+        holds(&self.expr, vars)
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+impl std::str::FromStr for Condition {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(input).map_err(|err| invalid(input, &err))?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.or().map_err(|err| invalid(input, &err))?;
+        if parser.pos < parser.tokens.len() {
+            return Err(invalid(
+                input,
+                &format!("unexpected '{}'", parser.tokens[parser.pos]),
+            ));
+        }
+        Ok(Condition {
+            expr,
+            text: input.trim().to_string(),
+        })
+    }
+}
+
+fn invalid(input: &str, reason: &str) -> String {
+    format!("Invalid stop condition '{}': {}", input.trim(), reason)
+}
+
+fn holds(expr: &Expr, vars: &Vars) -> bool {
+    match expr {
+        Expr::Or(a, b) => holds(a, vars) || holds(b, vars),
+        Expr::And(a, b) => holds(a, vars) && holds(b, vars),
+        Expr::Not(a) => !holds(a, vars),
+        Expr::Compare(cmp, a, b) => {
+            let (Some(a), Some(b)) = (eval(a, vars), eval(b, vars)) else {
+                return false;
+            };
+            match cmp {
+                Cmp::Lt => a < b,
+                Cmp::Le => a <= b,
+                Cmp::Gt => a > b,
+                Cmp::Ge => a >= b,
+                Cmp::Eq => a == b,
+                Cmp::Ne => a != b,
+            }
+        }
+    }
+}
+
+// Value of a term, borrowed where it is a number or a value of the trajectory; None after a
+// division by zero or for a power above MAX_POWER_BITS
+fn eval<'a>(term: &'a Term, vars: &Vars<'a>) -> Option<Cow<'a, BigInt>> {
+    Some(match term {
+        Term::Number(number) => Cow::Borrowed(number),
+        Term::Var(Var::N) => Cow::Borrowed(vars.n),
+        Term::Var(Var::Seed) => Cow::Borrowed(vars.seed),
+        Term::Var(Var::Max) => Cow::Borrowed(vars.max),
+        Term::Var(Var::Step) => Cow::Owned(BigInt::from(vars.step)),
+        Term::Var(Var::Bits) => Cow::Owned(BigInt::from(vars.n.bits())),
+        Term::Pow(base, exponent) => {
+            let base = eval(base, vars)?;
+            if power_bits(&base, *exponent) > MAX_POWER_BITS as f64 {
+                return None;
+            }
+            Cow::Owned(Pow::pow(base.as_ref(), *exponent))
+        }
+        Term::Binary(op, a, b) => {
+            let (a, b) = (eval(a, vars)?, eval(b, vars)?);
+            let (a, b) = (a.as_ref(), b.as_ref());
+            Cow::Owned(match op {
+                Op::Add => a + b,
+                Op::Sub => a - b,
+                Op::Mul => a * b,
+                Op::Div if b.is_zero() => return None,
+                Op::Div => a / b,
+                Op::Rem if b.is_zero() => return None,
+                Op::Rem => a % b,
+            })
+        }
+    })
+}
+
+// Bit length of base^exponent, from the leading 64 bits of the base
+fn power_bits(base: &BigInt, exponent: u32) -> f64 {
+    let shift = base.bits().saturating_sub(64);
+    let leading = (base.magnitude() >> shift).to_f64().unwrap_or(0.0);
+    f64::from(exponent) * (leading.log2() + shift as f64)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Number(BigInt),
+    Name(String),
+    // operators and parentheses
+    Symbol(&'static str),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Number(number) => write!(f, "{}", number),
+            Token::Name(name) => write!(f, "{}", name),
+            Token::Symbol(symbol) => write!(f, "{}", symbol),
+        }
+    }
+}
+
+// Longest first, so "<=" is not read as "<" and "="
+const SYMBOLS: [&str; 17] = [
+    "||", "&&", "<=", ">=", "==", "!=", "<", ">", "!", "(", ")", "+", "-", "*", "/", "%", "^",
+];
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = input.trim_start();
+    while let Some(c) = rest.chars().next() {
+        let len = if c.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            tokens.push(Token::Number(rest[..len].parse().expect("ASCII digits")));
+            len
+        } else if c.is_ascii_alphabetic() {
+            let len = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            tokens.push(Token::Name(rest[..len].to_string()));
+            len
+        } else if let Some(symbol) = SYMBOLS.iter().find(|symbol| rest.starts_with(**symbol)) {
+            tokens.push(Token::Symbol(symbol));
+            symbol.len()
+        } else {
+            return Err(format!("unexpected '{}'", c));
+        };
+        rest = rest[len..].trim_start();
+    }
+    Ok(tokens)
+}
+
+// Recursive descent over the tokens, from the loosest operator to the tightest:
+// || && ! comparisons + - * / % ^
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    // Consume the symbol if it comes next
+    fn eat(&mut self, symbol: &str) -> bool {
+        let next = matches!(self.peek(), Some(Token::Symbol(next)) if *next == symbol);
+        if next {
+            self.pos += 1;
+        }
+        next
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), String> {
+        if self.eat(symbol) {
+            Ok(())
+        } else {
+            Err(match self.peek() {
+                Some(token) => format!("expected '{}' instead of '{}'", symbol, token),
+                None => format!("expected '{}' at the end", symbol),
+            })
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.eat("||") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.not()?;
+        while self.eat("&&") {
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        // a parenthesis opens either a condition, `(n < 10 || step > 5)`, or a term,
+        // `(n + 1) / 2 > 10`; the condition is tried first
+        let start = self.pos;
+        if self.eat("(") {
+            if let Ok(expr) = self.or() {
+                if self.eat(")") {
+                    return Ok(expr);
+                }
+            }
+            self.pos = start;
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let a = self.sum()?;
+        let cmp = match self.peek() {
+            Some(Token::Symbol("<")) => Cmp::Lt,
+            Some(Token::Symbol("<=")) => Cmp::Le,
+            Some(Token::Symbol(">")) => Cmp::Gt,
+            Some(Token::Symbol(">=")) => Cmp::Ge,
+            Some(Token::Symbol("==")) => Cmp::Eq,
+            Some(Token::Symbol("!=")) => Cmp::Ne,
+            Some(token) => return Err(format!("expected a comparison instead of '{}'", token)),
+            None => return Err("expected a comparison like 'n < seed' at the end".to_string()),
+        };
+        self.pos += 1;
+        Ok(Expr::Compare(cmp, a, self.sum()?))
+    }
+
+    fn sum(&mut self) -> Result<Term, String> {
+        let mut term = self.product()?;
+        loop {
+            let op = if self.eat("+") {
+                Op::Add
+            } else if self.eat("-") {
+                Op::Sub
+            } else {
+                return Ok(term);
+            };
+            term = Term::Binary(op, Box::new(term), Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Result<Term, String> {
+        let mut term = self.power()?;
+        loop {
+            let op = if self.eat("*") {
+                Op::Mul
+            } else if self.eat("/") {
+                Op::Div
+            } else if self.eat("%") {
+                Op::Rem
+            } else {
+                return Ok(term);
+            };
+            term = Term::Binary(op, Box::new(term), Box::new(self.power()?));
+        }
+    }
+
+    // Right associative like the seeds, `2^2^4` is 2^16; the exponent must be a number
+    fn power(&mut self) -> Result<Term, String> {
+        let base = self.atom()?;
+        if !self.eat("^") {
+            return Ok(base);
+        }
+        let exponent = match self.power()? {
+            Term::Number(exponent) => u32::try_from(exponent)
+                .map_err(|_| "the exponent does not fit 32 bits".to_string())?,
+            _ => return Err("the exponent of '^' must be a number".to_string()),
+        };
+        Ok(match base {
+            Term::Number(base) => {
+                let bits = power_bits(&base, exponent);
+                if bits > MAX_POWER_BITS as f64 {
+                    return Err(format!(
+                        "{}^{} has about {} bits, more than the {} a power may have",
+                        base, exponent, bits as u64, MAX_POWER_BITS
+                    ));
+                }
+                Term::Number(Pow::pow(base, exponent))
+            }
+            base => Term::Pow(Box::new(base), exponent),
+        })
+    }
+
+    fn atom(&mut self) -> Result<Term, String> {
+        let term = match self.peek().cloned() {
+            Some(Token::Number(number)) => Term::Number(number),
+            Some(Token::Name(name)) => Term::Var(match name.as_str() {
+                "n" => Var::N,
+                "seed" => Var::Seed,
+                "step" => Var::Step,
+                "bits" => Var::Bits,
+                "max" => Var::Max,
+                _ => {
+                    return Err(format!(
+                        "unknown variable '{}', use n, seed, step, bits or max",
+                        name
+                    ))
+                }
+            }),
+            Some(Token::Symbol("(")) => {
+                self.pos += 1;
+                let term = self.sum()?;
+                self.expect(")")?;
+                return Ok(term);
+            }
+            Some(token) => {
+                return Err(format!(
+                    "expected a number or variable instead of '{}'",
+                    token
+                ))
+            }
+            None => return Err("expected a number or variable at the end".to_string()),
+        };
+        self.pos += 1;
+        Ok(term)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Whether `condition` holds at value `n` and `step` of the trajectory of 27
+    fn holds_at(condition: &str, n: u64, step: usize) -> bool {
+        let condition: Condition = condition.parse().expect("valid condition");
+        let (n, seed) = (BigInt::from(n), BigInt::from(27));
+        condition.holds(&Vars {
+            n: &n,
+            seed: &seed,
+            step,
+            max: &BigInt::from(9232),
+        })
+    }
+
+    #[test]
+    fn arithmetic_binds_by_precedence() {
+        assert!(holds_at("n + 2 * 3 == 7", 1, 0));
+        assert!(holds_at("2 + 3 * 4 ^ 2 == 50", 1, 0));
+        assert!(holds_at("n - 2 - 3 == 0", 5, 0));
+        assert!(holds_at("n / 2 * 2 == 6", 7, 0));
+        assert!(holds_at("n % 4 * 2 == 6", 7, 0));
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or_and_not_tighter_than_and() {
+        // n < 10 || (step > 5 && step < 3)
+        assert!(holds_at("n < 10 || step > 5 && step < 3", 5, 7));
+        assert!(!holds_at("n < 10 || step > 5 && step < 3", 20, 7));
+        // (!(n < 10)) && step > 5
+        assert!(holds_at("!n < 10 && step > 5", 20, 7));
+        assert!(!holds_at("!n < 10 && step > 5", 5, 7));
+    }
+
+    #[test]
+    fn parentheses_open_a_term_or_a_condition() {
+        assert!(holds_at("(n + 1) / 2 > 10", 21, 0));
+        assert!(!holds_at("(n + 1) / 2 > 10", 19, 0));
+        assert!(holds_at("(n < 10 || step > 5)", 20, 6));
+        assert!(!holds_at("(n < 10 || step > 5)", 20, 3));
+        assert!(holds_at("!(n < 10 || step > 5) && (n - 1) % 3 == 1", 20, 3));
+        assert!(holds_at("((n + 1)) * (2) == 42 && ((step > 1))", 20, 3));
+        assert!(holds_at("(n) < seed", 20, 0));
+    }
+
+    #[test]
+    fn powers_are_right_associative() {
+        assert!(holds_at("2^3^2 == 512", 1, 0));
+        assert!(holds_at("n^2^2 == 81", 3, 0));
+        assert!(holds_at("bits == 2^2 + 1", 16, 0));
+        assert_eq!(
+            "n^2^3 > 1"
+                .parse::<Condition>()
+                .map(|condition| condition.expr),
+            Ok(Expr::Compare(
+                Cmp::Gt,
+                Term::Pow(Box::new(Term::Var(Var::N)), 8),
+                Term::Number(BigInt::from(1))
+            ))
+        );
+    }
+
+    #[test]
+    fn division_by_zero_fails_the_comparison() {
+        assert!(!holds_at("n / 0 > 0", 5, 0));
+        assert!(!holds_at("n / 0 <= 0", 5, 0));
+        assert!(!holds_at("n % (step - step) == 0", 5, 3));
+        assert!(holds_at("n / 0 > 0 || n == 5", 5, 0));
+        assert!(holds_at("!(n / 0 > 0)", 5, 0));
+    }
+
+    #[test]
+    fn powers_beyond_the_limit_are_refused_before_they_are_computed() {
+        assert!("step > 10^4000000000".parse::<Condition>().is_err());
+        assert!("step > 2^2^31".parse::<Condition>().is_err());
+        assert!("step > 2^(2^24 + 1)".parse::<Condition>().is_err());
+        assert!("step < 2^2^24".parse::<Condition>().is_ok());
+        assert!(!holds_at("n^4000000000 > 1", 3, 0));
+        assert!(holds_at("n^4000000000 == 1", 1, 0));
+    }
+
+    #[test]
+    fn invalid_conditions_are_rejected() {
+        for condition in [
+            "",
+            "n <",
+            "n < 10 ||",
+            "(n < 10",
+            "n < 10)",
+            "n ^ n > 1",
+            "x > 1",
+            "n",
+        ] {
+            assert!(condition.parse::<Condition>().is_err(), "{}", condition);
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use crate::batch::{Normalize, StatsFormat};
+use crate::color::ColorMode;
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Defaults loaded from the config file. Every key is optional; command line flags and
+// COLLATZ_* environment variables take precedence over the values found here.
+//
+// Example `~/.config/collatz/config.toml`:
+//
+//     output_dir = "/data/collatz"
+//     output_name = "collatz_{seed_hash}_{timestamp}.txt"
+//     color_mode = "magnitude"
+//     print = "head:20,tail:20"
+//     group_digits = "_"
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub output_dir: Option<PathBuf>,
+    pub output_name: Option<String>,
+    pub sink: Option<String>,
+    pub odd_only: Option<bool>,
+    pub sample_every: Option<u64>,
+    pub annotate: Option<bool>,
+    pub radix: Option<u32>,
+    pub max_part_size: Option<String>,
+    pub max_disk: Option<String>,
+    pub buffer_size: Option<String>,
+    pub fast_io: Option<bool>,
+    pub force_backend: Option<String>,
+    pub auto: Option<bool>,
+    pub max_steps: Option<usize>,
+    pub timeout: Option<String>,
+    pub max_value_size: Option<String>,
+    pub until: Option<String>,
+    pub stop_when: Option<String>,
+    pub color_mode: Option<ColorMode>,
+    pub even_color: Option<String>,
+    pub odd_color: Option<String>,
+    pub print: Option<String>,
+    pub no_print: Option<bool>,
+    pub group_digits: Option<String>,
+    pub max_width: Option<usize>,
+    pub lang: Option<String>,
+    pub stats_format: Option<StatsFormat>,
+    pub stopping_time_definition: Option<String>,
+    pub store: Option<String>,
+    pub top: Option<usize>,
+    pub normalize: Option<Vec<Normalize>>,
+    pub peaks: Option<usize>,
+    pub notify_webhook: Option<String>,
+    pub notify_email: Option<String>,
+    pub history_file: Option<PathBuf>,
+    pub history: Option<bool>,
+}
+
+// Default location of the config file: $XDG_CONFIG_HOME/collatz/config.toml or ~/.config/collatz/config.toml
+pub fn default_config_path() -> Option<PathBuf> {
+    let config_home = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_home.join("collatz").join("config.toml"))
+}
+
+// Function to load the config file. An explicitly given file must exist, the default one is optional.
+pub fn load(explicit_path: Option<&Path>) -> Result<Config, String> {
+    let path = match explicit_path {
+        Some(path) => path.to_path_buf(),
+        None => match default_config_path() {
+            Some(path) if path.exists() => path,
+            _ => return Ok(Config::default()),
+        },
+    };
+    let content = fs::read_to_string(&path)
+        .map_err(|err| format!("Failed to read config file {}: {}", path.display(), err))?;
+    toml::from_str(&content)
+        .map_err(|err| format!("Invalid config file {}: {}", path.display(), err))
+}
@@ -0,0 +1,268 @@
+use crate::format::NumberFormat;
+use crate::metadata;
+use crate::parts;
+use crate::scan::{self, Line, Scanner};
+use num_bigint::BigInt;
+use std::io::{self, BufRead};
+use std::path::Path;
+
+// Statistics of one trajectory file, gathered while both files are read in lockstep
+#[derive(Default)]
+struct FileStats {
+    seed: Option<String>,
+    steps: usize,
+    even: usize,
+    odd: usize,
+    max_digits: Vec<u8>,
+    max_index: usize,
+    invalid: usize,
+}
+
+impl FileStats {
+    fn push(&mut self, line: &Line) {
+        self.steps += 1;
+        match line {
+            Line::Value(digits) => {
+                if scan::is_even(digits) {
+                    self.even += 1;
+                } else {
+                    self.odd += 1;
+                }
+                if scan::compare(digits, &self.max_digits).is_gt() {
+                    self.max_digits = digits.to_vec();
+                    self.max_index = self.steps;
+                }
+            }
+            _ => self.invalid += 1,
+        }
+    }
+}
+
+// A value as shown in the report, or the reason there is none
+fn describe(line: Option<&Line>, number_format: &NumberFormat) -> String {
+    match line {
+        Some(Line::Value(digits)) => number_format.format(&scan::to_bigint(digits)),
+        Some(Line::Invalid(err)) => format!("invalid line ({})", err),
+        Some(Line::Comment) | None => "end of file".to_string(),
+    }
+}
+
+fn same_value(a: &Line, b: &Line) -> bool {
+    matches!((a, b), (Line::Value(a), Line::Value(b)) if a == b)
+}
+
+fn open(path: &Path, buffer_size: usize) -> Result<Scanner<Box<dyn BufRead>>, String> {
+    parts::open(path, buffer_size)
+        .map(Scanner::new)
+        .map_err(|err| format!("Failed to open {}: {}", path.display(), err))
+}
+
+// Seed expression from the metadata header of a sequence file
+fn header_seed(path: &Path) -> Option<String> {
+    let reader = parts::open(path, 8 << 10).ok()?;
+    for line in reader.lines() {
+        let line = line.ok()?;
+        let (key, value) = metadata::parse_comment(&line)?;
+        if key == "seed" {
+            return Some(value.to_string());
+        }
+    }
+    None
+}
+
+/// Compares two sequence files (or manifests of split sequences).
+///
+/// Reports the first step where the trajectories differ and, aligning both files at their end,
+/// the step where they merge into a common tail, followed by the statistics of both files and
+/// their difference. Both files are read twice, in lockstep.
+pub fn run(
+    path_a: &Path,
+    path_b: &Path,
+    buffer_size: usize,
+    number_format: &NumberFormat,
+) -> Result<(), String> {
+    let read_error = |err: io::Error| format!("Failed to read: {}", err);
+    let mut stats_a = FileStats {
+        seed: header_seed(path_a),
+        ..FileStats::default()
+    };
+    let mut stats_b = FileStats {
+        seed: header_seed(path_b),
+        ..FileStats::default()
+    };
+
+    // first pass from the start: statistics and first divergence
+    let mut a = open(path_a, buffer_size)?;
+    let mut b = open(path_b, buffer_size)?;
+    let mut divergence = None;
+    loop {
+        let line_a = a.next_value().map_err(read_error)?;
+        let line_b = b.next_value().map_err(read_error)?;
+        if line_a.is_none() && line_b.is_none() {
+            break;
+        }
+        if let Some(line) = &line_a {
+            stats_a.push(line);
+        }
+        if let Some(line) = &line_b {
+            stats_b.push(line);
+        }
+        let equal = matches!((&line_a, &line_b), (Some(x), Some(y)) if same_value(x, y));
+        if divergence.is_none() && !equal {
+            divergence = Some((
+                stats_a.steps.max(stats_b.steps),
+                describe(line_a.as_ref(), number_format),
+                describe(line_b.as_ref(), number_format),
+            ));
+        }
+    }
+
+    // second pass aligned at the end: the merge point is right after the last mismatch
+    let (mut longer, mut shorter) = (open(path_a, buffer_size)?, open(path_b, buffer_size)?);
+    let a_is_longer = stats_a.steps >= stats_b.steps;
+    if !a_is_longer {
+        std::mem::swap(&mut longer, &mut shorter);
+    }
+    let offset = stats_a.steps.abs_diff(stats_b.steps);
+    for _ in 0..offset {
+        longer.next_value().map_err(read_error)?;
+    }
+    let mut last_mismatch = 0;
+    let mut merge_value = None;
+    let mut step = 0;
+    while let (Some(x), Some(y)) = (
+        longer.next_value().map_err(read_error)?,
+        shorter.next_value().map_err(read_error)?,
+    ) {
+        step += 1;
+        if !same_value(&x, &y) {
+            last_mismatch = step;
+            merge_value = None;
+        } else if merge_value.is_none() {
+            merge_value = Some(describe(Some(&x), number_format));
+        }
+    }
+
+    println!(
+        "a: {} (seed: {})",
+        path_a.display(),
+        stats_a.seed.as_deref().unwrap_or("unknown")
+    );
+    println!(
+        "b: {} (seed: {})",
+        path_b.display(),
+        stats_b.seed.as_deref().unwrap_or("unknown")
+    );
+    println!();
+    match divergence {
+        None => println!("identical trajectories ({} steps)", stats_a.steps),
+        Some((step, value_a, value_b)) => {
+            println!(
+                "first difference: step {} (a: {}, b: {})",
+                step, value_a, value_b
+            );
+            match merge_value {
+                Some(value) => {
+                    let (merge_a, merge_b) = if a_is_longer {
+                        (last_mismatch + 1 + offset, last_mismatch + 1)
+                    } else {
+                        (last_mismatch + 1, last_mismatch + 1 + offset)
+                    };
+                    println!(
+                        "merge: step {} in a, step {} in b at value {}",
+                        merge_a, merge_b, value
+                    );
+                }
+                None => println!("merge: the trajectories have no common tail"),
+            }
+        }
+    }
+
+    println!();
+    let max_a = scan::to_bigint(&stats_a.max_digits);
+    let max_b = scan::to_bigint(&stats_b.max_digits);
+    let rows = [
+        (
+            "total stopping time",
+            stats_a.steps.to_string(),
+            stats_b.steps.to_string(),
+            signed(stats_b.steps as i128 - stats_a.steps as i128),
+        ),
+        (
+            "even",
+            stats_a.even.to_string(),
+            stats_b.even.to_string(),
+            signed(stats_b.even as i128 - stats_a.even as i128),
+        ),
+        (
+            "odd",
+            stats_a.odd.to_string(),
+            stats_b.odd.to_string(),
+            signed(stats_b.odd as i128 - stats_a.odd as i128),
+        ),
+        (
+            "max pos",
+            stats_a.max_index.to_string(),
+            stats_b.max_index.to_string(),
+            signed(stats_b.max_index as i128 - stats_a.max_index as i128),
+        ),
+        (
+            "max value",
+            number_format.format(&max_a),
+            number_format.format(&max_b),
+            signed_value(&(&max_b - &max_a), number_format),
+        ),
+    ];
+    let width = |column: fn(&(&str, String, String, String)) -> usize| {
+        rows.iter().map(column).max().unwrap_or(0)
+    };
+    let widths = [
+        width(|row| row.0.len()),
+        width(|row| row.1.chars().count()).max(1),
+        width(|row| row.2.chars().count()).max(1),
+        width(|row| row.3.chars().count()).max(5),
+    ];
+    println!(
+        "{:<w0$}  {:>w1$}  {:>w2$}  {:>w3$}",
+        "",
+        "a",
+        "b",
+        "b - a",
+        w0 = widths[0],
+        w1 = widths[1],
+        w2 = widths[2],
+        w3 = widths[3]
+    );
+    for (name, value_a, value_b, delta) in &rows {
+        println!(
+            "{:<w0$}  {:>w1$}  {:>w2$}  {:>w3$}",
+            name,
+            value_a,
+            value_b,
+            delta,
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+            w3 = widths[3]
+        );
+    }
+    if stats_a.invalid + stats_b.invalid > 0 {
+        println!();
+        println!(
+            "invalid lines: {} in a, {} in b",
+            stats_a.invalid, stats_b.invalid
+        );
+    }
+    Ok(())
+}
+
+fn signed(delta: i128) -> String {
+    format!("{:+}", delta)
+}
+
+fn signed_value(delta: &BigInt, number_format: &NumberFormat) -> String {
+    match delta.sign() {
+        num_bigint::Sign::Minus => number_format.format(delta),
+        _ => format!("+{}", number_format.format(delta)),
+    }
+}
@@ -0,0 +1,241 @@
+use num_bigint::BigInt;
+use std::io::{self, Write};
+
+// 10^19 is the largest power of ten that fits into a u64
+const CHUNK_BASE: u64 = 10_000_000_000_000_000_000;
+const CHUNK_DIGITS: usize = 19;
+
+/// Formats values as decimal lines into buffers that are reused across calls.
+///
+/// `writeln!(file, "{}", n)` allocates the digit string of every value anew, which adds up for
+/// billions of lines. This converts the magnitude in place, 19 digits per division pass.
+#[derive(Default)]
+pub struct DigitWriter {
+    limbs: Vec<u64>,
+    chunks: Vec<u64>,
+    line: Vec<u8>,
+}
+
+impl DigitWriter {
+    /// Writes `n` and a newline, returning the number of digits written.
+    pub fn write_line(&mut self, n: &BigInt, output: &mut impl Write) -> io::Result<usize> {
+        self.line.clear();
+        if n.sign() == num_bigint::Sign::Minus {
+            self.line.push(b'-');
+        }
+
+        to_chunks(n, &mut self.limbs, &mut self.chunks);
+        push_chunks(&mut self.line, &self.chunks);
+        self.line.push(b'\n');
+        output.write_all(&self.line)?;
+        Ok(self.line.len() - 1)
+    }
+}
+
+/// Decimal digits of the current value of a trajectory, updated in place by each step.
+///
+/// A halving is a long division of the digits by 2 and 3n+1 a multiplication with carry, both
+/// one pass over the digits, while converting the value anew (as [`DigitWriter`] does) takes
+/// time quadratic in its length. The digits are kept in chunks of 19, so writing a line is only
+/// the formatting of the chunks. It writes the same lines as [`DigitWriter`].
+pub struct DecimalValue {
+    // base 10^19 chunks, least significant first, without leading zero chunks
+    chunks: Vec<u64>,
+    line: Vec<u8>,
+}
+
+impl DecimalValue {
+    pub fn new(n: &BigInt) -> Self {
+        let mut chunks = Vec::new();
+        to_chunks(n, &mut Vec::new(), &mut chunks);
+        DecimalValue {
+            chunks,
+            line: Vec::new(),
+        }
+    }
+
+    /// Applies one step of the Collatz map: halves an even value, 3n+1 for an odd one.
+    pub fn step(&mut self) {
+        if self.chunks.first().is_some_and(|chunk| chunk % 2 == 1) {
+            self.triple_plus_one();
+        } else {
+            self.halve();
+        }
+    }
+
+    fn halve(&mut self) {
+        // the remainder of a chunk carries half of the base into the next lower one
+        let mut remainder = 0;
+        for chunk in self.chunks.iter_mut().rev() {
+            let odd = *chunk % 2;
+            *chunk = remainder * (CHUNK_BASE / 2) + *chunk / 2;
+            remainder = odd;
+        }
+        if self.chunks.last() == Some(&0) {
+            self.chunks.pop();
+        }
+    }
+
+    fn triple_plus_one(&mut self) {
+        let mut carry = 1;
+        for chunk in &mut self.chunks {
+            let value = u128::from(*chunk) * 3 + carry;
+            *chunk = (value % u128::from(CHUNK_BASE)) as u64;
+            carry = value / u128::from(CHUNK_BASE);
+        }
+        if carry > 0 {
+            self.chunks.push(carry as u64);
+        }
+    }
+
+    /// Writes the value and a newline, returning the number of digits written.
+    pub fn write_line(&mut self, output: &mut impl Write) -> io::Result<usize> {
+        self.line.clear();
+        push_chunks(&mut self.line, &self.chunks);
+        self.line.push(b'\n');
+        output.write_all(&self.line)?;
+        Ok(self.line.len() - 1)
+    }
+}
+
+// Convert the magnitude of `n` to base 10^19 chunks, least significant first, using `limbs` as
+// scratch space
+fn to_chunks(n: &BigInt, limbs: &mut Vec<u64>, chunks: &mut Vec<u64>) {
+    limbs.clear();
+    limbs.extend(n.magnitude().iter_u64_digits());
+    chunks.clear();
+    // repeatedly divide the limbs (little endian) by 10^19, collecting the remainders
+    while !limbs.is_empty() {
+        let mut remainder: u128 = 0;
+        for limb in limbs.iter_mut().rev() {
+            let value = (remainder << 64) | u128::from(*limb);
+            *limb = (value / u128::from(CHUNK_BASE)) as u64;
+            remainder = value % u128::from(CHUNK_BASE);
+        }
+        chunks.push(remainder as u64);
+        while limbs.last() == Some(&0) {
+            limbs.pop();
+        }
+    }
+}
+
+// Append the decimal digits of chunks, most significant first; no chunks is 0
+fn push_chunks(line: &mut Vec<u8>, chunks: &[u64]) {
+    match chunks.split_last() {
+        None => line.push(b'0'),
+        Some((first, rest)) => {
+            push_chunk(line, *first, false);
+            for chunk in rest.iter().rev() {
+                push_chunk(line, *chunk, true);
+            }
+        }
+    }
+}
+
+// Append the decimal digits of a chunk, zero padded to 19 digits for all but the leading chunk
+fn push_chunk(line: &mut Vec<u8>, mut chunk: u64, pad: bool) {
+    let mut digits = [b'0'; CHUNK_DIGITS];
+    let mut start = CHUNK_DIGITS;
+    while chunk > 0 {
+        start -= 1;
+        digits[start] = b'0' + (chunk % 10) as u8;
+        chunk /= 10;
+    }
+    if pad {
+        start = 0;
+    } else if start == CHUNK_DIGITS {
+        start -= 1;
+    }
+    line.extend_from_slice(&digits[start..]);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_traits::{One, Pow};
+    use proptest::prelude::*;
+
+    // Step `seed` along its whole trajectory, at most `max_steps` steps, and compare the line of
+    // every value with its `to_string()`
+    fn check_trajectory(seed: BigInt, max_steps: usize) {
+        let mut n = seed;
+        let mut decimal = DecimalValue::new(&n);
+        let mut line = Vec::new();
+        for step in 0..=max_steps {
+            line.clear();
+            let digits = decimal.write_line(&mut line).unwrap();
+            let expected = n.to_string() + "\n";
+            assert_eq!(
+                std::str::from_utf8(&line).unwrap(),
+                expected,
+                "differs at step {}",
+                step
+            );
+            assert_eq!(digits, expected.len() - 1);
+            if n.is_one() {
+                return;
+            }
+            if n.bit(0) {
+                n = BigInt::from(3) * n + 1;
+            } else {
+                n >>= 1;
+            }
+            decimal.step();
+        }
+    }
+
+    fn ten_to(exponent: u32) -> BigInt {
+        BigInt::from(10).pow(exponent)
+    }
+
+    #[test]
+    fn small_seeds() {
+        for seed in 1..2000 {
+            check_trajectory(BigInt::from(seed), usize::MAX);
+        }
+    }
+
+    #[test]
+    fn carries_across_chunks() {
+        // values around multiples of 10^19 carry from one chunk into the next when tripled and
+        // borrow across chunk boundaries when halved
+        for chunks in 1..6 {
+            let base = ten_to(19 * chunks);
+            for offset in [-3, -1, 0, 1, 3] {
+                check_trajectory(&base + offset, usize::MAX);
+                check_trajectory(&base * 3 + offset, usize::MAX);
+            }
+            check_trajectory(&base / 3 + 1, usize::MAX);
+        }
+        // 3n+1 of 333...333 (38 digits) is 10^38, a carry through both chunks into a new one
+        check_trajectory((ten_to(38) - 1) / 3, usize::MAX);
+        check_trajectory(BigInt::from(u64::MAX), usize::MAX);
+        check_trajectory(BigInt::from(u128::MAX), usize::MAX);
+    }
+
+    #[test]
+    fn chunks_with_leading_zeros() {
+        // a chunk below 10^18 must be padded to 19 digits unless it is the leading one
+        check_trajectory(ten_to(57) + 7, usize::MAX);
+        check_trajectory(ten_to(38) * 5 + ten_to(5) + 1, usize::MAX);
+    }
+
+    #[test]
+    fn large_seeds() {
+        check_trajectory((BigInt::one() << 2000u32) - 1, usize::MAX);
+        check_trajectory(ten_to(1000) + 1, usize::MAX);
+        // only the start of the trajectory of a seed of ~30000 digits
+        check_trajectory((BigInt::one() << 100_000u32) - 1, 200);
+    }
+
+    proptest! {
+        #[test]
+        fn arbitrary_seeds(digits in proptest::collection::vec(any::<u64>(), 1..8)) {
+            let seed = BigInt::from(num_bigint::BigUint::new(
+                digits.iter().flat_map(|d| [*d as u32, (*d >> 32) as u32]).collect(),
+            ));
+            prop_assume!(seed >= BigInt::one());
+            check_trajectory(seed, usize::MAX);
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use collatz_bigint_file::stats::{Row, StoppingTime};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+// Percentiles printed in the summary
+const PERCENTILES: [u32; 6] = [10, 25, 50, 75, 90, 99];
+// Number of bins of the text histogram
+const HISTOGRAM_BINS: usize = 20;
+
+/// Stopping times of the seeds of a batch by one definition, aggregated into a distribution.
+///
+/// Seeds share few distinct stopping times, so the exact counts per stopping time stay small
+/// and the percentiles are exact, whatever the number of seeds.
+pub struct Distribution {
+    definition: StoppingTime,
+    // number of seeds per stopping time
+    counts: BTreeMap<usize, u64>,
+    // seeds without a stopping time: truncated, or never below the seed
+    unknown: u64,
+}
+
+impl Distribution {
+    pub fn new(definition: StoppingTime) -> Self {
+        Distribution {
+            definition,
+            counts: BTreeMap::new(),
+            unknown: 0,
+        }
+    }
+
+    pub fn record(&mut self, row: &Row) {
+        match row.stopping_time_by(self.definition) {
+            Some(stopping_time) => *self.counts.entry(stopping_time).or_insert(0) += 1,
+            None => self.unknown += 1,
+        }
+    }
+
+    /// Number of seeds recorded, with or without a stopping time.
+    pub fn seeds(&self) -> u64 {
+        self.total() + self.unknown
+    }
+
+    fn total(&self) -> u64 {
+        self.counts.values().sum()
+    }
+
+    // Smallest stopping time with at least `percent` percent of the seeds at or below it
+    fn percentile(&self, percent: u32) -> usize {
+        let rank = (self.total() * u64::from(percent)).div_ceil(100).max(1);
+        let mut seen = 0;
+        for (&stopping_time, &count) in &self.counts {
+            seen += count;
+            if seen >= rank {
+                return stopping_time;
+            }
+        }
+        0
+    }
+
+    // Counts of the bins of `width` stopping times from the smallest to the largest one, with
+    // the first stopping time of each bin
+    fn bins(&self, width: usize) -> Vec<(usize, u64)> {
+        let (Some((&min, _)), Some((&max, _))) =
+            (self.counts.first_key_value(), self.counts.last_key_value())
+        else {
+            return Vec::new();
+        };
+        let start = min / width * width;
+        let mut bins: Vec<(usize, u64)> = (start..=max)
+            .step_by(width)
+            .map(|bin_start| (bin_start, 0))
+            .collect();
+        for (&stopping_time, &count) in &self.counts {
+            bins[(stopping_time - start) / width].1 += count;
+        }
+        bins
+    }
+
+    pub fn write_summary(&self, output: &mut impl Write) -> io::Result<()> {
+        let total = self.total();
+        writeln!(output)?;
+        if total == 0 {
+            writeln!(
+                output,
+                "{}: not known for any seed",
+                self.definition.label()
+            )?;
+        } else {
+            let sum: u64 = self
+                .counts
+                .iter()
+                .map(|(&stopping_time, &count)| stopping_time as u64 * count)
+                .sum();
+            let (&min, _) = self.counts.first_key_value().expect("counts are not empty");
+            let (&max, _) = self.counts.last_key_value().expect("counts are not empty");
+            writeln!(
+                output,
+                "{} of {} seeds: mean {:.2}, median {}, min {}, max {}",
+                self.definition.label(),
+                total,
+                sum as f64 / total as f64,
+                self.percentile(50),
+                min,
+                max
+            )?;
+            let percentiles = PERCENTILES
+                .iter()
+                .map(|&percent| format!("p{} {}", percent, self.percentile(percent)))
+                .collect::<Vec<_>>();
+            writeln!(output, "percentiles: {}", percentiles.join(", "))?;
+
+            let width = (max - min + 1).div_ceil(HISTOGRAM_BINS);
+            let bins = self.bins(width);
+            let largest = bins.iter().map(|(_, count)| *count).max().unwrap_or(1);
+            for (bin_start, count) in bins {
+                writeln!(
+                    output,
+                    "{:>7}-{:<7} {:>10} {}",
+                    bin_start,
+                    bin_start + width - 1,
+                    count,
+                    "█".repeat((count * crate::HISTOGRAM_WIDTH).div_ceil(largest) as usize)
+                )?;
+            }
+        }
+        if self.unknown > 0 {
+            let reason = match self.definition {
+                StoppingTime::Total => "truncated",
+                StoppingTime::BelowSeed => "truncated or never below the seed",
+            };
+            writeln!(
+                output,
+                "{} seeds without a known stopping time ({}) are left out",
+                self.unknown, reason
+            )?;
+        }
+        Ok(())
+    }
+
+    // CSV of the binned distribution, one line per bin of `width` stopping times
+    pub fn write_csv(&self, output: &mut impl Write, width: usize) -> io::Result<()> {
+        writeln!(output, "min_stopping_time,max_stopping_time,seeds")?;
+        for (bin_start, count) in self.bins(width) {
+            writeln!(output, "{},{},{}", bin_start, bin_start + width - 1, count)?;
+        }
+        Ok(())
+    }
+}
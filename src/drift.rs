@@ -0,0 +1,171 @@
+use crate::chart;
+use collatz_bigint_file::sequence::{drift_per_step, Map};
+use num_bigint::BigInt;
+
+// Size of the chart of the drift analysis in characters
+const CHART_WIDTH: usize = 64;
+const CHART_HEIGHT: usize = 12;
+// Leading digits that are converted to f64 for the logarithm of a value
+const LEADING_DIGITS: usize = 15;
+
+// Base 2 logarithm of a value given by its digits in a base
+pub fn log2_digits(digits: &[u8], radix: u32) -> f64 {
+    let leading = digits.len().min(LEADING_DIGITS);
+    let value = digits[..leading].iter().fold(0.0, |value, digit| {
+        let digit = (*digit as char).to_digit(radix).unwrap_or(0);
+        value * f64::from(radix) + f64::from(digit)
+    });
+    value.log2() + (digits.len() - leading) as f64 * f64::from(radix).log2()
+}
+
+// Base 2 logarithm of a big integer, from its leading 64 bits
+pub fn log2(n: &BigInt) -> f64 {
+    let bits = n.bits();
+    let shift = bits.saturating_sub(64);
+    let leading: BigInt = n >> shift;
+    let leading = leading.iter_u64_digits().next().unwrap_or(0) as f64;
+    leading.log2() + shift as f64
+}
+
+/// Comparison of a trajectory with the heuristic geometric drift of [`drift_per_step`](collatz_bigint_file::sequence::drift_per_step),
+/// collected in one pass: the deviation of log2 n from the model line and a downsampled copy of
+/// the trajectory for the chart.
+pub struct DriftAnalysis {
+    start: f64,
+    drift: f64,
+    steps: u64,
+    last: f64,
+    sum_squares: f64,
+    // largest deviation above and below the model, with its step
+    above: (f64, u64),
+    below: (f64, u64),
+    // largest log2 n of each bucket of `bucket_size` steps, starting with the seed
+    buckets: Vec<f64>,
+    bucket_size: u64,
+    in_last_bucket: u64,
+}
+
+impl DriftAnalysis {
+    pub fn new(seed: &BigInt, map: Map) -> Self {
+        let start = log2(seed);
+        DriftAnalysis {
+            start,
+            drift: drift_per_step(map),
+            steps: 0,
+            last: start,
+            sum_squares: 0.0,
+            above: (0.0, 0),
+            below: (0.0, 0),
+            buckets: vec![start],
+            bucket_size: 1,
+            in_last_bucket: 1,
+        }
+    }
+
+    // log2 n predicted by the model after a number of steps, never below log2 1
+    fn model(&self, step: f64) -> f64 {
+        (self.start + self.drift * step).max(0.0)
+    }
+
+    pub fn push(&mut self, log2: f64) {
+        self.steps += 1;
+        self.last = log2;
+        let deviation = log2 - self.model(self.steps as f64);
+        self.sum_squares += deviation * deviation;
+        if deviation > self.above.0 {
+            self.above = (deviation, self.steps);
+        }
+        if deviation < self.below.0 {
+            self.below = (deviation, self.steps);
+        }
+
+        if self.in_last_bucket == self.bucket_size {
+            // keep at most two buckets per chart column by merging neighbours
+            if self.buckets.len() == 2 * CHART_WIDTH {
+                self.buckets = self
+                    .buckets
+                    .chunks(2)
+                    .map(|pair| pair.iter().copied().fold(0.0, f64::max))
+                    .collect();
+                self.bucket_size *= 2;
+            }
+            self.buckets.push(0.0);
+            self.in_last_bucket = 0;
+        }
+        let last = self
+            .buckets
+            .last_mut()
+            .expect("buckets start with the seed");
+        *last = last.max(log2);
+        self.in_last_bucket += 1;
+    }
+
+    pub fn print(&self) {
+        println!();
+        println!(
+            "drift model: log2 n changes by {:.4} per step, from {:.2} bits at the seed",
+            self.drift, self.start
+        );
+        let predicted = self.start / -self.drift;
+        if self.last == 0.0 {
+            println!(
+                "steps to reach 1: predicted {:.0}, actual {} ({:.2} times the prediction)",
+                predicted,
+                self.steps,
+                self.steps as f64 / predicted.max(1.0)
+            );
+        } else {
+            println!(
+                "steps to reach 1: predicted {:.0}, still {:.2} bits after {} steps where the model expects {:.2}",
+                predicted,
+                self.last,
+                self.steps,
+                self.model(self.steps as f64)
+            );
+        }
+        if self.steps > 0 {
+            println!(
+                "deviation from the model: RMS {:.2} bits, largest {:+.2} at step {} and {:+.2} at step {}",
+                (self.sum_squares / self.steps as f64).sqrt(),
+                self.above.0,
+                self.above.1,
+                self.below.0,
+                self.below.1
+            );
+        }
+        // the model at the middle of every bucket
+        let model: Vec<f64> = (0..self.buckets.len())
+            .map(|bucket| {
+                let middle = bucket as u64 * self.bucket_size + self.bucket_size / 2;
+                self.model(middle.min(self.steps) as f64)
+            })
+            .collect();
+        println!("log2 n per step: █ trajectory, • model");
+        print!(
+            "{}",
+            chart::plot(
+                &self.buckets,
+                Some(&model),
+                self.steps as usize,
+                CHART_WIDTH,
+                CHART_HEIGHT
+            )
+        );
+    }
+
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"drift_per_step\":{:.6},\"start_log2\":{:.6},\"steps\":{},\"predicted_steps\":{:.1},\"final_log2\":{:.6},\"rms_deviation\":{:.6},\"max_above\":{{\"deviation\":{:.6},\"step\":{}}},\"max_below\":{{\"deviation\":{:.6},\"step\":{}}}}}",
+            self.drift,
+            self.start,
+            self.steps,
+            self.start / -self.drift,
+            self.last,
+            (self.sum_squares / self.steps.max(1) as f64).sqrt(),
+            self.above.0,
+            self.above.1,
+            self.below.0,
+            self.below.1
+        )
+    }
+}
@@ -0,0 +1,140 @@
+use crate::color::ColorScheme;
+use crate::format::NumberFormat;
+use crate::scan;
+use colored::Colorize;
+use std::collections::VecDeque;
+
+// Default window of the terminal dump: first and last values printed, the rest is elided
+pub const DEFAULT_PRINT: &str = "head:50,tail:50";
+
+// Which part of the sequence is printed in the terminal
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrintLimit {
+    All,
+    Nothing,
+    Window { head: usize, tail: usize },
+}
+
+// Helper function to parse a print spec like "all", "none", "head:100" or "head:10,tail:5"
+pub fn parse_print_limit(input: &str) -> Result<PrintLimit, String> {
+    match input.trim() {
+        "all" => return Ok(PrintLimit::All),
+        "none" => return Ok(PrintLimit::Nothing),
+        _ => {}
+    }
+    let (mut head, mut tail) = (0, 0);
+    for part in input.split(',') {
+        let (key, value) = part.trim().split_once(':').ok_or_else(|| {
+            format!(
+                "Invalid print spec '{}'. Expected e.g. 'head:10,tail:5'",
+                part
+            )
+        })?;
+        let value = value
+            .trim()
+            .parse::<usize>()
+            .map_err(|_| format!("Invalid count '{}' in print spec", value))?;
+        match key.trim() {
+            "head" => head = value,
+            "tail" => tail = value,
+            other => {
+                return Err(format!(
+                    "Unknown print spec key '{}'. Use 'head' or 'tail'",
+                    other
+                ))
+            }
+        }
+    }
+    Ok(PrintLimit::Window { head, tail })
+}
+
+// Prints the values of the sequence according to the print limit, keeping the tail in a ring buffer.
+// Values arrive as decimal digits and are only parsed and formatted once they are actually printed.
+pub struct Dump {
+    limit: PrintLimit,
+    color_scheme: ColorScheme,
+    number_format: NumberFormat,
+    seen: usize,
+    // whether the value before the next one is odd; a power of two after an odd value is where
+    // the trajectory enters its final descent, highlighted
+    previous_odd: bool,
+    // digits, parity and whether the previous value was odd of the last values, buffers are
+    // reused once the ring is full
+    tail: VecDeque<(Vec<u8>, bool, bool)>,
+    omitted: usize,
+}
+
+impl Dump {
+    pub fn new(
+        limit: PrintLimit,
+        color_scheme: ColorScheme,
+        number_format: NumberFormat,
+        seed_is_odd: bool,
+    ) -> Self {
+        Dump {
+            limit,
+            color_scheme,
+            number_format,
+            seen: 0,
+            previous_odd: seed_is_odd,
+            tail: VecDeque::new(),
+            omitted: 0,
+        }
+    }
+
+    pub fn push(&mut self, digits: &[u8], is_even: bool) {
+        self.seen += 1;
+        let after_odd = std::mem::replace(&mut self.previous_odd, !is_even);
+        match self.limit {
+            PrintLimit::All => self.print_value(digits, is_even, after_odd),
+            PrintLimit::Nothing => self.omitted += 1,
+            PrintLimit::Window { head, tail } => {
+                if self.seen <= head {
+                    self.print_value(digits, is_even, after_odd);
+                } else if tail == 0 {
+                    self.omitted += 1;
+                } else {
+                    let mut buffer = if self.tail.len() == tail {
+                        self.omitted += 1;
+                        self.tail.pop_front().map(|(buffer, _, _)| buffer)
+                    } else {
+                        None
+                    }
+                    .unwrap_or_default();
+                    buffer.clear();
+                    buffer.extend_from_slice(digits);
+                    self.tail.push_back((buffer, is_even, after_odd));
+                }
+            }
+        }
+    }
+
+    // Base of the digits pushed to the dump
+    pub fn radix(&self) -> u32 {
+        self.number_format.radix
+    }
+
+    // Print the elision note and the buffered tail
+    pub fn finish(self) {
+        if self.omitted > 0 && self.limit != PrintLimit::Nothing {
+            print!(
+                "\n\n... {} values omitted (full sequence in the output file, use --print all to show everything) ...\n\n",
+                self.omitted
+            );
+        }
+        for (digits, is_even, after_odd) in &self.tail {
+            self.print_value(digits, *is_even, *after_odd);
+        }
+    }
+
+    fn print_value(&self, digits: &[u8], is_even: bool, after_odd: bool) {
+        let num = scan::to_bigint_in(digits, self.number_format.radix);
+        let color = self.color_scheme.color_for(&num, is_even);
+        let value = self.number_format.format(&num).color(color);
+        if after_odd && num.trailing_zeros() == Some(num.bits() - 1) {
+            print!("{} ", value.bold().underline());
+        } else {
+            print!("{} ", value);
+        }
+    }
+}
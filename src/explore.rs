@@ -0,0 +1,308 @@
+use crate::format::NumberFormat;
+use crate::output;
+use colored::{Color, Colorize};
+use num_bigint::BigInt;
+use num_traits::One;
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+const HELP: &str = "←/h back  ↑↓/k j select  →/l/Enter open  b bookmark  e export  q quit";
+
+// Values of the path shown before it is elided from the left
+const PATH_SHOWN: usize = 12;
+
+// Key presses, from arrow keys or their letters
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Key {
+    Up,
+    Down,
+    Open,
+    Back,
+    Bookmark,
+    Export,
+    Quit,
+}
+
+impl Key {
+    fn from_letter(letter: u8) -> Option<Key> {
+        match letter {
+            b'k' | b'w' => Some(Key::Up),
+            b'j' | b's' => Some(Key::Down),
+            b'l' | b'd' | b'\r' | b'\n' => Some(Key::Open),
+            b'h' | b'a' | 0x7f | 0x08 => Some(Key::Back),
+            b'b' => Some(Key::Bookmark),
+            b'e' => Some(Key::Export),
+            // Ctrl-C and Ctrl-D arrive as bytes in raw mode
+            b'q' | 0x03 | 0x04 => Some(Key::Quit),
+            _ => None,
+        }
+    }
+}
+
+// Predecessors of n in the Collatz map: 2n always, and (n-1)/3 if that is odd and not 1, which
+// would close the cycle 1 → 4 → 2 → 1
+fn children(n: &BigInt) -> Vec<BigInt> {
+    let mut children = vec![n << 1];
+    if n % 6u32 == BigInt::from(4) {
+        let odd = (n - 1u32) / 3u32;
+        if !odd.is_one() {
+            children.push(odd);
+        }
+    }
+    children
+}
+
+// State of the walk: the path from 1 to the current node, the child selected under it and the
+// nodes bookmarked so far with their paths
+struct Explorer {
+    path: Vec<BigInt>,
+    selected: usize,
+    bookmarks: Vec<Vec<BigInt>>,
+    // result of the last bookmark or export
+    status: String,
+}
+
+impl Explorer {
+    fn current(&self) -> &BigInt {
+        self.path.last().expect("the path starts at 1")
+    }
+
+    // Apply a key; false once the explorer should close
+    fn press(&mut self, key: Key, export: &Path, force: bool) -> bool {
+        self.status.clear();
+        let count = children(self.current()).len();
+        match key {
+            Key::Up => self.selected = self.selected.checked_sub(1).unwrap_or(count - 1),
+            Key::Down => self.selected = (self.selected + 1) % count,
+            Key::Open => {
+                let child = children(self.current()).swap_remove(self.selected);
+                self.path.push(child);
+                self.selected = 0;
+            }
+            Key::Back => {
+                if self.path.len() > 1 {
+                    let child = self.path.pop().expect("more than the root");
+                    // keep the node we came from selected
+                    self.selected = children(self.current())
+                        .iter()
+                        .position(|n| *n == child)
+                        .unwrap_or(0);
+                }
+            }
+            Key::Bookmark => {
+                if self.bookmarks.contains(&self.path) {
+                    self.status = format!("{} is bookmarked already", self.current());
+                } else {
+                    self.bookmarks.push(self.path.clone());
+                    self.status = format!("bookmarked {}", self.current());
+                }
+            }
+            Key::Export => {
+                self.status = match self.export(export, force) {
+                    Ok(paths) => format!("exported {} paths to {}", paths, export.display()),
+                    Err(err) => err,
+                }
+            }
+            Key::Quit => return false,
+        }
+        true
+    }
+
+    // Write the bookmarked paths, or the current one without bookmarks, as trajectories down
+    // to 1; returns the number of paths written
+    fn export(&self, path: &Path, force: bool) -> Result<usize, String> {
+        let paths = if self.bookmarks.is_empty() {
+            std::slice::from_ref(&self.path)
+        } else {
+            &self.bookmarks[..]
+        };
+        let write_error = |err| format!("Failed to write {}: {}", path.display(), err);
+        let mut output = io::BufWriter::new(output::create(path, force)?);
+        writeln!(
+            output,
+            "# collatz_bigint_file {}",
+            env!("CARGO_PKG_VERSION")
+        )
+        .map_err(write_error)?;
+        for nodes in paths {
+            let values: Vec<String> = nodes.iter().rev().map(BigInt::to_string).collect();
+            writeln!(
+                output,
+                "# {}: {} steps to 1\n{}",
+                values[0],
+                nodes.len() - 1,
+                values.join(" ")
+            )
+            .map_err(write_error)?;
+        }
+        output.flush().map_err(write_error)?;
+        Ok(paths.len())
+    }
+
+    // The screen: the path from 1, the current node and its children with the selected one
+    // marked, in lines without their line ends
+    fn render(&self, number_format: &NumberFormat, even: Color, odd: Color) -> Vec<String> {
+        let paint = |n: &BigInt| {
+            let color = if n.bit(0) { odd } else { even };
+            number_format.format(n).color(color)
+        };
+        let skipped = self.path.len().saturating_sub(PATH_SHOWN);
+        let mut path: Vec<String> = self.path[skipped..]
+            .iter()
+            .map(|n| paint(n).to_string())
+            .collect();
+        if skipped > 0 {
+            path.insert(0, format!("… ({} more)", skipped));
+        }
+        let mut lines = vec![
+            "Reverse Collatz tree".bold().to_string(),
+            HELP.dimmed().to_string(),
+            String::new(),
+            format!("path: {}", path.join(" → ")),
+            format!(
+                "{} {}",
+                paint(self.current()).bold(),
+                format!("({} steps to 1)", self.path.len() - 1).dimmed()
+            ),
+        ];
+        for (i, child) in children(self.current()).iter().enumerate() {
+            let rule = if child.bit(0) {
+                // an odd multiple of 3 has no odd predecessor, only the doublings
+                if child % 3u32 == BigInt::from(0) {
+                    "(n-1)/3, multiple of 3: only doublings above"
+                } else {
+                    "(n-1)/3"
+                }
+            } else {
+                "2n"
+            };
+            let marker = if i == self.selected { "▶" } else { " " };
+            lines.push(format!("  {} {}  {}", marker, paint(child), rule.dimmed()));
+        }
+        lines.push(String::new());
+        lines.push(format!("bookmarks: {}", self.bookmarks.len()));
+        if !self.status.is_empty() {
+            lines.push(self.status.clone());
+        }
+        lines
+    }
+}
+
+/// Walks the inverse Collatz tree from 1 upward, showing the children of every node: 2n, and
+/// (n-1)/3 when it is a positive odd integer other than 1.
+///
+/// In a terminal the keys act at once: the arrow keys (or h, j, k, l) select a child, open it
+/// and go back; `b` bookmarks the current node and `e` writes the paths of the bookmarks (or
+/// the current path without any) to `export` as trajectories down to 1. The terminal is put in
+/// raw mode with `stty` while the explorer runs. Without a terminal every line of stdin is read
+/// as a sequence of letter keys and the screen is printed after each line, so walks can be
+/// scripted, e.g. `printf 'llll\nb\ne\n' | collatz_bigint_file explore`.
+pub fn run(
+    export: &Path,
+    force: bool,
+    number_format: &NumberFormat,
+    even: Color,
+    odd: Color,
+) -> Result<(), String> {
+    let mut explorer = Explorer {
+        path: vec![BigInt::one()],
+        selected: 0,
+        bookmarks: Vec::new(),
+        status: String::new(),
+    };
+    let print_error = |err: io::Error| format!("Failed to print the tree: {}", err);
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        let mut stdout = io::stdout().lock();
+        for line in io::stdin().lock().lines() {
+            let line = line.map_err(|err| format!("Failed to read input: {}", err))?;
+            for key in line.bytes().filter_map(Key::from_letter) {
+                if !explorer.press(key, export, force) {
+                    return Ok(());
+                }
+            }
+            for line in explorer.render(number_format, even, odd) {
+                writeln!(stdout, "{}", line).map_err(print_error)?;
+            }
+            writeln!(stdout).map_err(print_error)?;
+        }
+        return Ok(());
+    }
+
+    let _raw = RawMode::enable()?;
+    let mut stdin = io::stdin().lock();
+    let mut stdout = io::stdout().lock();
+    loop {
+        // clear the screen, in raw mode every line needs its carriage return
+        let screen = explorer.render(number_format, even, odd).join("\r\n");
+        write!(stdout, "\x1b[2J\x1b[H{}\r\n", screen)
+            .and_then(|()| stdout.flush())
+            .map_err(print_error)?;
+        let Some(key) = read_key(&mut stdin).map_err(print_error)? else {
+            continue;
+        };
+        if !explorer.press(key, export, force) {
+            return Ok(());
+        }
+    }
+}
+
+// Read one key press in raw mode: an arrow key is the escape sequence ESC [ A to D
+fn read_key(input: &mut impl Read) -> io::Result<Option<Key>> {
+    let mut byte = [0u8];
+    if input.read(&mut byte)? == 0 {
+        return Ok(Some(Key::Quit));
+    }
+    if byte[0] != 0x1b {
+        return Ok(Key::from_letter(byte[0]));
+    }
+    let mut sequence = [0u8; 2];
+    input.read_exact(&mut sequence)?;
+    Ok(match sequence {
+        [b'[', b'A'] => Some(Key::Up),
+        [b'[', b'B'] => Some(Key::Down),
+        [b'[', b'C'] => Some(Key::Open),
+        [b'[', b'D'] => Some(Key::Back),
+        _ => None,
+    })
+}
+
+// Raw mode of the terminal, set with stty and restored when dropped
+struct RawMode {
+    saved: String,
+}
+
+impl RawMode {
+    fn enable() -> Result<RawMode, String> {
+        let stty_error = |err| format!("Failed to run stty: {}", err);
+        let saved = Command::new("stty")
+            .arg("-g")
+            .stdin(Stdio::inherit())
+            .output()
+            .map_err(stty_error)?;
+        if !saved.status.success() {
+            return Err("Failed to read the terminal mode with stty".to_string());
+        }
+        let status = Command::new("stty")
+            .args(["raw", "-echo"])
+            .stdin(Stdio::inherit())
+            .status()
+            .map_err(stty_error)?;
+        if !status.success() {
+            return Err("Failed to put the terminal in raw mode with stty".to_string());
+        }
+        Ok(RawMode {
+            saved: String::from_utf8_lossy(&saved.stdout).trim().to_string(),
+        })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = Command::new("stty")
+            .arg(&self.saved)
+            .stdin(Stdio::inherit())
+            .status();
+        println!();
+    }
+}
@@ -0,0 +1,190 @@
+//! C API for embedding the engine, declared in `include/collatz_bigint_file.h`.
+//!
+//! Link against the `cdylib` or `staticlib` build of this crate. All functions return 0 on
+//! success and a negative `COLLATZ_ERROR_*` code otherwise. Seeds are NUL-terminated decimal
+//! strings; expressions like `2^100-1` are only understood by the command line tool.
+
+use crate::digits::DigitWriter;
+use crate::sequence::{Limits, Map, Truncation};
+use crate::stats::{self, Row};
+use num_bigint::BigInt;
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::ops::ControlFlow;
+use std::ptr;
+use std::time::Duration;
+
+pub const COLLATZ_OK: c_int = 0;
+pub const COLLATZ_ERROR_NULL: c_int = -1;
+pub const COLLATZ_ERROR_INVALID_SEED: c_int = -2;
+
+/// Limits of a computation, 0 means no limit.
+#[repr(C)]
+pub struct CollatzLimits {
+    pub max_steps: u64,
+    pub timeout_ms: u64,
+    pub max_bits: u64,
+}
+
+/// Receives the values of a trajectory, see [`collatz_compute`].
+#[repr(C)]
+pub struct CollatzCallbacks {
+    /// Called with each value after the seed as decimal digits (not NUL-terminated), valid
+    /// only during the call. A non-zero return value stops the computation.
+    pub on_value: Option<
+        unsafe extern "C" fn(
+            user_data: *mut c_void,
+            step: u64,
+            digits: *const c_char,
+            len: usize,
+        ) -> c_int,
+    >,
+    pub user_data: *mut c_void,
+}
+
+/// Statistics of a trajectory. `max_value` is allocated by the library, release it with
+/// [`collatz_stats_free`].
+#[repr(C)]
+pub struct CollatzStats {
+    pub stopping_time: u64,
+    /// steps until the trajectory first drops below the seed, -1 if it never does
+    pub glide: i64,
+    pub max_index: u64,
+    pub odd: u64,
+    /// decimal, NUL-terminated
+    pub max_value: *mut c_char,
+    /// one of the `COLLATZ_TRUNCATED_*` values
+    pub truncated: c_int,
+}
+
+pub const COLLATZ_TRUNCATED_NONE: c_int = 0;
+pub const COLLATZ_TRUNCATED_MAX_STEPS: c_int = 1;
+pub const COLLATZ_TRUNCATED_TIMEOUT: c_int = 2;
+pub const COLLATZ_TRUNCATED_MAX_BITS: c_int = 3;
+pub const COLLATZ_TRUNCATED_CANCELLED: c_int = 4;
+
+/// Computes the trajectory of `seed` with the `3n+1` map, passes every value to
+/// `callbacks->on_value` and stores the statistics in `stats`.
+///
+/// `limits` and `callbacks` may be null, `stats` may be null if only the values are needed.
+///
+/// # Safety
+///
+/// `seed` has to be a NUL-terminated string; the other pointers have to be null or valid.
+#[no_mangle]
+pub unsafe extern "C" fn collatz_compute(
+    seed: *const c_char,
+    limits: *const CollatzLimits,
+    callbacks: *const CollatzCallbacks,
+    stats: *mut CollatzStats,
+) -> c_int {
+    let start = match parse_seed(seed) {
+        Ok(start) => start,
+        Err(code) => return code,
+    };
+    let limits = to_limits(limits.as_ref());
+    let mut digits = DigitWriter::default();
+    let mut line = Vec::new();
+    let mut step = 0;
+    let row = match callbacks
+        .as_ref()
+        .and_then(|callbacks| Some((callbacks.on_value?, callbacks.user_data)))
+    {
+        Some((on_value, user_data)) => {
+            stats::visit_trajectory(String::new(), &start, &limits, Map::Standard, |n| {
+                step += 1;
+                line.clear();
+                let len = digits
+                    .write_line(n, &mut line)
+                    .expect("Writing to memory cannot fail");
+                match on_value(user_data, step, line.as_ptr().cast(), len) {
+                    0 => ControlFlow::Continue(()),
+                    _ => ControlFlow::Break(()),
+                }
+            })
+        }
+        None => stats::trajectory(String::new(), &start, &limits, Map::Standard),
+    };
+    if let Some(stats) = stats.as_mut() {
+        *stats = to_stats(&row);
+    }
+    COLLATZ_OK
+}
+
+/// Computes only the statistics of the trajectory of `seed`, like [`collatz_compute`] without
+/// callbacks.
+///
+/// # Safety
+///
+/// `seed` has to be a NUL-terminated string, `limits` null or valid, `stats` valid.
+#[no_mangle]
+pub unsafe extern "C" fn collatz_stats(
+    seed: *const c_char,
+    limits: *const CollatzLimits,
+    stats: *mut CollatzStats,
+) -> c_int {
+    if stats.is_null() {
+        return COLLATZ_ERROR_NULL;
+    }
+    collatz_compute(seed, limits, ptr::null(), stats)
+}
+
+/// Releases the memory owned by `stats`; the struct itself belongs to the caller.
+///
+/// # Safety
+///
+/// `stats` has to be null or filled by [`collatz_compute`] or [`collatz_stats`].
+#[no_mangle]
+pub unsafe extern "C" fn collatz_stats_free(stats: *mut CollatzStats) {
+    if let Some(stats) = stats.as_mut() {
+        if !stats.max_value.is_null() {
+            drop(CString::from_raw(stats.max_value));
+            stats.max_value = ptr::null_mut();
+        }
+    }
+}
+
+unsafe fn parse_seed(seed: *const c_char) -> Result<BigInt, c_int> {
+    if seed.is_null() {
+        return Err(COLLATZ_ERROR_NULL);
+    }
+    BigInt::parse_bytes(CStr::from_ptr(seed).to_bytes(), 10)
+        .filter(|n| *n >= BigInt::from(1))
+        .ok_or(COLLATZ_ERROR_INVALID_SEED)
+}
+
+fn to_limits(limits: Option<&CollatzLimits>) -> Limits {
+    let Some(limits) = limits else {
+        return Limits::default();
+    };
+    Limits {
+        max_steps: (limits.max_steps > 0).then_some(limits.max_steps as usize),
+        timeout: (limits.timeout_ms > 0).then(|| Duration::from_millis(limits.timeout_ms)),
+        max_bits: (limits.max_bits > 0).then_some(limits.max_bits),
+        ..Limits::default()
+    }
+}
+
+fn to_stats(row: &Row) -> CollatzStats {
+    CollatzStats {
+        stopping_time: row.stopping_time as u64,
+        glide: row.glide.map_or(-1, |glide| glide as i64),
+        max_index: row.max_index as u64,
+        odd: row.odd as u64,
+        max_value: CString::new(row.max_value.to_string())
+            .expect("Digits contain no NUL")
+            .into_raw(),
+        truncated: match row.truncated {
+            None => COLLATZ_TRUNCATED_NONE,
+            Some(Truncation::MaxSteps(_)) => COLLATZ_TRUNCATED_MAX_STEPS,
+            Some(Truncation::Timeout(_)) => COLLATZ_TRUNCATED_TIMEOUT,
+            Some(Truncation::MaxBits(_)) => COLLATZ_TRUNCATED_MAX_BITS,
+            Some(Truncation::Cancelled) => COLLATZ_TRUNCATED_CANCELLED,
+            // `to_limits` always runs down to 1
+            Some(Truncation::Until(_) | Truncation::Condition) => {
+                unreachable!("the C API has no stop value")
+            }
+            // only the functions writing a sequence file check the output limit
+            Some(Truncation::MaxBytes(_)) => unreachable!("the C API writes no sequence file"),
+        },
+    }
+}
@@ -0,0 +1,217 @@
+use crate::scan;
+use crate::sink::SequenceSink;
+use collatz_bigint_file::input;
+use std::fmt;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// One stage of `--filter`, deciding which of the values it receives are passed on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Filter {
+    // the odd values
+    OddOnly,
+    // values larger than every value before them
+    Records,
+    // local maxima, larger than the values right before and after them
+    Peaks,
+    // every k-th value
+    Every(u64),
+    // values above a threshold, kept as its decimal digits
+    Above(String),
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let input = input.trim();
+        let invalid = || {
+            format!(
+                "Unknown filter '{}'. Use odd-only, records, peaks, every:K or above:N",
+                input
+            )
+        };
+        match input.split_once(':') {
+            None => match input {
+                "odd-only" => Ok(Filter::OddOnly),
+                "records" => Ok(Filter::Records),
+                "peaks" => Ok(Filter::Peaks),
+                _ => Err(invalid()),
+            },
+            Some(("every", every)) => every
+                .trim()
+                .parse()
+                .ok()
+                .filter(|&every| every > 0)
+                .map(Filter::Every)
+                .ok_or_else(|| format!("Invalid filter '{}': every needs K > 0", input)),
+            Some(("above", threshold)) => input::parse_seed(threshold, u64::MAX)
+                .map(|threshold| Filter::Above(threshold.to_string()))
+                .map_err(|_| {
+                    format!(
+                        "Invalid filter '{}': above needs a positive integer or expression like 2^100",
+                        input
+                    )
+                }),
+            Some(_) => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Filter::OddOnly => write!(f, "odd-only"),
+            Filter::Records => write!(f, "records"),
+            Filter::Peaks => write!(f, "peaks"),
+            Filter::Every(every) => write!(f, "every:{}", every),
+            Filter::Above(threshold) => write!(f, "above:{}", threshold),
+        }
+    }
+}
+
+// Helper function to write the stages of --filter as given, e.g. "odd-only,every:100"
+pub fn describe(filters: &[Filter]) -> String {
+    filters
+        .iter()
+        .map(Filter::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+// State of one stage while the values stream through
+struct Stage {
+    filter: Filter,
+    // values received, for every:K
+    received: u64,
+    // largest value received, for records
+    record: Vec<u8>,
+    // for peaks: the value before the held one, and the held one with its step, which is a peak
+    // if the next value is smaller
+    before: Vec<u8>,
+    held: Option<(u64, Vec<u8>)>,
+}
+
+impl Stage {
+    // Passes on the values among `step` and `value` (the digits, without the newline) that the
+    // stage keeps; a peak is only known once the next value arrived
+    fn push(&mut self, step: u64, value: Vec<u8>) -> Option<(u64, Vec<u8>)> {
+        match &self.filter {
+            Filter::OddOnly => matches!(value.last(), Some(b'1' | b'3' | b'5' | b'7' | b'9'))
+                .then_some((step, value)),
+            Filter::Records => {
+                if scan::compare(&value, &self.record).is_gt() {
+                    self.record.clone_from(&value);
+                    Some((step, value))
+                } else {
+                    None
+                }
+            }
+            Filter::Peaks => {
+                let (held_step, held) = self.held.replace((step, value))?;
+                let next = &self.held.as_ref().expect("a value is held").1;
+                if scan::compare(&held, &self.before).is_gt() && scan::compare(&held, next).is_gt()
+                {
+                    self.before.clone_from(&held);
+                    Some((held_step, held))
+                } else {
+                    self.before = held;
+                    None
+                }
+            }
+            Filter::Every(every) => {
+                self.received += 1;
+                self.received
+                    .is_multiple_of(*every)
+                    .then_some((step, value))
+            }
+            Filter::Above(threshold) => scan::compare(&value, threshold.as_bytes())
+                .is_gt()
+                .then_some((step, value)),
+        }
+    }
+}
+
+/// Sink wrapper passing the values of a sequence through a stack of filters (`--filter`).
+///
+/// Every value goes through the stages in order and each stage only sees the values the
+/// previous ones kept, so `odd-only,every:100` keeps every 100th odd value and
+/// `every:100,odd-only` the odd ones among every 100th value. Kept values are written as
+/// `<step> <value>` with their step in the whole sequence. Comment lines pass through unchanged;
+/// a value held by `peaks` to compare it with the next one is dropped at the end, as the last
+/// value has no successor.
+pub struct Filtered {
+    inner: Box<dyn SequenceSink>,
+    stages: Vec<Stage>,
+    line: Vec<u8>,
+    step: u64,
+}
+
+impl Filtered {
+    pub fn new(inner: Box<dyn SequenceSink>, filters: &[Filter]) -> Self {
+        Filtered {
+            inner,
+            stages: filters
+                .iter()
+                .map(|filter| Stage {
+                    filter: filter.clone(),
+                    received: 0,
+                    record: Vec::new(),
+                    before: Vec::new(),
+                    held: None,
+                })
+                .collect(),
+            line: Vec::new(),
+            step: 0,
+        }
+    }
+
+    fn end_line(&mut self) -> io::Result<()> {
+        if self.line.first() == Some(&b'#') {
+            self.inner.write_all(&self.line)?;
+        } else {
+            self.step += 1;
+            let value = self.line.trim_ascii_end().to_vec();
+            let kept = self
+                .stages
+                .iter_mut()
+                .try_fold((self.step, value), |(step, value), stage| {
+                    stage.push(step, value)
+                });
+            if let Some((step, value)) = kept {
+                write!(self.inner, "{} ", step)?;
+                self.inner.write_all(&value)?;
+                self.inner.write_all(b"\n")?;
+            }
+        }
+        self.line.clear();
+        Ok(())
+    }
+}
+
+impl Write for Filtered {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for part in buf.split_inclusive(|&byte| byte == b'\n') {
+            self.line.extend_from_slice(part);
+            if part.ends_with(b"\n") {
+                self.end_line()?;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl SequenceSink for Filtered {
+    fn finish(self: Box<Self>) -> io::Result<Option<PathBuf>> {
+        self.inner.finish()
+    }
+
+    fn size(&self) -> u64 {
+        self.inner.size()
+    }
+}
@@ -0,0 +1,130 @@
+use crate::format::NumberFormat;
+use collatz_bigint_file::sequence::{Limits, Truncation};
+use num_bigint::BigInt;
+use num_traits::One;
+use std::time::Instant;
+
+// 3n+1 steps between two checks of the timeout
+const TIMEOUT_CHECK_INTERVAL: u64 = 64;
+
+// Threshold of a query and the first step that passed it, with its value
+struct Query {
+    label: &'static str,
+    threshold: BigInt,
+    found: Option<(usize, BigInt)>,
+}
+
+/// Prints the first step at which the trajectory of `seed` rises above `above` and the first at
+/// which it falls below `below` (both strict; step 0 is the seed), computed value by value
+/// without keeping or writing the trajectory.
+///
+/// Only 3n+1 steps can rise and a run of halvings can only fall, so runs that do not cross the
+/// `below` threshold are skipped with one shift. The computation stops as soon as every query is
+/// answered. `--max-steps`, `--timeout` and `--max-value-size` end it early.
+pub fn run(
+    seed: &str,
+    above: Option<&str>,
+    below: Option<&str>,
+    limits: &Limits,
+    number_format: &NumberFormat,
+) -> Result<(), String> {
+    let mut n = crate::parse_input(seed.to_string(), limits.max_bits.unwrap_or(u64::MAX))?;
+    let threshold = |label, value: &str| {
+        crate::parse_input(value.to_string(), u64::MAX)
+            .map(|threshold| Query {
+                label,
+                threshold,
+                found: None,
+            })
+            .map_err(|err| format!("Invalid threshold '{}': {}", value, err))
+    };
+    let mut above = above.map(|value| threshold("above", value)).transpose()?;
+    let mut below = below.map(|value| threshold("below", value)).transpose()?;
+
+    let deadline = limits.timeout.map(|timeout| Instant::now() + timeout);
+    let mut step = 0;
+    let mut rises = 0u64;
+    let mut truncated = None;
+    let pending = |query: &Option<Query>| query.as_ref().is_some_and(|query| query.found.is_none());
+    // the seed itself may already pass a threshold
+    if let Some(query) = above.as_mut().filter(|query| n > query.threshold) {
+        query.found = Some((0, n.clone()));
+    }
+    if let Some(query) = below.as_mut().filter(|query| n < query.threshold) {
+        query.found = Some((0, n.clone()));
+    }
+    while (pending(&above) || pending(&below)) && !n.is_one() {
+        let steps_left = limits
+            .max_steps
+            .map_or(usize::MAX, |max_steps| max_steps.saturating_sub(step));
+        if steps_left == 0 {
+            truncated = limits.max_steps.map(Truncation::MaxSteps);
+            break;
+        }
+        match n.trailing_zeros() {
+            Some(zeros) if zeros > 0 => {
+                let run = (zeros as usize).min(steps_left);
+                match below.as_mut() {
+                    // the run crosses the threshold: halve value by value up to the crossing
+                    Some(query) if query.found.is_none() && (&n >> run) < query.threshold => {
+                        while n >= query.threshold {
+                            n >>= 1;
+                            step += 1;
+                        }
+                        query.found = Some((step, n.clone()));
+                    }
+                    _ => {
+                        n >>= run;
+                        step += run;
+                    }
+                }
+            }
+            _ => {
+                rises += 1;
+                if rises.is_multiple_of(TIMEOUT_CHECK_INTERVAL)
+                    && deadline.is_some_and(|deadline| Instant::now() >= deadline)
+                {
+                    truncated = limits.timeout.map(Truncation::Timeout);
+                    break;
+                }
+                if limits
+                    .max_bits
+                    .is_some_and(|max_bits| n.bits() + 2 > max_bits)
+                {
+                    truncated = limits.max_bits.map(Truncation::MaxBits);
+                    break;
+                }
+                n = BigInt::from(3) * n + 1;
+                step += 1;
+                if let Some(query) = above
+                    .as_mut()
+                    .filter(|query| query.found.is_none() && n > query.threshold)
+                {
+                    query.found = Some((step, n.clone()));
+                }
+            }
+        }
+    }
+
+    for query in [above, below].into_iter().flatten() {
+        let threshold = number_format.format(&query.threshold);
+        match (query.found, truncated) {
+            (Some((step, value)), _) => println!(
+                "first {} {}: step {}, value {}",
+                query.label,
+                threshold,
+                step,
+                number_format.format(&value)
+            ),
+            (None, Some(truncation)) => println!(
+                "first {} {}: unknown, {} at step {}",
+                query.label, threshold, truncation, step
+            ),
+            (None, None) => println!(
+                "first {} {}: never, the trajectory reaches 1 at step {}",
+                query.label, threshold, step
+            ),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,195 @@
+use crate::drift;
+use num_bigint::BigInt;
+use std::env;
+
+// How values are displayed in the terminal; the output file always keeps the plain full value
+#[derive(Clone, Debug)]
+pub struct NumberFormat {
+    // separator inserted between groups of three digits (four in binary and hexadecimal)
+    pub separator: Option<String>,
+    // values with more digits are abbreviated in scientific notation
+    pub max_width: Option<usize>,
+    // base of the digits, 2 to 36
+    pub radix: u32,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        NumberFormat {
+            separator: None,
+            max_width: None,
+            radix: 10,
+        }
+    }
+}
+
+// Helper function to parse the radix option: a base from 2 to 36
+pub fn parse_radix(input: &str) -> Result<u32, String> {
+    match input.trim().parse::<u32>() {
+        Ok(radix) if (2..=36).contains(&radix) => Ok(radix),
+        _ => Err(format!(
+            "Invalid radix {:?}: expected a base from 2 to 36",
+            input
+        )),
+    }
+}
+
+// Helper function to parse the digit grouping option: "locale" or a literal separator like "_"
+pub fn parse_separator(input: &str) -> Result<String, String> {
+    match input {
+        "" => Err("Digit separator must not be empty".to_string()),
+        "locale" => Ok(locale_separator().to_string()),
+        separator => Ok(separator.to_string()),
+    }
+}
+
+// Thousands separator of the system locale (LC_ALL, LC_NUMERIC or LANG), defaults to ","
+fn locale_separator() -> &'static str {
+    let locale = ["LC_ALL", "LC_NUMERIC", "LANG"]
+        .iter()
+        .filter_map(|key| env::var(key).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default();
+    // language_REGION.encoding@modifier, e.g. de_CH.UTF-8
+    let mut parts = locale.split(['.', '@']).next().unwrap_or("").split('_');
+    let language = parts.next().unwrap_or("");
+    let region = parts.next().unwrap_or("");
+    match (language, region) {
+        // Switzerland groups with an apostrophe in every language
+        (_, "CH") => "'",
+        ("de" | "nl" | "it" | "es" | "pt" | "da" | "id" | "tr", _) => ".",
+        ("fr" | "ru" | "sv" | "fi" | "pl" | "cs" | "nb" | "uk", _) => "\u{202f}",
+        _ => ",",
+    }
+}
+
+impl NumberFormat {
+    pub fn format(&self, num: &BigInt) -> String {
+        let digits = num.to_str_radix(self.radix);
+        let (sign, magnitude) = match digits.strip_prefix('-') {
+            Some(magnitude) => ("-", magnitude),
+            None => ("", digits.as_str()),
+        };
+
+        match self.max_width {
+            Some(max_width) if magnitude.len() > max_width && self.radix == 10 => {
+                format!("{}{}", sign, scientific(magnitude))
+            }
+            // the low digits show the structure in other bases, keep both ends
+            Some(max_width) if magnitude.len() > max_width => {
+                format!("{}{}", sign, elide(magnitude, max_width))
+            }
+            _ => match &self.separator {
+                Some(separator) => {
+                    let group = if matches!(self.radix, 2 | 16) { 4 } else { 3 };
+                    format!("{}{}", sign, group_digits(magnitude, separator, group))
+                }
+                None => digits,
+            },
+        }
+    }
+}
+
+// Insert the separator between groups of digits, e.g. "1234567" -> "1_234_567"
+fn group_digits(digits: &str, separator: &str, group: usize) -> String {
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / group * separator.len());
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(group) {
+            grouped.push_str(separator);
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+// Abbreviate a decimal digit string as "≈3.21e+1204 (1205 digits)"
+fn scientific(digits: &str) -> String {
+    let mut exponent = digits.len() - 1;
+    // three significant digits, rounded half up using the fourth digit
+    let mut leading: u32 = digits[..digits.len().min(3)].parse().unwrap_or(0);
+    for _ in digits.len()..3 {
+        leading *= 10;
+    }
+    if digits.as_bytes().get(3).is_some_and(|d| *d >= b'5') {
+        leading += 1;
+    }
+    if leading == 1000 {
+        leading = 100;
+        exponent += 1;
+    }
+    format!(
+        "≈{}.{:02}e+{} ({} digits)",
+        leading / 100,
+        leading % 100,
+        exponent,
+        digits.len()
+    )
+}
+
+/// Describes `n` by its leading decimal digits, digit count and bits without converting it to
+/// decimal, e.g. "≈1.00e+3010299 (3010300 digits, 10000001 bits)", for `--digits-only`.
+///
+/// The digits follow from log10 n, computed from the 64 leading bits; the exponent is exact and
+/// the three leading digits are exact up to the rounding of the last one, except for values
+/// within about 1e-15 of a power of ten.
+pub fn approximate(n: &BigInt) -> String {
+    if n.bits() <= 64 {
+        return n.to_string();
+    }
+    let log10 = drift::log2(n) * std::f64::consts::LOG10_2;
+    let digits = log10.floor() as u64 + 1;
+    let mut exponent = digits - 1;
+    let mut leading = (10f64.powf(log10.fract()) * 100.0).round() as u32;
+    if leading >= 1000 {
+        leading = 100;
+        exponent += 1;
+    }
+    format!(
+        "≈{}.{:02}e+{} ({} digits, {} bits)",
+        leading / 100,
+        leading % 100,
+        exponent,
+        digits,
+        n.bits()
+    )
+}
+
+/// Number of digits of `n` in base `radix`, without converting it: `n < 2^bits` has at most
+/// `ceil(bits * log_radix 2)` digits and at least one less, which the logarithm of its leading
+/// bits decides. Only a value within the rounding of that logarithm of a power of the radix is
+/// compared with the power itself.
+pub fn digit_count(n: &BigInt, radix: u32) -> u64 {
+    let bits = n.bits();
+    if bits <= 1 {
+        return 1;
+    }
+    let log2_radix = f64::from(radix).log2();
+    if radix.is_power_of_two() {
+        return bits.div_ceil(log2_radix as u64);
+    }
+    let upper = (bits as f64 / log2_radix).ceil() as u64;
+    // n has `upper` digits if it is at least radix^(upper - 1)
+    let boundary = (upper - 1) as f64 * log2_radix;
+    let margin = drift::log2(n) - boundary;
+    let at_least = if margin.abs() > 1e-9 * boundary.max(1.0) {
+        margin > 0.0
+    } else {
+        *n >= BigInt::from(radix).pow((upper - 1) as u32)
+    };
+    if at_least {
+        upper
+    } else {
+        upper - 1
+    }
+}
+
+// Abbreviate a digit string in another base as "101101…000100 (4000 digits)"
+fn elide(digits: &str, max_width: usize) -> String {
+    let half = (max_width / 2).max(1);
+    format!(
+        "{}…{} ({} digits)",
+        &digits[..half],
+        &digits[digits.len() - half..],
+        digits.len()
+    )
+}
@@ -0,0 +1,180 @@
+use crate::websocket::base64;
+use clap::ValueEnum;
+use std::env;
+use std::io::{self, IsTerminal, Write};
+
+// Base64 characters per escape sequence of the kitty protocol
+const KITTY_CHUNK: usize = 4096;
+
+// Whether and how charts are also shown in the terminal
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum InlineGraphics {
+    /// Detect the protocol from the terminal, nothing if it supports none
+    #[default]
+    Auto,
+    /// The kitty graphics protocol (kitty, Ghostty, WezTerm)
+    Kitty,
+    /// Sixel graphics (foot, mlterm, xterm -ti vt340, ...)
+    Sixel,
+    /// Never show charts in the terminal
+    None,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Protocol {
+    Kitty,
+    Sixel,
+}
+
+/// The protocol to draw with: the one asked for, or with `auto` the one the terminal announces
+/// in `TERM`, `TERM_PROGRAM` or `KITTY_WINDOW_ID`, if stdout is a terminal at all.
+pub fn detect(mode: InlineGraphics) -> Option<Protocol> {
+    match mode {
+        InlineGraphics::Kitty => Some(Protocol::Kitty),
+        InlineGraphics::Sixel => Some(Protocol::Sixel),
+        InlineGraphics::None => None,
+        InlineGraphics::Auto if !io::stdout().is_terminal() => None,
+        InlineGraphics::Auto => {
+            let term = env::var("TERM").unwrap_or_default();
+            let program = env::var("TERM_PROGRAM").unwrap_or_default();
+            if env::var_os("KITTY_WINDOW_ID").is_some()
+                || matches!(term.as_str(), "xterm-kitty" | "xterm-ghostty")
+                || matches!(program.as_str(), "ghostty" | "WezTerm")
+            {
+                Some(Protocol::Kitty)
+            } else if term.contains("sixel")
+                || ["foot", "mlterm", "contour", "yaft"]
+                    .iter()
+                    .any(|name| term.starts_with(name))
+            {
+                Some(Protocol::Sixel)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// A raster image of palette colors, drawn with lines and shown in the terminal.
+pub struct Canvas {
+    width: usize,
+    height: usize,
+    // palette index of every pixel, row by row; 0 is the background
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    pub fn new(width: usize, height: usize) -> Self {
+        Canvas {
+            width,
+            height,
+            pixels: vec![0; width * height],
+        }
+    }
+
+    /// Draws a line two pixels thick from (x0, y0) to (x1, y1); parts outside are clipped.
+    pub fn line(&mut self, (x0, y0): (f64, f64), (x1, y1): (f64, f64), color: u8) {
+        let steps = (x1 - x0).abs().max((y1 - y0).abs()).ceil().max(1.0) as usize;
+        for i in 0..=steps {
+            let t = i as f64 / steps as f64;
+            let (x, y) = (x0 + (x1 - x0) * t, y0 + (y1 - y0) * t);
+            for (dx, dy) in [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)] {
+                self.set((x + dx) as isize, (y + dy) as isize, color);
+            }
+        }
+    }
+
+    fn set(&mut self, x: isize, y: isize, color: u8) {
+        if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+            self.pixels[y as usize * self.width + x as usize] = color;
+        }
+    }
+
+    /// Writes the image with `protocol`, in the `palette` colors as RGB.
+    pub fn write(
+        &self,
+        output: &mut impl Write,
+        protocol: Protocol,
+        palette: &[[u8; 3]],
+    ) -> io::Result<()> {
+        match protocol {
+            Protocol::Kitty => self.write_kitty(output, palette),
+            Protocol::Sixel => self.write_sixel(output, palette),
+        }?;
+        writeln!(output)
+    }
+
+    // Raw RGB pixels, base64 encoded and split into chunks; q=2 suppresses the replies
+    fn write_kitty(&self, output: &mut impl Write, palette: &[[u8; 3]]) -> io::Result<()> {
+        let rgb: Vec<u8> = self
+            .pixels
+            .iter()
+            .flat_map(|&color| palette[color as usize])
+            .collect();
+        let encoded = base64(&rgb);
+        let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(KITTY_CHUNK).collect();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let more = u8::from(i + 1 < chunks.len());
+            if i == 0 {
+                write!(
+                    output,
+                    "\x1b_Ga=T,f=24,q=2,s={},v={},m={};",
+                    self.width, self.height, more
+                )?;
+            } else {
+                write!(output, "\x1b_Gm={};", more)?;
+            }
+            output.write_all(chunk)?;
+            write!(output, "\x1b\\")?;
+        }
+        Ok(())
+    }
+
+    // Bands of six rows, one pass per color with run-length encoding
+    fn write_sixel(&self, output: &mut impl Write, palette: &[[u8; 3]]) -> io::Result<()> {
+        write!(output, "\x1bPq\"1;1;{};{}", self.width, self.height)?;
+        for (i, [r, g, b]) in palette.iter().enumerate() {
+            // sixel colors are percentages
+            let percent = |channel: &u8| u32::from(*channel) * 100 / 255;
+            write!(
+                output,
+                "#{};2;{};{};{}",
+                i,
+                percent(r),
+                percent(g),
+                percent(b)
+            )?;
+        }
+        let mut row = vec![0u8; self.width];
+        for band in (0..self.height).step_by(6) {
+            let rows = band..(band + 6).min(self.height);
+            for color in 0..palette.len() as u8 {
+                let mut used = false;
+                for (x, sixel) in row.iter_mut().enumerate() {
+                    *sixel = 0;
+                    for (bit, y) in rows.clone().enumerate() {
+                        if self.pixels[y * self.width + x] == color {
+                            *sixel |= 1 << bit;
+                        }
+                    }
+                    used |= *sixel != 0;
+                }
+                if !used {
+                    continue;
+                }
+                write!(output, "#{}", color)?;
+                for run in row.chunk_by(|a, b| a == b) {
+                    let c = (63 + run[0]) as char;
+                    match run.len() {
+                        len if len >= 4 => write!(output, "!{}{}", len, c)?,
+                        len => write!(output, "{}", c.to_string().repeat(len))?,
+                    }
+                }
+                // back to the start of the band for the next color
+                write!(output, "$")?;
+            }
+            write!(output, "-")?;
+        }
+        write!(output, "\x1b\\")
+    }
+}
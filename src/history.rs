@@ -0,0 +1,240 @@
+use crate::combine::json_object;
+use chrono::{DateTime, FixedOffset, Local};
+use clap::ValueEnum;
+use collatz_bigint_file::report::json_string;
+use collatz_bigint_file::warn;
+use colored::Colorize;
+use std::cmp::Reverse;
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+// largest max value kept in full in the catalog, larger ones only by their bit length
+pub const MAX_VALUE_BITS: u64 = 1024;
+
+// Default location of the catalog: $XDG_DATA_HOME/collatz/history.jsonl or ~/.local/share/collatz/history.jsonl
+pub fn default_path() -> Option<PathBuf> {
+    let data_home = match env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?)
+            .join(".local")
+            .join("share"),
+    };
+    Some(data_home.join("collatz").join("history.jsonl"))
+}
+
+/// Order of the runs listed by `history`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum HistorySort {
+    /// Newest first
+    #[default]
+    Date,
+    /// Longest total stopping time first, truncated runs after the complete ones
+    StoppingTime,
+    /// Longest glide first
+    Glide,
+    /// Largest max value first
+    MaxValue,
+    /// Longest run first
+    Duration,
+}
+
+/// One run in the catalog, written as a line of JSON.
+pub struct Entry {
+    pub time: DateTime<FixedOffset>,
+    pub version: String,
+    pub seed: String,
+    // steps computed; the total stopping time unless truncated
+    pub steps: usize,
+    pub truncated: Option<String>,
+    pub glide: Option<usize>,
+    pub even: u64,
+    pub odd: u64,
+    pub max_index: usize,
+    pub max_bits: u64,
+    // in decimal up to MAX_VALUE_BITS
+    pub max_value: Option<String>,
+    pub hash: Option<String>,
+    pub output_file: Option<String>,
+    pub duration: f64,
+}
+
+impl Entry {
+    fn to_json(&self) -> String {
+        let optional = |value: &Option<String>| value.as_deref().map_or("null".into(), json_string);
+        let number = |value: Option<usize>| value.map_or("null".into(), |value| value.to_string());
+        format!(
+            "{{\"time\":{},\"version\":{},\"seed\":{},\"steps\":{},\"truncated\":{},\"glide\":{},\"even\":{},\"odd\":{},\"max_index\":{},\"max_bits\":{},\"max_value\":{},\"hash\":{},\"output_file\":{},\"duration_seconds\":{:.3}}}",
+            json_string(&self.time.to_rfc3339()),
+            json_string(&self.version),
+            json_string(&self.seed),
+            self.steps,
+            optional(&self.truncated),
+            number(self.glide),
+            self.even,
+            self.odd,
+            self.max_index,
+            self.max_bits,
+            optional(&self.max_value),
+            optional(&self.hash),
+            optional(&self.output_file),
+            self.duration
+        )
+    }
+
+    // The entry of a catalog line, None if a field is missing or invalid
+    fn from_json(line: &str) -> Option<Entry> {
+        let mut fields = json_object(line)?;
+        let mut take = |name: &str| fields.remove(name).flatten();
+        Some(Entry {
+            time: DateTime::parse_from_rfc3339(&take("time")?).ok()?,
+            version: take("version")?,
+            seed: take("seed")?,
+            steps: take("steps")?.parse().ok()?,
+            truncated: take("truncated"),
+            glide: take("glide").map(|glide| glide.parse()).transpose().ok()?,
+            even: take("even")?.parse().ok()?,
+            odd: take("odd")?.parse().ok()?,
+            max_index: take("max_index")?.parse().ok()?,
+            max_bits: take("max_bits")?.parse().ok()?,
+            max_value: take("max_value"),
+            hash: take("hash"),
+            output_file: take("output_file"),
+            duration: take("duration_seconds")?.parse().ok()?,
+        })
+    }
+}
+
+/// Appends the entry of a run to the catalog at `path`, creating the file and its directory
+/// if needed.
+pub fn record(path: &Path, entry: &Entry) -> Result<(), String> {
+    let write_error = |err| format!("Failed to record the run in {}: {}", path.display(), err);
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(write_error)?;
+    }
+    let mut file = OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)
+        .map_err(write_error)?;
+    // one write per line, so runs finishing at the same time do not interleave
+    file.write_all(format!("{}\n", entry.to_json()).as_bytes())
+        .map_err(write_error)
+}
+
+/// Lists the runs recorded in the catalog at `path` as a table, ordered by `sort`, at most
+/// `limit` of them.
+///
+/// Truncated runs are marked with `*`, their step count is a lower bound of the total stopping
+/// time. Lines that are no entry, e.g. one cut off by a crash, are skipped with a warning.
+pub fn run(path: &Path, sort: HistorySort, limit: Option<usize>) -> Result<(), String> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            println!("No runs recorded yet in {}", path.display());
+            return Ok(());
+        }
+        Err(err) => return Err(format!("Failed to open {}: {}", path.display(), err)),
+    };
+    let mut entries = Vec::new();
+    for (line_num, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match Entry::from_json(&line) {
+            Some(entry) => entries.push(entry),
+            None => warn!(
+                "Skipping line {} of {}: not a recorded run",
+                line_num + 1,
+                path.display()
+            ),
+        }
+    }
+
+    // the catalog is in the order the runs finished, the newest last
+    entries.reverse();
+    match sort {
+        HistorySort::Date => entries.sort_by_key(|entry| Reverse(entry.time)),
+        HistorySort::StoppingTime => {
+            entries.sort_by_key(|entry| Reverse((entry.truncated.is_none(), entry.steps)))
+        }
+        HistorySort::Glide => entries.sort_by_key(|entry| Reverse(entry.glide)),
+        // decimal digits of equal length compare like the values
+        HistorySort::MaxValue => entries.sort_by_cached_key(|entry| {
+            let digits = entry.max_value.clone().unwrap_or_default();
+            Reverse((entry.max_bits, digits.len(), digits))
+        }),
+        HistorySort::Duration => entries.sort_by(|a, b| b.duration.total_cmp(&a.duration)),
+    }
+    let listed = limit.unwrap_or(entries.len()).min(entries.len());
+
+    let header = [
+        "date",
+        "seed",
+        "total stopping time",
+        "glide",
+        "max bits",
+        "duration",
+        "output file",
+    ];
+    let cells: Vec<[String; 7]> = entries[..listed]
+        .iter()
+        .map(|entry| {
+            [
+                entry
+                    .time
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string(),
+                entry.seed.clone(),
+                match entry.truncated {
+                    Some(_) => format!("{}*", entry.steps),
+                    None => entry.steps.to_string(),
+                },
+                entry
+                    .glide
+                    .map_or("-".to_string(), |glide| glide.to_string()),
+                entry.max_bits.to_string(),
+                format!("{:.3}s", entry.duration),
+                entry.output_file.clone().unwrap_or("-".to_string()),
+            ]
+        })
+        .collect();
+    let mut widths = header.map(str::len);
+    for row in &cells {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let line: Vec<String> = header
+        .iter()
+        .zip(widths)
+        .map(|(title, width)| format!("{:<width$}", title, width = width))
+        .collect();
+    println!("{}", line.join("  ").trim_end().bold());
+    for row in &cells {
+        let line: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .enumerate()
+            .map(|(column, (cell, width))| match column {
+                // the dates, seeds and files read from the left, the numbers from the right
+                0 | 1 | 6 => format!("{:<width$}", cell, width = width),
+                _ => format!("{:>width$}", cell, width = width),
+            })
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    }
+    if entries[..listed]
+        .iter()
+        .any(|entry| entry.truncated.is_some())
+    {
+        println!("* truncated, the steps computed before the limit");
+    }
+    if listed < entries.len() {
+        println!("{} of {} runs", listed, entries.len());
+    }
+    Ok(())
+}
@@ -0,0 +1,145 @@
+//! Translations of the prompt, the input errors and the summary of a run.
+//!
+//! The English text is the key: `tr!("even: {}", even)` looks the template up in the catalog of
+//! the language chosen with `--lang` or the system locale and fills the `{}` placeholders in
+//! order. Texts missing from a catalog stay English.
+
+use std::env;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Lang {
+    #[default]
+    En,
+    De,
+}
+
+impl FromStr for Lang {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.trim().to_lowercase().as_str() {
+            "en" | "english" => Ok(Lang::En),
+            "de" | "german" | "deutsch" => Ok(Lang::De),
+            other => Err(format!("Unknown language '{}'. Use en or de", other)),
+        }
+    }
+}
+
+// Language of the system locale (LC_ALL, LC_MESSAGES or LANG), English if it has no catalog
+pub fn system_lang() -> Lang {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|key| env::var(key).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default();
+    match locale.get(..2) {
+        Some("de") => Lang::De,
+        _ => Lang::En,
+    }
+}
+
+pub fn init(lang: Lang) {
+    LANG.get_or_init(|| lang);
+}
+
+// Template of `text` in the chosen language
+pub fn tr(text: &'static str) -> &'static str {
+    match LANG.get().copied().unwrap_or_default() {
+        Lang::En => text,
+        Lang::De => german(text).unwrap_or(text),
+    }
+}
+
+// Replace the `{}` placeholders of a template by the arguments in order
+pub fn fill(template: &str, args: &[&dyn fmt::Display]) -> String {
+    let mut filled = String::with_capacity(template.len());
+    let mut args = args.iter();
+    let mut parts = template.split("{}");
+    filled.push_str(parts.next().unwrap_or_default());
+    for part in parts {
+        if let Some(arg) = args.next() {
+            filled.push_str(&arg.to_string());
+        }
+        filled.push_str(part);
+    }
+    filled
+}
+
+macro_rules! tr {
+    ($text:literal) => {
+        $crate::i18n::tr($text)
+    };
+    ($text:literal, $($arg:expr),+ $(,)?) => {
+        $crate::i18n::fill($crate::i18n::tr($text), &[$(&$arg),+])
+    };
+}
+
+fn german(text: &str) -> Option<&'static str> {
+    Some(match text {
+        "Enter a positiv integer as start value for the Collatz sequence (e.g., 27 or 2^199-1 or 2^199):" => {
+            "Geben Sie eine positive ganze Zahl als Startwert der Collatz-Folge ein (z. B. 27, 2^199-1 oder 2^199):"
+        }
+        "Invalid input. Please enter a valid positive integer or a valid expression like '2^199' or '2^199-1'." => {
+            "Ungültige Eingabe. Bitte eine positive ganze Zahl oder einen Ausdruck wie '2^199' oder '2^199-1' eingeben."
+        }
+        "The start value has about {} bits, more than the limit of {} bits ({} bytes). Raise --max-value-size to compute it anyway." => {
+            "Der Startwert hat etwa {} Bits, mehr als die Grenze von {} Bits ({} Bytes). Mit einem größeren --max-value-size wird er trotzdem berechnet."
+        }
+        "Input: {}" => "Eingabe: {}",
+        "seed: {}" => "Startwert: {}",
+        "Parsed input: {}" => "Eingelesener Wert: {}",
+        "Parsed input: unknown (resumed file without header)" => {
+            "Eingelesener Wert: unbekannt (fortgesetzte Datei ohne Kopf)"
+        }
+        "Output file: {}" => "Ausgabedatei: {}",
+        "Output: {}" => "Ausgabe: {}",
+        "total stopping time: unknown, {} after {} steps (continue with --resume {})" => {
+            "Gesamtstoppzeit: unbekannt, {} nach {} Schritten (fortsetzen mit --resume {})"
+        }
+        "total stopping time: unknown, {} after {} steps" => {
+            "Gesamtstoppzeit: unbekannt, {} nach {} Schritten"
+        }
+        "total stopping time: {}" => "Gesamtstoppzeit: {}",
+        "stopping time below the seed (glide): {}" => "Stoppzeit unter den Startwert (Glide): {}",
+        "stopping time below the seed (glide): not reached yet" => {
+            "Stoppzeit unter den Startwert (Glide): noch nicht erreicht"
+        }
+        "stopping time below the seed (glide): never below the seed" => {
+            "Stoppzeit unter den Startwert (Glide): nie unter dem Startwert"
+        }
+        "stopping time below the seed (glide): unknown (resumed file without header)" => {
+            "Stoppzeit unter den Startwert (Glide): unbekannt (fortgesetzte Datei ohne Kopf)"
+        }
+        "first power of two: 2^{} at step {}" => "erste Zweierpotenz: 2^{} bei Schritt {}",
+        "even: {}" => "gerade: {}",
+        "odd: {}" => "ungerade: {}",
+        "max pos: {}" => "Position des Maximums: {}",
+        "max value: {}" => "Maximalwert: {}",
+        "unparseable lines: {} (skipped)" => "unlesbare Zeilen: {} (übersprungen)",
+        "highest peaks:" => "höchste Gipfel:",
+        "highest peaks of the continued part:" => "höchste Gipfel des fortgesetzten Teils:",
+        "{} at step {}" => "{} bei Schritt {}",
+        "peak: {} at step {}" => "Gipfel: {} bei Schritt {}",
+        "hash: {}" => "Hash: {}",
+        "share: {}" => "Teilen: {}",
+        "hash: {} (of the truncated sequence)" => "Hash: {} (der abgebrochenen Folge)",
+        "hash: unknown (resumed file without header)" => {
+            "Hash: unbekannt (fortgesetzte Datei ohne Kopf)"
+        }
+        "hash: not computed (--digits-only)" => "Hash: nicht berechnet (--digits-only)",
+        "halvings after odd steps, ν₂(3n+1): mean {} (expected 2)" => {
+            "Halbierungen nach ungeraden Schritten, ν₂(3n+1): Mittel {} (erwartet 2)"
+        }
+        "total time: {}s (generation {}s, analysis {}s)" => {
+            "Gesamtzeit: {}s (Erzeugung {}s, Analyse {}s)"
+        }
+        "steps/s: {}" => "Schritte/s: {}",
+        "digits/s: {} ({} digits written)" => "Ziffern/s: {} ({} Ziffern geschrieben)",
+        _ => return None,
+    })
+}
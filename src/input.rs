@@ -0,0 +1,214 @@
+//! Parser of start values: decimal integers and expressions like `2^199`, `2^199-1` or
+//! `2^(10^7)`.
+//!
+//! Shared by the command line tool and the fuzz targets in `fuzz/`, which check that no input
+//! makes it panic and that every accepted value is a positive integer within the size limit.
+
+use num_bigint::BigInt;
+use num_traits::Zero;
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// Why an input is not a start value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SeedError {
+    /// Neither a positive decimal integer nor an expression `b^e` or `b^e-s` with a positive result
+    /// and an exponent that fits `u32`
+    Invalid,
+    /// The value has about `bits` bits, more than `max_bits`
+    TooLarge { bits: u64, max_bits: u64 },
+}
+
+/// Parses `input` as a start value of at most `max_bits` bits.
+///
+/// Surrounding whitespace is ignored. The exponent may itself be a power, a tower like
+/// `2^(10^7)` or `2^10^7`, which is evaluated from the right as usual. Only ASCII digits count:
+/// other Unicode digits, numbers or exponents beyond `u32` in an expression and expressions
+/// whose result is not positive (`0^5`, `2^3-8`) are invalid. The size of `b^e` is checked
+/// before the power is computed.
+pub fn parse_seed(input: &str, max_bits: u64) -> Result<BigInt, SeedError> {
+    static EXPRESSION: OnceLock<Regex> = OnceLock::new();
+    let expression = EXPRESSION.get_or_init(|| {
+        Regex::new(r"^([0-9]+)\^(\([0-9]+(?:\^[0-9]+)+\)|[0-9]+(?:\^[0-9]+)*)(?:-([0-9]+))?$")
+            .expect("valid regex")
+    });
+    let input = input.trim();
+    let value = match expression.captures(input) {
+        Some(captures) => {
+            let number = |i: usize| {
+                captures
+                    .get(i)
+                    .map_or(Ok(0), |m| m.as_str().parse::<u32>())
+                    .map_err(|_| SeedError::Invalid)
+            };
+            let (base, subtract) = (number(1)?, number(3)?);
+            let exponent = tower(captures[2].trim_matches(['(', ')']))?;
+
+            // bit length of base^exponent, checked before the power is allocated
+            let bits = f64::from(exponent) * f64::from(base).log2();
+            if bits > max_bits as f64 {
+                return Err(SeedError::TooLarge {
+                    bits: bits as u64,
+                    max_bits,
+                });
+            }
+            BigInt::from(base).pow(exponent) - BigInt::from(subtract)
+        }
+        // a plain integer, without the sign `BigInt` would accept
+        None if !input.is_empty() && input.bytes().all(|byte| byte.is_ascii_digit()) => {
+            input.parse::<BigInt>().map_err(|_| SeedError::Invalid)?
+        }
+        None => return Err(SeedError::Invalid),
+    };
+    if value <= BigInt::zero() {
+        return Err(SeedError::Invalid);
+    }
+    if value.bits() > max_bits {
+        return Err(SeedError::TooLarge {
+            bits: value.bits(),
+            max_bits,
+        });
+    }
+    Ok(value)
+}
+
+// Value of an exponent like "10^7" or "2^3^2", evaluated from the right; invalid beyond u32
+fn tower(exponent: &str) -> Result<u32, SeedError> {
+    exponent
+        .rsplit('^')
+        .try_fold(None, |power: Option<u32>, base| {
+            let base = base.parse::<u32>().map_err(|_| SeedError::Invalid)?;
+            Ok(Some(match power {
+                None => base,
+                Some(power) => base.checked_pow(power).ok_or(SeedError::Invalid)?,
+            }))
+        })?
+        .ok_or(SeedError::Invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn parse(input: &str) -> Result<BigInt, SeedError> {
+        parse_seed(input, 1 << 20)
+    }
+
+    #[test]
+    fn towers_are_evaluated_from_the_right() {
+        assert_eq!(parse("2^3^2"), Ok(BigInt::from(512)));
+        assert_eq!(parse("2^(3^2)"), Ok(BigInt::from(512)));
+        assert_eq!(parse("2^2^2^2"), Ok(BigInt::from(65536)));
+        assert_eq!(parse("2^(2^2^2)-1"), Ok(BigInt::from(65535)));
+        assert_eq!(parse("10^1^5"), Ok(BigInt::from(10)));
+        // the parentheses only group a whole tower
+        assert_eq!(parse("(2^3)^2"), Err(SeedError::Invalid));
+        assert_eq!(parse("2^(3)"), Err(SeedError::Invalid));
+        assert_eq!(parse("2^(3^2"), Err(SeedError::Invalid));
+    }
+
+    #[test]
+    fn towers_beyond_u32_or_the_limit_are_rejected_before_the_power() {
+        assert_eq!(parse("2^2^2^2^2^2"), Err(SeedError::Invalid));
+        assert_eq!(parse("2^(2^32)"), Err(SeedError::Invalid));
+        assert_eq!(parse("2^(10^10)"), Err(SeedError::Invalid));
+        assert_eq!(
+            parse("2^(10^7)"),
+            Err(SeedError::TooLarge {
+                bits: 10_000_000,
+                max_bits: 1 << 20
+            })
+        );
+        assert_eq!(
+            parse_seed("2^2^2^2^2", 60_000),
+            Err(SeedError::TooLarge {
+                bits: 65536,
+                max_bits: 60_000
+            })
+        );
+        assert_eq!(parse("2^2^2^2^2").map(|n| n.bits()), Ok(65537));
+    }
+
+    #[test]
+    fn incomplete_expressions_are_invalid() {
+        for input in [
+            "2^", "^5", "^", "2^^5", "2^5-", "-5", "2^-5", "+5", "2^5+1", "2*5", "",
+        ] {
+            assert_eq!(parse(input), Err(SeedError::Invalid), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn results_must_be_positive() {
+        for input in ["0", "000", "0^5", "2^3-8", "2^3-9", "1^5-1"] {
+            assert_eq!(parse(input), Err(SeedError::Invalid), "{:?}", input);
+        }
+        assert_eq!(parse("2^0"), Ok(BigInt::from(1)));
+        assert_eq!(parse("0^0"), Ok(BigInt::from(1)));
+        assert_eq!(parse("007"), Ok(BigInt::from(7)));
+    }
+
+    #[test]
+    fn only_ascii_digits_count() {
+        // Arabic-Indic, fullwidth and superscript digits, and a Roman numeral
+        for input in ["٢٧", "２７", "2^²", "2²", "Ⅻ", "2^٣"] {
+            assert_eq!(parse(input), Err(SeedError::Invalid), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn surrounding_whitespace_is_ignored() {
+        for input in [" 27", "27\n", "\t27\r\n", "\u{a0}27\u{2003}", "\u{3000}27"] {
+            assert_eq!(parse(input), Ok(BigInt::from(27)), "{:?}", input);
+        }
+        assert_eq!(parse(" 2^5-1 "), Ok(BigInt::from(31)));
+        for input in ["2 7", "2 ^5", "2^ 5", "2^5 -1", "2^5\u{200b}"] {
+            assert_eq!(parse(input), Err(SeedError::Invalid), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn the_limit_counts_bits() {
+        assert_eq!(parse_seed("255", 8), Ok(BigInt::from(255)));
+        assert_eq!(
+            parse_seed("256", 8),
+            Err(SeedError::TooLarge {
+                bits: 9,
+                max_bits: 8
+            })
+        );
+        assert_eq!(parse_seed("2^8-1", 8), Ok(BigInt::from(255)));
+    }
+
+    proptest! {
+        #[test]
+        fn decimal_integers_round_trip(n in 1u128.., zeros in 0usize..3) {
+            let input = format!("{}{}", "0".repeat(zeros), n);
+            prop_assert_eq!(parse(&input), Ok(BigInt::from(n)));
+        }
+
+        #[test]
+        fn expressions_match_the_power(base in 0u32..1000, exponent in 0u32..200, subtract in 0u32..1000) {
+            let input = format!("{}^{}-{}", base, exponent, subtract);
+            let value = BigInt::from(base).pow(exponent) - BigInt::from(subtract);
+            if value > BigInt::zero() {
+                prop_assert_eq!(parse_seed(&input, u64::MAX), Ok(value));
+            } else {
+                prop_assert_eq!(parse_seed(&input, u64::MAX), Err(SeedError::Invalid));
+            }
+        }
+
+        #[test]
+        fn any_text_is_rejected_or_a_positive_value_within_the_limit(input in "\\PC{0,24}|[0-9^()\\- ]{0,24}") {
+            match parse_seed(&input, 4096) {
+                Ok(value) => {
+                    prop_assert!(value > BigInt::zero());
+                    prop_assert!(value.bits() <= 4096);
+                }
+                Err(SeedError::TooLarge { max_bits, .. }) => prop_assert_eq!(max_bits, 4096),
+                Err(SeedError::Invalid) => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use crate::format::NumberFormat;
+use collatz_bigint_file::stats::Row;
+use num_bigint::BigInt;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io::{self, Write};
+
+// The best `k` entries by a metric, as a min-heap so the weakest one is dropped first. Among
+// equal metrics the earlier seed ranks higher.
+struct Board<T: Ord> {
+    heap: BinaryHeap<Reverse<(T, Reverse<u64>, String)>>,
+    k: usize,
+}
+
+impl<T: Ord> Board<T> {
+    fn new(k: usize) -> Self {
+        Board {
+            heap: BinaryHeap::with_capacity(k + 1),
+            k,
+        }
+    }
+
+    // Whether `metric` of the seed number `index` makes it onto the board
+    fn qualifies(&self, metric: &T, index: u64) -> bool {
+        self.heap.len() < self.k
+            || self
+                .heap
+                .peek()
+                .is_some_and(|Reverse((weakest, weakest_index, _))| {
+                    (metric, Reverse(index)) > (weakest, *weakest_index)
+                })
+    }
+
+    fn push(&mut self, metric: T, index: u64, seed: &str) {
+        self.heap
+            .push(Reverse((metric, Reverse(index), seed.to_string())));
+        if self.heap.len() > self.k {
+            self.heap.pop();
+        }
+    }
+
+    // Entries from the best to the weakest
+    fn ranked(&self) -> Vec<&(T, Reverse<u64>, String)> {
+        let mut entries: Vec<_> = self.heap.iter().map(|Reverse(entry)| entry).collect();
+        entries.sort_by(|a, b| b.cmp(a));
+        entries
+    }
+}
+
+/// The top K seeds of a batch by total stopping time, by max value and by glide.
+///
+/// Each leaderboard is a heap of K entries, so a batch of any size keeps only 3·K seeds.
+/// Truncated trajectories are left out of the stopping time and max value boards, their
+/// numbers are only lower bounds, and seeds that never drop below themselves out of the glide
+/// board.
+pub struct Leaderboards {
+    seen: u64,
+    stopping_time: Board<usize>,
+    max_value: Board<BigInt>,
+    glide: Board<usize>,
+}
+
+impl Leaderboards {
+    pub fn new(k: usize) -> Self {
+        Leaderboards {
+            seen: 0,
+            stopping_time: Board::new(k),
+            max_value: Board::new(k),
+            glide: Board::new(k),
+        }
+    }
+
+    pub fn record(&mut self, row: &Row) {
+        let index = self.seen;
+        self.seen += 1;
+        if row.truncated.is_none() {
+            if self.stopping_time.qualifies(&row.stopping_time, index) {
+                self.stopping_time.push(row.stopping_time, index, &row.seed);
+            }
+            if self.max_value.qualifies(&row.max_value, index) {
+                self.max_value.push(row.max_value.clone(), index, &row.seed);
+            }
+        }
+        if let Some(glide) = row.glide {
+            if self.glide.qualifies(&glide, index) {
+                self.glide.push(glide, index, &row.seed);
+            }
+        }
+    }
+
+    pub fn write(&self, output: &mut impl Write, number_format: &NumberFormat) -> io::Result<()> {
+        let k = self.stopping_time.k;
+        writeln!(output)?;
+        writeln!(output, "top {} by total stopping time:", k)?;
+        for (rank, (stopping_time, _, seed)) in self.stopping_time.ranked().into_iter().enumerate()
+        {
+            writeln!(output, "{:>4}. {}: {}", rank + 1, seed, stopping_time)?;
+        }
+        writeln!(output, "top {} by max value:", k)?;
+        for (rank, (max_value, _, seed)) in self.max_value.ranked().into_iter().enumerate() {
+            writeln!(
+                output,
+                "{:>4}. {}: {}",
+                rank + 1,
+                seed,
+                number_format.format(max_value)
+            )?;
+        }
+        writeln!(output, "top {} by glide:", k)?;
+        for (rank, (glide, _, seed)) in self.glide.ranked().into_iter().enumerate() {
+            writeln!(output, "{:>4}. {}: {}", rank + 1, seed, glide)?;
+        }
+        Ok(())
+    }
+}
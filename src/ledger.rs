@@ -0,0 +1,135 @@
+use crate::work::{parse_range, Range, Report, REPORT_HEADER};
+use collatz_bigint_file::warn;
+use num_bigint::BigInt;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+// First line of a ledger, with the version of the format
+const LEDGER_HEADER: &str = "# collatz_bigint_file ledger 1";
+
+/// Ledger of the completed work units of a worker, so an interrupted scan of a range restarts
+/// where it stopped.
+///
+/// The file starts with the range and the unit size, which fix the units, followed by the
+/// report of every completed unit in the order they finished. A report is appended and synced
+/// to disk only once its unit is done, so a unit is either in the ledger as a whole or computed
+/// again; a report cut off by a crash is dropped when the ledger is opened.
+pub struct Ledger {
+    path: PathBuf,
+    file: File,
+    // reports of the units completed by earlier runs
+    done: Vec<Report>,
+}
+
+impl Ledger {
+    /// Opens the ledger of `range` in units of `unit_size` seeds, or creates it if the file does
+    /// not exist yet. A ledger of another range or unit size is an error.
+    pub fn open(path: &Path, range: &Range, unit_size: &BigInt) -> Result<Ledger, String> {
+        let open_error = |err| format!("Failed to open {}: {}", path.display(), err);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)
+            .map_err(open_error)?;
+        let mut content = String::new();
+        file.read_to_string(&mut content)
+            .map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+        let mut ledger = Ledger {
+            path: path.to_path_buf(),
+            file,
+            done: Vec::new(),
+        };
+        if content.is_empty() {
+            ledger.append(&format!(
+                "{}\nrange: {}\nunit size: {}\n",
+                LEDGER_HEADER, range, unit_size
+            ))?;
+            return Ok(ledger);
+        }
+
+        let invalid = |reason: &str| {
+            format!(
+                "{} is not a ledger of {}: {}",
+                path.display(),
+                range,
+                reason
+            )
+        };
+        let mut lines = content.split_inclusive('\n');
+        if lines.next().map(str::trim) != Some(LEDGER_HEADER) {
+            return Err(invalid("the header is missing"));
+        }
+        let ledger_range = lines
+            .next()
+            .and_then(|line| line.trim().strip_prefix("range:"))
+            .map(parse_range)
+            .transpose()?;
+        if ledger_range.as_ref() != Some(range) {
+            return Err(invalid("it was started for another range"));
+        }
+        let ledger_unit_size = lines
+            .next()
+            .and_then(|line| line.trim().strip_prefix("unit size:"))
+            .and_then(|size| size.trim().parse::<BigInt>().ok());
+        if ledger_unit_size.as_ref() != Some(unit_size) {
+            return Err(invalid("it was started with another --unit-size"));
+        }
+
+        // reports end with their elapsed time; anything after the last complete one was cut off
+        let mut offset = content.len() - lines.clone().map(str::len).sum::<usize>();
+        let mut valid = offset;
+        let mut block = String::new();
+        for line in lines {
+            offset += line.len();
+            block.push_str(line);
+            if line.trim() == REPORT_HEADER && block.len() != line.len() {
+                return Err(format!(
+                    "{}: incomplete unit before byte {}",
+                    path.display(),
+                    offset - line.len()
+                ));
+            }
+            if line.starts_with("elapsed:") {
+                let report =
+                    Report::parse(&block).map_err(|err| format!("{}: {}", path.display(), err))?;
+                ledger.done.push(report);
+                block.clear();
+                valid = offset;
+            }
+        }
+        if valid < content.len() {
+            warn!(
+                "Dropping the incomplete unit at the end of {}, it is computed again",
+                path.display()
+            );
+            ledger
+                .file
+                .set_len(valid as u64)
+                .map_err(|err| format!("Failed to repair {}: {}", path.display(), err))?;
+        }
+        Ok(ledger)
+    }
+
+    // Reports of the units completed by earlier runs
+    pub fn take_done(&mut self) -> Vec<Report> {
+        std::mem::take(&mut self.done)
+    }
+
+    /// Appends the report of a completed unit and syncs it to disk.
+    pub fn record(&mut self, report: &Report) -> Result<(), String> {
+        let mut text = Vec::new();
+        report
+            .write(&mut text)
+            .expect("writing to memory does not fail");
+        self.append(&String::from_utf8_lossy(&text))
+    }
+
+    fn append(&mut self, text: &str) -> Result<(), String> {
+        self.file
+            .write_all(text.as_bytes())
+            .and_then(|()| self.file.sync_data())
+            .map_err(|err| format!("Failed to write {}: {}", self.path.display(), err))
+    }
+}
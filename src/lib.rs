@@ -0,0 +1,21 @@
+//! Core of `collatz_bigint_file`: the sequence loop and the writer of the sequence file.
+//!
+//! The command line tool in `main.rs` builds on these; they are a library so the benchmarks in
+//! `benches/` and the examples can measure them directly, and so they compile to WebAssembly
+//! for the demo page in `web/` (`wasm` module, only built for `wasm32`).
+
+pub mod condition;
+pub mod digits;
+pub mod ffi;
+pub mod hash;
+pub mod input;
+pub mod log;
+pub mod progress;
+#[cfg(feature = "python")]
+mod python;
+pub mod report;
+pub mod sequence;
+pub mod stats;
+pub mod value;
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
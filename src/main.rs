@@ -1,15 +1,1063 @@
+use chrono::Local; // Import the 'chrono' crate for timestamps
+use clap::{CommandFactory, Parser, Subcommand}; // Import the 'clap' crate for command line argument parsing
 use colored::Color; // Import the 'colored' crate for text coloring in the terminal
-use colored::Colorize;
 use num_bigint::BigInt; // Import the 'num_bigint' crate for handling large integers with 'BigInt'
-use num_traits::{One, Zero}; // Import 'num_traits' for numeric traits like 'One' and 'Zero' for BigInt operations
-use regex::Regex; // Import the 'regex' crate for regular expression parsing
+use num_traits::Zero; // Import 'num_traits' for numeric traits like 'Zero' for BigInt operations
 use std::fs::File; // Import the 'std::fs' and 'std::io' modules for file operations and input/output
 use std::io;
-use std::io::{BufRead, BufWriter, Write};
-use std::path::PathBuf; // Import the 'std::path' module for working with file paths
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf}; // Import the 'std::path' module for working with file paths
+use std::time::{Duration, Instant};
 
-//output_file_path
-const OUTPUT_FILE_PATH: &str = "/Users/ralf/Projects/output_files/collatz_sequence.txt";
+mod animate;
+mod art;
+mod at;
+mod auto;
+mod batch;
+mod certificate;
+mod chart;
+mod clipboard;
+mod coalesce;
+mod color;
+mod combine;
+mod compare;
+mod completions;
+mod config;
+mod diff;
+mod distribution;
+mod drift;
+mod dump;
+mod explore;
+mod filter;
+mod first;
+mod format;
+mod graphics;
+mod history;
+#[macro_use]
+mod i18n;
+mod leaderboard;
+mod ledger;
+mod merge;
+mod metadata;
+mod metrics;
+mod notify;
+mod output;
+mod parity;
+mod parity_vector;
+mod parquet;
+mod parts;
+mod plan;
+mod plot;
+mod profile;
+mod qr;
+mod random;
+mod reconstruct;
+mod repl;
+mod residue;
+mod resume;
+mod scan;
+mod selftest;
+mod server;
+mod share;
+mod sink;
+mod slice;
+mod sonify;
+mod store;
+mod timing;
+mod verify;
+mod visited;
+mod watch;
+mod websocket;
+mod work;
+
+use batch::{Normalize, Normalizer, StatsFormat, Unresolved};
+use collatz_bigint_file::condition::Condition;
+use collatz_bigint_file::hash::Sha256;
+use collatz_bigint_file::input::{self, SeedError};
+use collatz_bigint_file::report::{json_string, SequenceStats};
+use collatz_bigint_file::sequence::{
+    collatz, collatz_fast_io, collatz_in_radix, Generated, Limits, Map, Peaks, Profile, Truncation,
+    Until,
+};
+use collatz_bigint_file::stats::{PowerOfTwoEntry, StoppingTime, Valuations};
+use collatz_bigint_file::value::Backend;
+use collatz_bigint_file::{info, log, progress, warn};
+use color::{ColorMode, ColorScheme};
+use config::Config;
+use drift::DriftAnalysis;
+use dump::{Dump, PrintLimit};
+use filter::{Filter, Filtered};
+use format::NumberFormat;
+use graphics::InlineGraphics;
+use history::HistorySort;
+use i18n::Lang;
+use leaderboard::Leaderboards;
+use metadata::{Footer, Header};
+use parity::{Analysis, ParityAnalysis};
+use parity_vector::ParityVector;
+use parts::PartWriter;
+use scan::{Line, Malformed, Scanner};
+use sink::{
+    Annotated, Budgeted, Condensed, Coverage, Radix, Sampled, SequenceSink, Sink, Tallied, Tally,
+};
+use slice::SliceFormat;
+use sonify::Sonification;
+use timing::Timing;
+
+//output directory
+const OUTPUT_DIR: &str = "/Users/ralf/Projects/output_files";
+// width in characters of the largest bar of the valuation histogram in the summary
+const HISTOGRAM_WIDTH: u64 = 40;
+// pause between the steps of --animate
+const ANIMATION_DELAY: Duration = Duration::from_millis(500);
+// largest max value sent in full with --notify-webhook or --notify-email
+const NOTIFY_MAX_VALUE_BITS: u64 = 1024;
+
+/// Computes the Collatz sequence of a (possibly huge) start value via a file on disk.
+///
+/// Defaults for the options can be set in `~/.config/collatz/config.toml` or with
+/// `COLLATZ_*` environment variables; command line flags take precedence.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Config file to load instead of ~/.config/collatz/config.toml
+    #[arg(long, env = "COLLATZ_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// Directory where the sequence file is written
+    #[arg(long, env = "COLLATZ_OUTPUT_DIR")]
+    output_dir: Option<PathBuf>,
+
+    /// Output file name template; supports {seed_hash}, {seed_bits} and {timestamp} [default: collatz_sequence.txt]
+    #[arg(long, value_name = "TEMPLATE", env = "COLLATZ_OUTPUT_NAME")]
+    output_name: Option<String>,
+
+    /// Overwrite the output file if it already exists
+    #[arg(long)]
+    force: bool,
+
+    /// Capacity of the write and read buffers of the sequence file (e.g. "64K", "8M") [default: 1M]
+    #[arg(long, value_name = "SIZE", env = "COLLATZ_BUFFER_SIZE", value_parser = output::parse_size)]
+    buffer_size: Option<u64>,
+
+    /// Derive the decimal digits of each value from those of the previous one instead of converting every value anew; much faster for seeds with many thousands of digits
+    #[arg(long, env = "COLLATZ_FAST_IO")]
+    fast_io: bool,
+
+    /// Representation of the values: "auto" (a u128 while a value fits, a big integer above) or "big" (a big integer for every value); the results are the same [default: auto]
+    #[arg(long, value_name = "BACKEND", env = "COLLATZ_FORCE_BACKEND", value_parser = str::parse::<Backend>)]
+    force_backend: Option<Backend>,
+
+    /// Stop the computation after this many steps and mark the sequence as truncated
+    #[arg(long, value_name = "STEPS", env = "COLLATZ_MAX_STEPS")]
+    max_steps: Option<usize>,
+
+    /// Stop the computation after this wall-clock time (e.g. "90s", "15m", "2h") and mark the sequence as truncated
+    #[arg(long, value_name = "DURATION", env = "COLLATZ_TIMEOUT", value_parser = timing::parse_duration)]
+    timeout: Option<Duration>,
+
+    /// Largest size of a single value in memory (e.g. "512M", "1G"); the computation stops cleanly before a value outgrows it [default: 1G]
+    #[arg(long, value_name = "SIZE", env = "COLLATZ_MAX_VALUE_SIZE", value_parser = output::parse_size)]
+    max_value_size: Option<u64>,
+
+    /// Value to compute down to: "one" (the whole sequence), "below-seed" (stop at the first value below the seed) or "value:N" (stop at the first value at or below N); an early stop marks the sequence as truncated [default: one]
+    #[arg(long, value_name = "STOP", env = "COLLATZ_UNTIL", value_parser = str::parse::<Until>, conflicts_with = "resume")]
+    until: Option<Until>,
+
+    /// Stop as soon as a condition holds after a step, e.g. "n < seed || step > 10^7 || bits > 100000", over the variables n, seed, step, bits (of n) and max; an early stop marks the sequence as truncated
+    #[arg(long, value_name = "CONDITION", env = "COLLATZ_STOP_WHEN", value_parser = str::parse::<Condition>, conflicts_with = "resume")]
+    stop_when: Option<Condition>,
+
+    /// Continue an interrupted run from an existing sequence file (or manifest) in append mode
+    #[arg(long, value_name = "FILE", conflicts_with = "bench")]
+    resume: Option<PathBuf>,
+
+    /// Where the sequence goes: "file", "stdout", "null", "tcp://host:port" or "unix:///path" [default: file]
+    #[arg(long, env = "COLLATZ_SINK", value_parser = str::parse::<Sink>)]
+    sink: Option<Sink>,
+
+    /// Write only the odd terms, each after the number of halvings leading to it; the full sequence can be reconstructed
+    #[arg(long, env = "COLLATZ_ODD_ONLY", conflicts_with = "resume")]
+    odd_only: bool,
+
+    /// Write only every K-th term with its step, plus the largest term of every K terms and the last K terms, for plotting
+    #[arg(long, value_name = "K", env = "COLLATZ_SAMPLE_EVERY", value_parser = clap::value_parser!(u64).range(1..), conflicts_with_all = ["resume", "odd_only"])]
+    sample_every: Option<u64>,
+
+    /// Keep only the values passing a stack of filters, applied in order and written with their step: "odd-only", "records", "peaks", "every:K", "above:N", e.g. "odd-only,records"
+    #[arg(long, value_name = "FILTERS", env = "COLLATZ_FILTER", value_parser = str::parse::<Filter>, value_delimiter = ',', conflicts_with_all = ["resume", "odd_only", "sample_every", "max_disk"])]
+    filter: Vec<Filter>,
+
+    /// Prefix every value line with its step and the rule that produced it, e.g. "77: 3n+1 -> 9232"
+    #[arg(long, env = "COLLATZ_ANNOTATE", conflicts_with_all = ["resume", "odd_only", "sample_every", "filter"])]
+    annotate: bool,
+
+    /// Base of the values in the file and the terminal, from 2 to 36, e.g. 2 for binary or 16 for hexadecimal [default: 10]
+    #[arg(long, value_name = "N", env = "COLLATZ_RADIX", value_parser = format::parse_radix, conflicts_with = "resume")]
+    radix: Option<u32>,
+
+    /// Never convert values to decimal, for seeds like 2^(10^7): the file holds the values in a power-of-two --radix (16 by default) and the summary gives the leading digits, digit count and bits
+    #[arg(long, env = "COLLATZ_DIGITS_ONLY", conflicts_with_all = ["resume", "fast_io", "odd_only", "sample_every", "filter", "annotate", "analyze", "sonify", "parity_file", "max_disk", "copy_stats"])]
+    digits_only: bool,
+
+    /// Split the sequence into numbered part files of about this size (e.g. "10G") plus a manifest
+    #[arg(long, value_name = "SIZE", env = "COLLATZ_MAX_PART_SIZE", value_parser = output::parse_size)]
+    max_part_size: Option<u64>,
+
+    /// Disk budget of the sequence file (e.g. "10G"): a run estimated to exceed it writes only the odd terms or a sample instead, and while it is written the file thins out further (odd terms, then a sample, then no values) to stay within the budget; the computation runs to the end
+    #[arg(long, value_name = "SIZE", env = "COLLATZ_MAX_DISK", value_parser = output::parse_size)]
+    max_disk: Option<u64>,
+
+    /// Coloring mode of the printed sequence [default: parity]
+    #[arg(long, value_enum, env = "COLLATZ_COLOR_MODE")]
+    color_mode: Option<ColorMode>,
+
+    /// Color of even values in parity mode (color name or "#rrggbb") [default: white]
+    #[arg(long, env = "COLLATZ_EVEN_COLOR", value_parser = color::parse_color)]
+    even_color: Option<Color>,
+
+    /// Color of odd values in parity mode (color name or "#rrggbb") [default: yellow]
+    #[arg(long, env = "COLLATZ_ODD_COLOR", value_parser = color::parse_color)]
+    odd_color: Option<Color>,
+
+    /// Part of the sequence printed in the terminal: "all", "none", "head:N", "tail:M" or "head:N,tail:M" [default: head:50,tail:50]
+    #[arg(long, env = "COLLATZ_PRINT", value_parser = dump::parse_print_limit)]
+    print: Option<PrintLimit>,
+
+    /// Do not print the sequence in the terminal, only the statistics
+    #[arg(long, env = "COLLATZ_NO_PRINT", conflicts_with = "print")]
+    no_print: bool,
+
+    /// Group digits of printed values with a separator: "locale" or e.g. "_" or ","
+    #[arg(long, value_name = "SEPARATOR", env = "COLLATZ_GROUP_DIGITS", value_parser = format::parse_separator)]
+    group_digits: Option<String>,
+
+    /// Language of the prompt, the input errors and the summary: "en" or "de" [default: from the system locale]
+    #[arg(long, value_name = "LANG", env = "COLLATZ_LANG")]
+    lang: Option<Lang>,
+
+    /// Also list the N highest peaks of the trajectory (values of 3n+1 steps) with their steps in the summary
+    #[arg(long, value_name = "N", env = "COLLATZ_PEAKS", value_parser = clap::value_parser!(u64).range(1..))]
+    peaks: Option<u64>,
+
+    /// Repeat the run N times, overwriting the output file, and print mean and standard deviation of the timings
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    bench: Option<u32>,
+
+    /// Print the trajectory one step at a time with the rule applied and the running maximum, as a classroom demonstration; no file is written
+    #[arg(long, conflicts_with_all = ["resume", "bench"])]
+    animate: bool,
+
+    /// Read the seed from the system clipboard instead of stdin
+    #[arg(long, conflicts_with = "resume")]
+    seed_from_clipboard: bool,
+
+    /// Copy the statistics of the run to the system clipboard, with the full seed and max value
+    #[arg(long)]
+    copy_stats: bool,
+
+    /// Print a share string of the seed and the statistics digest, which `verify --share` reproduces and confirms on another machine
+    #[arg(long, env = "COLLATZ_SHARE", conflicts_with = "digits_only")]
+    share: bool,
+
+    /// Also print the share string as a QR code in the terminal
+    #[arg(long, requires = "share")]
+    share_qr: bool,
+
+    /// Pick the backend (--fast-io, --digits-only), the output format and the buffer size from the size of the seed and the expected length of its trajectory; options given explicitly are kept
+    #[arg(long, env = "COLLATZ_AUTO", conflicts_with = "resume")]
+    auto: bool,
+
+    /// Print what --auto chose and why before the run
+    #[arg(long, requires = "auto")]
+    explain_plan: bool,
+
+    /// Check the seed and print the plan of the run: its size, the output file and format, and estimates of the steps, output size and memory; nothing is computed or written
+    #[arg(long, conflicts_with_all = ["resume", "bench", "animate"])]
+    dry_run: bool,
+
+    /// Pause between two steps of --animate (e.g. "100ms", "1s") [default: 500ms]
+    #[arg(long, value_name = "DURATION", value_parser = timing::parse_duration, requires = "animate")]
+    delay: Option<Duration>,
+
+    /// Abbreviate printed values with more digits than this as "≈3.21e+1204 (1205 digits)"
+    #[arg(long, value_name = "DIGITS", env = "COLLATZ_MAX_WIDTH")]
+    max_width: Option<usize>,
+
+    /// Extra analysis of the trajectory printed with the statistics: "parity" for entropy, run lengths and autocorrelation of the parity sequence, "drift" for the deviation from the heuristic drift model
+    #[arg(
+        long,
+        value_name = "ANALYSIS",
+        value_enum,
+        value_delimiter = ',',
+        env = "COLLATZ_ANALYZE"
+    )]
+    analyze: Vec<Analysis>,
+
+    /// Also write the results of --analyze to this file as JSON
+    #[arg(
+        long,
+        value_name = "FILE",
+        env = "COLLATZ_ANALYSIS_JSON",
+        requires = "analyze"
+    )]
+    analysis_json: Option<PathBuf>,
+
+    /// Also write the trajectory as a MIDI file: pitch follows log2 n, even and odd values play on different instruments
+    #[arg(long, value_name = "FILE", env = "COLLATZ_SONIFY")]
+    sonify: Option<PathBuf>,
+
+    /// Also write the parity vector of the trajectory to this file, one bit per step, for the reconstruct subcommand
+    #[arg(long, value_name = "FILE", env = "COLLATZ_PARITY_FILE")]
+    parity_file: Option<PathBuf>,
+
+    /// Also write the time spent per bit length of the values to this file, as CSV or, for a .folded file, as folded stacks for flame graphs
+    #[arg(long, value_name = "FILE", env = "COLLATZ_PROFILE", conflicts_with_all = ["animate", "dry_run"])]
+    profile: Option<PathBuf>,
+
+    /// Steps per timed chunk of --profile
+    #[arg(long, value_name = "N", default_value_t = 1000, value_parser = clap::value_parser!(u64).range(1..), requires = "profile")]
+    profile_steps: u64,
+
+    /// Format of the statistics lines when seeds are piped in, one per line [default: text]
+    #[arg(long, value_enum, env = "COLLATZ_STATS_FORMAT")]
+    stats_format: Option<StatsFormat>,
+
+    /// Also write the statistics of piped seeds to a database, e.g. "sqlite://results.db"
+    #[arg(long, value_name = "URL", env = "COLLATZ_STORE")]
+    store: Option<String>,
+
+    /// With --store, also write every value of each trajectory
+    #[arg(long, env = "COLLATZ_STORE_VALUES", requires = "store")]
+    store_values: bool,
+
+    /// Also write the statistics of piped seeds to a Parquet file
+    #[arg(long, value_name = "FILE", env = "COLLATZ_PARQUET")]
+    parquet: Option<PathBuf>,
+
+    /// Stopping time summarized for piped seeds: "total" (steps to reach 1) or "below-seed" (steps to drop below the seed, the glide) [default: total]
+    #[arg(long, value_name = "DEFINITION", env = "COLLATZ_STOPPING_TIME_DEFINITION", value_parser = str::parse::<StoppingTime>)]
+    stopping_time_definition: Option<StoppingTime>,
+
+    /// Also write the distribution of the stopping times of piped seeds to this CSV file, binned with --bin-width
+    #[arg(long, value_name = "FILE", env = "COLLATZ_DISTRIBUTION_CSV")]
+    distribution_csv: Option<PathBuf>,
+
+    /// Width in steps of the bins of --distribution-csv
+    #[arg(long, value_name = "STEPS", default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..), requires = "distribution_csv")]
+    bin_width: u64,
+
+    /// Also rank the K seeds of piped seeds with the longest total stopping time, highest max value and longest glide
+    #[arg(long, value_name = "K", env = "COLLATZ_TOP", value_parser = clap::value_parser!(u64).range(1..))]
+    top: Option<u64>,
+
+    /// Normalize piped seeds, comma separated: "canonical" names seeds by their decimal value, "unique" skips repeated values, "odd" skips even seeds, "not-multiple-of-3" skips multiples of 3
+    #[arg(
+        long,
+        value_enum,
+        value_name = "RULES",
+        value_delimiter = ',',
+        env = "COLLATZ_NORMALIZE"
+    )]
+    normalize: Vec<Normalize>,
+
+    /// Also write the piped seeds stopped by a limit to this file, for the retry subcommand
+    #[arg(long, value_name = "FILE", env = "COLLATZ_UNRESOLVED")]
+    unresolved: Option<PathBuf>,
+
+    /// Serve Prometheus metrics of the batch on this address, e.g. "0.0.0.0:9100" (GET /metrics)
+    #[arg(long, value_name = "ADDRESS", env = "COLLATZ_METRICS")]
+    metrics: Option<String>,
+
+    /// Post a JSON payload with the seed, the statistics and the duration to this URL when a run or batch finishes or fails
+    #[arg(long, value_name = "URL", env = "COLLATZ_NOTIFY_WEBHOOK")]
+    notify_webhook: Option<String>,
+
+    /// Mail the outcome of a run or batch to this address with the local sendmail
+    #[arg(long, value_name = "ADDRESS", env = "COLLATZ_NOTIFY_EMAIL")]
+    notify_email: Option<String>,
+
+    /// Catalog of the runs listed by the history subcommand [default: ~/.local/share/collatz/history.jsonl]
+    #[arg(long, value_name = "FILE", env = "COLLATZ_HISTORY_FILE")]
+    history_file: Option<PathBuf>,
+
+    /// Do not record the run in the history catalog
+    #[arg(long, env = "COLLATZ_NO_HISTORY")]
+    no_history: bool,
+
+    /// Read a single seed even if stdin is not a terminal, instead of one seed per line
+    #[arg(long)]
+    no_batch: bool,
+
+    /// Log progress to stderr: -v for milestones and a heuristic estimate of the time left, -vv for progress every 100000 steps
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+
+    /// Only log errors to stderr
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Byte-stable output for test suites: no colors, timestamps or timings, English texts
+    #[arg(long, env = "COLLATZ_GOLDEN")]
+    golden: bool,
+
+    /// Write progress events as JSON lines to "stderr", a file descriptor number like 3, or a file
+    #[arg(long, value_name = "TARGET", env = "COLLATZ_PROGRESS_EVENTS")]
+    progress_events: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Compare the trajectories of several seeds side by side, e.g. `compare 27 31 2^20-1`
+    Compare {
+        /// Start values or expressions like 2^199-1
+        #[arg(required = true)]
+        seeds: Vec<String>,
+    },
+    /// Compare two sequence files: first difference, merge point and statistics
+    Diff {
+        /// First sequence file (or manifest)
+        a: PathBuf,
+        /// Second sequence file (or manifest)
+        b: PathBuf,
+    },
+    /// Recompute a trajectory in memory and print its hash, or check it against an expected hash
+    Verify {
+        /// Start value or expression like 2^199-1; with --stdin taken from the input if not given
+        #[arg(required_unless_present_any = ["stdin", "share"])]
+        seed: Option<String>,
+        /// Hash printed in the summary of an earlier run ("sha256:...")
+        #[arg(long, value_name = "HASH")]
+        expect: Option<String>,
+        /// Check a trajectory read from stdin instead of recomputing it, e.g. the output of another program
+        #[arg(long)]
+        stdin: bool,
+        /// Reproduce the result of a share string printed with --share instead of a seed
+        #[arg(long, value_name = "STRING", conflicts_with_all = ["seed", "expect", "stdin"])]
+        share: Option<String>,
+    },
+    /// Replay a trajectory from its seed and a parity vector written with --parity-file, and check it against a sequence file or hash
+    Reconstruct {
+        /// Start value or expression like 2^199-1
+        #[arg(long)]
+        seed: String,
+        /// Parity vector written with --parity-file
+        #[arg(long, value_name = "FILE")]
+        parity_file: PathBuf,
+        /// Sequence file (or manifest) the replayed values must match
+        #[arg(long, value_name = "FILE")]
+        against: Option<PathBuf>,
+        /// Hash printed in the summary of an earlier run ("sha256:...")
+        #[arg(long, value_name = "HASH")]
+        expect: Option<String>,
+    },
+    /// Print the values at given steps of a trajectory without computing a sequence file, e.g. `at 2^1000-1 --step 5000`
+    At {
+        /// Start value or expression like 2^199-1
+        seed: String,
+        /// Steps to print, e.g. `--step 100` or `--steps 10,100,1000`; step 0 is the seed
+        #[arg(
+            long = "step",
+            visible_alias = "steps",
+            value_name = "K",
+            value_delimiter = ',',
+            required = true
+        )]
+        steps: Vec<u64>,
+    },
+    /// Find the first step at which a trajectory rises above or falls below a value, e.g. `first --seed 27 --above 9000`
+    First {
+        /// Start value or expression like 2^199-1
+        #[arg(long)]
+        seed: String,
+        /// First step with a value greater than this one, e.g. 10^100
+        #[arg(long, value_name = "X", required_unless_present = "below")]
+        above: Option<String>,
+        /// First step with a value less than this one, e.g. 1000
+        #[arg(long, value_name = "X")]
+        below: Option<String>,
+    },
+    /// Iterate a generalized map with a rule for each residue of n mod m, defined in a TOML file, with statistics per residue, e.g. `residue --map maps/collatz1932.toml 8 2^100`
+    Residue {
+        /// TOML file with the modulus, one rule per residue and the stop values, see maps/
+        #[arg(long, value_name = "FILE")]
+        map: PathBuf,
+        /// Start values or expressions like 2^199-1
+        #[arg(required = true)]
+        seeds: Vec<String>,
+    },
+    /// Extract the values of a window of steps from a sequence file, e.g. `slice collatz_sequence.txt --from 1000 --to 2000 --format csv`
+    Slice {
+        /// Sequence file (or manifest of a split sequence)
+        file: PathBuf,
+        /// First step to extract; step 1 is the first value after the seed
+        #[arg(long, value_name = "STEP", default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+        from: u64,
+        /// Last step to extract (included) [default: the end of the file]
+        #[arg(long, value_name = "STEP")]
+        to: Option<u64>,
+        /// Output format
+        #[arg(long, value_enum, default_value_t = SliceFormat::Text)]
+        format: SliceFormat,
+    },
+    /// Combine the statistics of batch runs (CSV or JSON lines) into one set without duplicates, with the global records and the gaps in the coverage, e.g. `combine results1.csv results2.csv --out combined.parquet`
+    Combine {
+        /// Statistics written with --stats-format csv or json
+        #[arg(required = true)]
+        inputs: Vec<PathBuf>,
+        /// Also write the combined statistics to this file: .parquet, .json/.jsonl (JSON lines) or CSV
+        #[arg(long, value_name = "FILE")]
+        out: Option<PathBuf>,
+    },
+    /// Find where the trajectories of a batch of seeds coalesce: merge matrix and common merge points, e.g. `merge 1..1000`
+    Merge {
+        /// Seeds or ranges of seeds (end excluded), e.g. 27 2^20-1 1..1000
+        #[arg(required = true)]
+        seeds: Vec<String>,
+        /// Compare every seed only with this one instead of all pairs
+        #[arg(long, value_name = "SEED")]
+        reference: Option<String>,
+        /// Write every compared pair to this CSV file
+        #[arg(long, value_name = "FILE")]
+        csv: Option<PathBuf>,
+    },
+    /// Walk every seed of a huge batch only until its trajectory joins one of an earlier seed, with the visited values spilled to disk beyond a memory ceiling, e.g. `coalesce 1..10^9 --memory 4G`
+    Coalesce {
+        /// Seeds or ranges of seeds (end excluded), e.g. 27 2^20-1 1..10^9
+        #[arg(required = true)]
+        seeds: Vec<String>,
+        /// Memory for the visited values (e.g. "4G"); beyond it they are spilled to disk
+        #[arg(long, value_name = "SIZE", default_value = "1G", value_parser = output::parse_size)]
+        memory: u64,
+        /// Directory of the spilled values [default: the temporary directory]
+        #[arg(long, value_name = "DIR")]
+        spill_dir: Option<PathBuf>,
+        /// Write one line per seed with its steps and join value to this CSV file
+        #[arg(long, value_name = "FILE")]
+        csv: Option<PathBuf>,
+    },
+    /// Draw several trajectories on a log scale into one SVG chart with a legend, e.g. `plot 27 97 871`
+    Plot {
+        /// Seeds, e.g. 27 2^20-1, or sequence files written by earlier runs
+        #[arg(required = true)]
+        inputs: Vec<String>,
+        /// SVG file to write
+        #[arg(long, value_name = "FILE", default_value = "collatz_plot.svg")]
+        output: PathBuf,
+        /// Also show the chart in the terminal with the kitty graphics protocol or sixels
+        #[arg(long, value_enum, value_name = "MODE", default_value_t = InlineGraphics::Auto)]
+        inline_graphics: InlineGraphics,
+    },
+    /// Draw trajectories as the "Collatz coral", paths turning at every even and odd step, into an SVG file, e.g. `art 1..10000`
+    Art {
+        /// Seeds or ranges of seeds (end excluded), e.g. 27 2^20-1 1..10000
+        #[arg(required = true)]
+        seeds: Vec<String>,
+        /// SVG file to write
+        #[arg(long, value_name = "FILE", default_value = "collatz_coral.svg")]
+        output: PathBuf,
+        /// Turn at every even value, in degrees counterclockwise
+        #[arg(
+            long,
+            value_name = "DEGREES",
+            default_value_t = 8.0,
+            allow_negative_numbers = true
+        )]
+        even_angle: f64,
+        /// Turn at every odd value, in degrees counterclockwise
+        #[arg(long, value_name = "DEGREES", default_value_t = -16.0, allow_negative_numbers = true)]
+        odd_angle: f64,
+    },
+    /// Collect the statistics of random odd seeds of a given size, reproducible with --rng-seed, e.g. `random --bits 2048 --count 100 --rng-seed 42`
+    Random {
+        /// Size of every seed in bits; the highest and the lowest bit are set
+        #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+        bits: u64,
+        /// Number of seeds
+        #[arg(long, default_value_t = 100)]
+        count: u64,
+        /// Seed of the random number generator; drawn from the clock and reported if not given
+        #[arg(long, value_name = "N")]
+        rng_seed: Option<u64>,
+    },
+    /// Compute the seeds written by --unresolved again with the limits given before the subcommand, and keep those still unresolved in the file, e.g. `--max-steps 100000000 retry unresolved.txt`
+    Retry {
+        /// File written by --unresolved
+        file: PathBuf,
+    },
+    /// List the runs recorded in the history catalog with their statistics, e.g. `history --sort stopping-time`
+    History {
+        /// Order of the runs
+        #[arg(long, value_enum, default_value_t = HistorySort::Date)]
+        sort: HistorySort,
+        /// List only this many runs
+        #[arg(long, value_name = "N")]
+        limit: Option<usize>,
+    },
+    /// Walk the inverse Collatz tree from 1 upward with the arrow keys, bookmark nodes and export their paths
+    Explore {
+        /// File the paths are written to with `e`
+        #[arg(long, value_name = "FILE", default_value = "collatz_tree_paths.txt")]
+        export: PathBuf,
+    },
+    /// Interactive session: enter seeds repeatedly, inspect the history with :stats and :plot
+    Repl,
+    /// Serve trajectories and statistics over HTTP: GET /collatz/{seed} and /collatz/{seed}?stats=true
+    Serve {
+        /// Port to listen on
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+        /// Address to bind to; use 0.0.0.0 to accept connections from other machines
+        #[arg(long, default_value = "127.0.0.1")]
+        bind: String,
+    },
+    /// Check every way of computing trajectories against reference values of the OEIS (A006577, A025586) for the seeds 1 to 100 and the stopping time records like 27, 97 and 871
+    Selftest,
+    /// Watch a directory for seed lists, compute each new one like a batch and move it to done/ with its statistics next to it, e.g. `watch incoming/`
+    Watch {
+        /// Directory to watch
+        dir: PathBuf,
+        /// Pause between two scans of the directory (e.g. "500ms", "10s")
+        #[arg(long, value_name = "DURATION", default_value = "2s", value_parser = timing::parse_duration)]
+        interval: Duration,
+        /// Process the files present once and exit instead of watching
+        #[arg(long)]
+        once: bool,
+    },
+    /// Verify that every seed of a range drops below itself and write a report for the coordinator
+    Worker {
+        /// Range of seeds, end excluded, e.g. 10^12..10^12+10^9
+        #[arg(long, value_name = "RANGE")]
+        assign: String,
+        /// Write the report to this file instead of stdout
+        #[arg(long, value_name = "FILE")]
+        report: Option<PathBuf>,
+        /// Also send the report to a coordinator, e.g. http://host:8090/report
+        #[arg(long, value_name = "URL")]
+        report_url: Option<String>,
+        /// Record completed units of the range in this file and skip them when restarted
+        #[arg(long, value_name = "FILE")]
+        ledger: Option<PathBuf>,
+        /// Also write a certificate of the verified range to this file, for `certify --check`
+        #[arg(long, value_name = "FILE", conflicts_with = "ledger")]
+        certificate: Option<PathBuf>,
+        /// Seeds per unit of --ledger and --jobs
+        #[arg(long, value_name = "N", default_value_t = 1_000_000, value_parser = clap::value_parser!(u64).range(1..))]
+        unit_size: u64,
+        /// Verify this many units at a time on separate threads
+        #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+        jobs: u64,
+    },
+    /// Check a certificate written by `worker --certificate` without searching for the glides again, e.g. `certify --check range.cert`
+    Certify {
+        /// Certificate to check
+        #[arg(long, value_name = "FILE")]
+        check: PathBuf,
+    },
+    /// Split a range into work units, merge worker reports, or collect them over HTTP with --listen
+    Coordinator {
+        /// Reports of adjacent work units to merge into one
+        reports: Vec<PathBuf>,
+        /// Print the work units of this range, one per line
+        #[arg(long, value_name = "RANGE", requires = "units", conflicts_with_all = ["reports", "listen"])]
+        split: Option<String>,
+        /// Number of work units for --split
+        #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+        units: Option<u64>,
+        /// Receive reports posted by workers to /report on this port
+        #[arg(long, value_name = "PORT", conflicts_with = "reports")]
+        listen: Option<u16>,
+        /// Address to bind to with --listen
+        #[arg(long, default_value = "127.0.0.1")]
+        bind: String,
+        /// Directory where --listen saves the reports
+        #[arg(long, value_name = "DIR", default_value = "reports")]
+        dir: PathBuf,
+    },
+    /// Print a shell completion script, e.g. `completions bash > /etc/bash_completion.d/collatz_bigint_file`
+    Completions {
+        #[arg(value_enum)]
+        shell: completions::Shell,
+    },
+    /// Print the man page (roff), e.g. `manpage > collatz_bigint_file.1`
+    Manpage,
+}
+
+// Settings of a run after merging command line, environment variables, config file and defaults
+struct Settings {
+    output_dir: PathBuf,
+    output_name: String,
+    force: bool,
+    max_part_size: Option<u64>,
+    // budget of the sequence file with --max-disk
+    max_disk: Option<u64>,
+    buffer_size: usize,
+    // update the digits of the values step by step with --fast-io
+    fast_io: bool,
+    limits: Limits,
+    sink: Sink,
+    odd_only: bool,
+    sample_every: Option<u64>,
+    // stages of --filter, none without it
+    filters: Vec<Filter>,
+    annotate: bool,
+    // values written in a power-of-two radix and never converted to decimal
+    digits_only: bool,
+    resume: Option<PathBuf>,
+    color_mode: ColorMode,
+    even_color: Color,
+    odd_color: Color,
+    print_limit: PrintLimit,
+    number_format: NumberFormat,
+    lang: Lang,
+    // byte-stable output with --golden
+    golden: bool,
+    // number of peaks listed in the summary
+    peaks: Option<usize>,
+    bench: Option<usize>,
+    // pause between the steps with --animate
+    animate: Option<Duration>,
+    dry_run: bool,
+    seed_from_clipboard: bool,
+    copy_stats: bool,
+    // print the share string after the summary, as a QR code with share_qr
+    share: bool,
+    share_qr: bool,
+    // with --auto, the choices the command line or config file left to it
+    auto: Option<auto::Given>,
+    explain_plan: bool,
+    analyses: Vec<Analysis>,
+    analysis_json: Option<PathBuf>,
+    parity_file: Option<PathBuf>,
+    sonify: Option<PathBuf>,
+    // time per bit length with --profile, in chunks of profile_steps
+    profile: Option<PathBuf>,
+    profile_steps: usize,
+    stats_format: StatsFormat,
+    store: Option<String>,
+    store_values: bool,
+    parquet: Option<PathBuf>,
+    stopping_time_definition: StoppingTime,
+    distribution_csv: Option<PathBuf>,
+    bin_width: usize,
+    // size of the leaderboards of a batch
+    top: Option<usize>,
+    unresolved: Option<PathBuf>,
+    // rules of --normalize for piped seeds
+    normalize: Vec<Normalize>,
+    metrics: Option<String>,
+    // where to report the end of a run or batch
+    notify_webhook: Option<String>,
+    notify_email: Option<String>,
+    // catalog the runs are recorded in, None with --no-history
+    history: Option<PathBuf>,
+    no_batch: bool,
+}
+
+impl Settings {
+    // Notifier of a job with --notify-webhook or --notify-email, started now
+    fn notifier(&self, job: &'static str) -> Option<notify::Notifier> {
+        notify::Notifier::new(
+            self.notify_webhook.as_deref(),
+            self.notify_email.as_deref(),
+            job,
+        )
+    }
+
+    // Whether the analysis pass reads the sequence back: only full sequence files can be
+    fn reads_back(&self) -> bool {
+        self.sink == Sink::File
+            && !self.odd_only
+            && self.sample_every.is_none()
+            && self.filters.is_empty()
+            && !self.digits_only
+    }
+
+    // Whether a truncated output file can be continued with --resume
+    fn resumable(&self) -> bool {
+        self.reads_back() && self.number_format.radix == 10
+    }
+
+    // Whether the hash is computed while writing: the read back file must hold decimal values
+    // A file under --max-disk may switch to a format that cannot be read back during the run
+    fn tallies(&self) -> bool {
+        !self.reads_back() || self.number_format.radix != 10 || self.disk_budget().is_some()
+    }
+
+    // The --max-disk budget of a new sequence file; the size of a filtered sequence is not known
+    // ahead, --filter excludes --max-disk
+    fn disk_budget(&self) -> Option<u64> {
+        self.max_disk
+            .filter(|_| self.sink == Sink::File && self.filters.is_empty() && self.resume.is_none())
+    }
+}
+
+// Function to merge the command line (including COLLATZ_* variables, handled by clap) with the config file
+fn resolve_settings(cli: Cli, config: Config) -> Result<Settings, String> {
+    let color_from_config = |value: Option<String>, default: Color| match value {
+        Some(name) => color::parse_color(&name),
+        None => Ok(default),
+    };
+    let even_color = match cli.even_color {
+        Some(color) => color,
+        None => color_from_config(config.even_color, Color::White)?,
+    };
+    let odd_color = match cli.odd_color {
+        Some(color) => color,
+        None => color_from_config(config.odd_color, Color::Yellow)?,
+    };
+    let print_limit = if cli.no_print {
+        PrintLimit::Nothing
+    } else if let Some(print_limit) = cli.print {
+        print_limit
+    } else if config.no_print == Some(true) {
+        PrintLimit::Nothing
+    } else {
+        dump::parse_print_limit(config.print.as_deref().unwrap_or(dump::DEFAULT_PRINT))?
+    };
+    let separator = match cli.group_digits {
+        Some(separator) => Some(separator),
+        None => config
+            .group_digits
+            .as_deref()
+            .map(format::parse_separator)
+            .transpose()?,
+    };
+    let radix = match cli.radix {
+        Some(radix) => radix,
+        // a resumed file keeps the format it was written in
+        None if cli.resume.is_some() => 10,
+        None if cli.digits_only => 16,
+        None => config
+            .radix
+            .map(|radix| format::parse_radix(&radix.to_string()))
+            .transpose()?
+            .unwrap_or(10),
+    };
+    if cli.digits_only && !radix.is_power_of_two() {
+        return Err(format!(
+            "--digits-only needs a power of two as --radix (2, 4, 8, 16 or 32), not {}",
+            radix
+        ));
+    }
+    let auto = (cli.auto || config.auto == Some(true)).then(|| {
+        let format = cli.odd_only
+            || config.odd_only.is_some()
+            || cli.sample_every.is_some()
+            || config.sample_every.is_some()
+            || !cli.filter.is_empty()
+            || cli.annotate
+            || config.annotate.is_some();
+        auto::Given {
+            backend: cli.fast_io || config.fast_io.is_some() || cli.digits_only,
+            radix: cli.radix.is_some() || config.radix.is_some(),
+            format,
+            buffer_size: cli.buffer_size.is_some() || config.buffer_size.is_some(),
+            decimal: format
+                || !cli.analyze.is_empty()
+                || cli.sonify.is_some()
+                || cli.parity_file.is_some()
+                || cli.copy_stats
+                || cli.share,
+        }
+    });
+    let sink = match cli.sink {
+        Some(sink) => sink,
+        None => config
+            .sink
+            .as_deref()
+            .map(str::parse)
+            .transpose()?
+            .unwrap_or_default(),
+    };
+    if cli.resume.is_some() && sink != Sink::File {
+        return Err(format!(
+            "--resume continues a sequence file and cannot write to --sink {}",
+            sink
+        ));
+    }
+
+    Ok(Settings {
+        output_dir: cli
+            .output_dir
+            .or(config.output_dir)
+            .unwrap_or_else(|| PathBuf::from(OUTPUT_DIR)),
+        output_name: cli
+            .output_name
+            .or(config.output_name)
+            .unwrap_or_else(|| output::DEFAULT_OUTPUT_NAME.to_string()),
+        force: cli.force,
+        buffer_size: match cli.buffer_size {
+            Some(size) => size,
+            None => output::parse_size(
+                config
+                    .buffer_size
+                    .as_deref()
+                    .unwrap_or(output::DEFAULT_BUFFER_SIZE),
+            )?,
+        } as usize,
+        fast_io: cli.fast_io || config.fast_io == Some(true),
+        limits: Limits {
+            max_steps: cli.max_steps.or(config.max_steps),
+            max_bits: Some(
+                match cli.max_value_size {
+                    Some(size) => size,
+                    None => output::parse_size(
+                        config
+                            .max_value_size
+                            .as_deref()
+                            .unwrap_or(output::DEFAULT_MAX_VALUE_SIZE),
+                    )?,
+                }
+                .saturating_mul(8),
+            ),
+            // --max-disk thins the file instead of stopping the computation, see `Budgeted`
+            max_bytes: None,
+            timeout: match cli.timeout {
+                Some(timeout) => Some(timeout),
+                None => config
+                    .timeout
+                    .as_deref()
+                    .map(timing::parse_duration)
+                    .transpose()?,
+            },
+            // a resumed file always continues down to 1
+            until: match cli.until {
+                Some(until) => until,
+                None if cli.resume.is_some() => Until::One,
+                None => config
+                    .until
+                    .as_deref()
+                    .map(str::parse)
+                    .transpose()?
+                    .unwrap_or_default(),
+            },
+            stop_when: match cli.stop_when {
+                Some(condition) => Some(condition),
+                None if cli.resume.is_some() => None,
+                None => config.stop_when.as_deref().map(str::parse).transpose()?,
+            },
+            backend: match cli.force_backend {
+                Some(backend) => backend,
+                None => config
+                    .force_backend
+                    .as_deref()
+                    .map(str::parse)
+                    .transpose()?
+                    .unwrap_or_default(),
+            },
+        },
+        sink,
+        // a resumed file keeps the format it was written in
+        odd_only: (cli.odd_only || config.odd_only == Some(true)) && cli.resume.is_none(),
+        sample_every: cli
+            .sample_every
+            .or(config.sample_every)
+            .filter(|_| cli.resume.is_none()),
+        filters: cli.filter,
+        annotate: (cli.annotate || config.annotate == Some(true)) && cli.resume.is_none(),
+        digits_only: cli.digits_only,
+        resume: cli.resume,
+        max_part_size: match cli.max_part_size {
+            Some(size) => Some(size),
+            None => config
+                .max_part_size
+                .as_deref()
+                .map(output::parse_size)
+                .transpose()?,
+        },
+        max_disk: match cli.max_disk {
+            Some(size) => Some(size),
+            None => config
+                .max_disk
+                .as_deref()
+                .map(output::parse_size)
+                .transpose()?,
+        },
+        color_mode: cli
+            .color_mode
+            .or(config.color_mode)
+            .unwrap_or(ColorMode::Parity),
+        even_color,
+        odd_color,
+        print_limit,
+        number_format: NumberFormat {
+            separator,
+            max_width: cli.max_width.or(config.max_width),
+            radix,
+        },
+        lang: match cli.lang {
+            _ if cli.golden => Lang::En,
+            Some(lang) => lang,
+            None => match config.lang.as_deref() {
+                Some(lang) => lang.parse()?,
+                None => i18n::system_lang(),
+            },
+        },
+        golden: cli.golden,
+        peaks: cli
+            .peaks
+            .map(|peaks| peaks as usize)
+            .or(config.peaks)
+            .filter(|&peaks| peaks > 0),
+        bench: cli.bench.map(|rounds| rounds as usize),
+        animate: cli.animate.then(|| cli.delay.unwrap_or(ANIMATION_DELAY)),
+        dry_run: cli.dry_run,
+        seed_from_clipboard: cli.seed_from_clipboard,
+        copy_stats: cli.copy_stats,
+        share: cli.share,
+        share_qr: cli.share_qr,
+        auto,
+        explain_plan: cli.explain_plan,
+        analyses: cli.analyze,
+        analysis_json: cli.analysis_json,
+        parity_file: cli.parity_file,
+        sonify: cli.sonify,
+        profile: cli.profile,
+        profile_steps: cli.profile_steps as usize,
+        stats_format: cli
+            .stats_format
+            .or(config.stats_format)
+            .unwrap_or(StatsFormat::Text),
+        store: cli.store.or(config.store),
+        store_values: cli.store_values,
+        parquet: cli.parquet,
+        stopping_time_definition: match cli.stopping_time_definition {
+            Some(definition) => definition,
+            None => config
+                .stopping_time_definition
+                .as_deref()
+                .map(str::parse)
+                .transpose()?
+                .unwrap_or_default(),
+        },
+        distribution_csv: cli.distribution_csv,
+        bin_width: cli.bin_width as usize,
+        unresolved: cli.unresolved,
+        normalize: if cli.normalize.is_empty() {
+            config.normalize.unwrap_or_default()
+        } else {
+            cli.normalize
+        },
+        top: cli
+            .top
+            .map(|top| top as usize)
+            .or(config.top)
+            .filter(|&top| top > 0),
+        metrics: cli.metrics,
+        notify_webhook: cli.notify_webhook.or(config.notify_webhook),
+        notify_email: cli.notify_email.or(config.notify_email),
+        history: match cli.history_file.or(config.history_file) {
+            _ if cli.no_history || config.history == Some(false) => None,
+            Some(path) => Some(path),
+            None => history::default_path(),
+        },
+        no_batch: cli.no_batch,
+    })
+}
 
 // Helper function to parse BigInt
 fn parse_bigint(input: &str) -> Result<BigInt, String> {
@@ -20,11 +1068,17 @@ fn parse_bigint(input: &str) -> Result<BigInt, String> {
 }
 
 //function to read start value for collatz sequence
-fn read_input() -> String {
-    println!(
+// With `--sink stdout` the prompt goes to stderr, stdout only carries the sequence.
+fn read_input(prompt_to_stderr: bool) -> String {
+    let prompt = tr!(
         "Enter a positiv integer as start value for the Collatz sequence (e.g., 27 or 2^199-1 or 2^199):"
     );
-    println!();
+    if prompt_to_stderr {
+        eprintln!("{}\n", prompt);
+    } else {
+        println!("{}", prompt);
+        println!();
+    }
 
     let mut input_value = String::default();
     io::stdin()
@@ -34,159 +1088,1714 @@ fn read_input() -> String {
 }
 
 // Function to parse the input value
-fn parse_input(input_value: String) -> Option<BigInt> {
-    // Use regex to match expressions like "2^199-1" or "2^199"
-    let re = Regex::new(r"(\d+)\^(\d+)(?:-(\d+))?").unwrap();
-    match re.captures(&input_value) {
-        Some(captures) => {
-            let base = captures[1].parse::<u32>().unwrap();
-            let exponent = captures[2].parse::<u32>().unwrap();
-            let subtract = captures
-                .get(3)
-                .map(|m| m.as_str())
-                .unwrap_or("0")
-                .parse::<u32>()
-                .unwrap();
-
-            // Calculate the parsed value as (base^exponent) - subtract
-            Some(BigInt::from(base).pow(exponent) - BigInt::from(subtract))
-        }
-        None => match parse_bigint(&input_value) {
-            Ok(value) => Some(value),
-            Err(_) => None,
-        },
-    }
+// Values with more than `max_bits` bits are rejected before they are computed.
+fn parse_input(input_value: String, max_bits: u64) -> Result<BigInt, String> {
+    input::parse_seed(&input_value, max_bits).map_err(|err| match err {
+        SeedError::TooLarge { bits, max_bits } => value_too_large(bits, max_bits),
+        SeedError::Invalid => tr!("Invalid input. Please enter a valid positive integer or a valid expression like '2^199' or '2^199-1'.").to_string(),
+    })
 }
 
-//function to define path for output file
-fn def_output() -> (PathBuf, File) {
-    let output_file_path = PathBuf::from(OUTPUT_FILE_PATH);
-    let output_file = File::create(&output_file_path).expect("Failed to create output file");
-    (output_file_path, output_file)
+fn value_too_large(bits: u64, max_bits: u64) -> String {
+    tr!(
+        "The start value has about {} bits, more than the limit of {} bits ({} bytes). Raise --max-value-size to compute it anyway.",
+        bits,
+        max_bits,
+        max_bits / 8
+    )
 }
 
-/// Calculates the Collatz sequence for a given starting value.
-///
-/// The Collatz sequence is a series of numbers where each number is derived from the previous
-/// number using the following rules:
-///
-/// - If the number is even, divide it by 2.
-/// - If the number is odd, multiply it by 3 and add 1.
-///
-/// The sequence continues until the number reaches 1.
-///
-/// # Arguments
-///
-/// - `n`: The starting value for the Collatz sequence.
-/// - `output_file`: A mutable reference to a `BufWriter<File>` to write the sequence to a file.
-///
-fn collatz(mut n: BigInt, output_file: &mut BufWriter<File>) {
-    while n != BigInt::one() {
-        match n.clone() % BigInt::from(2) {
-            x if x == BigInt::zero() => n /= BigInt::from(2),
-            _ => n = BigInt::from(3) * n + BigInt::one(),
-        }
-        writeln!(output_file, "{}", n).expect("Failed to write to file");
+// Function to open the side channel of --progress-events: stderr, an inherited file descriptor
+// (through /dev/fd, which Linux and macOS provide) or a file
+fn open_progress_events(target: &str) -> Result<Box<dyn Write + Send>, String> {
+    if target == "stderr" {
+        return Ok(Box::new(io::stderr()));
     }
+    let path = match target.parse::<u32>() {
+        Ok(fd) if cfg!(unix) => PathBuf::from(format!("/dev/fd/{}", fd)),
+        Ok(_) => {
+            return Err("File descriptors for --progress-events need a Unix system".to_string())
+        }
+        Err(_) => PathBuf::from(target),
+    };
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&path)
+        .map(|file| Box::new(file) as Box<dyn Write + Send>)
+        .map_err(|err| format!("Failed to open {} for progress events: {}", target, err))
+}
+
+//function to define path for output file
+fn def_output(settings: &Settings, seed: &BigInt, force: bool) -> Result<(PathBuf, File), String> {
+    let file_name = output::render_name(&settings.output_name, seed, Local::now())?;
+    let output_file_path = settings.output_dir.join(file_name);
+    let output_file = output::create(&output_file_path, force)?;
+    info!("Opened output file {}", output_file_path.display());
+    Ok((output_file_path, output_file))
 }
 
 // Function to read the file line by line, calculate statistics, format and print sequence
 fn line_read(
-    reader: io::BufReader<File>,
-    even: &mut i32,
-    odd: &mut i32,
-    max_value: &mut BigInt,
-    max_index: &mut usize,
-    stopping_time: &mut usize,
-) {
+    source: &str,
+    mut scanner: Scanner<impl BufRead>,
+    mut hasher: Option<&mut Sha256>,
+    mut parities: Option<&mut ParityVector>,
+    seed: Option<&BigInt>,
+    mut stats: Stats,
+    mut dump: Dump,
+) -> Stats {
     println!();
-    for (line_num, line) in reader.lines().enumerate() {
-        let line = line.expect("Failed to read line");
-
-        match parse_bigint(&line) {
-            Ok(num) => {
-                let color = match num.clone() % &BigInt::from(2) {
-                    x if x == BigInt::zero() => {
-                        *even += 1;
-                        Color::White
-                    }
-                    _ => {
-                        *odd += 1;
-                        Color::Yellow
-                    }
-                };
+    let radix = dump.radix();
+    // digits of the seed in the base of the file until a value drops below it
+    let mut seed_digits = seed.map_or(Vec::new(), |seed| seed.to_str_radix(radix).into_bytes());
+    // digits of the largest value, only parsed once at the end
+    let mut max_digits = Vec::new();
+    let mut step = 0;
+    let mut malformed = Malformed::default();
+    while let Some(line) = scanner.next_line().expect("Failed to read line") {
+        match line {
+            // skip the metadata header and footer
+            Line::Comment => {}
+            Line::Value(digits) => {
+                step += 1;
+                let is_even = scan::is_even_in(digits, radix);
+                if is_even {
+                    stats.even += 1;
+                } else {
+                    stats.odd += 1;
+                }
+                stats.valuations.push(is_even);
+                stats.power_of_two.push(is_even);
+                if let Some(parity) = stats.parity.as_mut() {
+                    parity.push(is_even);
+                }
+                if let Some(drift) = stats.drift.as_mut() {
+                    drift.push(drift::log2_digits(digits, radix));
+                }
+                if let Some(sonification) = stats.sonification.as_mut() {
+                    sonification.push(drift::log2_digits(digits, radix), is_even);
+                }
+                if let Some(parities) = parities.as_mut() {
+                    parities
+                        .push(is_even)
+                        .expect("Failed to write the parity vector");
+                }
 
-                if num > max_value.clone() {
-                    *max_value = num.clone();
-                    *max_index = line_num + 1;
+                if scan::compare(digits, &max_digits).is_gt() {
+                    max_digits.clear();
+                    max_digits.extend_from_slice(digits);
+                    stats.max_index = step;
                 }
 
-                *stopping_time = line_num + 1;
+                stats.stopping_time = step;
+                if !seed_digits.is_empty() && scan::compare(digits, &seed_digits).is_lt() {
+                    stats.glide = Some(step);
+                    seed_digits.clear();
+                }
+
+                if let Some(hasher) = hasher.as_mut() {
+                    hasher.update(digits);
+                    hasher.update(b"\n");
+                }
 
-                let formatted_num = num.clone().to_string().color(color);
-                print!("{} ", formatted_num);
+                dump.push(digits, is_even);
             }
-            Err(err) => {
-                eprintln!("Error parsing line {}: {}", line_num + 1, err);
+            Line::Invalid(err) => {
+                step += 1;
+                malformed.push(scanner.line_num(), scanner.offset(), err);
             }
         }
     }
+    if !max_digits.is_empty() {
+        stats.max_value = scan::to_bigint_in(&max_digits, radix);
+    }
+    dump.finish();
+    malformed.report(source);
+    stats.malformed = malformed.count();
+    stats
 }
 
-fn main() {
-    //inizialize variables
-    let mut max_value = BigInt::zero();
-    let mut max_index = 0;
-    let mut even = 0;
-    let mut odd = 0;
-    let mut stopping_time = 0;
-
-    //call function to read the start value of the collatz sequence
-    let input_value = read_input();
-
-    //call function to parse the input value
-    if let Some(parsed_input) = parse_input(input_value.clone()) {
-        
-        // call function to define the path for the output file
-        let (output_file_path, output_file) = def_output();
-
-        // Open the file in append mode
-        let mut output_file = BufWriter::new(output_file);
-
-        //call collatz function
-        collatz(parsed_input.clone(), &mut output_file);
-
-        // Close the output_file to release the write lock
-        drop(output_file);
-
-        // Reopen the file for reading
-        let file = File::open(output_file_path).expect("Failed to open file for reading");
-        let reader = std::io::BufReader::new(file);
-
-        //call the function to read the file line by line, calculate statistics, format and print sequence
-        line_read(
-            reader,
-            &mut even,
-            &mut odd,
-            &mut max_value,
-            &mut max_index,
-            &mut stopping_time,
+// Function to start the parity analysis of a run if it was asked for; the parity sequence starts
+// with the seed when it is known
+fn parity_analysis(settings: &Settings, seed: Option<&BigInt>) -> Option<ParityAnalysis> {
+    if !settings.analyses.contains(&Analysis::Parity) {
+        return None;
+    }
+    let mut parity = ParityAnalysis::new();
+    if let Some(seed) = seed {
+        parity.push(!seed.bit(0));
+    }
+    Some(parity)
+}
+
+// Function to start the drift analysis of a run if it was asked for and the seed is known
+fn drift_analysis(settings: &Settings, seed: Option<&BigInt>) -> Option<DriftAnalysis> {
+    if !settings.analyses.contains(&Analysis::Drift) {
+        return None;
+    }
+    match seed {
+        Some(seed) => Some(DriftAnalysis::new(seed, Map::Standard)),
+        None => {
+            warn!("Skipping the drift analysis: the seed of the resumed file is unknown");
+            None
+        }
+    }
+}
+
+// Function to start the sonification of a run if it was asked for and the seed is known
+fn sonification(settings: &Settings, seed: Option<&BigInt>) -> Option<Sonification> {
+    settings.sonify.as_ref()?;
+    match seed {
+        Some(seed) => Some(Sonification::new(seed)),
+        None => {
+            warn!("Skipping the sonification: the seed of the resumed file is unknown");
+            None
+        }
+    }
+}
+
+// Function to create the parity vector file of a run if it was asked for and the seed is known
+fn parity_vector(
+    settings: &Settings,
+    seed: Option<&BigInt>,
+    force: bool,
+) -> Result<Option<ParityVector>, String> {
+    let Some(path) = &settings.parity_file else {
+        return Ok(None);
+    };
+    match seed {
+        Some(seed) => ParityVector::create(path, seed, force).map(Some),
+        None => {
+            warn!("Skipping the parity vector: the seed of the resumed file is unknown");
+            Ok(None)
+        }
+    }
+}
+
+// Function to complete the parity vector file of a run
+fn finish_parities(parities: Option<ParityVector>) {
+    if let Some(parities) = parities {
+        let (path, steps) = parities
+            .finish()
+            .expect("Failed to write the parity vector");
+        info!(
+            "Wrote the parity vector of {} steps to {}",
+            steps,
+            path.display()
         );
-        println!();
-        println!();
-        //print input value and parsed input value
-        print!("Input: {}", input_value);
-        println!("Parsed input: {}", parsed_input);
-        println!();
-        //print statistics
-        println!("stopping time: {}", stopping_time);
-        println!("even (white): {}", even);
-        println!("odd (yellow): {}", odd);
-        println!("max pos: {}", max_index);
-        println!("max value: {}", max_value);
-        println!();
+    }
+}
+
+// Function to print the highest peaks of a run with --peaks
+fn print_peaks(report: &Report, number_format: &NumberFormat) {
+    let peaks = report.peaks.ranked();
+    if peaks.is_empty() {
+        return;
+    }
+    if report.resumed {
+        println!("{}", tr!("highest peaks of the continued part:"));
+    } else {
+        println!("{}", tr!("highest peaks:"));
+    }
+    for (rank, (step, value)) in peaks.into_iter().enumerate() {
+        println!(
+            "{:>4}. {}",
+            rank + 1,
+            tr!("{} at step {}", number_format.format(value), step)
+        );
+    }
+}
+
+// Function to print the distribution of the halvings after each odd step as a histogram
+fn print_valuations(valuations: &Valuations) {
+    let Some(mean) = valuations.mean() else {
+        return;
+    };
+    let counts = valuations.counts();
+    let total: u64 = counts.iter().sum();
+    let largest = counts.iter().copied().max().unwrap_or(1);
+    println!();
+    println!(
+        "{}",
+        tr!(
+            "halvings after odd steps, ν₂(3n+1): mean {} (expected 2)",
+            format!("{:.3}", mean)
+        )
+    );
+    for (valuation, &count) in counts.iter().enumerate().skip(1) {
+        println!(
+            "{:>4}: {:>8} {:>6.2}% {}",
+            valuation,
+            count,
+            100.0 * count as f64 / total as f64,
+            "█".repeat((count * HISTOGRAM_WIDTH).div_ceil(largest) as usize)
+        );
+    }
+}
+
+// Statistics of the analysis pass
+#[derive(Default)]
+struct Stats {
+    stopping_time: usize,
+    even: usize,
+    odd: usize,
+    max_index: usize,
+    max_value: BigInt,
+    // halvings after each odd step, ν₂(3n+1)
+    valuations: Valuations,
+    // with --analyze parity
+    parity: Option<ParityAnalysis>,
+    // with --analyze drift
+    drift: Option<DriftAnalysis>,
+    // stopping time below the seed, None if the trajectory never drops below it or the seed is
+    // unknown
+    glide: Option<usize>,
+    // first power of two of the trajectory
+    power_of_two: PowerOfTwoEntry,
+    // with --sonify
+    sonification: Option<Sonification>,
+    // lines of the read back file that hold no value
+    malformed: u64,
+}
+
+// Output of a run: a new sequence file or an existing one that is continued
+struct Run {
+    input_value: String,
+    parsed_input: Option<BigInt>,
+    output: Box<dyn SequenceSink>,
+    // value to continue the computation from, None if the sequence is already complete
+    start_value: Option<BigInt>,
+    generated: Generated,
+    // with --parity-file
+    parities: Option<ParityVector>,
+}
+
+// Function to fit a new sequence file into the --max-disk budget: if the estimated size exceeds
+// it, write only the odd terms, or a sample coarse enough to fit, and warn. As the estimate is
+// only a heuristic, the file is kept within the budget while it is written, see `Budgeted`.
+fn fit_disk_budget(settings: &mut Settings, seed: &BigInt) -> Result<(), String> {
+    let Some(budget) = settings.disk_budget() else {
+        return Ok(());
+    };
+    let estimate = plan::output_size(settings, seed);
+    if let Some(format) = fit_format(settings, seed, budget, "--max-disk")? {
+        warn!(
+            "The sequence file would take about {}, more than --max-disk {}: writing {} instead",
+            output::format_size(estimate),
+            output::format_size(budget as f64),
+            format
+        );
+    }
+    Ok(())
+}
+
+// Function to switch a sequence file estimated above `budget` to the odd terms, or a sample
+// coarse enough to fit; returns the format written instead, None if the file fits as it is.
+// `origin` names the budget in the error if not even a sample fits
+fn fit_format(
+    settings: &mut Settings,
+    seed: &BigInt,
+    budget: u64,
+    origin: &str,
+) -> Result<Option<String>, String> {
+    if plan::output_size(settings, seed) <= budget as f64 {
+        return Ok(None);
+    }
+    if settings.sample_every.is_none() && !settings.odd_only {
+        settings.annotate = false;
+        settings.odd_only = true;
+    }
+    if plan::output_size(settings, seed) > budget as f64 {
+        // a sample shrinks with k until the last k values, kept in full, outweigh it
+        settings.odd_only = false;
+        let mut every = settings.sample_every.unwrap_or(1);
+        let mut size = f64::INFINITY;
+        loop {
+            every = every.saturating_mul(2);
+            settings.sample_every = Some(every);
+            let sampled = plan::output_size(settings, seed);
+            if sampled <= budget as f64 {
+                break;
+            }
+            if sampled >= size {
+                return Err(format!(
+                    "Even a sample of the sequence would take about {}, more than {} {}",
+                    output::format_size(size),
+                    origin,
+                    output::format_size(budget as f64)
+                ));
+            }
+            size = sampled;
+        }
+    }
+    Ok(Some(match settings.sample_every {
+        Some(every) => format!("every {}th value", every),
+        None => "only the odd terms".to_string(),
+    }))
+}
+
+// Function to create the output file for a new seed and write the metadata header
+fn new_run(
+    settings: &Settings,
+    input_value: String,
+    parsed_input: BigInt,
+    force: bool,
+) -> Result<Run, String> {
+    let mut output: Box<dyn SequenceSink> = match settings.sink {
+        Sink::File => {
+            // call function to define the path for the output file
+            let (output_file_path, output_file) = def_output(settings, &parsed_input, force)?;
+            Box::new(PartWriter::new(
+                output_file_path,
+                output_file,
+                settings.max_part_size,
+                settings.buffer_size,
+                force,
+            ))
+        }
+        _ => sink::open(&settings.sink, settings.buffer_size)?,
+    };
+    // the conversion comes last, the other wrappers look at the decimal digits
+    let radix = settings.number_format.radix;
+    // --digits-only writes the values in the radix itself
+    if radix != 10 && !settings.digits_only {
+        output = Box::new(Radix::new(output, radix));
+    }
+    if let Some(budget) = settings.disk_budget() {
+        let coverage = match (settings.odd_only, settings.sample_every) {
+            (true, _) => Coverage::OddTerms,
+            (false, Some(every)) => Coverage::Sample(every),
+            (false, None) => Coverage::All {
+                annotated: settings.annotate,
+            },
+        };
+        output = Box::new(Budgeted::new(
+            output,
+            coverage,
+            &parsed_input,
+            budget,
+            radix,
+            settings.limits.max_steps,
+        ));
+    } else if settings.odd_only {
+        output = Box::new(Condensed::new(output, &parsed_input));
+    } else if let Some(every) = settings.sample_every {
+        output = Box::new(Sampled::new(output, every));
+    } else if !settings.filters.is_empty() {
+        output = Box::new(Filtered::new(output, &settings.filters));
+    } else if settings.annotate {
+        output = Box::new(Annotated::new(output, 0, parsed_input.bit(0)));
+    }
+
+    metadata::write_header(
+        &mut output,
+        &Header {
+            seed: &input_value,
+            parsed: &parsed_input,
+            map: "3n+1",
+            start_time: (!settings.golden).then(Local::now),
+            digits_only: settings.digits_only,
+        },
+    )
+    .and_then(|()| match (settings.odd_only, settings.sample_every) {
+        (true, _) => writeln!(output, "# terms: {}", metadata::ODD_TERMS),
+        (false, Some(every)) => writeln!(output, "# sample every: {}", every),
+        (false, None) if !settings.filters.is_empty() => {
+            writeln!(output, "# filter: {}", filter::describe(&settings.filters))
+        }
+        (false, None) if settings.annotate => writeln!(output, "# annotated: true"),
+        (false, None) => Ok(()),
+    })
+    .and_then(|()| match radix {
+        10 => Ok(()),
+        radix => writeln!(output, "# radix: {}", radix),
+    })
+    .map_err(|err| format!("Failed to write to file: {}", err))?;
+
+    let parities = parity_vector(settings, Some(&parsed_input), force)?;
+    Ok(Run {
+        input_value,
+        start_value: Some(parsed_input.clone()),
+        parsed_input: Some(parsed_input),
+        output,
+        generated: Generated::default(),
+        parities,
+    })
+}
+
+// Function to reopen an existing sequence file and continue from its last value
+fn resume_run(path: &Path, settings: &Settings) -> Result<Run, String> {
+    let state = resume::prepare(path)?;
+    if state.repaired_bytes > 0 {
+        warn!(
+            "Repaired {}: removed {} bytes of an incomplete last line",
+            state.append_path.display(),
+            state.repaired_bytes
+        );
+    }
+    let file = resume::open_append(&state)?;
+    let mut files = state.previous_parts.clone();
+    files.push(state.append_path.clone());
+    let output_file = PartWriter::append(
+        files,
+        file,
+        settings.max_part_size,
+        settings.buffer_size,
+        settings.force,
+    )
+    .map_err(|err| format!("Failed to open {}: {}", path.display(), err))?;
+
+    if state.complete {
+        info!(
+            "{} is already complete, nothing left to compute",
+            path.display()
+        );
+    } else {
+        info!("Resuming {} after step {}", path.display(), state.steps);
+    }
+    let parities = parity_vector(settings, state.parsed.as_ref(), settings.force)?;
+    // an annotated file goes on counting its steps
+    let mut output: Box<dyn SequenceSink> = Box::new(output_file);
+    if state.annotated {
+        output = Box::new(Annotated::new(output, state.steps, state.last_value.bit(0)));
+    }
+    Ok(Run {
+        input_value: state
+            .seed
+            .unwrap_or_else(|| format!("(resumed from {})", path.display())),
+        parsed_input: state.parsed,
+        output,
+        start_value: (!state.complete).then_some(state.last_value),
+        generated: Generated {
+            steps: state.steps,
+            max_value: state.max_value,
+            max_index: state.max_index,
+            ..Generated::default()
+        },
+        parities,
+    })
+}
+
+// Collect the statistics of `count` random odd seeds of `bits` bits, like seeds piped in
+fn random(bits: u64, count: u64, rng_seed: Option<u64>, settings: &Settings) -> Result<(), String> {
+    let max_bits = settings.limits.max_bits.unwrap_or(u64::MAX);
+    if bits > max_bits {
+        return Err(value_too_large(bits, max_bits));
+    }
+    if rng_seed.is_none() && settings.golden {
+        return Err("--golden needs a fixed --rng-seed".to_string());
+    }
+    let rng_seed = rng_seed.unwrap_or_else(|| {
+        let rng_seed = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+        eprintln!("Random seeds drawn with --rng-seed {}", rng_seed);
+        rng_seed
+    });
+    info!(
+        "Collecting the statistics of {} random odd seeds of {} bits (--rng-seed {})",
+        count, bits, rng_seed
+    );
+    run_batch(
+        io::BufReader::new(random::RandomSeeds::new(bits, count, rng_seed)),
+        settings,
+        Unresolved::new(settings.unresolved.as_deref(), settings.force)?,
+    )
+    .map(|_| ())
+}
+
+// Compute the seeds of a file of unresolved seeds again, and keep only those still unresolved
+fn retry(path: &Path, settings: &Settings) -> Result<(), String> {
+    let seeds = std::fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+    run_batch(
+        io::Cursor::new(seeds),
+        settings,
+        Unresolved::new(Some(path), true)?,
+    )
+    .map(|_| ())
+}
+
+// Function to verify an assigned range and deliver the report
+#[allow(clippy::too_many_arguments)]
+fn run_worker(
+    assign: &str,
+    report_path: Option<&Path>,
+    report_url: Option<&str>,
+    ledger: Option<&Path>,
+    certificate_path: Option<&Path>,
+    unit_size: u64,
+    jobs: usize,
+    settings: &Settings,
+) -> Result<(), String> {
+    let range = work::parse_range(assign)?;
+    info!("Verifying {}", range);
+    let report = if ledger.is_some() || jobs > 1 {
+        let unit_size = BigInt::from(unit_size);
+        let ledger = ledger
+            .map(|path| ledger::Ledger::open(path, &range, &unit_size))
+            .transpose()?;
+        work::verify_units(
+            &range,
+            &unit_size,
+            jobs,
+            ledger,
+            &settings.limits,
+            certificate_path.is_some(),
+        )?
+    } else {
+        work::verify_range(&range, &settings.limits, certificate_path.is_some())
+    };
+    match report_path {
+        Some(path) => {
+            let mut file = output::create(path, settings.force)?;
+            report
+                .write(&mut file)
+                .map_err(|err| format!("Failed to write {}: {}", path.display(), err))?;
+        }
+        None => report
+            .write(&mut io::stdout())
+            .map_err(|err| format!("Failed to write the report: {}", err))?,
+    }
+    if let Some(url) = report_url {
+        work::post_report(url, &report)?;
+        info!("Sent the report to {}", url);
+    }
+    if let Some(path) = certificate_path {
+        if report.unverified.is_empty() {
+            certificate::write(path, &report, settings.force)?;
+            info!("Wrote the certificate to {}", path.display());
+        } else {
+            warn!("No certificate written, the range is not fully verified");
+        }
+    }
+    match report.unverified.len() {
+        0 => Ok(()),
+        count => Err(format!(
+            "{} seeds of {} could not be verified",
+            count, range
+        )),
+    }
+}
+
+// Function for the three modes of the coordinator: split a range, collect or merge reports
+fn coordinate(
+    reports: Vec<PathBuf>,
+    split: Option<String>,
+    units: Option<u64>,
+    listen: Option<u16>,
+    bind: &str,
+    dir: &Path,
+) -> Result<(), String> {
+    if let (Some(range), Some(units)) = (split, units) {
+        for unit in work::split(&work::parse_range(&range)?, units) {
+            println!("{}", unit);
+        }
+        return Ok(());
+    }
+    if let Some(port) = listen {
+        return work::collect(bind, port, dir);
+    }
+    if reports.is_empty() {
+        return Err("Give the reports to merge, or use --split or --listen".to_string());
+    }
+    let reports = reports
+        .iter()
+        .map(|path| work::read_report(path))
+        .collect::<Result<Vec<_>, _>>()?;
+    let merged = work::merge(reports)?;
+    merged
+        .write(&mut io::stdout())
+        .map_err(|err| format!("Failed to write the report: {}", err))?;
+    match merged.unverified.len() {
+        0 => Ok(()),
+        count => Err(format!(
+            "{} seeds of {} could not be verified",
+            count, merged.range
+        )),
+    }
+}
+
+// Statistics of a run, printed in the summary
+struct Report {
+    input_value: String,
+    parsed_input: Option<BigInt>,
+    // None for sinks other than a file
+    output_file_path: Option<PathBuf>,
+    stats: Stats,
+    truncated: Option<Truncation>,
+    // hash of the trajectory, unknown for resumed files without the seed in the header
+    hash: Option<String>,
+    // with --peaks, of the steps computed by this run
+    peaks: Peaks,
+    // whether the run continued a file, so the peaks miss the steps before
+    resumed: bool,
+    // with --profile, of the steps computed by this run
+    profile: Profile,
+}
+
+// Statistics of a run in the fields of every report format, the max value as given
+fn sequence_stats(report: &Report, max_value: String) -> SequenceStats<'_> {
+    let stats = &report.stats;
+    SequenceStats {
+        seed: report.input_value.trim(),
+        stopping_time: stats.stopping_time,
+        glide: stats.glide,
+        max_value,
+        max_index: stats.max_index,
+        max_bits: stats.max_value.bits(),
+        odd: stats.odd,
+        valuation_mean: stats.valuations.mean(),
+        truncated: report.truncated.map(|truncation| truncation.to_string()),
+    }
+}
+
+// Statistics of a run for --notify-webhook and --notify-email, as JSON values
+fn notification_stats(report: &Report) -> Vec<(&'static str, String)> {
+    let stats = &report.stats;
+    let mut fields: Vec<(&'static str, String)> =
+        sequence_stats(report, stats.max_value.to_string())
+            .fields()
+            // the notification names the seed itself; huge values would bloat the payload,
+            // their bit length says enough
+            .filter(|field| match field.name {
+                "seed" => false,
+                "max_value" => stats.max_value.bits() <= NOTIFY_MAX_VALUE_BITS,
+                _ => !field.value.is_null(),
+            })
+            .map(|field| (field.name, field.value.json()))
+            .collect();
+    fields.push(("even", stats.even.to_string()));
+    if let Some(hash) = &report.hash {
+        fields.push(("hash", json_string(hash)));
+    }
+    if let Some(path) = &report.output_file_path {
+        fields.push(("output_file", json_string(&path.display().to_string())));
+    }
+    fields
+}
+
+// Print the choices of --auto, on stderr if the sequence goes to stdout
+fn explain_plan(choices: &[auto::Choice], to_stderr: bool) {
+    let mut lines = vec!["auto plan:".to_string()];
+    for choice in choices {
+        lines.push(format!(
+            "  {}: {} ({})",
+            choice.setting, choice.value, choice.reason
+        ));
+    }
+    for line in lines {
+        if to_stderr {
+            eprintln!("{}", line);
+        } else {
+            println!("{}", line);
+        }
+    }
+}
+
+// Print the share string of a complete run with a known hash, and its QR code with --share-qr
+fn print_share(report: &Report, qr: bool) {
+    if report.truncated.is_some() {
+        warn!("Not sharing a truncated result, it cannot be verified");
+        return;
+    }
+    let shared = report.hash.as_deref().and_then(|hash| {
+        share::Shared::new(
+            &report.input_value,
+            report.stats.stopping_time,
+            report.stats.max_index,
+            report.stats.max_value.bits(),
+            hash,
+        )
+    });
+    let Some(shared) = shared else {
+        warn!("Not sharing a result without its hash");
+        return;
+    };
+    let text = shared.encode();
+    println!("{}", tr!("share: {}", text));
+    if qr {
+        match qr::encode(text.as_bytes()) {
+            Ok(code) => {
+                for line in code.render() {
+                    println!("{}", line);
+                }
+            }
+            Err(err) => warn!("{}", err),
+        }
+    }
+}
+
+// Entry of a run in the history catalog
+fn history_entry(report: &Report, duration: Duration) -> history::Entry {
+    let stats = &report.stats;
+    history::Entry {
+        time: Local::now().fixed_offset(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        seed: report.input_value.trim().to_string(),
+        steps: stats.stopping_time,
+        truncated: report.truncated.map(|truncation| truncation.to_string()),
+        glide: stats.glide,
+        even: stats.even as u64,
+        odd: stats.odd as u64,
+        max_index: stats.max_index,
+        max_bits: stats.max_value.bits(),
+        max_value: (stats.max_value.bits() <= history::MAX_VALUE_BITS)
+            .then(|| stats.max_value.to_string()),
+        hash: report.hash.clone(),
+        output_file: report
+            .output_file_path
+            .as_ref()
+            // absolute, so the file is found from anywhere
+            .map(|path| {
+                std::fs::canonicalize(path)
+                    .unwrap_or_else(|_| path.clone())
+                    .display()
+                    .to_string()
+            }),
+        duration: duration.as_secs_f64(),
+    }
+}
+
+// Function to print the error of a run or batch, notify it and exit
+fn fail(notifier: Option<&notify::Notifier>, err: &str) -> ! {
+    eprintln!("{}", err);
+    if let Some(notifier) = notifier {
+        notifier.failed(err);
+    }
+    std::process::exit(1);
+}
+
+// Function to format a value of the summary: its leading digits, digit count and bits with
+// --digits-only, which never converts a value to decimal
+fn summary_value(n: &BigInt, settings: &Settings) -> String {
+    if settings.digits_only {
+        format::approximate(n)
     } else {
-        println!("Invalid input. Please enter a valid positive integer or a valid expression like '2^199' or '2^199-1'.")
+        settings.number_format.format(n)
+    }
+}
+
+// Statistics of a run as plain text for --copy-stats, with every value in full
+fn stats_text(report: &Report) -> String {
+    let stats = &report.stats;
+    let mut lines = vec![tr!("seed: {}", report.input_value.trim())];
+    match report.truncated {
+        Some(truncation) => lines.push(tr!(
+            "total stopping time: unknown, {} after {} steps",
+            truncation,
+            stats.stopping_time
+        )),
+        None => lines.push(tr!("total stopping time: {}", stats.stopping_time)),
+    }
+    if let Some(glide) = stats.glide {
+        lines.push(tr!("stopping time below the seed (glide): {}", glide));
+    }
+    lines.push(tr!("even: {}", stats.even));
+    lines.push(tr!("odd: {}", stats.odd));
+    lines.push(tr!("max pos: {}", stats.max_index));
+    lines.push(tr!("max value: {}", stats.max_value));
+    for (step, value) in report.peaks.ranked() {
+        lines.push(tr!("peak: {} at step {}", value, step));
+    }
+    if let Some(hash) = &report.hash {
+        lines.push(tr!("hash: {}", hash));
+    }
+    lines.join("\n") + "\n"
+}
+
+// Print one statistics line per seed read from `input`, then the distribution of the stopping
+// times, like seeds piped into the program; returns the number of seeds
+fn run_batch(
+    input: impl BufRead,
+    settings: &Settings,
+    mut unresolved: Unresolved,
+) -> Result<u64, String> {
+    if let Some(address) = &settings.metrics {
+        metrics::serve(address)
+            .map_err(|err| format!("Failed to serve metrics on {}: {}", address, err))?;
+    }
+    let store = settings
+        .store
+        .as_ref()
+        .map(|url| store::Store::open(url, settings.store_values))
+        .transpose()?;
+    let parquet = settings
+        .parquet
+        .as_ref()
+        .map(|path| parquet::ParquetWriter::create(path))
+        .transpose()?;
+    let mut leaderboards = settings.top.map(Leaderboards::new);
+    let mut normalizer = Normalizer::new(&settings.normalize);
+    let distribution = batch::run(
+        input,
+        io::stdout().lock(),
+        settings.stats_format,
+        &settings.limits,
+        &settings.number_format,
+        store,
+        parquet,
+        settings.stopping_time_definition,
+        leaderboards.as_mut(),
+        &mut unresolved,
+        &mut normalizer,
+    )
+    .map_err(|err| format!("Failed to process seeds: {}", err))?;
+    unresolved.finish()?;
+    // CSV and JSON lines on stdout stay machine readable
+    let mut summary: Box<dyn Write> = match settings.stats_format {
+        StatsFormat::Text => Box::new(io::stdout()),
+        _ => Box::new(io::stderr()),
+    };
+    distribution
+        .write_summary(&mut summary)
+        .map_err(|err| format!("Failed to print the distribution: {}", err))?;
+    normalizer
+        .write_summary(&mut summary)
+        .map_err(|err| format!("Failed to print the skipped seeds: {}", err))?;
+    unresolved
+        .write_summary(&mut summary)
+        .map_err(|err| format!("Failed to print the unresolved seeds: {}", err))?;
+    if let Some(leaderboards) = &leaderboards {
+        leaderboards
+            .write(&mut summary, &settings.number_format)
+            .map_err(|err| format!("Failed to print the leaderboards: {}", err))?;
+    }
+    if let Some(path) = &settings.distribution_csv {
+        File::create(path)
+            .and_then(|mut file| distribution.write_csv(&mut file, settings.bin_width))
+            .map_err(|err| format!("Failed to write {}: {}", path.display(), err))?;
+    }
+    Ok(distribution.seeds())
+}
+
+// Function to compute the sequence into the sink, with the digits updated step by step if
+// --fast-io asks for it
+fn compute(
+    start_value: BigInt,
+    output: &mut impl Write,
+    generated: Generated,
+    settings: &Settings,
+) -> Generated {
+    if settings.fast_io {
+        collatz_fast_io(start_value, output, generated, &settings.limits)
+    } else {
+        collatz(start_value, output, generated, &settings.limits)
+    }
+}
+
+// Function to compute the sequence with --digits-only, collecting the statistics of a Tally
+// from the values themselves, as there are no decimal digits to look at and no hash
+fn compute_digits_only(
+    start_value: BigInt,
+    output: &mut impl Write,
+    generated: Generated,
+    settings: &Settings,
+    seed: Option<&BigInt>,
+) -> (Generated, Tallied) {
+    let mut tallied = Tallied {
+        valuations: Valuations::new(seed.is_some_and(|seed| seed.bit(0))),
+        power_of_two: PowerOfTwoEntry::new(seed.map(|seed| seed.bit(0))),
+        ..Tallied::default()
+    };
+    let mut steps = 0;
+    let generated = collatz_in_radix(
+        start_value,
+        output,
+        generated,
+        &settings.limits,
+        settings.number_format.radix,
+        |n| {
+            let odd = n.bit(0);
+            if odd {
+                tallied.odd += 1;
+            }
+            tallied.valuations.push(!odd);
+            tallied.power_of_two.push(!odd);
+            steps += 1;
+            if tallied.glide.is_none() && seed.is_some_and(|seed| n < seed) {
+                tallied.glide = Some(steps);
+            }
+        },
+    );
+    (generated, tallied)
+}
+
+// Function to generate the sequence of a run into its sink and read it back for the statistics
+fn execute(run: Run, settings: &Settings, print_limit: PrintLimit) -> (Report, Timing) {
+    let Run {
+        input_value,
+        parsed_input,
+        mut output,
+        start_value,
+        generated,
+        mut parities,
+    } = run;
+
+    let start = Instant::now();
+    let resumed_steps = generated.steps;
+    let generated = Generated {
+        peaks: Peaks::new(settings.peaks.unwrap_or(0)),
+        profile: Profile::new(
+            settings
+                .profile
+                .as_ref()
+                .map_or(0, |_| settings.profile_steps),
+        ),
+        ..generated
+    };
+    // odd values and hash, counted while writing to a sink that cannot be read back (or holds
+    // values in another base than the decimal of the hash)
+    let mut tallied = None;
+    let generated = match start_value {
+        Some(start_value) => {
+            //call collatz function
+            let generated = if settings.digits_only {
+                let (generated, digits_only) = compute_digits_only(
+                    start_value,
+                    &mut output,
+                    generated,
+                    settings,
+                    parsed_input.as_ref(),
+                );
+                tallied = Some(digits_only);
+                generated
+            } else if !settings.tallies() {
+                compute(start_value, &mut output, generated, settings)
+            } else {
+                let mut tally = Tally::new(
+                    &mut output,
+                    parsed_input.as_ref(),
+                    parity_analysis(settings, parsed_input.as_ref()),
+                    drift_analysis(settings, parsed_input.as_ref()),
+                    parities.as_mut(),
+                    sonification(settings, parsed_input.as_ref()),
+                );
+                let generated = compute(start_value, &mut tally, generated, settings);
+                tallied = Some(tally.finish());
+                generated
+            };
+
+            metadata::write_footer(
+                &mut output,
+                &Footer {
+                    stopping_time: generated.steps,
+                    max_value: &generated.max_value,
+                    max_index: generated.max_index,
+                    duration: (!settings.golden).then(|| start.elapsed()),
+                    truncated: generated.truncated,
+                    digits_only: settings.digits_only,
+                },
+            )
+            .expect("Failed to write to file");
+            generated
+        }
+        None => generated,
+    };
+
+    if let Some(truncation) = generated.truncated {
+        warn!("Stopped the computation early: {}", truncation);
+    }
+    let fallback = output.fallback();
+    if let Some(fallback) = &fallback {
+        warn!(
+            "The sequence file would have exceeded --max-disk {}: wrote {}",
+            output::format_size(settings.max_disk.unwrap_or_default() as f64),
+            fallback
+        );
+    }
+
+    // Close the output_file to release the write lock
+    let output_file_path = output.finish().expect("Failed to write to file");
+    let generation = start.elapsed();
+    let mut timing = Timing {
+        generation,
+        analysis: Duration::ZERO,
+        steps: generated.steps - resumed_steps,
+        digits: generated.digits,
+    };
+    // a condensed or sampled file cannot be read back value by value either, nor one that
+    // switched to such a format to stay within --max-disk
+    if !settings.reads_back() || fallback.is_some() {
+        let Tallied {
+            odd,
+            hash,
+            valuations,
+            parity,
+            drift,
+            glide,
+            power_of_two,
+            sonification,
+        } = tallied.unwrap_or_default();
+        finish_parities(parities);
+        info!(
+            "Generated {} steps in {:.3}s into {}",
+            generated.steps,
+            generation.as_secs_f64(),
+            match &output_file_path {
+                Some(path) => path.display().to_string(),
+                None => settings.sink.to_string(),
+            }
+        );
+        let report = Report {
+            input_value,
+            parsed_input,
+            output_file_path,
+            stats: Stats {
+                stopping_time: generated.steps,
+                even: generated.steps - odd,
+                odd,
+                max_index: generated.max_index,
+                max_value: generated.max_value,
+                valuations,
+                parity,
+                drift,
+                glide,
+                power_of_two,
+                sonification,
+                // the values were tallied while written, nothing is read back
+                malformed: 0,
+            },
+            truncated: generated.truncated,
+            // the hash is taken over the decimal digits
+            hash: (!settings.digits_only).then_some(hash),
+            peaks: generated.peaks,
+            resumed: resumed_steps > 0,
+            profile: generated.profile,
+        };
+        return (report, timing);
+    }
+    let output_file_path = output_file_path.expect("Failed to find the output file");
+    info!(
+        "Generated {} steps in {:.3}s, reading {} back",
+        generated.steps,
+        generation.as_secs_f64(),
+        output_file_path.display()
+    );
+
+    // Reopen the file (or all part files) for reading
+    let reader = parts::open(&output_file_path, settings.buffer_size)
+        .expect("Failed to open file for reading");
+
+    let color_scheme = ColorScheme {
+        mode: settings.color_mode,
+        even: settings.even_color,
+        odd: settings.odd_color,
+        max_bits: generated.max_value.bits(),
+    };
+
+    // the canonical encoding of the trajectory starts with the seed
+    let parsed_input_known = parsed_input.is_some();
+    let mut hasher = Sha256::default();
+    if let Some(parsed_input) = &parsed_input {
+        hasher.update(parsed_input.to_string().as_bytes());
+        hasher.update(b"\n");
     }
+    //call the function to read the file line by line, calculate statistics, format and print sequence
+    let analysis_start = Instant::now();
+    let stats = line_read(
+        &output_file_path.display().to_string(),
+        Scanner::with_radix(reader, settings.number_format.radix),
+        tallied.is_none().then_some(&mut hasher),
+        parities.as_mut().filter(|_| tallied.is_none()),
+        parsed_input.as_ref(),
+        Stats {
+            // the halvings after an odd seed count, unknown for resumed files without a header
+            valuations: Valuations::new(parsed_input.as_ref().is_some_and(|seed| seed.bit(0))),
+            parity: parity_analysis(settings, parsed_input.as_ref()),
+            drift: drift_analysis(settings, parsed_input.as_ref()),
+            power_of_two: PowerOfTwoEntry::new(parsed_input.as_ref().map(|seed| seed.bit(0))),
+            sonification: sonification(settings, parsed_input.as_ref()),
+            ..Stats::default()
+        },
+        Dump::new(
+            print_limit,
+            color_scheme,
+            settings.number_format.clone(),
+            parsed_input.as_ref().is_some_and(|seed| seed.bit(0)),
+        ),
+    );
+
+    timing.analysis = analysis_start.elapsed();
+    finish_parities(parities);
+    let report = Report {
+        input_value,
+        parsed_input,
+        output_file_path: Some(output_file_path),
+        stats,
+        truncated: generated.truncated,
+        hash: match tallied {
+            Some(tallied) => Some(tallied.hash),
+            None => parsed_input_known.then(|| hasher.finish_hex()),
+        },
+        peaks: generated.peaks,
+        resumed: resumed_steps > 0,
+        profile: generated.profile,
+    };
+    (report, timing)
 }
 
+fn main() {
+    let mut cli = Cli::parse();
+    let command = cli.command.take();
+
+    // generated from the command line definition, independent of any config
+    match command {
+        Some(Command::Completions { shell }) => {
+            print!("{}", completions::generate(&mut Cli::command(), shell));
+            return;
+        }
+        Some(Command::Manpage) => {
+            print!("{}", completions::manpage(&mut Cli::command()));
+            return;
+        }
+        _ => {}
+    }
+    let config = match config::load(cli.config.as_deref()) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+    log::init(cli.verbose, cli.quiet);
+    if cli.golden {
+        colored::control::set_override(false);
+        log::hide_times();
+    }
+    if let Some(target) = &cli.progress_events {
+        match open_progress_events(target) {
+            Ok(output) => progress::init(output),
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+    let mut settings = match resolve_settings(cli, config) {
+        Ok(settings) => settings,
+        Err(err) => {
+            eprintln!("Invalid setting: {}", err);
+            std::process::exit(1);
+        }
+    };
+    i18n::init(settings.lang);
+
+    if let Some(command) = command {
+        let result = match command {
+            Command::Compare { seeds } => {
+                compare::run(&seeds, &settings.limits, &settings.number_format)
+            }
+            Command::Diff { a, b } => {
+                diff::run(&a, &b, settings.buffer_size, &settings.number_format)
+            }
+            Command::Verify {
+                share: Some(share), ..
+            } => verify::share(&share, &settings.limits),
+            Command::Verify {
+                seed,
+                expect,
+                stdin: true,
+                ..
+            } => verify::stream(
+                seed.as_deref(),
+                expect.as_deref(),
+                io::stdin().lock(),
+                settings.limits.max_bits.unwrap_or(u64::MAX),
+            ),
+            Command::Verify {
+                seed,
+                expect,
+                stdin: false,
+                ..
+            } => verify::run(
+                seed.as_deref().unwrap_or_default(),
+                expect.as_deref(),
+                &settings.limits,
+            ),
+            Command::Reconstruct {
+                seed,
+                parity_file,
+                against,
+                expect,
+            } => parse_input(seed.clone(), settings.limits.max_bits.unwrap_or(u64::MAX)).and_then(
+                |start| {
+                    reconstruct::run(
+                        &seed,
+                        start,
+                        &parity_file,
+                        against.as_deref(),
+                        expect.as_deref(),
+                        settings.buffer_size,
+                    )
+                },
+            ),
+            Command::At { seed, steps } => {
+                at::run(&seed, &steps, &settings.limits, &settings.number_format)
+            }
+            Command::First { seed, above, below } => first::run(
+                &seed,
+                above.as_deref(),
+                below.as_deref(),
+                &settings.limits,
+                &settings.number_format,
+            ),
+            Command::Residue { map, seeds } => {
+                residue::run(&map, &seeds, &settings.limits, &settings.number_format)
+            }
+            Command::Slice {
+                file,
+                from,
+                to,
+                format,
+            } => slice::run(
+                &file,
+                from as usize,
+                to.map(|to| to as usize),
+                format,
+                settings.buffer_size,
+            ),
+            Command::Combine { inputs, out } => combine::run(
+                &inputs,
+                out.as_deref(),
+                settings.top.unwrap_or(1),
+                &settings.number_format,
+                settings.force,
+            ),
+            Command::Merge {
+                seeds,
+                reference,
+                csv,
+            } => merge::run(
+                &seeds,
+                reference.as_deref(),
+                csv.as_deref(),
+                &settings.limits,
+                &settings.number_format,
+                settings.force,
+            ),
+            Command::Coalesce {
+                seeds,
+                memory,
+                spill_dir,
+                csv,
+            } => coalesce::run(
+                &seeds,
+                memory,
+                &spill_dir.unwrap_or_else(std::env::temp_dir),
+                csv.as_deref(),
+                &settings.limits,
+                &settings.number_format,
+                settings.force,
+            ),
+            Command::Plot {
+                inputs,
+                output,
+                inline_graphics,
+            } => plot::run(
+                &inputs,
+                &output,
+                &settings.limits,
+                settings.buffer_size,
+                settings.force,
+                inline_graphics,
+            ),
+            Command::Art {
+                seeds,
+                output,
+                even_angle,
+                odd_angle,
+            } => art::run(
+                &seeds,
+                &output,
+                even_angle,
+                odd_angle,
+                &settings.limits,
+                settings.force,
+            ),
+            Command::Random {
+                bits,
+                count,
+                rng_seed,
+            } => random(bits, count, rng_seed, &settings),
+            Command::Retry { file } => retry(&file, &settings),
+            Command::History { sort, limit } => match &settings.history {
+                Some(path) => history::run(path, sort, limit),
+                None => Err("No history catalog: set --history-file or HOME".to_string()),
+            },
+            Command::Explore { export } => explore::run(
+                &export,
+                settings.force,
+                &settings.number_format,
+                settings.even_color,
+                settings.odd_color,
+            ),
+            Command::Repl => repl::run(settings.limits, settings.number_format)
+                .map_err(|err| format!("Failed to read input: {}", err)),
+            Command::Serve { port, bind } => {
+                server::run(&bind, port, settings.limits, settings.number_format)
+                    .map_err(|err| format!("Server failed: {}", err))
+            }
+            Command::Worker {
+                assign,
+                report,
+                report_url,
+                ledger,
+                certificate,
+                unit_size,
+                jobs,
+            } => run_worker(
+                &assign,
+                report.as_deref(),
+                report_url.as_deref(),
+                ledger.as_deref(),
+                certificate.as_deref(),
+                unit_size,
+                jobs as usize,
+                &settings,
+            ),
+            Command::Watch {
+                dir,
+                interval,
+                once,
+            } => watch::run(&dir, interval, once, &settings),
+            Command::Selftest => selftest::run(),
+            Command::Certify { check } => certificate::check(&check, settings.buffer_size),
+            Command::Coordinator {
+                reports,
+                split,
+                units,
+                listen,
+                bind,
+                dir,
+            } => coordinate(reports, split, units, listen, &bind, &dir),
+            Command::Completions { .. } | Command::Manpage => unreachable!(),
+        };
+        if let Err(err) = result {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // seeds piped in: one statistics line per seed instead of a sequence file
+    if !io::stdin().is_terminal()
+        && !settings.no_batch
+        && settings.resume.is_none()
+        && settings.bench.is_none()
+        && settings.animate.is_none()
+        && !settings.dry_run
+        && !settings.seed_from_clipboard
+    {
+        let notifier = settings.notifier("batch");
+        let result = Unresolved::new(settings.unresolved.as_deref(), settings.force)
+            .and_then(|unresolved| run_batch(io::stdin().lock(), &settings, unresolved));
+        match result {
+            Ok(seeds) => {
+                if let Some(notifier) = &notifier {
+                    notifier.finished("finished", &[("seeds", seeds.to_string())]);
+                }
+            }
+            Err(err) => fail(notifier.as_ref(), &err),
+        }
+        return;
+    }
+
+    let mut notifier = settings.notifier("run");
+
+    let run = match &settings.resume {
+        // continue an existing sequence file in append mode
+        Some(path) => resume_run(path, &settings),
+        None => {
+            //call function to read the start value of the collatz sequence
+            let input_value = if settings.seed_from_clipboard {
+                match clipboard::read() {
+                    Ok(text) => text,
+                    Err(err) => fail(notifier.as_ref(), &err),
+                }
+            } else {
+                read_input(settings.sink == Sink::Stdout)
+            };
+
+            //call function to parse the input value
+            let parsed_input = match parse_input(
+                input_value.clone(),
+                settings.limits.max_bits.unwrap_or(u64::MAX),
+            ) {
+                Ok(parsed_input) => parsed_input,
+                Err(err) => {
+                    println!("{}", err);
+                    return;
+                }
+            };
+            if let Some(given) = settings.auto {
+                match auto::plan(&mut settings, &parsed_input, &given) {
+                    Ok(choices) if settings.explain_plan => {
+                        explain_plan(&choices, settings.sink == Sink::Stdout)
+                    }
+                    Ok(_) => {}
+                    Err(err) => fail(notifier.as_ref(), &err),
+                }
+            }
+            if settings.digits_only {
+                info!("Parsed seed: {}", format::approximate(&parsed_input));
+            } else {
+                info!(
+                    "Parsed seed: {} bits, {} decimal digits",
+                    parsed_input.bits(),
+                    format::digit_count(&parsed_input, 10)
+                );
+            }
+            if let Some(delay) = settings.animate {
+                if let Err(err) = animate::run(
+                    &parsed_input,
+                    &settings.limits,
+                    delay,
+                    &settings.number_format,
+                    settings.even_color,
+                    settings.odd_color,
+                ) {
+                    eprintln!("Failed to print the animation: {}", err);
+                    std::process::exit(1);
+                }
+                return;
+            }
+            if let Some(notifier) = notifier.as_mut() {
+                notifier.set_seed(&input_value);
+            }
+            if let Err(err) = fit_disk_budget(&mut settings, &parsed_input) {
+                fail(notifier.as_ref(), &err);
+            }
+            if settings.dry_run {
+                if let Err(err) = plan::print(&settings, &input_value, &parsed_input) {
+                    eprintln!("{}", err);
+                    std::process::exit(1);
+                }
+                return;
+            }
+            new_run(&settings, input_value, parsed_input, settings.force)
+        }
+    };
+    let mut run = match run {
+        Ok(run) => run,
+        Err(err) => fail(notifier.as_ref(), &err),
+    };
+    if let Some(notifier) = notifier.as_mut() {
+        notifier.set_seed(&run.input_value);
+    }
+
+    // repeat the run for --bench, only the last round prints the sequence
+    let rounds = settings.bench.unwrap_or(1);
+    let mut timings = Vec::with_capacity(rounds);
+    let mut round = 1;
+    let report = loop {
+        let print_limit = if round < rounds {
+            PrintLimit::Nothing
+        } else {
+            settings.print_limit
+        };
+        let (report, timing) = execute(run, &settings, print_limit);
+        timings.push(timing);
+        if round == rounds {
+            break report;
+        }
+        round += 1;
+        info!("Benchmark round {}/{}", round, rounds);
+        // later rounds overwrite the file of the first one
+        run = match new_run(
+            &settings,
+            report.input_value,
+            report.parsed_input.unwrap_or_default(),
+            true,
+        ) {
+            Ok(run) => run,
+            Err(err) => fail(notifier.as_ref(), &err),
+        };
+    };
+
+    if let Some(path) = &settings.analysis_json {
+        let mut analyses = Vec::new();
+        if let Some(parity) = &report.stats.parity {
+            analyses.push(format!("\"parity\":{}", parity.to_json()));
+        }
+        if let Some(drift) = &report.stats.drift {
+            analyses.push(format!("\"drift\":{}", drift.to_json()));
+        }
+        let json = format!("{{{}}}\n", analyses.join(","));
+        if let Err(err) = std::fs::write(path, json) {
+            fail(
+                notifier.as_ref(),
+                &format!("Failed to write {}: {}", path.display(), err),
+            );
+        }
+    }
+
+    if let (Some(path), Some(sonification)) = (&settings.sonify, &report.stats.sonification) {
+        if sonification.dropped() > 0 {
+            warn!(
+                "The sonification stops after {} notes, {} values are left out",
+                sonify::MAX_NOTES,
+                sonification.dropped()
+            );
+        }
+        if let Err(err) = sonification.write(path, settings.force) {
+            fail(notifier.as_ref(), &err);
+        }
+        info!("Wrote the sonification to {}", path.display());
+    }
+
+    if let Some(path) = &settings.profile {
+        if let Err(err) = profile::write(path, &report.profile, settings.force) {
+            fail(notifier.as_ref(), &err);
+        }
+        info!("Wrote the profile to {}", path.display());
+    }
+
+    if settings.copy_stats {
+        match clipboard::write(&stats_text(&report)) {
+            Ok(()) => info!("Copied the statistics to the clipboard"),
+            Err(err) => fail(notifier.as_ref(), &err),
+        }
+    }
+    if let Some(notifier) = &notifier {
+        let status = match report.truncated {
+            Some(_) => "truncated",
+            None => "finished",
+        };
+        notifier.finished(status, &notification_stats(&report));
+    }
+    if let Some(path) = &settings.history {
+        let duration = timings.last().map_or(Duration::ZERO, Timing::total);
+        match history::record(path, &history_entry(&report, duration)) {
+            Ok(()) => info!("Recorded the run in {}", path.display()),
+            Err(err) => warn!("{}", err),
+        }
+    }
+
+    // the sequence went to stdout and ends with its footer, a summary would mix with it
+    if settings.sink == Sink::Stdout {
+        return;
+    }
+    let number_format = &settings.number_format;
+    println!();
+    println!();
+    //print input value and parsed input value
+    println!("{}", tr!("Input: {}", report.input_value.trim()));
+    match &report.parsed_input {
+        Some(parsed_input) => println!(
+            "{}",
+            tr!("Parsed input: {}", summary_value(parsed_input, &settings))
+        ),
+        None => println!(
+            "{}",
+            tr!("Parsed input: unknown (resumed file without header)")
+        ),
+    }
+    match &report.output_file_path {
+        Some(output_file_path) => {
+            println!("{}", tr!("Output file: {}", output_file_path.display()))
+        }
+        None => println!("{}", tr!("Output: {}", settings.sink)),
+    }
+    println!();
+    //print statistics
+    match (report.truncated, &report.output_file_path) {
+        (Some(truncation), Some(output_file_path)) if settings.resumable() => println!(
+            "{}",
+            tr!(
+                "total stopping time: unknown, {} after {} steps (continue with --resume {})",
+                truncation,
+                report.stats.stopping_time,
+                output_file_path.display()
+            )
+        ),
+        (Some(truncation), _) => println!(
+            "{}",
+            tr!(
+                "total stopping time: unknown, {} after {} steps",
+                truncation,
+                report.stats.stopping_time
+            )
+        ),
+        (None, _) => println!(
+            "{}",
+            tr!("total stopping time: {}", report.stats.stopping_time)
+        ),
+    }
+    match report.stats.glide {
+        Some(glide) => println!("{}", tr!("stopping time below the seed (glide): {}", glide)),
+        None if report.truncated.is_some() => {
+            println!(
+                "{}",
+                tr!("stopping time below the seed (glide): not reached yet")
+            )
+        }
+        None if report.parsed_input.is_some() => {
+            println!(
+                "{}",
+                tr!("stopping time below the seed (glide): never below the seed")
+            )
+        }
+        None => {
+            println!(
+                "{}",
+                tr!("stopping time below the seed (glide): unknown (resumed file without header)")
+            )
+        }
+    }
+    // the first power of two is only known once the sequence reached 1
+    if let (None, Some((step, exponent))) = (report.truncated, report.stats.power_of_two.entry()) {
+        println!(
+            "{}",
+            tr!("first power of two: 2^{} at step {}", exponent, step)
+        );
+    }
+    println!("{}", tr!("even: {}", report.stats.even));
+    println!("{}", tr!("odd: {}", report.stats.odd));
+    println!("{}", tr!("max pos: {}", report.stats.max_index));
+    println!(
+        "{}",
+        tr!(
+            "max value: {}",
+            summary_value(&report.stats.max_value, &settings)
+        )
+    );
+    if report.stats.malformed > 0 {
+        println!(
+            "{}",
+            tr!("unparseable lines: {} (skipped)", report.stats.malformed)
+        );
+    }
+    print_peaks(&report, number_format);
+    match &report.hash {
+        Some(hash) if report.truncated.is_none() => println!("{}", tr!("hash: {}", hash)),
+        Some(hash) => println!("{}", tr!("hash: {} (of the truncated sequence)", hash)),
+        None if settings.digits_only => println!("{}", tr!("hash: not computed (--digits-only)")),
+        None => println!("{}", tr!("hash: unknown (resumed file without header)")),
+    }
+    if settings.share {
+        print_share(&report, settings.share_qr);
+    }
+    print_valuations(&report.stats.valuations);
+    if let Some(parity) = &report.stats.parity {
+        parity.print();
+    }
+    if let Some(drift) = &report.stats.drift {
+        drift.print();
+    }
+    println!();
+    // timings differ from run to run, --golden leaves them out
+    if settings.golden {
+        return;
+    }
+    //print timing of the (last) run
+    if let Some(timing) = timings.last() {
+        timing.print();
+    }
+    if timings.len() > 1 {
+        timing::print_bench(&timings);
+    }
+    println!();
+}
@@ -0,0 +1,323 @@
+use crate::format::NumberFormat;
+use crate::output;
+use crate::work;
+use collatz_bigint_file::sequence::{Limits, Map};
+use collatz_bigint_file::stats;
+use colored::Colorize;
+use num_bigint::BigInt;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::io::{BufWriter, Write};
+use std::ops::ControlFlow;
+use std::path::Path;
+
+// Seeds compared pairwise at most; against a reference every seed is walked only once
+const MAX_PAIRWISE_SEEDS: usize = 1_000;
+const MAX_REFERENCE_SEEDS: usize = 100_000;
+// Seeds up to which the full matrix is printed
+const MATRIX_SEEDS: usize = 12;
+// Merge points listed in the summary
+const TOP_MERGE_POINTS: usize = 10;
+
+// Trajectory of a seed as the hashes of its values, seed first
+struct Hashed {
+    label: String,
+    seed: BigInt,
+    hashes: Vec<u64>,
+    // step of every value, by its hash
+    steps: HashMap<u64, usize>,
+    truncated: bool,
+}
+
+impl Hashed {
+    fn new(label: String, seed: BigInt, limits: &Limits) -> Self {
+        let mut hashes = vec![value_hash(&seed)];
+        let row = stats::visit_trajectory(label.clone(), &seed, limits, Map::Standard, |n| {
+            hashes.push(value_hash(n));
+            ControlFlow::Continue(())
+        });
+        let steps = hashes
+            .iter()
+            .enumerate()
+            .map(|(step, hash)| (*hash, step))
+            .collect();
+        Hashed {
+            label,
+            seed,
+            hashes,
+            steps,
+            truncated: row.truncated.is_some(),
+        }
+    }
+
+    // First common value with `other`: the steps of both trajectories to it and its hash. Once
+    // two trajectories share a value they share the rest, so this is where they coalesce.
+    fn merge(&self, other: &Hashed) -> Option<(usize, usize, u64)> {
+        self.hashes.iter().enumerate().find_map(|(step, hash)| {
+            other
+                .steps
+                .get(hash)
+                .map(|other_step| (step, *other_step, *hash))
+        })
+    }
+
+    // Value at `step`, recomputed from the seed
+    fn value_at(&self, step: usize, limits: &Limits) -> BigInt {
+        let mut value = self.seed.clone();
+        let mut seen = 0;
+        stats::visit_trajectory(String::new(), &self.seed, limits, Map::Standard, |n| {
+            seen += 1;
+            if seen > step {
+                return ControlFlow::Break(());
+            }
+            value = n.clone();
+            ControlFlow::Continue(())
+        });
+        value
+    }
+}
+
+// 64-bit hash of a value; a collision between two values of a batch is negligible
+fn value_hash(n: &BigInt) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    n.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Computes where the trajectories of a batch of seeds coalesce and prints a merge matrix and
+/// the most common merge points.
+///
+/// Every value is reduced to a 64-bit hash and each trajectory indexed by its hashes, so the
+/// first common value of two trajectories is found in one walk over one of them. Without a
+/// `reference` all pairs are compared: up to 12 seeds the matrix shows the steps of each row
+/// seed until it joins the trajectory of each column seed. With a `reference` every seed is
+/// compared only with it. `csv` receives one line per compared pair. Trajectories stopped by a
+/// limit may never meet, their pairs show `-`.
+pub fn run(
+    args: &[String],
+    reference: Option<&str>,
+    csv: Option<&Path>,
+    limits: &Limits,
+    number_format: &NumberFormat,
+    force: bool,
+) -> Result<(), String> {
+    let max_bits = limits.max_bits.unwrap_or(u64::MAX);
+    let max_seeds = match reference {
+        Some(_) => MAX_REFERENCE_SEEDS,
+        None => MAX_PAIRWISE_SEEDS,
+    };
+    let trajectories: Vec<Hashed> = work::expand_seeds(args, max_bits, max_seeds)?
+        .into_iter()
+        .map(|(label, seed)| Hashed::new(label, seed, limits))
+        .collect();
+    let reference = reference
+        .map(|reference| {
+            crate::parse_input(reference.to_string(), max_bits)
+                .map(|seed| Hashed::new(reference.to_string(), seed, limits))
+                .map_err(|err| format!("{}: {}", reference, err))
+        })
+        .transpose()?;
+
+    // compared pairs as indices into `trajectories`, the reference as None
+    let pairs: Vec<(usize, Option<usize>)> = match &reference {
+        Some(_) => (0..trajectories.len()).map(|i| (i, None)).collect(),
+        None => (0..trajectories.len())
+            .flat_map(|i| (i + 1..trajectories.len()).map(move |j| (i, Some(j))))
+            .collect(),
+    };
+    let other = |j: Option<usize>| match j {
+        Some(j) => &trajectories[j],
+        None => reference
+            .as_ref()
+            .expect("pairs without a second seed have a reference"),
+    };
+    let merges: Vec<Option<(usize, usize, u64)>> = pairs
+        .iter()
+        .map(|&(i, j)| trajectories[i].merge(other(j)))
+        .collect();
+
+    // the value of every merge point, recomputed once from one of the seeds meeting there
+    let mut points: HashMap<u64, (u64, usize, usize)> = HashMap::new();
+    for (&(i, _), merge) in pairs.iter().zip(&merges) {
+        if let Some((step, _, hash)) = merge {
+            points.entry(*hash).or_insert((0, i, *step)).0 += 1;
+        }
+    }
+    let mut values: HashMap<u64, BigInt> = HashMap::with_capacity(points.len());
+    let value = |values: &mut HashMap<u64, BigInt>, hash: u64| -> BigInt {
+        values
+            .entry(hash)
+            .or_insert_with(|| {
+                let (_, i, step) = points[&hash];
+                trajectories[i].value_at(step, limits)
+            })
+            .clone()
+    };
+
+    if let Some(path) = csv {
+        let mut output = BufWriter::new(output::create(path, force)?);
+        let write_error = |err| format!("Failed to write {}: {}", path.display(), err);
+        writeln!(output, "seed_a,seed_b,steps_a,steps_b,merge_value").map_err(write_error)?;
+        for (&(i, j), merge) in pairs.iter().zip(&merges) {
+            let (steps_a, steps_b, merge_value) = match merge {
+                Some((step, other_step, hash)) => (
+                    step.to_string(),
+                    other_step.to_string(),
+                    value(&mut values, *hash).to_string(),
+                ),
+                None => Default::default(),
+            };
+            writeln!(
+                output,
+                "{},{},{},{},{}",
+                trajectories[i].label,
+                other(j).label,
+                steps_a,
+                steps_b,
+                merge_value
+            )
+            .map_err(write_error)?;
+        }
+        output.flush().map_err(write_error)?;
+    }
+
+    match &reference {
+        Some(reference) => {
+            let width = trajectories
+                .iter()
+                .map(|trajectory| trajectory.label.chars().count())
+                .fold("seed".len(), usize::max);
+            println!(
+                "{}",
+                format!(
+                    "{:<width$}  {:>5}  {:>9}  merge value",
+                    "seed",
+                    "steps",
+                    "reference",
+                    width = width
+                )
+                .bold()
+            );
+            for (&(i, _), merge) in pairs.iter().zip(&merges) {
+                let (steps, reference_steps, merge_value) = match merge {
+                    Some((step, other_step, hash)) => (
+                        step.to_string(),
+                        other_step.to_string(),
+                        number_format.format(&value(&mut values, *hash)),
+                    ),
+                    None => ("-".to_string(), "-".to_string(), "-".to_string()),
+                };
+                println!(
+                    "{:<width$}  {:>5}  {:>9}  {}",
+                    trajectories[i].label,
+                    steps,
+                    reference_steps,
+                    merge_value,
+                    width = width
+                );
+            }
+            println!("(steps of each seed and of the reference {} until their trajectories merge)", reference.label);
+        }
+        None if trajectories.len() <= MATRIX_SEEDS => {
+            print_matrix(&trajectories, &pairs, &merges);
+        }
+        None => println!(
+            "{} pairs of {} seeds compared; the matrix is printed for up to {} seeds, use --csv for all pairs",
+            pairs.len(),
+            trajectories.len(),
+            MATRIX_SEEDS
+        ),
+    }
+
+    // merge points shared by the most pairs: the seeds cluster around them
+    let mut common: Vec<(u64, u64)> = points
+        .iter()
+        .map(|(hash, (pairs, _, _))| (*hash, *pairs))
+        .collect();
+    common.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    if !common.is_empty() {
+        println!();
+        println!("{}", "most common merge points".bold());
+    }
+    for &(hash, count) in common.iter().take(TOP_MERGE_POINTS) {
+        println!(
+            "{:>12}  {} of {} pairs ({:.1}%)",
+            number_format.format(&value(&mut values, hash)),
+            count,
+            pairs.len(),
+            100.0 * count as f64 / pairs.len() as f64
+        );
+    }
+    let unmerged = merges.iter().filter(|merge| merge.is_none()).count();
+    if unmerged > 0 {
+        let truncated = trajectories
+            .iter()
+            .chain(reference.as_ref())
+            .filter(|trajectory| trajectory.truncated)
+            .count();
+        println!(
+            "{} pairs never meet within the computed steps ({} trajectories stopped by a limit)",
+            unmerged, truncated
+        );
+    }
+    Ok(())
+}
+
+// Steps of each row seed until it joins the trajectory of each column seed
+fn print_matrix(
+    trajectories: &[Hashed],
+    pairs: &[(usize, Option<usize>)],
+    merges: &[Option<(usize, usize, u64)>],
+) {
+    let n = trajectories.len();
+    let mut cells = vec![vec!["0".to_string(); n]; n];
+    for (&(i, j), merge) in pairs.iter().zip(merges) {
+        let j = j.expect("pairwise merges have two seeds");
+        let (steps_i, steps_j) = match merge {
+            Some((step, other_step, _)) => (step.to_string(), other_step.to_string()),
+            None => ("-".to_string(), "-".to_string()),
+        };
+        cells[i][j] = steps_i;
+        cells[j][i] = steps_j;
+    }
+    let label_width = trajectories
+        .iter()
+        .map(|trajectory| trajectory.label.chars().count())
+        .fold(0, usize::max);
+    let width = cells
+        .iter()
+        .flatten()
+        .map(String::len)
+        .chain((1..=n).map(|column| column.to_string().len() + 1))
+        .fold(0, usize::max);
+
+    let header: String = (1..=n)
+        .map(|column| format!("  {:>width$}", format!("#{}", column), width = width))
+        .collect();
+    println!(
+        "{}",
+        format!(
+            "{:>4} {:<label_width$}{}",
+            "",
+            "",
+            header,
+            label_width = label_width
+        )
+        .bold()
+    );
+    for (i, row) in cells.iter().enumerate() {
+        let line: String = row
+            .iter()
+            .map(|cell| format!("  {:>width$}", cell, width = width))
+            .collect();
+        println!(
+            "{:>4} {:<label_width$}{}",
+            format!("#{}", i + 1),
+            trajectories[i].label,
+            line,
+            label_width = label_width
+        );
+    }
+    println!("(steps of the row seed until it joins the trajectory of the column seed)");
+}
@@ -0,0 +1,87 @@
+use chrono::{DateTime, Local};
+use collatz_bigint_file::sequence::Truncation;
+use num_bigint::BigInt;
+use std::io::{self, Write};
+use std::time::Duration;
+
+// Lines starting with this character are metadata comments, not sequence values
+pub const COMMENT_PREFIX: char = '#';
+
+// Value of the "# terms" header line of files written with --odd-only
+pub const ODD_TERMS: &str = "odd";
+
+// Context written at the top of the sequence file
+pub struct Header<'a> {
+    pub seed: &'a str,
+    pub parsed: &'a BigInt,
+    pub map: &'a str,
+    // left out with --golden, so the file is the same on every run
+    pub start_time: Option<DateTime<Local>>,
+    // --digits-only: the bit length of the seed instead of its decimal digits
+    pub digits_only: bool,
+}
+
+// Statistics written at the end of the sequence file
+pub struct Footer<'a> {
+    // steps written; only the stopping time if the sequence was not truncated
+    pub stopping_time: usize,
+    pub max_value: &'a BigInt,
+    pub max_index: usize,
+    // left out with --golden
+    pub duration: Option<Duration>,
+    pub truncated: Option<Truncation>,
+    // --digits-only: the bit length of the max value instead of its decimal digits
+    pub digits_only: bool,
+}
+
+pub fn write_header(writer: &mut impl Write, header: &Header) -> io::Result<()> {
+    writeln!(
+        writer,
+        "# collatz_bigint_file {}",
+        env!("CARGO_PKG_VERSION")
+    )?;
+    writeln!(writer, "# seed: {}", header.seed.trim())?;
+    if header.digits_only {
+        writeln!(writer, "# parsed bits: {}", header.parsed.bits())?;
+    } else {
+        writeln!(writer, "# parsed: {}", header.parsed)?;
+    }
+    writeln!(writer, "# map: {}", header.map)?;
+    match header.start_time {
+        Some(start_time) => writeln!(writer, "# start time: {}", start_time.to_rfc3339()),
+        None => Ok(()),
+    }
+}
+
+// A truncated sequence gets "# truncated" and "# steps" instead of "# stopping time", so it
+// can still be continued with --resume
+pub fn write_footer(writer: &mut impl Write, footer: &Footer) -> io::Result<()> {
+    match footer.truncated {
+        Some(truncation) => {
+            writeln!(writer, "# truncated: {}", truncation)?;
+            writeln!(writer, "# steps: {}", footer.stopping_time)?;
+        }
+        None => writeln!(writer, "# stopping time: {}", footer.stopping_time)?,
+    }
+    if footer.digits_only {
+        writeln!(writer, "# max value bits: {}", footer.max_value.bits())?;
+    } else {
+        writeln!(writer, "# max value: {}", footer.max_value)?;
+    }
+    writeln!(writer, "# max pos: {}", footer.max_index)?;
+    match footer.duration {
+        Some(duration) => writeln!(writer, "# duration: {:.3}s", duration.as_secs_f64()),
+        None => Ok(()),
+    }
+}
+
+// Helper function to split a metadata line like "# seed: 27" into key and value.
+// Returns None for lines that are not metadata.
+pub fn parse_comment(line: &str) -> Option<(&str, &str)> {
+    let comment = line.strip_prefix(COMMENT_PREFIX)?.trim();
+    Some(
+        comment
+            .split_once(':')
+            .map_or((comment, ""), |(key, value)| (key.trim(), value.trim())),
+    )
+}
@@ -0,0 +1,162 @@
+use crate::server;
+use collatz_bigint_file::stats::Row;
+use collatz_bigint_file::warn;
+use std::fmt::Write as _;
+use std::io::{self, BufReader};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::Instant;
+
+// Counters of the running process, exported in the Prometheus text format
+pub static METRICS: Metrics = Metrics {
+    seeds: AtomicU64::new(0),
+    steps: AtomicU64::new(0),
+    truncated: AtomicU64::new(0),
+    invalid: AtomicU64::new(0),
+    record_stopping_time: AtomicU64::new(0),
+    record_glide: AtomicU64::new(0),
+    record_max_bits: AtomicU64::new(0),
+};
+
+static START: OnceLock<Instant> = OnceLock::new();
+
+pub struct Metrics {
+    seeds: AtomicU64,
+    steps: AtomicU64,
+    truncated: AtomicU64,
+    invalid: AtomicU64,
+    record_stopping_time: AtomicU64,
+    record_glide: AtomicU64,
+    record_max_bits: AtomicU64,
+}
+
+impl Metrics {
+    // Count a computed trajectory and update the records
+    pub fn record(&self, row: &Row) {
+        self.seeds.fetch_add(1, Ordering::Relaxed);
+        self.steps
+            .fetch_add(row.stopping_time as u64, Ordering::Relaxed);
+        if row.truncated.is_some() {
+            self.truncated.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        self.record_stopping_time
+            .fetch_max(row.stopping_time as u64, Ordering::Relaxed);
+        if let Some(glide) = row.glide {
+            self.record_glide.fetch_max(glide as u64, Ordering::Relaxed);
+        }
+        self.record_max_bits
+            .fetch_max(row.max_value.bits(), Ordering::Relaxed);
+    }
+
+    // Count a trajectory of which only the steps are known, e.g. a streamed one
+    pub fn record_steps(&self, steps: usize, truncated: bool) {
+        self.seeds.fetch_add(1, Ordering::Relaxed);
+        self.steps.fetch_add(steps as u64, Ordering::Relaxed);
+        if truncated {
+            self.truncated.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.record_stopping_time
+                .fetch_max(steps as u64, Ordering::Relaxed);
+        }
+    }
+
+    pub fn record_invalid(&self) {
+        self.invalid.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Renders all metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let metrics = [
+            (
+                "collatz_seeds_total",
+                "counter",
+                "Seeds whose trajectory was computed.",
+                &self.seeds,
+            ),
+            (
+                "collatz_steps_total",
+                "counter",
+                "Collatz steps computed.",
+                &self.steps,
+            ),
+            (
+                "collatz_truncated_total",
+                "counter",
+                "Trajectories stopped by --max-steps, --timeout or --max-value-size.",
+                &self.truncated,
+            ),
+            (
+                "collatz_invalid_seeds_total",
+                "counter",
+                "Input lines or requests rejected as invalid seeds.",
+                &self.invalid,
+            ),
+            (
+                "collatz_record_stopping_time",
+                "gauge",
+                "Longest total stopping time (steps to reach 1) of a complete trajectory so far.",
+                &self.record_stopping_time,
+            ),
+            (
+                "collatz_record_glide",
+                "gauge",
+                "Longest glide (stopping time below the seed) so far.",
+                &self.record_glide,
+            ),
+            (
+                "collatz_record_max_value_bits",
+                "gauge",
+                "Bit length of the largest value reached so far.",
+                &self.record_max_bits,
+            ),
+        ];
+        let mut output = String::new();
+        for (name, kind, help, value) in metrics {
+            writeln!(output, "# HELP {} {}", name, help).unwrap();
+            writeln!(output, "# TYPE {} {}", name, kind).unwrap();
+            writeln!(output, "{} {}", name, value.load(Ordering::Relaxed)).unwrap();
+        }
+        let uptime = START.get_or_init(Instant::now).elapsed();
+        output.push_str("# HELP collatz_uptime_seconds Seconds since the process started.\n");
+        output.push_str("# TYPE collatz_uptime_seconds gauge\n");
+        writeln!(output, "collatz_uptime_seconds {:.3}", uptime.as_secs_f64()).unwrap();
+        output
+    }
+}
+
+// Content type of the Prometheus text format
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+// Start the clock of collatz_uptime_seconds
+pub fn start() {
+    START.get_or_init(Instant::now);
+}
+
+/// Serves `GET /metrics` on `address` (e.g. "0.0.0.0:9100") in a background thread, for batch
+/// runs that have no HTTP server of their own.
+pub fn serve(address: &str) -> io::Result<()> {
+    start();
+    let listener = TcpListener::bind(address)?;
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            if let Err(err) = stream.and_then(respond) {
+                warn!("Metrics request failed: {}", err);
+            }
+        }
+    });
+    Ok(())
+}
+
+fn respond(mut stream: TcpStream) -> io::Result<()> {
+    let Some(request) = server::read_request(&mut BufReader::new(stream.try_clone()?))? else {
+        return Ok(());
+    };
+    if request.method == "GET" && request.path == "/metrics" {
+        server::respond(&mut stream, "200 OK", CONTENT_TYPE, &METRICS.render())
+    } else {
+        server::error(&mut stream, "404 Not Found", "Use GET /metrics")
+    }
+}
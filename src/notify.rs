@@ -0,0 +1,199 @@
+use crate::work;
+use chrono::{DateTime, Local};
+use collatz_bigint_file::report::json_string;
+use collatz_bigint_file::{info, warn};
+use std::env;
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::process::{Command, Stdio};
+use std::time::Instant;
+
+/// Notification at the end of a long job, sent to the webhook of `--notify-webhook` and the
+/// address of `--notify-email`, so nobody has to watch the terminal.
+///
+/// Created when the job starts, it records the start time; [`Notifier::finished`] or
+/// [`Notifier::failed`] then sends the outcome with the duration. A notification that cannot be
+/// delivered is only a warning, it never changes the outcome of the job.
+pub struct Notifier {
+    webhook: Option<String>,
+    email: Option<String>,
+    // "run" for the sequence of a seed, "batch" for piped seeds
+    job: &'static str,
+    seed: Option<String>,
+    started_at: DateTime<Local>,
+    started: Instant,
+}
+
+impl Notifier {
+    /// Starts the clock of a job, or returns None without a webhook or address to notify.
+    pub fn new(webhook: Option<&str>, email: Option<&str>, job: &'static str) -> Option<Notifier> {
+        if webhook.is_none() && email.is_none() {
+            return None;
+        }
+        Some(Notifier {
+            webhook: webhook.map(str::to_string),
+            email: email.map(str::to_string),
+            job,
+            seed: None,
+            started_at: Local::now(),
+            started: Instant::now(),
+        })
+    }
+
+    /// Sets the seed once it is known, e.g. after the prompt.
+    pub fn set_seed(&mut self, seed: &str) {
+        self.seed = Some(seed.trim().to_string());
+    }
+
+    /// Sends the statistics of a job that ended, `status` being "finished" or "truncated".
+    /// `stats` are pairs of names and JSON values, e.g. `("stopping_time", "111")`.
+    pub fn finished(&self, status: &str, stats: &[(&str, String)]) {
+        self.send(status, stats, None);
+    }
+
+    /// Sends the error of a job that failed.
+    pub fn failed(&self, error: &str) {
+        self.send("failed", &[], Some(error));
+    }
+
+    fn send(&self, status: &str, stats: &[(&str, String)], error: Option<&str>) {
+        let duration = self.started.elapsed().as_secs_f64();
+        let mut fields = vec![
+            ("job", json_string(self.job)),
+            ("status", json_string(status)),
+        ];
+        if let Some(seed) = &self.seed {
+            fields.push(("seed", json_string(seed)));
+        }
+        if let Some(host) = host_name() {
+            fields.push(("host", json_string(&host)));
+        }
+        fields.push(("started", json_string(&self.started_at.to_rfc3339())));
+        fields.push(("duration_seconds", format!("{:.3}", duration)));
+        let stats_json = stats
+            .iter()
+            .map(|(name, value)| format!("{}:{}", json_string(name), value))
+            .collect::<Vec<_>>()
+            .join(",");
+        fields.push(("stats", format!("{{{}}}", stats_json)));
+        if let Some(error) = error {
+            fields.push(("error", json_string(error)));
+        }
+
+        if let Some(url) = &self.webhook {
+            let json = fields
+                .iter()
+                .map(|(name, value)| format!("{}:{}", json_string(name), value))
+                .collect::<Vec<_>>()
+                .join(",");
+            match post_json(url, &format!("{{{}}}", json)) {
+                Ok(()) => info!("Sent the notification to {}", url),
+                Err(err) => warn!("{}", err),
+            }
+        }
+        if let Some(address) = &self.email {
+            let subject = match &self.seed {
+                Some(seed) => format!("collatz_bigint_file {} {}: {}", self.job, seed, status),
+                None => format!("collatz_bigint_file {}: {}", self.job, status),
+            };
+            // the values without the JSON quotes, one per line
+            let body: String = fields
+                .iter()
+                .filter(|(name, _)| *name != "stats")
+                .chain(stats)
+                .map(|(name, value)| format!("{}: {}\n", name, value.trim_matches('"')))
+                .collect();
+            match send_mail(address, &subject, &body) {
+                Ok(()) => info!("Sent the notification to {}", address),
+                Err(err) => warn!("{}", err),
+            }
+        }
+    }
+}
+
+// POST the JSON payload: plain HTTP directly, HTTPS with curl, as there is no TLS in the program
+fn post_json(url: &str, json: &str) -> Result<(), String> {
+    if !url.starts_with("https://") {
+        return work::post(url, "the notification", "application/json", json.as_bytes());
+    }
+    let mut child = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--max-time", "30"])
+        .args([
+            "--header",
+            "Content-Type: application/json",
+            "--data-binary",
+            "@-",
+        ])
+        .arg(url)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| match err.kind() {
+            ErrorKind::NotFound => {
+                "Failed to send the notification: https:// webhooks need curl to be installed"
+                    .to_string()
+            }
+            _ => format!("Failed to run curl: {}", err),
+        })?;
+    let written = child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(json.as_bytes());
+    let output = child
+        .wait_with_output()
+        .map_err(|err| format!("Failed to run curl: {}", err))?;
+    match written {
+        Ok(()) if output.status.success() => Ok(()),
+        _ => Err(format!(
+            "Failed to send the notification to {}: {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+    }
+}
+
+// Hand a mail to the local mail transfer agent with `sendmail -t`, which reads the recipient
+// from the header
+fn send_mail(address: &str, subject: &str, body: &str) -> Result<(), String> {
+    let mut child = Command::new("sendmail")
+        .arg("-t")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(|err| match err.kind() {
+            ErrorKind::NotFound => {
+                "Failed to send the notification: --notify-email needs sendmail to be installed"
+                    .to_string()
+            }
+            _ => format!("Failed to run sendmail: {}", err),
+        })?;
+    let written = write!(
+        child.stdin.take().expect("stdin is piped"),
+        "To: {}\nSubject: {}\nContent-Type: text/plain; charset=utf-8\n\n{}",
+        address,
+        subject,
+        body
+    );
+    let status = child
+        .wait()
+        .map_err(|err| format!("Failed to run sendmail: {}", err))?;
+    match written {
+        Ok(()) if status.success() => Ok(()),
+        Err(err) => Err(format!("Failed to send the mail to {}: {}", address, err)),
+        Ok(()) => Err(format!(
+            "Failed to send the mail to {}: sendmail {}",
+            address, status
+        )),
+    }
+}
+
+// Name of the machine, so notifications of several machines can be told apart
+fn host_name() -> Option<String> {
+    env::var("HOSTNAME")
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
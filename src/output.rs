@@ -0,0 +1,131 @@
+use chrono::{DateTime, Local};
+use num_bigint::BigInt;
+use std::fs::{self, File, OpenOptions};
+use std::io::ErrorKind;
+use std::path::Path;
+
+// Default template of the output file name
+pub const DEFAULT_OUTPUT_NAME: &str = "collatz_sequence.txt";
+
+// Default capacity of the write buffer of the sequence file
+pub const DEFAULT_BUFFER_SIZE: &str = "1M";
+
+// Default limit of the memory of a single value of the sequence
+pub const DEFAULT_MAX_VALUE_SIZE: &str = "1G";
+
+// Function to render an output file name template. Supported placeholders:
+//
+// - `{seed_hash}`: 16 hex digits digest of the parsed start value
+// - `{seed_bits}`: bit length of the parsed start value
+// - `{timestamp}`: local start time as `YYYYmmdd-HHMMSS`
+pub fn render_name(
+    template: &str,
+    seed: &BigInt,
+    start_time: DateTime<Local>,
+) -> Result<String, String> {
+    let mut name = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        name.push_str(&rest[..open]);
+        let close = rest[open..].find('}').ok_or_else(|| {
+            format!(
+                "Unclosed placeholder in output name template '{}'",
+                template
+            )
+        })?;
+        match &rest[open + 1..open + close] {
+            "seed_hash" => name.push_str(&seed_digest(seed)),
+            "seed_bits" => name.push_str(&seed.bits().to_string()),
+            "timestamp" => name.push_str(&start_time.format("%Y%m%d-%H%M%S").to_string()),
+            other => {
+                return Err(format!(
+                    "Unknown placeholder '{{{}}}' in output name template. Use {{seed_hash}}, {{seed_bits}} or {{timestamp}}",
+                    other
+                ))
+            }
+        }
+        rest = &rest[open + close + 1..];
+    }
+    name.push_str(rest);
+    if name.is_empty() || name.contains('/') {
+        return Err(format!("Invalid output file name '{}'", name));
+    }
+    Ok(name)
+}
+
+// Stable digest of a value (64 bit FNV-1a over its decimal digits), so the same seed always
+// gets the same file name across runs and platforms
+pub fn seed_digest(seed: &BigInt) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in seed.to_string().bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+// Helper function to parse a size like "4096", "64K", "500M" or "10G" (powers of 1024) into bytes
+pub fn parse_size(input: &str) -> Result<u64, String> {
+    let input = input.trim();
+    let (digits, multiplier) = match input.char_indices().last() {
+        Some((i, 'K' | 'k')) => (&input[..i], 1 << 10),
+        Some((i, 'M' | 'm')) => (&input[..i], 1 << 20),
+        Some((i, 'G' | 'g')) => (&input[..i], 1 << 30),
+        Some((i, 'T' | 't')) => (&input[..i], 1 << 40),
+        _ => (input, 1),
+    };
+    digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|value| value.checked_mul(multiplier))
+        .filter(|size| *size > 0)
+        .ok_or_else(|| {
+            format!(
+                "Invalid size '{}'. Expected e.g. '4096', '500M' or '10G'",
+                input
+            )
+        })
+}
+
+// Helper function to print a number of bytes with the suffixes of `parse_size`, e.g. "1.5G"
+pub fn format_size(bytes: f64) -> String {
+    let mut size = bytes;
+    for suffix in ["", "K", "M", "G", "T"] {
+        if size < 1024.0 || suffix == "T" {
+            return match suffix {
+                "" => format!("{:.0}", size),
+                suffix => format!("{:.1}{}", size, suffix),
+            };
+        }
+        size /= 1024.0;
+    }
+    unreachable!()
+}
+
+// Function to create the output file, creating missing parent directories.
+// An existing file is only overwritten with `force`.
+pub fn create(path: &Path, force: bool) -> Result<File, String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|err| {
+            format!(
+                "Failed to create output directory {}: {}",
+                parent.display(),
+                err
+            )
+        })?;
+    }
+    let mut options = OpenOptions::new();
+    options.write(true);
+    if force {
+        options.create(true).truncate(true);
+    } else {
+        options.create_new(true);
+    }
+    options.open(path).map_err(|err| match err.kind() {
+        ErrorKind::AlreadyExists => format!(
+            "Output file {} already exists. Use --force to overwrite it or choose another --output-name",
+            path.display()
+        ),
+        _ => format!("Failed to create output file {}: {}", path.display(), err),
+    })
+}
@@ -0,0 +1,196 @@
+// Longest block of parities whose entropy is measured, 2^8 counters
+const MAX_BLOCK: usize = 8;
+// Largest lag of the autocorrelation
+const MAX_LAG: usize = 8;
+
+// Analyses selected with --analyze
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Analysis {
+    /// Entropy, run lengths and autocorrelation of the parity sequence
+    Parity,
+    /// Deviation from the heuristic geometric drift of log2 n, with a chart of both
+    Drift,
+}
+
+/// Statistics of the parity sequence of a trajectory (1 for odd, 0 for even), collected in one
+/// pass with constant memory: the empirical entropy of blocks of up to 8 parities, the lengths of
+/// the runs of equal parity and the autocorrelation for lags up to 8.
+///
+/// A sequence of fair coin flips has an entropy of 1 bit per parity and no autocorrelation; the
+/// 3n+1 map is not that, since every odd value is followed by an even one.
+pub struct ParityAnalysis {
+    length: u64,
+    odd: u64,
+    // last parities, the newest in the lowest bit
+    history: u64,
+    // block_counts[k - 1][pattern] counts the blocks of k parities
+    block_counts: Vec<Vec<u64>>,
+    // runs[parity][length] counts the finished runs
+    runs: [Vec<u64>; 2],
+    current_run: u64,
+    // lag_sums[lag - 1] sums x(t) * x(t - lag) with x = ±1
+    lag_sums: [i64; MAX_LAG],
+}
+
+impl ParityAnalysis {
+    pub fn new() -> Self {
+        ParityAnalysis {
+            length: 0,
+            odd: 0,
+            history: 0,
+            block_counts: (1..=MAX_BLOCK).map(|k| vec![0; 1 << k]).collect(),
+            runs: [Vec::new(), Vec::new()],
+            current_run: 0,
+            lag_sums: [0; MAX_LAG],
+        }
+    }
+
+    pub fn push(&mut self, is_even: bool) {
+        let bit = u64::from(!is_even);
+        if self.length > 0 && self.history & 1 != bit {
+            self.finish_run();
+        }
+        self.current_run += 1;
+
+        for lag in 1..=MAX_LAG.min(self.length as usize) {
+            let earlier = (self.history >> (lag - 1)) & 1;
+            self.lag_sums[lag - 1] += if earlier == bit { 1 } else { -1 };
+        }
+        self.history = (self.history << 1) | bit;
+        self.length += 1;
+        self.odd += bit;
+        for k in 1..=MAX_BLOCK.min(self.length as usize) {
+            let pattern = (self.history & ((1 << k) - 1)) as usize;
+            self.block_counts[k - 1][pattern] += 1;
+        }
+    }
+
+    // Function to count the run that ends with the last parity
+    fn finish_run(&mut self) {
+        let runs = &mut self.runs[(self.history & 1) as usize];
+        let length = self.current_run as usize;
+        if runs.len() <= length {
+            runs.resize(length + 1, 0);
+        }
+        runs[length] += 1;
+        self.current_run = 0;
+    }
+
+    // Entropy in bits per parity of the blocks of k parities
+    fn block_entropy(&self, k: usize) -> f64 {
+        let counts = &self.block_counts[k - 1];
+        let total: u64 = counts.iter().sum();
+        if total == 0 {
+            return 0.0;
+        }
+        let entropy: f64 = counts
+            .iter()
+            .filter(|&&count| count > 0)
+            .map(|&count| {
+                let p = count as f64 / total as f64;
+                -p * p.log2()
+            })
+            .sum();
+        entropy / k as f64
+    }
+
+    // Autocorrelation of the ±1 parity sequence at a lag, None if it is too short or constant
+    fn autocorrelation(&self, lag: usize) -> Option<f64> {
+        let pairs = self
+            .length
+            .checked_sub(lag as u64)
+            .filter(|&pairs| pairs > 0)?;
+        let mean = (2.0 * self.odd as f64 - self.length as f64) / self.length as f64;
+        let variance = 1.0 - mean * mean;
+        (variance > 0.0)
+            .then(|| (self.lag_sums[lag - 1] as f64 / pairs as f64 - mean * mean) / variance)
+    }
+
+    // Finished runs plus the last one, which ends with the sequence
+    fn all_runs(&self) -> [Vec<u64>; 2] {
+        let mut runs = self.runs.clone();
+        if self.current_run > 0 {
+            let last = &mut runs[(self.history & 1) as usize];
+            let length = self.current_run as usize;
+            if last.len() <= length {
+                last.resize(length + 1, 0);
+            }
+            last[length] += 1;
+        }
+        runs
+    }
+
+    pub fn print(&self) {
+        if self.length == 0 {
+            return;
+        }
+        println!();
+        println!(
+            "parity sequence: {} values, {} odd ({:.2}%)",
+            self.length,
+            self.odd,
+            100.0 * self.odd as f64 / self.length as f64
+        );
+        let entropies = (1..=MAX_BLOCK)
+            .filter(|&k| k as u64 <= self.length)
+            .map(|k| format!("{}: {:.4}", k, self.block_entropy(k)))
+            .collect::<Vec<_>>();
+        println!(
+            "entropy in bits per parity, by block length: {}",
+            entropies.join(", ")
+        );
+        let correlations = (1..=MAX_LAG)
+            .filter_map(|lag| Some(format!("{}: {:+.4}", lag, self.autocorrelation(lag)?)))
+            .collect::<Vec<_>>();
+        if !correlations.is_empty() {
+            println!("autocorrelation by lag: {}", correlations.join(", "));
+        }
+        let runs = self.all_runs();
+        for (parity, name) in [(0, "even"), (1, "odd")] {
+            let lengths = runs[parity]
+                .iter()
+                .enumerate()
+                .filter(|(_, &count)| count > 0)
+                .map(|(length, count)| format!("{}×{}", count, length))
+                .collect::<Vec<_>>();
+            if !lengths.is_empty() {
+                println!(
+                    "runs of {} values (count×length): {}",
+                    name,
+                    lengths.join(" ")
+                );
+            }
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let entropies = (1..=MAX_BLOCK)
+            .filter(|&k| k as u64 <= self.length)
+            .map(|k| format!("{:.6}", self.block_entropy(k)))
+            .collect::<Vec<_>>();
+        let correlations = (1..=MAX_LAG)
+            .map(|lag| {
+                self.autocorrelation(lag)
+                    .map_or("null".to_string(), |r| format!("{:.6}", r))
+            })
+            .collect::<Vec<_>>();
+        let runs = self.all_runs().map(|counts| {
+            counts
+                .iter()
+                .enumerate()
+                .filter(|(_, &count)| count > 0)
+                .map(|(length, count)| format!("\"{}\":{}", length, count))
+                .collect::<Vec<_>>()
+                .join(",")
+        });
+        format!(
+            "{{\"length\":{},\"odd\":{},\"block_entropy\":[{}],\"autocorrelation\":[{}],\"runs\":{{\"even\":{{{}}},\"odd\":{{{}}}}}}}",
+            self.length,
+            self.odd,
+            entropies.join(","),
+            correlations.join(","),
+            runs[0],
+            runs[1]
+        )
+    }
+}
@@ -0,0 +1,229 @@
+use crate::output;
+use num_bigint::BigInt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+// First bytes of a parity vector file, naming the format and its version
+const MAGIC: &[u8; 8] = b"CLZPAR1\n";
+
+/// Writer of the parity vector of a trajectory: one bit per step, 1 if the step is 3n+1 (the
+/// value before it is odd) and 0 if it is a halving, starting with the step from the seed.
+///
+/// The file holds the 8 bytes of `MAGIC`, then the bits packed 8 to a byte, least significant
+/// bit first. A 1 bit after the last step marks the end, the rest of the last byte is zero. The
+/// seed and the parity vector determine the whole trajectory, see `reconstruct`.
+pub struct ParityVector {
+    path: PathBuf,
+    output: BufWriter<File>,
+    byte: u8,
+    filled: u32,
+    steps: u64,
+    // parity of the last value; it only becomes a step once a value follows it
+    pending: bool,
+}
+
+impl ParityVector {
+    pub fn create(path: &Path, seed: &BigInt, force: bool) -> Result<Self, String> {
+        let mut output = BufWriter::new(output::create(path, force)?);
+        output
+            .write_all(MAGIC)
+            .map_err(|err| format!("Failed to write {}: {}", path.display(), err))?;
+        Ok(ParityVector {
+            path: path.to_path_buf(),
+            output,
+            byte: 0,
+            filled: 0,
+            steps: 0,
+            pending: seed.bit(0),
+        })
+    }
+
+    fn push_bit(&mut self, bit: bool) -> io::Result<()> {
+        self.byte |= u8::from(bit) << self.filled;
+        self.filled += 1;
+        if self.filled == 8 {
+            self.output.write_all(&[self.byte])?;
+            self.byte = 0;
+            self.filled = 0;
+        }
+        Ok(())
+    }
+
+    // Record the next value of the trajectory, which completes the step from the previous one
+    pub fn push(&mut self, is_even: bool) -> io::Result<()> {
+        self.push_bit(self.pending)?;
+        self.pending = !is_even;
+        self.steps += 1;
+        Ok(())
+    }
+
+    // Write the end marker and flush; returns the path and the number of steps
+    pub fn finish(mut self) -> io::Result<(PathBuf, u64)> {
+        self.push_bit(true)?;
+        if self.filled > 0 {
+            self.output.write_all(&[self.byte])?;
+        }
+        self.output.flush()?;
+        Ok((self.path, self.steps))
+    }
+}
+
+/// Reader of the steps of a parity vector file, `true` for 3n+1.
+pub struct ParityReader {
+    input: BufReader<File>,
+    byte: u8,
+    // bits of `byte` still to be returned
+    left: u32,
+    // the byte after `byte`, None once `byte` is the last one
+    next: Option<u8>,
+}
+
+impl ParityReader {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let read_error = |err: io::Error| format!("Failed to read {}: {}", path.display(), err);
+        let file = File::open(path)
+            .map_err(|err| format!("Failed to open {}: {}", path.display(), err))?;
+        let mut input = BufReader::new(file);
+        let mut magic = [0; MAGIC.len()];
+        input.read_exact(&mut magic).map_err(read_error)?;
+        if &magic != MAGIC {
+            return Err(format!("{} is not a parity vector file", path.display()));
+        }
+        let mut reader = ParityReader {
+            input,
+            byte: 0,
+            left: 0,
+            next: None,
+        };
+        reader.next = reader.read_byte().map_err(read_error)?;
+        if reader.next.is_none() {
+            return Err(format!(
+                "{} is truncated: the end marker is missing",
+                path.display()
+            ));
+        }
+        Ok(reader)
+    }
+
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        let mut byte = [0];
+        match self.input.read(&mut byte)? {
+            0 => Ok(None),
+            _ => Ok(Some(byte[0])),
+        }
+    }
+
+    // Next step, None after the last one
+    pub fn next_step(&mut self) -> io::Result<Option<bool>> {
+        if self.left == 0 {
+            let Some(byte) = self.next else {
+                return Ok(None);
+            };
+            self.byte = byte;
+            self.next = self.read_byte()?;
+            self.left = match self.next {
+                Some(_) => 8,
+                // the highest 1 bit of the last byte is the end marker
+                None if byte != 0 => 7 - byte.leading_zeros(),
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "the end marker is missing",
+                    ))
+                }
+            };
+            if self.left == 0 {
+                return Ok(None);
+            }
+        }
+        let bit = self.byte & 1 == 1;
+        self.byte >>= 1;
+        self.left -= 1;
+        Ok(Some(bit))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use collatz_bigint_file::sequence::{Limits, Map};
+    use collatz_bigint_file::stats;
+    use num_bigint::Sign;
+    use num_traits::One;
+    use proptest::prelude::*;
+    use std::ops::ControlFlow;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // A file of its own in the temporary directory for every round trip
+    fn temp_path() -> PathBuf {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+        std::env::temp_dir().join(format!(
+            "collatz_parity_{}_{}.bin",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ))
+    }
+
+    // Write the parity vector of the trajectory of `seed`, read it back and replay it: the steps
+    // must be the parities of the values, and end where the trajectory ends
+    fn round_trip(seed: &BigInt, max_steps: usize) {
+        let path = temp_path();
+        let mut vector = ParityVector::create(&path, seed, true).unwrap();
+        let mut values = Vec::new();
+        let limits = Limits {
+            max_steps: Some(max_steps),
+            ..Limits::default()
+        };
+        stats::visit_trajectory(String::new(), seed, &limits, Map::Standard, |n| {
+            vector.push(!n.bit(0)).unwrap();
+            values.push(n.clone());
+            ControlFlow::Continue(())
+        });
+        let (_, steps) = vector.finish().unwrap();
+        assert_eq!(steps, values.len() as u64);
+
+        let mut reader = ParityReader::open(&path).unwrap();
+        let mut n = seed.clone();
+        for value in &values {
+            let odd = reader.next_step().unwrap().expect("a step per value");
+            assert_eq!(odd, n.bit(0));
+            n = if odd { BigInt::from(3) * n + 1 } else { n >> 1 };
+            assert_eq!(&n, value);
+        }
+        assert_eq!(reader.next_step().unwrap(), None);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn steps_of_every_length_modulo_a_byte() {
+        // 27 has 111 steps, 1 none at all; the others end at every position of the last byte
+        for seed in 1..=40u32 {
+            round_trip(&BigInt::from(seed), usize::MAX);
+        }
+        for max_steps in 0..=16 {
+            round_trip(&BigInt::from(27), max_steps);
+        }
+    }
+
+    #[test]
+    fn truncated_files_are_rejected() {
+        let path = temp_path();
+        std::fs::write(&path, MAGIC).unwrap();
+        assert!(ParityReader::open(&path).is_err());
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(0);
+        std::fs::write(&path, &bytes).unwrap();
+        let mut reader = ParityReader::open(&path).unwrap();
+        assert!(reader.next_step().is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    proptest! {
+        #[test]
+        fn any_trajectory_round_trips(bytes in proptest::collection::vec(any::<u8>(), 0..40)) {
+            let seed = BigInt::from_bytes_le(Sign::Plus, &bytes) + BigInt::one();
+            round_trip(&seed, 100_000);
+        }
+    }
+}
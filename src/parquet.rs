@@ -0,0 +1,366 @@
+use collatz_bigint_file::report::{Field, SequenceStats, Value};
+use collatz_bigint_file::stats::Row;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+// Rows buffered in memory before they are written as a row group
+const ROW_GROUP_SIZE: usize = 1 << 20;
+
+const MAGIC: &[u8] = b"PAR1";
+
+// Physical types, repetition types and encodings of the Parquet format
+const TYPE_INT64: i32 = 2;
+const TYPE_DOUBLE: i32 = 5;
+const TYPE_BYTE_ARRAY: i32 = 6;
+const REQUIRED: i32 = 0;
+const OPTIONAL: i32 = 1;
+const CONVERTED_UTF8: i32 = 0;
+const ENCODING_PLAIN: i32 = 0;
+const ENCODING_RLE: i32 = 3;
+
+/// Writer of the per-seed statistics of a batch as a Parquet file.
+///
+/// The columns are the fields of [`SequenceStats`], `max_value` as decimal text; the fields that
+/// can be unknown, like `glide` or `truncated`, are optional columns. Pages are PLAIN encoded and
+/// uncompressed, one page per column and row group of about a million rows.
+pub struct ParquetWriter {
+    file: BufWriter<File>,
+    offset: u64,
+    columns: Vec<Column>,
+    rows: usize,
+    row_groups: Vec<RowGroup>,
+}
+
+impl ParquetWriter {
+    pub fn create(path: &Path) -> Result<ParquetWriter, String> {
+        let file = File::create(path)
+            .map_err(|err| format!("Failed to create {}: {}", path.display(), err))?;
+        let mut file = BufWriter::new(file);
+        file.write_all(MAGIC)
+            .map_err(|err| format!("Failed to write to {}: {}", path.display(), err))?;
+        Ok(ParquetWriter {
+            file,
+            offset: MAGIC.len() as u64,
+            columns: SequenceStats::schema().iter().map(Column::new).collect(),
+            rows: 0,
+            row_groups: Vec::new(),
+        })
+    }
+
+    pub fn write_row(&mut self, row: &Row) -> io::Result<()> {
+        self.write_stats(&SequenceStats::new(row, row.max_value.to_string()))
+    }
+
+    /// Writes the statistics of one seed, the max value as `stats` has it.
+    pub fn write_stats(&mut self, stats: &SequenceStats) -> io::Result<()> {
+        for (column, field) in self.columns.iter_mut().zip(stats.fields()) {
+            column.push(field.value);
+        }
+        self.rows += 1;
+        if self.rows == ROW_GROUP_SIZE {
+            self.write_row_group()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the last row group and the footer with the schema and the row group offsets.
+    pub fn finish(mut self) -> io::Result<()> {
+        if self.rows > 0 {
+            self.write_row_group()?;
+        }
+        let mut meta = Thrift::default();
+        meta.i32(1, 1);
+        meta.list(2, THRIFT_STRUCT, self.columns.len() + 1);
+        meta.begin_element();
+        meta.binary(4, b"schema");
+        meta.i32(5, self.columns.len() as i32);
+        meta.end_struct();
+        for column in &self.columns {
+            meta.begin_element();
+            meta.i32(1, column.kind.physical_type());
+            meta.i32(3, column.repetition);
+            meta.binary(4, column.name.as_bytes());
+            if column.kind == Kind::Text {
+                meta.i32(6, CONVERTED_UTF8);
+            }
+            meta.end_struct();
+        }
+        let total_rows = self
+            .row_groups
+            .iter()
+            .map(|group| group.rows)
+            .sum::<usize>();
+        meta.i64(3, total_rows as i64);
+        meta.list(4, THRIFT_STRUCT, self.row_groups.len());
+        for group in &self.row_groups {
+            meta.begin_element();
+            meta.list(1, THRIFT_STRUCT, group.chunks.len());
+            for (column, chunk) in self.columns.iter().zip(&group.chunks) {
+                meta.begin_element();
+                meta.i64(2, chunk.offset as i64);
+                meta.begin_struct(3);
+                meta.i32(1, column.kind.physical_type());
+                meta.list(2, THRIFT_I32, 2);
+                meta.list_i32(ENCODING_PLAIN);
+                meta.list_i32(ENCODING_RLE);
+                meta.list(3, THRIFT_BINARY, 1);
+                meta.list_binary(column.name.as_bytes());
+                meta.i32(4, 0); // uncompressed
+                meta.i64(5, group.rows as i64);
+                meta.i64(6, chunk.size as i64);
+                meta.i64(7, chunk.size as i64);
+                meta.i64(9, chunk.offset as i64);
+                meta.end_struct();
+                meta.end_struct();
+            }
+            let size = group.chunks.iter().map(|chunk| chunk.size).sum::<u64>();
+            meta.i64(2, size as i64);
+            meta.i64(3, group.rows as i64);
+            meta.end_struct();
+        }
+        meta.binary(
+            6,
+            format!("collatz_bigint_file version {}", env!("CARGO_PKG_VERSION")).as_bytes(),
+        );
+        meta.end_struct();
+
+        self.file.write_all(&meta.out)?;
+        self.file
+            .write_all(&(meta.out.len() as u32).to_le_bytes())?;
+        self.file.write_all(MAGIC)?;
+        self.file.flush()
+    }
+
+    fn write_row_group(&mut self) -> io::Result<()> {
+        let mut chunks = Vec::with_capacity(self.columns.len());
+        for column in &mut self.columns {
+            let page = column.take_page();
+            let mut header = Thrift::default();
+            header.i32(1, 0); // data page
+            header.i32(2, page.len() as i32);
+            header.i32(3, page.len() as i32);
+            header.begin_struct(5);
+            header.i32(1, self.rows as i32);
+            header.i32(2, ENCODING_PLAIN);
+            header.i32(3, ENCODING_RLE);
+            header.i32(4, ENCODING_RLE);
+            header.end_struct();
+            header.end_struct();
+
+            self.file.write_all(&header.out)?;
+            self.file.write_all(&page)?;
+            let size = (header.out.len() + page.len()) as u64;
+            chunks.push(Chunk {
+                offset: self.offset,
+                size,
+            });
+            self.offset += size;
+        }
+        self.row_groups.push(RowGroup {
+            rows: self.rows,
+            chunks,
+        });
+        self.rows = 0;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Int,
+    Float,
+    Text,
+}
+
+impl Kind {
+    fn physical_type(self) -> i32 {
+        match self {
+            Kind::Int => TYPE_INT64,
+            Kind::Float => TYPE_DOUBLE,
+            Kind::Text => TYPE_BYTE_ARRAY,
+        }
+    }
+}
+
+// Values of one column in the current row group, already PLAIN encoded
+struct Column {
+    name: &'static str,
+    kind: Kind,
+    repetition: i32,
+    values: Vec<u8>,
+    // definition level of every row of an optional column: 1 for a value, 0 for null
+    defined: Vec<bool>,
+}
+
+impl Column {
+    // Column of a field of the schema, optional if the field is null there
+    fn new(field: &Field) -> Column {
+        Column {
+            name: field.name,
+            kind: match field.value {
+                Value::Int(_) => Kind::Int,
+                Value::Float(_) => Kind::Float,
+                Value::Text(_) => Kind::Text,
+            },
+            repetition: if field.value.is_null() {
+                OPTIONAL
+            } else {
+                REQUIRED
+            },
+            values: Vec::new(),
+            defined: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, value: Value) {
+        self.push_defined(!value.is_null());
+        match value {
+            Value::Int(Some(value)) => self.values.extend_from_slice(&value.to_le_bytes()),
+            Value::Float(Some(value)) => self.values.extend_from_slice(&value.to_le_bytes()),
+            Value::Text(Some(value)) => {
+                self.values
+                    .extend_from_slice(&(value.len() as u32).to_le_bytes());
+                self.values.extend_from_slice(value.as_bytes());
+            }
+            Value::Int(None) | Value::Float(None) | Value::Text(None) => {}
+        }
+    }
+
+    fn push_defined(&mut self, defined: bool) {
+        if self.repetition == OPTIONAL {
+            self.defined.push(defined);
+        }
+    }
+
+    // Page body: definition levels of optional columns (length prefixed RLE runs of bit width
+    // 1), then the values
+    fn take_page(&mut self) -> Vec<u8> {
+        let mut page = Vec::new();
+        if self.repetition == OPTIONAL {
+            let mut levels = Vec::new();
+            for run in self.defined.chunk_by(|a, b| a == b) {
+                write_varint(&mut levels, (run.len() as u64) << 1);
+                levels.push(u8::from(run[0]));
+            }
+            page.extend_from_slice(&(levels.len() as u32).to_le_bytes());
+            page.extend_from_slice(&levels);
+        }
+        page.append(&mut self.values);
+        self.defined.clear();
+        page
+    }
+}
+
+struct RowGroup {
+    rows: usize,
+    chunks: Vec<Chunk>,
+}
+
+// Position of a column chunk (page header and page) in the file
+struct Chunk {
+    offset: u64,
+    size: u64,
+}
+
+// Element types of the Thrift compact protocol used by the Parquet metadata
+const THRIFT_I32: u8 = 5;
+const THRIFT_I64: u8 = 6;
+const THRIFT_BINARY: u8 = 8;
+const THRIFT_LIST: u8 = 9;
+const THRIFT_STRUCT: u8 = 12;
+
+// Encoder of the Thrift compact protocol, just enough for the Parquet metadata. Fields have to be
+// written in increasing id order; `last_field` holds the last id of every open struct.
+struct Thrift {
+    out: Vec<u8>,
+    last_field: Vec<i16>,
+}
+
+impl Default for Thrift {
+    fn default() -> Self {
+        Thrift {
+            out: Vec::new(),
+            last_field: vec![0],
+        }
+    }
+}
+
+impl Thrift {
+    fn field(&mut self, id: i16, kind: u8) {
+        let last = self
+            .last_field
+            .last_mut()
+            .expect("field outside of a struct");
+        let delta = id - *last;
+        if (1..=15).contains(&delta) {
+            self.out.push((delta as u8) << 4 | kind);
+        } else {
+            self.out.push(kind);
+            write_varint(&mut self.out, zigzag(id as i64));
+        }
+        *last = id;
+    }
+
+    fn i32(&mut self, id: i16, value: i32) {
+        self.field(id, THRIFT_I32);
+        write_varint(&mut self.out, zigzag(value as i64));
+    }
+
+    fn i64(&mut self, id: i16, value: i64) {
+        self.field(id, THRIFT_I64);
+        write_varint(&mut self.out, zigzag(value));
+    }
+
+    fn binary(&mut self, id: i16, value: &[u8]) {
+        self.field(id, THRIFT_BINARY);
+        self.list_binary(value);
+    }
+
+    fn begin_struct(&mut self, id: i16) {
+        self.field(id, THRIFT_STRUCT);
+        self.last_field.push(0);
+    }
+
+    // a struct inside a list has no field header
+    fn begin_element(&mut self) {
+        self.last_field.push(0);
+    }
+
+    fn end_struct(&mut self) {
+        self.out.push(0);
+        self.last_field.pop();
+    }
+
+    fn list(&mut self, id: i16, element: u8, len: usize) {
+        self.field(id, THRIFT_LIST);
+        if len < 15 {
+            self.out.push((len as u8) << 4 | element);
+        } else {
+            self.out.push(0xf0 | element);
+            write_varint(&mut self.out, len as u64);
+        }
+    }
+
+    fn list_i32(&mut self, value: i32) {
+        write_varint(&mut self.out, zigzag(value as i64));
+    }
+
+    fn list_binary(&mut self, value: &[u8]) {
+        write_varint(&mut self.out, value.len() as u64);
+        self.out.extend_from_slice(value);
+    }
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+// Unsigned LEB128, used by Thrift and by the RLE run headers
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
@@ -0,0 +1,192 @@
+use crate::output;
+use collatz_bigint_file::info;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+// Extension of the manifest listing the part files of a split sequence
+pub const MANIFEST_EXTENSION: &str = "manifest";
+
+/// Writer for the sequence file that rotates into numbered part files.
+///
+/// Output starts in the regular file. Once it grows beyond `max_part_size` bytes, the file is
+/// renamed to `<stem>.part0001.<ext>` and output continues in `<stem>.part0002.<ext>` and so on.
+/// Files are only rotated at line boundaries. `finish` writes a `<stem>.manifest` listing all
+/// parts if the output was split.
+pub struct PartWriter {
+    path: PathBuf,
+    current: BufWriter<File>,
+    // bytes of the current part, and of all parts written or appended to by this writer
+    written: u64,
+    total: u64,
+    max_part_size: Option<u64>,
+    buffer_size: usize,
+    force: bool,
+    parts: Vec<PathBuf>,
+    // a resumed split sequence rewrites its existing manifest
+    overwrite_manifest: bool,
+}
+
+impl PartWriter {
+    pub fn new(
+        path: PathBuf,
+        file: File,
+        max_part_size: Option<u64>,
+        buffer_size: usize,
+        force: bool,
+    ) -> Self {
+        PartWriter {
+            path,
+            current: BufWriter::with_capacity(buffer_size, file),
+            written: 0,
+            total: 0,
+            max_part_size,
+            buffer_size,
+            force,
+            parts: Vec::new(),
+            overwrite_manifest: false,
+        }
+    }
+
+    /// Continues an existing sequence in append mode. `files` are the files of the sequence as
+    /// listed by `sequence_files`; the last one is the file opened for appending.
+    pub fn append(
+        files: Vec<PathBuf>,
+        file: File,
+        max_part_size: Option<u64>,
+        buffer_size: usize,
+        force: bool,
+    ) -> io::Result<Self> {
+        let last = files.last().cloned().unwrap_or_default();
+        let written = file.metadata()?.len();
+        let mut writer = PartWriter::new(last.clone(), file, max_part_size, buffer_size, force);
+        writer.written = written;
+        if files.len() > 1 {
+            // rebuild the base path "<stem>.<ext>" from "<stem>.partNNNN.<ext>"
+            let name = last
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned();
+            let suffix = format!(".part{:04}", files.len());
+            writer.path = last.with_file_name(name.replacen(&suffix, "", 1));
+            writer.parts = files;
+            writer.overwrite_manifest = true;
+        }
+        Ok(writer)
+    }
+
+    /// Bytes written by this writer, over all parts.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    fn part_path(&self, number: usize) -> PathBuf {
+        let stem = self.path.file_stem().unwrap_or_default().to_string_lossy();
+        let name = match self.path.extension() {
+            Some(extension) => {
+                format!("{}.part{:04}.{}", stem, number, extension.to_string_lossy())
+            }
+            None => format!("{}.part{:04}", stem, number),
+        };
+        self.path.with_file_name(name)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.current.flush()?;
+        if self.parts.is_empty() {
+            let first = self.part_path(1);
+            if first.exists() && !self.force {
+                return Err(io::Error::other(format!(
+                    "Part file {} already exists. Use --force to overwrite it",
+                    first.display()
+                )));
+            }
+            fs::rename(&self.path, &first)?;
+            self.parts.push(first);
+        }
+        let next = self.part_path(self.parts.len() + 1);
+        let file = output::create(&next, self.force).map_err(io::Error::other)?;
+        self.current = BufWriter::with_capacity(self.buffer_size, file);
+        info!("Continuing in part file {}", next.display());
+        self.parts.push(next);
+        self.written = 0;
+        Ok(())
+    }
+
+    /// Flushes the output and returns the path to read the sequence back from: the manifest for
+    /// split output, the regular file otherwise.
+    pub fn finish(mut self) -> io::Result<PathBuf> {
+        self.current.flush()?;
+        if self.parts.is_empty() {
+            return Ok(self.path);
+        }
+        let manifest_path = self.path.with_extension(MANIFEST_EXTENSION);
+        let mut manifest = BufWriter::new(
+            output::create(&manifest_path, self.force || self.overwrite_manifest)
+                .map_err(io::Error::other)?,
+        );
+        writeln!(
+            manifest,
+            "# collatz_bigint_file parts: {}",
+            self.parts.len()
+        )?;
+        for part in &self.parts {
+            writeln!(
+                manifest,
+                "{}",
+                part.file_name().unwrap_or_default().to_string_lossy()
+            )?;
+        }
+        manifest.flush()?;
+        Ok(manifest_path)
+    }
+}
+
+impl Write for PartWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.current.write_all(buf)?;
+        self.written += buf.len() as u64;
+        self.total += buf.len() as u64;
+        // values are written a line at a time, so a buffer ending in a newline closes a line
+        if let Some(max_part_size) = self.max_part_size {
+            if self.written >= max_part_size && buf.ends_with(b"\n") {
+                self.rotate()?;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.current.flush()
+    }
+}
+
+// Function to list the files of a sequence: the parts named in a manifest or the file itself
+pub fn sequence_files(path: &Path) -> io::Result<Vec<PathBuf>> {
+    if path
+        .extension()
+        .is_none_or(|extension| extension != MANIFEST_EXTENSION)
+    {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let directory = path.parent().unwrap_or(Path::new(""));
+    let mut files = Vec::new();
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        let name = line.trim();
+        if !name.is_empty() && !name.starts_with('#') {
+            files.push(directory.join(name));
+        }
+    }
+    Ok(files)
+}
+
+// Function to open a sequence for reading, iterating across part files transparently
+pub fn open(path: &Path, buffer_size: usize) -> io::Result<Box<dyn BufRead>> {
+    let mut reader: Box<dyn Read> = Box::new(io::empty());
+    for file in sequence_files(path)? {
+        reader = Box::new(reader.chain(File::open(file)?));
+    }
+    Ok(Box::new(BufReader::with_capacity(buffer_size, reader)))
+}
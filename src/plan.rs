@@ -0,0 +1,193 @@
+use crate::drift;
+use crate::filter::{self, Filter};
+use crate::format;
+use crate::output;
+use crate::sink::Sink;
+use crate::Settings;
+use chrono::Local;
+use collatz_bigint_file::sequence::{expected_steps, Map, Until};
+use num_bigint::BigInt;
+
+// Bytes of the values the computation holds at a time, in units of the seed's size: the
+// current value, 3n+1 while it is computed and the largest value so far
+const VALUES_IN_MEMORY: f64 = 3.0;
+// Bytes of the header and footer lines of a sequence file besides the seed and the max value:
+// the version, map, start time, format, stopping time, max position and duration
+const METADATA_BYTES: f64 = 200.0;
+
+/// Prints what a run of `seed` would do, without computing or writing anything: the parsed
+/// size of the seed, where and how the values would be written, and heuristic estimates of the
+/// number of steps, the output size and the memory footprint.
+///
+/// The estimates follow the drift model of `sequence::drift_per_step`: log2 n falls linearly from
+/// the seed to 1, so the values have half the digits of the seed on average. Single
+/// trajectories can be much longer than the model says.
+pub fn print(settings: &Settings, input_value: &str, seed: &BigInt) -> Result<(), String> {
+    let bits = seed.bits();
+    let digits = format::digit_count(seed, settings.number_format.radix);
+    println!("seed: {}", input_value.trim());
+    println!(
+        "parsed: {} bits, {} digits in base {}",
+        bits, digits, settings.number_format.radix
+    );
+
+    match &settings.sink {
+        Sink::File => {
+            let name = output::render_name(&settings.output_name, seed, Local::now())?;
+            let path = settings.output_dir.join(name);
+            let state = match (path.exists(), settings.force) {
+                (false, _) => "new file",
+                (true, true) => "exists, --force overwrites it",
+                (true, false) => "exists, the run fails without --force",
+            };
+            println!("output: {} ({})", path.display(), state);
+            if let Some(size) = settings.max_part_size {
+                println!(
+                    "parts: split into files of at most {}",
+                    output::format_size(size as f64)
+                );
+            }
+        }
+        sink => println!("output: {}", sink),
+    }
+    let values = match (settings.odd_only, settings.sample_every) {
+        (true, _) => "odd values only".to_string(),
+        (false, Some(every)) => format!("every {}th value", every),
+        (false, None) if !settings.filters.is_empty() => format!(
+            "the values kept by --filter {}, each with its step",
+            filter::describe(&settings.filters)
+        ),
+        (false, None) if settings.annotate => "every value with its step and rule".to_string(),
+        (false, None) => "every value".to_string(),
+    };
+    println!(
+        "format: {}, one per line, base {}",
+        values, settings.number_format.radix
+    );
+
+    let (model_steps, steps) = estimated_steps(settings, seed);
+    let limits = &settings.limits;
+    let capped = if steps < model_steps {
+        " (capped by --max-steps)"
+    } else {
+        ""
+    };
+    let stop = match (&limits.until, &limits.stop_when) {
+        (Until::One, None) => "",
+        (Until::One, Some(_)) => ", fewer with --stop-when",
+        _ => ", fewer with --until",
+    };
+    println!(
+        "estimated steps: ~{:.0}{}{} (heuristic)",
+        steps, capped, stop
+    );
+
+    println!(
+        "estimated output size: ~{} (heuristic)",
+        output::format_size(output_size(settings, seed))
+    );
+    if let Some(budget) = settings.max_disk {
+        println!("disk budget: {}", output::format_size(budget as f64));
+    }
+    let value_bytes = (bits as f64 / 8.0).max(8.0);
+    println!(
+        "estimated memory: ~{} (values ~{}, digits ~{}, write buffer {})",
+        output::format_size(
+            VALUES_IN_MEMORY * value_bytes + digits as f64 + settings.buffer_size as f64
+        ),
+        output::format_size(VALUES_IN_MEMORY * value_bytes),
+        output::format_size(digits as f64),
+        output::format_size(settings.buffer_size as f64)
+    );
+
+    let limit = |limit: Option<String>| limit.unwrap_or_else(|| "none".to_string());
+    println!(
+        "limits: max steps {}, timeout {}, max value size {}",
+        limit(limits.max_steps.map(|steps| steps.to_string())),
+        limit(
+            limits
+                .timeout
+                .map(|timeout| format!("{}s", timeout.as_secs_f64()))
+        ),
+        limit(
+            limits
+                .max_bits
+                .map(|bits| output::format_size(bits as f64 / 8.0))
+        )
+    );
+    if let Some(condition) = &limits.stop_when {
+        println!("stop when: {}", condition);
+    }
+    for (what, path) in [
+        ("parity vector", &settings.parity_file),
+        ("sonification", &settings.sonify),
+        ("analysis", &settings.analysis_json),
+    ] {
+        if let Some(path) = path {
+            println!("also written: {} to {}", what, path.display());
+        }
+    }
+    Ok(())
+}
+
+/// Estimates the size in bytes of the sequence file written for `seed` in the format of
+/// `settings`, with the model of [`print`]: the values, and the header and footer around them.
+///
+/// Every length is taken from the bit length of the seed, which is never converted.
+pub fn output_size(settings: &Settings, seed: &BigInt) -> f64 {
+    let (model_steps, steps) = estimated_steps(settings, seed);
+    let digits = format::digit_count(seed, settings.number_format.radix) as f64;
+    // the seed on the "# seed" and "# parsed" lines, and at most about as long the max value of
+    // the footer, all in decimal
+    let decimal = format::digit_count(seed, 10) as f64;
+    let metadata = METADATA_BYTES + 3.0 * decimal;
+    // falling linearly, the value at step s has 1 - s/m of the seed's digits, m the model's steps;
+    // the mean over the steps up to `end` is taken at half of them
+    let mean_digits =
+        |from: f64, end: f64| digits * (1.0 - (from + end) / 2.0 / model_steps.max(1.0));
+    // the halvings before an odd term, the step of a sample or the step and rule of --annotate
+    let step_digits = steps.max(1.0).log10().floor() + 1.0;
+    let prefix = match (settings.odd_only, settings.sample_every) {
+        (true, _) => "2 ".len() as f64,
+        (false, Some(_)) => step_digits + 1.0,
+        (false, None) if !settings.filters.is_empty() => step_digits + 1.0,
+        (false, None) if settings.annotate => step_digits + ": 3n+1 -> ".len() as f64,
+        (false, None) => 0.0,
+    };
+    let line = |from: f64, end: f64| mean_digits(from, end) + prefix + 1.0;
+    metadata
+        + match (settings.odd_only, settings.sample_every) {
+            // a third of the values are odd
+            (true, _) => steps / 3.0 * line(0.0, steps),
+            // every k-th value and the largest of every k, then the last k values in full
+            (false, Some(every)) => {
+                let tail = (every as f64).min(steps);
+                2.0 * (steps - tail) / every as f64 * line(0.0, steps - tail)
+                    + tail * line(steps - tail, steps)
+            }
+            // the share of the values each stage keeps; records, peaks and thresholds are
+            // counted as keeping everything, so the estimate is an upper bound
+            (false, None) => {
+                let kept: f64 = settings
+                    .filters
+                    .iter()
+                    .map(|filter| match filter {
+                        Filter::OddOnly => 1.0 / 3.0,
+                        Filter::Every(every) => 1.0 / *every as f64,
+                        _ => 1.0,
+                    })
+                    .product();
+                kept * steps * line(0.0, steps)
+            }
+        }
+}
+
+// Steps of the drift model down to 1 and the steps computed within --max-steps
+fn estimated_steps(settings: &Settings, seed: &BigInt) -> (f64, f64) {
+    let model_steps = expected_steps(drift::log2(seed), Map::Standard);
+    let steps = settings
+        .limits
+        .max_steps
+        .map_or(model_steps, |max_steps| model_steps.min(max_steps as f64));
+    (model_steps, steps)
+}
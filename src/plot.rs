@@ -0,0 +1,269 @@
+use crate::drift;
+use crate::graphics::{self, Canvas, InlineGraphics};
+use crate::output;
+use crate::parts;
+use crate::scan::{Line, Scanner};
+use collatz_bigint_file::sequence::{Limits, Map};
+use collatz_bigint_file::stats;
+use colored::Colorize;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::ops::ControlFlow;
+use std::path::Path;
+
+// Colors of the trajectories in order (Tableau 10), distinguishable side by side
+const PALETTE: [&str; 10] = [
+    "#4e79a7", "#f28e2b", "#e15759", "#76b7b2", "#59a14f", "#edc948", "#b07aa1", "#ff9da7",
+    "#9c755f", "#bab0ac",
+];
+// Points drawn per trajectory at most; longer ones keep the largest value of each stretch
+const MAX_POINTS: usize = 2000;
+// Size of the drawing and its margins, in pixels
+const WIDTH: f64 = 1000.0;
+const HEIGHT: f64 = 600.0;
+const LEFT: f64 = 60.0;
+const RIGHT: f64 = 20.0;
+const TOP: f64 = 20.0;
+const BOTTOM: f64 = 45.0;
+// Size of the image shown in the terminal relative to the drawing
+const INLINE_SCALE: f64 = 0.8;
+
+// One trajectory as log2 of its values, from step `first_step` on
+struct Series {
+    label: String,
+    first_step: usize,
+    log2: Vec<f64>,
+}
+
+/// Draws the trajectories of several seeds or sequence files into one SVG chart, on a log
+/// scale, each in its own color with a legend.
+///
+/// An argument that names an existing file is read as a sequence file, whose values start at
+/// step 1; any other argument is a seed computed in memory from step 0. Trajectories stopped by
+/// `--max-steps`, `--timeout` or `--max-value-size` are drawn as far as they got and marked in
+/// the legend.
+///
+/// With a terminal that supports the kitty graphics protocol or sixels, the chart is also shown
+/// inline, as an image of the lines with the legend as text below it.
+pub fn run(
+    inputs: &[String],
+    path: &Path,
+    limits: &Limits,
+    buffer_size: usize,
+    force: bool,
+    inline: InlineGraphics,
+) -> Result<(), String> {
+    if inputs.len() > PALETTE.len() {
+        return Err(format!(
+            "Too many trajectories, at most {} fit in one chart",
+            PALETTE.len()
+        ));
+    }
+    let mut series = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        series.push(if Path::new(input).is_file() {
+            read_file(input, buffer_size)?
+        } else {
+            compute(input, limits)?
+        });
+    }
+
+    let last_step = series
+        .iter()
+        .map(|series| series.first_step + series.log2.len().saturating_sub(1))
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let max_log2 = series
+        .iter()
+        .flat_map(|series| series.log2.iter().copied())
+        .fold(1.0, f64::max)
+        .ceil();
+    let x = |step: f64| LEFT + (WIDTH - LEFT - RIGHT) * step / last_step as f64;
+    let y = |log2: f64| HEIGHT - BOTTOM - (HEIGHT - TOP - BOTTOM) * log2 / max_log2;
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"0 0 {w} {h}\" width=\"{w}\" height=\"{h}\" font-family=\"sans-serif\" font-size=\"12\">",
+        w = WIDTH,
+        h = HEIGHT
+    )
+    .expect("writing to a string does not fail");
+    writeln!(svg, "<rect width=\"100%\" height=\"100%\" fill=\"white\"/>")
+        .expect("writing to a string does not fail");
+
+    // axes with five ticks each; the y axis is labeled in powers of two
+    writeln!(
+        svg,
+        "<path d=\"M{l} {t}V{b}H{r}\" fill=\"none\" stroke=\"#333\"/>",
+        l = LEFT,
+        t = TOP,
+        b = HEIGHT - BOTTOM,
+        r = WIDTH - RIGHT
+    )
+    .expect("writing to a string does not fail");
+    for tick in 0..=5 {
+        let step = (last_step as f64 * tick as f64 / 5.0).round();
+        writeln!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>",
+            x(step),
+            HEIGHT - BOTTOM + 16.0,
+            step
+        )
+        .expect("writing to a string does not fail");
+        let log2 = (max_log2 * tick as f64 / 5.0).round();
+        writeln!(
+            svg,
+            "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">2^{}</text>",
+            LEFT - 6.0,
+            y(log2) + 4.0,
+            log2
+        )
+        .expect("writing to a string does not fail");
+    }
+    writeln!(
+        svg,
+        "<text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">step</text>",
+        (LEFT + WIDTH - RIGHT) / 2.0,
+        HEIGHT - 8.0
+    )
+    .expect("writing to a string does not fail");
+
+    // points of every trajectory in the coordinates of the drawing
+    let lines: Vec<Vec<(f64, f64)>> = series
+        .iter()
+        .map(|series| {
+            let per_point = series.log2.len().div_ceil(MAX_POINTS).max(1);
+            series
+                .log2
+                .chunks(per_point)
+                .enumerate()
+                .map(|(i, chunk)| {
+                    let log2 = chunk.iter().copied().fold(0.0, f64::max);
+                    (x((series.first_step + i * per_point) as f64), y(log2))
+                })
+                .collect()
+        })
+        .collect();
+    for (line, color) in lines.iter().zip(PALETTE) {
+        let mut data = String::new();
+        for (i, (x, y)) in line.iter().enumerate() {
+            write!(data, "{}{:.1} {:.1}", if i == 0 { "M" } else { "L" }, x, y)
+                .expect("writing to a string does not fail");
+        }
+        writeln!(
+            svg,
+            "<path d=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"1.2\" stroke-linejoin=\"round\"/>",
+            data, color
+        )
+        .expect("writing to a string does not fail");
+    }
+
+    // legend in the upper right corner
+    for (i, (series, color)) in series.iter().zip(PALETTE).enumerate() {
+        let top = TOP + 8.0 + 18.0 * i as f64;
+        writeln!(
+            svg,
+            "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"12\" height=\"12\" fill=\"{}\"/><text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>",
+            WIDTH - RIGHT - 20.0,
+            top,
+            color,
+            WIDTH - RIGHT - 26.0,
+            top + 10.0,
+            xml_escape(&series.label)
+        )
+        .expect("writing to a string does not fail");
+    }
+    svg.push_str("</svg>\n");
+
+    output::create(path, force)?
+        .write_all(svg.as_bytes())
+        .map_err(|err| format!("Failed to write {}: {}", path.display(), err))?;
+    println!(
+        "Plotted {} trajectories into {}",
+        series.len(),
+        path.display()
+    );
+
+    if let Some(protocol) = graphics::detect(inline) {
+        let mut canvas = Canvas::new(
+            (WIDTH * INLINE_SCALE) as usize,
+            (HEIGHT * INLINE_SCALE) as usize,
+        );
+        let scale = |(x, y): (f64, f64)| (x * INLINE_SCALE, y * INLINE_SCALE);
+        let (left, bottom) = scale((LEFT, HEIGHT - BOTTOM));
+        let (right, top) = scale((WIDTH - RIGHT, TOP));
+        canvas.line((left, top), (left, bottom), 1);
+        canvas.line((left, bottom), (right, bottom), 1);
+        for (i, line) in lines.iter().enumerate() {
+            for pair in line.windows(2) {
+                canvas.line(scale(pair[0]), scale(pair[1]), i as u8 + 2);
+            }
+        }
+        let mut palette = vec![[255, 255, 255], [51, 51, 51]];
+        palette.extend(PALETTE.iter().map(|color| rgb(color)));
+        let mut stdout = io::stdout().lock();
+        canvas
+            .write(&mut stdout, protocol, &palette)
+            .map_err(|err| format!("Failed to draw the chart: {}", err))?;
+        println!(
+            "step 0 to {} (x), log2 of the values 0 to {} (y)",
+            last_step, max_log2
+        );
+        for (series, [r, g, b]) in series.iter().zip(&palette[2..]) {
+            println!("{} {}", "■".truecolor(*r, *g, *b), series.label);
+        }
+    }
+    Ok(())
+}
+
+// Channels of a color like "#4e79a7"
+fn rgb(color: &str) -> [u8; 3] {
+    let channel = |i: usize| u8::from_str_radix(&color[1 + 2 * i..3 + 2 * i], 16).unwrap_or(0);
+    [channel(0), channel(1), channel(2)]
+}
+
+// Compute the trajectory of a seed in memory
+fn compute(seed: &str, limits: &Limits) -> Result<Series, String> {
+    let start = crate::parse_input(seed.to_string(), limits.max_bits.unwrap_or(u64::MAX))
+        .map_err(|err| format!("{}: {}", seed, err))?;
+    let mut log2 = vec![drift::log2(&start)];
+    let row = stats::visit_trajectory(seed.to_string(), &start, limits, Map::Standard, |n| {
+        log2.push(drift::log2(n));
+        ControlFlow::Continue(())
+    });
+    let label = match row.truncated {
+        Some(truncation) => format!("{} ({})", seed, truncation),
+        None => seed.to_string(),
+    };
+    Ok(Series {
+        label,
+        first_step: 0,
+        log2,
+    })
+}
+
+// Read the values of a sequence file (or all its part files)
+fn read_file(path: &str, buffer_size: usize) -> Result<Series, String> {
+    let read_error = |err| format!("Failed to read {}: {}", path, err);
+    let mut scanner = Scanner::new(parts::open(Path::new(path), buffer_size).map_err(read_error)?);
+    let mut log2 = Vec::new();
+    while let Some(line) = scanner.next_value().map_err(read_error)? {
+        if let Line::Value(digits) = line {
+            log2.push(drift::log2_digits(digits, 10));
+        }
+    }
+    Ok(Series {
+        label: path.to_string(),
+        first_step: 1,
+        log2,
+    })
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,57 @@
+use crate::output;
+use collatz_bigint_file::sequence::Profile;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// Writes the profile of a run to `path`: folded stacks for flame graph tools if the file name
+/// ends in `.folded`, CSV otherwise.
+///
+/// The CSV has one line per bucket of bit lengths and direction with the chunks, steps and
+/// seconds spent, the nanoseconds per step and the share of the total time. The folded stacks
+/// are lines like `collatz;4096-8191 bits;falling 1234567`, weighted in microseconds, which
+/// `flamegraph.pl`, `inferno-flamegraph` and speedscope read as they are.
+pub fn write(path: &Path, profile: &Profile, force: bool) -> Result<(), String> {
+    let entries = profile.entries();
+    let total: f64 = entries.iter().map(|entry| entry.time.as_secs_f64()).sum();
+    let write_error = |err| format!("Failed to write {}: {}", path.display(), err);
+    let mut output = BufWriter::new(output::create(path, force)?);
+    let folded = path
+        .extension()
+        .is_some_and(|extension| extension == "folded");
+    if !folded {
+        writeln!(
+            output,
+            "bits_from,bits_to,direction,chunks,steps,seconds,ns_per_step,share"
+        )
+        .map_err(write_error)?;
+    }
+    for entry in &entries {
+        let direction = if entry.rising { "rising" } else { "falling" };
+        let seconds = entry.time.as_secs_f64();
+        if folded {
+            writeln!(
+                output,
+                "collatz;{}-{} bits;{} {}",
+                entry.bits.0,
+                entry.bits.1,
+                direction,
+                entry.time.as_micros()
+            )
+        } else {
+            writeln!(
+                output,
+                "{},{},{},{},{},{:.6},{:.1},{:.4}",
+                entry.bits.0,
+                entry.bits.1,
+                direction,
+                entry.chunks,
+                entry.steps,
+                seconds,
+                seconds * 1e9 / entry.steps.max(1) as f64,
+                if total > 0.0 { seconds / total } else { 0.0 }
+            )
+        }
+        .map_err(write_error)?;
+    }
+    output.flush().map_err(write_error)
+}
@@ -0,0 +1,35 @@
+//! Machine-readable progress events as JSON lines on a side channel, so GUIs and wrappers can
+//! show their own progress while stdout carries the sequence or the statistics.
+//!
+//! Nothing is written until [`init`] sets the output. While a sequence is computed,
+//! [`collatz`](crate::sequence::collatz) then writes an event every [`INTERVAL`], e.g.
+//! `{"event":"progress","step":120000,"bits":4711,"max_bits":5012,"rate":81234.5,"elapsed":1.501}`
+//! with the steps per second since the last event, and a last one when it stops:
+//! `{"event":"done","steps":250133,"max_bits":5012,"truncated":null,"elapsed":3.052}`.
+
+use std::io::Write;
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+/// Time between two progress events.
+pub const INTERVAL: Duration = Duration::from_millis(500);
+
+static OUTPUT: OnceLock<Mutex<Box<dyn Write + Send>>> = OnceLock::new();
+
+/// Sends the events to `output` from now on; only the first call has an effect.
+pub fn init(output: Box<dyn Write + Send>) {
+    OUTPUT.get_or_init(|| Mutex::new(output));
+}
+
+pub fn enabled() -> bool {
+    OUTPUT.get().is_some()
+}
+
+/// Writes one event, a JSON object, as a line and flushes it. Failures are ignored, a reader
+/// that went away must not stop the computation.
+pub fn emit(event: &str) {
+    if let Some(output) = OUTPUT.get() {
+        let mut output = output.lock().unwrap_or_else(|err| err.into_inner());
+        let _ = writeln!(output, "{}", event).and_then(|()| output.flush());
+    }
+}
@@ -0,0 +1,231 @@
+//! The `collatz_bigint` Python module, built with the `python` feature.
+//!
+//! Values cross as Python ints through their bytes, never as decimal text, so they are not
+//! bound by Python's limit on int/str conversions. The iterator computes in a Rust thread that
+//! never calls into Python: it only waits for the consumer, so it can neither hold up `close()`
+//! nor run Python code while the interpreter shuts down.
+
+use crate::input;
+use crate::report::{SequenceStats, Value};
+use crate::sequence::{Limits, Map};
+use crate::stats::{visit_trajectory, Row};
+use num_bigint::BigInt;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::ops::ControlFlow;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+// Values the iterator computes ahead of the consumer
+const BUFFER: usize = 1024;
+
+// A seed as an int or as text like "2^100-1"
+#[derive(FromPyObject)]
+enum Seed {
+    Int(BigInt),
+    Text(String),
+}
+
+impl Seed {
+    fn parse(self, limits: &Limits) -> PyResult<(String, BigInt)> {
+        let invalid = |seed: &dyn std::fmt::Display| {
+            PyValueError::new_err(format!(
+                "{}: invalid seed, expected a positive integer",
+                seed
+            ))
+        };
+        match self {
+            Seed::Int(n) if n >= BigInt::from(1) => Ok((String::new(), n)),
+            Seed::Int(n) => Err(invalid(&n)),
+            Seed::Text(text) => match input::parse_seed(&text, limits.max_bits.unwrap_or(u64::MAX))
+            {
+                Ok(n) => Ok((text.trim().to_string(), n)),
+                Err(_) => Err(invalid(&text)),
+            },
+        }
+    }
+}
+
+fn limits(
+    max_steps: Option<usize>,
+    timeout: Option<f64>,
+    max_bits: Option<u64>,
+) -> PyResult<Limits> {
+    let timeout = timeout
+        .map(|seconds| {
+            Duration::try_from_secs_f64(seconds)
+                .map_err(|_| PyValueError::new_err(format!("invalid timeout {}", seconds)))
+        })
+        .transpose()?;
+    Ok(Limits {
+        max_steps,
+        timeout,
+        max_bits,
+        ..Limits::default()
+    })
+}
+
+// The statistics as a dict with the fields of `--stats-format json`, the seed and the max value
+// as ints
+fn stats_dict<'py>(py: Python<'py>, start: &BigInt, row: &Row) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    for field in SequenceStats::new(row, String::new()).fields() {
+        match (field.name, field.value) {
+            ("seed", _) => dict.set_item("seed", start)?,
+            ("max_value", _) => dict.set_item("max_value", &row.max_value)?,
+            (name, Value::Int(value)) => dict.set_item(name, value)?,
+            (name, Value::Float(value)) => dict.set_item(name, value)?,
+            (name, Value::Text(value)) => dict.set_item(name, value)?,
+        }
+    }
+    Ok(dict)
+}
+
+/// Statistics of the trajectory of `seed` as a dict; `truncated` names the limit that stopped it.
+#[pyfunction]
+#[pyo3(signature = (seed, max_steps=None, timeout=None, max_bits=None))]
+fn stats<'py>(
+    py: Python<'py>,
+    seed: Seed,
+    max_steps: Option<usize>,
+    timeout: Option<f64>,
+    max_bits: Option<u64>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let limits = limits(max_steps, timeout, max_bits)?;
+    let (label, start) = seed.parse(&limits)?;
+    let row = py.allow_threads(|| crate::stats::trajectory(label, &start, &limits, Map::Standard));
+    stats_dict(py, &start, &row)
+}
+
+/// All values of the trajectory after `seed` as a list of ints.
+#[pyfunction]
+#[pyo3(signature = (seed, max_steps=None, timeout=None, max_bits=None))]
+fn trajectory(
+    py: Python<'_>,
+    seed: Seed,
+    max_steps: Option<usize>,
+    timeout: Option<f64>,
+    max_bits: Option<u64>,
+) -> PyResult<Vec<BigInt>> {
+    let limits = limits(max_steps, timeout, max_bits)?;
+    let (label, start) = seed.parse(&limits)?;
+    Ok(py.allow_threads(|| {
+        let mut values = Vec::new();
+        visit_trajectory(label, &start, &limits, Map::Standard, |n| {
+            values.push(n.clone());
+            ControlFlow::Continue(())
+        });
+        values
+    }))
+}
+
+/// Iterates over the values after `seed` while they are computed.
+///
+/// The computation runs in a background thread a few values ahead, so even trajectories that do
+/// not fit in memory can be consumed. Once iteration is done, `stats` holds the statistics.
+/// Closing the iterator early cancels the computation.
+#[pyclass]
+struct Trajectory {
+    start: BigInt,
+    // None once the computation ended or was cancelled
+    values: Mutex<Option<Receiver<BigInt>>>,
+    worker: Mutex<Option<JoinHandle<Row>>>,
+    #[pyo3(get)]
+    stats: Option<Py<PyDict>>,
+}
+
+#[pymethods]
+impl Trajectory {
+    #[new]
+    #[pyo3(signature = (seed, max_steps=None, timeout=None, max_bits=None))]
+    fn new(
+        seed: Seed,
+        max_steps: Option<usize>,
+        timeout: Option<f64>,
+        max_bits: Option<u64>,
+    ) -> PyResult<Self> {
+        let limits = limits(max_steps, timeout, max_bits)?;
+        let (label, start) = seed.parse(&limits)?;
+        let (sender, receiver) = mpsc::sync_channel(BUFFER);
+        let seed = start.clone();
+        let worker = thread::spawn(move || {
+            // a dropped receiver, after close() or at exit, cancels the computation
+            visit_trajectory(label, &seed, &limits, Map::Standard, |n| {
+                match sender.send(n.clone()) {
+                    Ok(()) => ControlFlow::Continue(()),
+                    Err(_) => ControlFlow::Break(()),
+                }
+            })
+        });
+        Ok(Trajectory {
+            start,
+            values: Mutex::new(Some(receiver)),
+            worker: Mutex::new(Some(worker)),
+            stats: None,
+        })
+    }
+
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&mut self, py: Python<'_>) -> PyResult<Option<BigInt>> {
+        let values = self
+            .values
+            .get_mut()
+            .expect("no other thread holds the lock");
+        let Some(receiver) = values.take() else {
+            return Ok(None);
+        };
+        let (receiver, received) = py.allow_threads(move || {
+            let received = receiver.recv().ok();
+            (receiver, received)
+        });
+        *values = Some(receiver);
+        match received {
+            Some(value) => Ok(Some(value)),
+            None => {
+                self.finish(py)?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Stops the computation; `stats` then reports it as cancelled.
+    fn close(&mut self, py: Python<'_>) -> PyResult<()> {
+        self.finish(py)
+    }
+}
+
+impl Trajectory {
+    // Drop the receiver, which ends the computation if it still runs, and collect its stats
+    fn finish(&mut self, py: Python<'_>) -> PyResult<()> {
+        self.values
+            .get_mut()
+            .expect("no other thread holds the lock")
+            .take();
+        let worker = self
+            .worker
+            .get_mut()
+            .expect("no other thread holds the lock")
+            .take();
+        if let Some(worker) = worker {
+            let row = py
+                .allow_threads(|| worker.join())
+                .expect("the computation does not panic");
+            self.stats = Some(stats_dict(py, &self.start, &row)?.unbind());
+        }
+        Ok(())
+    }
+}
+
+#[pymodule]
+fn collatz_bigint(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(stats, module)?)?;
+    module.add_function(wrap_pyfunction!(trajectory, module)?)?;
+    module.add_class::<Trajectory>()?;
+    Ok(())
+}
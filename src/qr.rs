@@ -0,0 +1,446 @@
+// QR codes (ISO/IEC 18004) for the terminal: byte mode, error correction level L, versions 1
+// to 10, which hold up to 271 bytes
+
+use colored::Colorize;
+
+// Per version from 1: all codewords, error correction codewords per block, and blocks
+const VERSIONS: [(usize, usize, usize); 10] = [
+    (26, 7, 1),
+    (44, 10, 1),
+    (70, 15, 1),
+    (100, 20, 1),
+    (134, 26, 1),
+    (172, 18, 2),
+    (196, 20, 2),
+    (242, 24, 2),
+    (292, 30, 2),
+    (346, 18, 4),
+];
+
+// Centers of the alignment patterns per version from 2, on both axes
+const ALIGNMENT: [&[usize]; 10] = [
+    &[],
+    &[6, 18],
+    &[6, 22],
+    &[6, 26],
+    &[6, 30],
+    &[6, 34],
+    &[6, 22, 38],
+    &[6, 24, 42],
+    &[6, 26, 46],
+    &[6, 28, 50],
+];
+
+// Quiet zone around the code, in modules
+const QUIET: usize = 4;
+
+/// The modules of a QR code, true for dark ones, indexed by row and column.
+pub struct QrCode {
+    size: usize,
+    modules: Vec<Vec<bool>>,
+    // modules of the fixed patterns, kept out of the data and the mask
+    function: Vec<Vec<bool>>,
+}
+
+/// Encodes `data` in the smallest version that holds it, with the mask of the lowest penalty.
+pub fn encode(data: &[u8]) -> Result<QrCode, String> {
+    let version = (1..=VERSIONS.len())
+        .find(|&version| 4 + count_bits(version) + data.len() * 8 <= data_codewords(version) * 8)
+        .ok_or_else(|| {
+            format!(
+                "{} bytes are too many for a terminal QR code (at most 271)",
+                data.len()
+            )
+        })?;
+
+    let mut bits = Bits::default();
+    bits.push(0b0100, 4);
+    bits.push(data.len() as u32, count_bits(version));
+    for &byte in data {
+        bits.push(byte.into(), 8);
+    }
+    let capacity = data_codewords(version) * 8;
+    bits.push(0, (capacity - bits.len()).min(4));
+    bits.push(0, (8 - bits.len() % 8) % 8);
+    let mut codewords = bits.into_bytes();
+    for pad in [0xec, 0x11].into_iter().cycle() {
+        if codewords.len() == data_codewords(version) {
+            break;
+        }
+        codewords.push(pad);
+    }
+
+    let mut code = QrCode::new(version);
+    code.place(&interleave(version, &codewords));
+    let mask = (0..8)
+        .min_by_key(|&mask| {
+            code.apply_mask(mask);
+            code.draw_format(mask);
+            let penalty = code.penalty();
+            // applying a mask twice undoes it
+            code.apply_mask(mask);
+            penalty
+        })
+        .expect("eight masks");
+    code.apply_mask(mask);
+    code.draw_format(mask);
+    Ok(code)
+}
+
+// Bits of the character count in byte mode
+fn count_bits(version: usize) -> usize {
+    if version < 10 {
+        8
+    } else {
+        16
+    }
+}
+
+fn data_codewords(version: usize) -> usize {
+    let (total, ecc, blocks) = VERSIONS[version - 1];
+    total - ecc * blocks
+}
+
+#[derive(Default)]
+struct Bits(Vec<bool>);
+
+impl Bits {
+    fn push(&mut self, value: u32, len: usize) {
+        self.0.extend((0..len).rev().map(|i| value >> i & 1 == 1));
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        self.0
+            .chunks(8)
+            .map(|byte| byte.iter().fold(0, |acc, &bit| acc << 1 | u8::from(bit)))
+            .collect()
+    }
+}
+
+// Split the data codewords into blocks, add the error correction of each and interleave them:
+// the first codeword of every block, then the second, ...; the later blocks are one longer
+fn interleave(version: usize, data: &[u8]) -> Vec<u8> {
+    let (total, ecc_len, blocks) = VERSIONS[version - 1];
+    let short_blocks = blocks - total % blocks;
+    let short_len = total / blocks - ecc_len;
+    let divisor = rs_divisor(ecc_len);
+    let mut split = Vec::with_capacity(blocks);
+    let mut start = 0;
+    for block in 0..blocks {
+        let len = short_len + usize::from(block >= short_blocks);
+        let data = &data[start..start + len];
+        split.push((data, rs_remainder(data, &divisor)));
+        start += len;
+    }
+    let mut result = Vec::with_capacity(total);
+    for i in 0..=short_len {
+        for (data, _) in &split {
+            if let Some(&codeword) = data.get(i) {
+                result.push(codeword);
+            }
+        }
+    }
+    for i in 0..ecc_len {
+        for (_, ecc) in &split {
+            result.push(ecc[i]);
+        }
+    }
+    result
+}
+
+// Product in GF(2^8) modulo x^8 + x^4 + x^3 + x^2 + 1
+fn gf_multiply(x: u8, y: u8) -> u8 {
+    let mut z: u16 = 0;
+    for i in (0..8).rev() {
+        z = (z << 1) ^ ((z >> 7) * 0x11d);
+        z ^= u16::from(y >> i & 1) * u16::from(x);
+    }
+    z as u8
+}
+
+// Coefficients of the Reed-Solomon generator polynomial of the degree, highest first and
+// without the leading 1
+fn rs_divisor(degree: usize) -> Vec<u8> {
+    let mut result = vec![0; degree];
+    result[degree - 1] = 1;
+    let mut root = 1;
+    for _ in 0..degree {
+        for j in 0..degree {
+            result[j] = gf_multiply(result[j], root);
+            if j + 1 < degree {
+                result[j] ^= result[j + 1];
+            }
+        }
+        root = gf_multiply(root, 2);
+    }
+    result
+}
+
+// Error correction codewords of a block: the remainder of its polynomial divided by the generator
+fn rs_remainder(data: &[u8], divisor: &[u8]) -> Vec<u8> {
+    let mut result = vec![0; divisor.len()];
+    for &byte in data {
+        let factor = byte ^ result.remove(0);
+        result.push(0);
+        for (coefficient, &d) in result.iter_mut().zip(divisor) {
+            *coefficient ^= gf_multiply(d, factor);
+        }
+    }
+    result
+}
+
+impl QrCode {
+    // An empty code of the version with its finder, timing and alignment patterns drawn and the
+    // areas of the format and version information reserved
+    fn new(version: usize) -> QrCode {
+        let size = 17 + 4 * version;
+        let mut code = QrCode {
+            size,
+            modules: vec![vec![false; size]; size],
+            function: vec![vec![false; size]; size],
+        };
+        for i in 0..size {
+            code.set_function(6, i, i % 2 == 0);
+            code.set_function(i, 6, i % 2 == 0);
+        }
+        for (row, col) in [(3, 3), (size - 4, 3), (3, size - 4)] {
+            code.draw_finder(row, col);
+        }
+        let centers = ALIGNMENT[version - 1];
+        let last = centers.len().saturating_sub(1);
+        for (i, &row) in centers.iter().enumerate() {
+            for (j, &col) in centers.iter().enumerate() {
+                // the corners with a finder pattern have none
+                if !matches!((i, j), (0, 0)) && (i, j) != (0, last) && (i, j) != (last, 0) {
+                    code.draw_alignment(row, col);
+                }
+            }
+        }
+        code.draw_format(0);
+        if version >= 7 {
+            code.draw_version(version);
+        }
+        code
+    }
+
+    fn set_function(&mut self, row: usize, col: usize, dark: bool) {
+        self.modules[row][col] = dark;
+        self.function[row][col] = true;
+    }
+
+    // A finder pattern around its center, with the light separator where it fits
+    fn draw_finder(&mut self, row: usize, col: usize) {
+        for dr in -4..=4_isize {
+            for dc in -4..=4_isize {
+                let (r, c) = (row as isize + dr, col as isize + dc);
+                if (0..self.size as isize).contains(&r) && (0..self.size as isize).contains(&c) {
+                    let distance = dr.abs().max(dc.abs());
+                    self.set_function(r as usize, c as usize, distance != 2 && distance != 4);
+                }
+            }
+        }
+    }
+
+    fn draw_alignment(&mut self, row: usize, col: usize) {
+        for dr in -2..=2_isize {
+            for dc in -2..=2_isize {
+                let (r, c) = ((row as isize + dr) as usize, (col as isize + dc) as usize);
+                self.set_function(r, c, dr.abs().max(dc.abs()) != 1);
+            }
+        }
+    }
+
+    // Both copies of the error correction level (L) and mask, and the dark module
+    fn draw_format(&mut self, mask: u32) {
+        let data = 0b01 << 3 | mask;
+        let mut remainder = data;
+        for _ in 0..10 {
+            remainder = (remainder << 1) ^ ((remainder >> 9) * 0x537);
+        }
+        let bits = (data << 10 | remainder) ^ 0x5412;
+        let bit = |i: usize| bits >> i & 1 == 1;
+        let size = self.size;
+        for i in 0..6 {
+            self.set_function(i, 8, bit(i));
+        }
+        self.set_function(7, 8, bit(6));
+        self.set_function(8, 8, bit(7));
+        self.set_function(8, 7, bit(8));
+        for i in 9..15 {
+            self.set_function(8, 14 - i, bit(i));
+        }
+        for i in 0..8 {
+            self.set_function(8, size - 1 - i, bit(i));
+        }
+        for i in 8..15 {
+            self.set_function(size - 15 + i, 8, bit(i));
+        }
+        self.set_function(size - 8, 8, true);
+    }
+
+    // Both copies of the version number with its error correction, from version 7
+    fn draw_version(&mut self, version: usize) {
+        let mut remainder = version as u32;
+        for _ in 0..12 {
+            remainder = (remainder << 1) ^ ((remainder >> 11) * 0x1f25);
+        }
+        let bits = (version as u32) << 12 | remainder;
+        for i in 0..18 {
+            let dark = bits >> i & 1 == 1;
+            let (a, b) = (self.size - 11 + i % 3, i / 3);
+            self.set_function(b, a, dark);
+            self.set_function(a, b, dark);
+        }
+    }
+
+    // Fill the data modules in pairs of columns from the right, upward and downward in turns,
+    // skipping the vertical timing pattern; modules left over stay light
+    fn place(&mut self, codewords: &[u8]) {
+        let mut i = 0;
+        let mut right = self.size - 1;
+        loop {
+            if right == 6 {
+                right = 5;
+            }
+            for vertical in 0..self.size {
+                for col in [right, right - 1] {
+                    let upward = (right + 1) & 2 == 0;
+                    let row = if upward {
+                        self.size - 1 - vertical
+                    } else {
+                        vertical
+                    };
+                    if !self.function[row][col] && i < codewords.len() * 8 {
+                        self.modules[row][col] = codewords[i / 8] >> (7 - i % 8) & 1 == 1;
+                        i += 1;
+                    }
+                }
+            }
+            if right < 2 {
+                break;
+            }
+            right -= 2;
+        }
+    }
+
+    // Invert the data modules where the mask pattern holds
+    fn apply_mask(&mut self, mask: u32) {
+        for row in 0..self.size {
+            for col in 0..self.size {
+                let (r, c) = (row, col);
+                let invert = match mask {
+                    0 => (r + c) % 2 == 0,
+                    1 => r % 2 == 0,
+                    2 => c % 3 == 0,
+                    3 => (r + c) % 3 == 0,
+                    4 => (c / 3 + r / 2) % 2 == 0,
+                    5 => r * c % 2 + r * c % 3 == 0,
+                    6 => (r * c % 2 + r * c % 3) % 2 == 0,
+                    _ => ((r + c) % 2 + r * c % 3) % 2 == 0,
+                };
+                if invert && !self.function[row][col] {
+                    self.modules[row][col] ^= true;
+                }
+            }
+        }
+    }
+
+    // Penalty of the masked code: runs of five or more equal modules, 2x2 blocks, patterns that
+    // look like a finder, and an unbalanced share of dark modules
+    fn penalty(&self) -> usize {
+        let size = self.size;
+        let at = |row: usize, col: usize, transposed: bool| match transposed {
+            false => self.modules[row][col],
+            true => self.modules[col][row],
+        };
+        let finder_like = [
+            true, false, true, true, true, false, true, false, false, false, false,
+        ];
+        let mut penalty = 0;
+        for transposed in [false, true] {
+            for row in 0..size {
+                let mut run = 1;
+                for col in 1..size {
+                    if at(row, col, transposed) == at(row, col - 1, transposed) {
+                        run += 1;
+                        if run == 5 {
+                            penalty += 3;
+                        } else if run > 5 {
+                            penalty += 1;
+                        }
+                    } else {
+                        run = 1;
+                    }
+                }
+                for col in 0..=size - finder_like.len() {
+                    let matches = |reversed: bool| {
+                        (0..finder_like.len()).all(|i| {
+                            let expected = match reversed {
+                                false => finder_like[i],
+                                true => finder_like[finder_like.len() - 1 - i],
+                            };
+                            at(row, col + i, transposed) == expected
+                        })
+                    };
+                    if matches(false) || matches(true) {
+                        penalty += 40;
+                    }
+                }
+            }
+        }
+        for row in 1..size {
+            for col in 1..size {
+                let dark = self.modules[row][col];
+                if self.modules[row - 1][col] == dark
+                    && self.modules[row][col - 1] == dark
+                    && self.modules[row - 1][col - 1] == dark
+                {
+                    penalty += 3;
+                }
+            }
+        }
+        let dark = self.modules.iter().flatten().filter(|&&dark| dark).count();
+        let percent = dark * 100 / (size * size);
+        penalty + percent.abs_diff(50) / 5 * 10
+    }
+
+    /// The code as lines of half blocks, two rows of modules per line, with its quiet zone;
+    /// dark modules are black on white, or printed as blocks on a light terminal without colors.
+    pub fn render(&self) -> Vec<String> {
+        let colored = colored::control::SHOULD_COLORIZE.should_colorize();
+        let total = self.size + 2 * QUIET;
+        let dark = |row: usize, col: usize| {
+            let (row, col) = (row.wrapping_sub(QUIET), col.wrapping_sub(QUIET));
+            row < self.size && col < self.size && self.modules[row][col]
+        };
+        (0..total.div_ceil(2))
+            .map(|line| {
+                (0..total)
+                    .map(|col| {
+                        let top = dark(2 * line, col);
+                        let bottom = 2 * line + 1 < total && dark(2 * line + 1, col);
+                        if colored {
+                            let color = |dark: bool| match dark {
+                                true => colored::Color::Black,
+                                false => colored::Color::BrightWhite,
+                            };
+                            "▀".color(color(top)).on_color(color(bottom)).to_string()
+                        } else {
+                            match (top, bottom) {
+                                (true, true) => "█",
+                                (true, false) => "▀",
+                                (false, true) => "▄",
+                                (false, false) => " ",
+                            }
+                            .to_string()
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
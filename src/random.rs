@@ -0,0 +1,72 @@
+use num_bigint::{BigInt, Sign};
+use std::io::{self, Read};
+
+/// Random odd seeds of exactly `bits` bits, one decimal per line, read like seeds piped into
+/// batch mode.
+///
+/// The generator is SplitMix64, so the same `rng_seed` gives the same seeds on every machine and
+/// in every version. The highest and the lowest bit of every seed are set, the bits between them
+/// are uniformly random.
+pub struct RandomSeeds {
+    state: u64,
+    bits: u64,
+    left: u64,
+    // the current line and how much of it was read
+    line: Vec<u8>,
+    read: usize,
+}
+
+impl RandomSeeds {
+    pub fn new(bits: u64, count: u64, rng_seed: u64) -> Self {
+        RandomSeeds {
+            state: rng_seed,
+            bits,
+            left: count,
+            line: Vec::new(),
+            read: 0,
+        }
+    }
+
+    // SplitMix64, the generator also used to seed xorshift and xoshiro
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn next_seed(&mut self) -> BigInt {
+        let mut digits: Vec<u64> = (0..self.bits.div_ceil(64))
+            .map(|_| self.next_u64())
+            .collect();
+        let top = ((self.bits - 1) % 64) as u32;
+        let last = digits.len() - 1;
+        digits[last] &= u64::MAX >> (63 - top);
+        digits[last] |= 1 << top;
+        digits[0] |= 1;
+        let words: Vec<u32> = digits
+            .iter()
+            .flat_map(|digit| [*digit as u32, (digit >> 32) as u32])
+            .collect();
+        BigInt::from_slice(Sign::Plus, &words)
+    }
+}
+
+impl Read for RandomSeeds {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.read == self.line.len() {
+            if self.left == 0 {
+                return Ok(0);
+            }
+            self.left -= 1;
+            let seed = self.next_seed();
+            self.line = format!("{}\n", seed).into_bytes();
+            self.read = 0;
+        }
+        let len = buf.len().min(self.line.len() - self.read);
+        buf[..len].copy_from_slice(&self.line[self.read..self.read + len]);
+        self.read += len;
+        Ok(len)
+    }
+}
@@ -0,0 +1,133 @@
+use crate::parity_vector::ParityReader;
+use crate::parts;
+use crate::scan::{Line, Scanner};
+use collatz_bigint_file::digits::DigitWriter;
+use collatz_bigint_file::hash::Sha256;
+use num_bigint::BigInt;
+use num_traits::One;
+use std::io::{self, BufRead};
+use std::path::Path;
+
+/// Replays a trajectory from its seed and parity vector and cross-checks it.
+///
+/// Every step applies 3n+1 or a halving as the parity vector says, and fails if that does not
+/// fit the parity of the current value, which catches a corrupt parity vector or a wrong seed.
+/// The replayed values are compared with the value lines of `against` (a sequence file or
+/// manifest) and their hash with `expect`; without either the hash is only printed.
+pub fn run(
+    seed: &str,
+    start: BigInt,
+    parity_file: &Path,
+    against: Option<&Path>,
+    expect: Option<&str>,
+    buffer_size: usize,
+) -> Result<(), String> {
+    let mut parities = ParityReader::open(parity_file)?;
+    let mut file: Option<Scanner<Box<dyn BufRead>>> = against
+        .map(|path| {
+            parts::open(path, buffer_size)
+                .map(Scanner::new)
+                .map_err(|err| format!("Failed to open {}: {}", path.display(), err))
+        })
+        .transpose()?;
+    let read_error =
+        |path: &Path, err: io::Error| format!("Failed to read {}: {}", path.display(), err);
+
+    let mut digits = DigitWriter::default();
+    let mut line = Vec::new();
+    let mut hasher = Sha256::default();
+    digits
+        .write_line(&start, &mut hasher)
+        .expect("hashing does not fail");
+    let mut n = start;
+    let mut step = 0;
+    while let Some(odd) = parities
+        .next_step()
+        .map_err(|err| read_error(parity_file, err))?
+    {
+        step += 1;
+        if odd != n.bit(0) {
+            return Err(format!(
+                "MISMATCH: step {} of the parity vector is {} but the value before it is {}",
+                step,
+                if odd { "3n+1" } else { "a halving" },
+                if odd { "even" } else { "odd" }
+            ));
+        }
+        if odd {
+            n = BigInt::from(3) * n + BigInt::one();
+        } else {
+            n >>= 1;
+        }
+        line.clear();
+        digits
+            .write_line(&n, &mut line)
+            .expect("writing to memory does not fail");
+        hasher.update(&line);
+
+        if let (Some(file), Some(path)) = (file.as_mut(), against) {
+            let value = file.next_value().map_err(|err| read_error(path, err))?;
+            match value {
+                Some(Line::Value(digits)) if digits == &line[..line.len() - 1] => {}
+                Some(Line::Value(digits)) => {
+                    return Err(format!(
+                        "MISMATCH: step {} is {} in {}, {} replayed",
+                        step,
+                        String::from_utf8_lossy(digits),
+                        path.display(),
+                        n
+                    ))
+                }
+                Some(Line::Invalid(err)) => {
+                    return Err(format!(
+                        "MISMATCH: step {} is an invalid line in {} ({})",
+                        step,
+                        path.display(),
+                        err
+                    ))
+                }
+                Some(Line::Comment) | None => {
+                    return Err(format!(
+                        "MISMATCH: {} ends after {} steps, the parity vector has more",
+                        path.display(),
+                        step - 1
+                    ))
+                }
+            }
+        }
+    }
+    let hash = hasher.finish_hex();
+
+    println!("seed: {}", seed.trim());
+    println!("steps replayed: {}", step);
+    if n.is_one() {
+        println!("total stopping time: {}", step);
+    } else {
+        println!("last value: {} (the trajectory is truncated)", n);
+    }
+    println!("hash: {}", hash);
+    if let (Some(file), Some(path)) = (file.as_mut(), against) {
+        if let Some(Line::Value(_) | Line::Invalid(_)) =
+            file.next_value().map_err(|err| read_error(path, err))?
+        {
+            return Err(format!(
+                "MISMATCH: {} continues after the {} steps of the parity vector",
+                path.display(),
+                step
+            ));
+        }
+        println!("OK: the replayed trajectory matches {}", path.display());
+    }
+    match expect {
+        None => Ok(()),
+        Some(expected) if expected.trim().eq_ignore_ascii_case(&hash) => {
+            println!("OK: the replayed trajectory matches the hash");
+            Ok(())
+        }
+        Some(expected) => Err(format!(
+            "MISMATCH: expected {}, replayed {}",
+            expected.trim(),
+            hash
+        )),
+    }
+}
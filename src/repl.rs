@@ -0,0 +1,227 @@
+use crate::batch::{self, StatsFormat};
+use crate::chart;
+use crate::format::NumberFormat;
+use crate::timing;
+use collatz_bigint_file::sequence::{drift_per_step, Limits, Map};
+use collatz_bigint_file::stats::{self, Row};
+use std::io::{self, BufRead, Write};
+use std::ops::ControlFlow;
+
+const HELP: &str = "\
+Enter a seed or expression (e.g. 27 or 2^199-1) to compute its trajectory in memory.
+
+Commands:
+  :stats              statistics of all trajectories computed in this session
+  :plot               chart of the bit length of the last trajectory and the drift model
+  :set                show the settings
+  :set KEY=VALUE      change a setting: map=standard|shortcut, max-steps=N|none,
+                      timeout=DURATION|none, max-width=DIGITS|none
+  :clear              forget the session history
+  :help               show this help
+  :quit               leave the session (or Ctrl-D)";
+
+// Size of the :plot chart in characters
+const PLOT_WIDTH: usize = 64;
+const PLOT_HEIGHT: usize = 12;
+
+// State of an interactive session
+struct Session {
+    limits: Limits,
+    map: Map,
+    number_format: NumberFormat,
+    history: Vec<(Row, Map)>,
+    // bit lengths of the values of the last trajectory, starting with the seed
+    last_bits: Vec<u64>,
+}
+
+/// Reads seeds and `:` commands from stdin until `:quit` or end of input.
+pub fn run(limits: Limits, number_format: NumberFormat) -> io::Result<()> {
+    let mut session = Session {
+        limits,
+        map: Map::Standard,
+        number_format,
+        history: Vec::new(),
+        last_bits: Vec::new(),
+    };
+    println!("Collatz session. Type :help for commands, :quit to leave.");
+    let stdin = io::stdin();
+    let mut line = String::new();
+    loop {
+        print!("collatz> ");
+        io::stdout().flush()?;
+        line.clear();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            println!();
+            return Ok(());
+        }
+        let input = line.trim();
+        match input {
+            "" => {}
+            ":quit" | ":q" | ":exit" => return Ok(()),
+            ":help" | ":h" => println!("{}", HELP),
+            ":stats" => session.print_stats(),
+            ":plot" => session.plot(),
+            ":clear" => {
+                session.history.clear();
+                session.last_bits.clear();
+            }
+            ":set" => session.print_settings(),
+            command if command.starts_with(":set ") => {
+                if let Err(err) = session.set(command[":set ".len()..].trim()) {
+                    println!("{}", err);
+                }
+            }
+            command if command.starts_with(':') => {
+                println!("Unknown command '{}'. Type :help for commands", command)
+            }
+            seed => session.compute(seed)?,
+        }
+    }
+}
+
+impl Session {
+    fn compute(&mut self, seed: &str) -> io::Result<()> {
+        let max_bits = self.limits.max_bits.unwrap_or(u64::MAX);
+        let start = match crate::parse_input(seed.to_string(), max_bits) {
+            Ok(start) => start,
+            Err(err) => {
+                println!("{}", err);
+                return Ok(());
+            }
+        };
+        let mut bits = vec![start.bits()];
+        let row = stats::visit_trajectory(seed.to_string(), &start, &self.limits, self.map, |n| {
+            bits.push(n.bits());
+            ControlFlow::Continue(())
+        });
+        batch::write_row(
+            &mut io::stdout().lock(),
+            &row,
+            StatsFormat::Text,
+            &self.number_format,
+        )?;
+        self.last_bits = bits;
+        self.history.push((row, self.map));
+        Ok(())
+    }
+
+    fn print_stats(&self) {
+        if self.history.is_empty() {
+            println!("No trajectories computed yet");
+            return;
+        }
+        println!(
+            "{:>3}  {:<20}  {:<9}  {:>13}  {:>7}  {:>8}  max value",
+            "#", "seed", "map", "total stop", "glide", "max pos"
+        );
+        for (i, (row, map)) in self.history.iter().enumerate() {
+            println!(
+                "{:>3}  {:<20}  {:<9}  {:>13}  {:>7}  {:>8}  {}",
+                i + 1,
+                row.seed,
+                map.name(),
+                format!(
+                    "{}{}",
+                    row.stopping_time,
+                    if row.truncated.is_some() { "*" } else { "" }
+                ),
+                row.glide.map_or("-".to_string(), |glide| glide.to_string()),
+                row.max_index,
+                self.number_format.format(&row.max_value)
+            );
+        }
+    }
+
+    fn plot(&self) {
+        match self.history.last() {
+            Some((row, map)) => {
+                // the heuristic drift from the bit length of the seed
+                let drift = drift_per_step(*map);
+                let start = self.last_bits[0] as f64;
+                let model: Vec<f64> = (0..self.last_bits.len())
+                    .map(|step| (start + drift * step as f64).max(1.0))
+                    .collect();
+                let bits: Vec<f64> = self.last_bits.iter().map(|&bits| bits as f64).collect();
+                println!(
+                    "{} (bit length per step: █ trajectory, • model drifting {:.3} bits per step)",
+                    row.seed, drift
+                );
+                print!(
+                    "{}",
+                    chart::plot(&bits, Some(&model), bits.len() - 1, PLOT_WIDTH, PLOT_HEIGHT)
+                );
+            }
+            None => println!("No trajectory to plot yet"),
+        }
+    }
+
+    fn print_settings(&self) {
+        let none = || "none".to_string();
+        println!("map={}", self.map.name());
+        println!(
+            "max-steps={}",
+            self.limits
+                .max_steps
+                .map_or_else(none, |steps| steps.to_string())
+        );
+        println!(
+            "timeout={}",
+            self.limits
+                .timeout
+                .map_or_else(none, |timeout| format!("{}s", timeout.as_secs_f64()))
+        );
+        println!(
+            "max-width={}",
+            self.number_format
+                .max_width
+                .map_or_else(none, |width| width.to_string())
+        );
+    }
+
+    fn set(&mut self, assignment: &str) -> Result<(), String> {
+        let (key, value) = assignment
+            .split_once('=')
+            .map(|(key, value)| (key.trim(), value.trim()))
+            .ok_or_else(|| format!("Invalid setting '{}'. Expected KEY=VALUE", assignment))?;
+        let unset = value == "none";
+        match key {
+            "map" => self.map = value.parse()?,
+            "max-steps" => {
+                self.limits.max_steps = if unset {
+                    None
+                } else {
+                    Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("Invalid step count '{}'", value))?,
+                    )
+                }
+            }
+            "timeout" => {
+                self.limits.timeout = if unset {
+                    None
+                } else {
+                    Some(timing::parse_duration(value)?)
+                }
+            }
+            "max-width" => {
+                self.number_format.max_width = if unset {
+                    None
+                } else {
+                    Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("Invalid width '{}'", value))?,
+                    )
+                }
+            }
+            other => {
+                return Err(format!(
+                    "Unknown setting '{}'. Use map, max-steps, timeout or max-width",
+                    other
+                ))
+            }
+        }
+        Ok(())
+    }
+}
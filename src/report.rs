@@ -0,0 +1,302 @@
+//! Per-seed statistics as written by every report format: text lines, CSV, JSON, Markdown and
+//! HTML tables, the Parquet and SQLite columns of a batch and the JSON of the WebAssembly demo.
+
+use crate::stats::Row;
+/// Statistics of one seed. Every format writes the [`SequenceStats::fields`] in order, which
+/// destructure the struct, so a statistic added here does not compile until it is listed there.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SequenceStats<'a> {
+    pub seed: &'a str,
+    /// total stopping time, or the steps computed if the trajectory was truncated
+    pub stopping_time: usize,
+    pub glide: Option<usize>,
+    /// formatted by the caller: plain decimal for CSV and JSON, with --group-digits for text
+    pub max_value: String,
+    pub max_index: usize,
+    pub max_bits: u64,
+    pub odd: usize,
+    pub valuation_mean: Option<f64>,
+    pub truncated: Option<String>,
+}
+
+/// One statistic of [`SequenceStats`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Field {
+    /// CSV column, JSON key and column name of Parquet and SQLite, e.g. "stopping_time"
+    pub name: &'static str,
+    /// Column header of the Markdown and HTML tables, e.g. "total stopping time"
+    pub label: &'static str,
+    /// What precedes the value on the text line, e.g. ", total stopping time "
+    pub text: &'static str,
+    pub value: Value,
+}
+
+/// Value of a [`Field`], None if it is unknown or does not apply.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Int(Option<u64>),
+    // rounded to four decimals
+    Float(Option<f64>),
+    Text(Option<String>),
+}
+
+impl Value {
+    pub fn is_null(&self) -> bool {
+        matches!(
+            self,
+            Value::Int(None) | Value::Float(None) | Value::Text(None)
+        )
+    }
+
+    /// The value as JSON: a number, a string or null.
+    pub fn json(&self) -> String {
+        match self {
+            Value::Text(Some(text)) => json_string(text),
+            value => value.plain().unwrap_or_else(|| "null".to_string()),
+        }
+    }
+
+    // Plain text of the value, None if it is null
+    fn plain(&self) -> Option<String> {
+        match self {
+            Value::Int(value) => value.map(|value| value.to_string()),
+            Value::Float(value) => value.map(|value| value.to_string()),
+            Value::Text(value) => value.clone(),
+        }
+    }
+}
+
+impl<'a> SequenceStats<'a> {
+    pub fn new(row: &'a Row, max_value: String) -> Self {
+        SequenceStats {
+            seed: &row.seed,
+            stopping_time: row.stopping_time,
+            glide: row.glide,
+            max_value,
+            max_index: row.max_index,
+            max_bits: row.max_value.bits(),
+            odd: row.odd,
+            valuation_mean: row.valuations.mean(),
+            truncated: row.truncated.map(|truncation| truncation.to_string()),
+        }
+    }
+
+    // Statistics with every optional field unknown, for the names and types of the fields
+    fn empty() -> SequenceStats<'static> {
+        SequenceStats {
+            seed: "",
+            stopping_time: 0,
+            glide: None,
+            max_value: String::new(),
+            max_index: 0,
+            max_bits: 0,
+            odd: 0,
+            valuation_mean: None,
+            truncated: None,
+        }
+    }
+
+    /// The fields in the order of every format, with the values of no seed: the optional
+    /// fields are null, the others hold their type.
+    pub fn schema() -> Vec<Field> {
+        SequenceStats::empty().fields().collect()
+    }
+
+    /// Names of the fields in order, the CSV header.
+    pub fn names() -> Vec<&'static str> {
+        SequenceStats::schema()
+            .into_iter()
+            .map(|field| field.name)
+            .collect()
+    }
+
+    /// The fields with their values in order.
+    pub fn fields(&self) -> impl Iterator<Item = Field> {
+        // no `..`, so a new statistic has to be given a field below
+        let SequenceStats {
+            seed,
+            stopping_time,
+            glide,
+            max_value,
+            max_index,
+            max_bits,
+            odd,
+            valuation_mean,
+            truncated,
+        } = self;
+        let field = |name, label, text, value| Field {
+            name,
+            label,
+            text,
+            value,
+        };
+        let count = |value: usize| Value::Int(Some(value as u64));
+        [
+            field("seed", "seed", "", Value::Text(Some(seed.to_string()))),
+            field(
+                "stopping_time",
+                "total stopping time",
+                ": total stopping time ",
+                count(*stopping_time),
+            ),
+            field(
+                "glide",
+                "glide",
+                ", glide ",
+                Value::Int(glide.map(|glide| glide as u64)),
+            ),
+            field(
+                "max_value",
+                "max value",
+                ", max value ",
+                Value::Text(Some(max_value.clone())),
+            ),
+            field("max_index", "max at step", " at step ", count(*max_index)),
+            field(
+                "max_bits",
+                "max bits",
+                ", max bits ",
+                Value::Int(Some(*max_bits)),
+            ),
+            field("odd", "odd", ", odd ", count(*odd)),
+            field(
+                "valuation_mean",
+                "valuation mean",
+                ", valuation mean ",
+                Value::Float(valuation_mean.map(round)),
+            ),
+            field(
+                "truncated",
+                "truncated",
+                ", truncated: ",
+                Value::Text(truncated.clone()),
+            ),
+        ]
+        .into_iter()
+    }
+
+    /// "27: total stopping time 111, glide 96, max value 9232 at step 77, ...", without the
+    /// fields that are unknown or do not apply.
+    pub fn text(&self) -> String {
+        self.fields()
+            .filter_map(|field| Some(field.text.to_string() + &field.value.plain()?))
+            .collect()
+    }
+
+    /// The CSV header line.
+    pub fn csv_header() -> String {
+        SequenceStats::names().join(",")
+    }
+
+    /// One CSV line, unknown fields left empty.
+    pub fn csv(&self) -> String {
+        let values: Vec<String> = self
+            .fields()
+            .map(|field| match field.value {
+                Value::Text(Some(text)) => csv_field(&text),
+                value => value.plain().unwrap_or_default(),
+            })
+            .collect();
+        values.join(",")
+    }
+
+    /// One JSON object, unknown fields null.
+    pub fn json(&self) -> String {
+        let members: Vec<String> = self
+            .fields()
+            .map(|field| format!("{}:{}", json_string(field.name), field.value.json()))
+            .collect();
+        format!("{{{}}}", members.join(","))
+    }
+
+    /// The header and delimiter rows of a Markdown table.
+    pub fn markdown_header() -> String {
+        let schema = SequenceStats::schema();
+        let labels: Vec<&str> = schema.iter().map(|field| field.label).collect();
+        let delimiters: Vec<&str> = schema
+            .iter()
+            .map(|field| match field.value {
+                Value::Text(_) => "---",
+                Value::Int(_) | Value::Float(_) => "---:",
+            })
+            .collect();
+        format!("| {} |\n|{}|", labels.join(" | "), delimiters.join("|"))
+    }
+
+    /// One row of a Markdown table, unknown fields left empty.
+    pub fn markdown(&self) -> String {
+        let cells: Vec<String> = self
+            .fields()
+            .map(|field| field.value.plain().unwrap_or_default().replace('|', "\\|"))
+            .collect();
+        format!("| {} |", cells.join(" | "))
+    }
+
+    /// The opening of an HTML table up to its body.
+    pub fn html_header() -> String {
+        let headers: String = SequenceStats::schema()
+            .iter()
+            .map(|field| format!("<th>{}</th>", field.label))
+            .collect();
+        format!("<table>\n<thead><tr>{}</tr></thead>\n<tbody>", headers)
+    }
+
+    /// One row of an HTML table, unknown fields left empty.
+    pub fn html(&self) -> String {
+        let cells: String = self
+            .fields()
+            .map(|field| {
+                format!(
+                    "<td>{}</td>",
+                    html_text(&field.value.plain().unwrap_or_default())
+                )
+            })
+            .collect();
+        format!("<tr>{}</tr>", cells)
+    }
+
+    /// The end of an HTML table opened by [`SequenceStats::html_header`].
+    pub fn html_footer() -> &'static str {
+        "</tbody>\n</table>"
+    }
+}
+
+fn round(mean: f64) -> f64 {
+    (mean * 1e4).round() / 1e4
+}
+
+// Quote a CSV field if it contains a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// `value` as a JSON string literal.
+pub fn json_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn html_text(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,321 @@
+use crate::drift;
+use crate::format::NumberFormat;
+use collatz_bigint_file::sequence::{Limits, Truncation};
+use num_bigint::BigInt;
+use num_traits::Signed;
+use regex::Regex;
+use serde::Deserialize;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+// Steps of a trajectory without --max-steps or --timeout: generalized maps need not reach a stop
+// value, and many are believed to diverge
+const DEFAULT_MAX_STEPS: usize = 1_000_000;
+// Steps between two checks of the timeout
+const TIMEOUT_CHECK_INTERVAL: usize = 64;
+
+// Definition of a map in its TOML file, e.g. the original function of Collatz:
+//
+//     name = "Collatz 1932"
+//     modulus = 3
+//     rules = ["2n/3", "(4n-1)/3", "(4n+1)/3"]
+//     stop = [1]
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Definition {
+    name: Option<String>,
+    modulus: u32,
+    // one rule per residue, in order from 0 to modulus - 1
+    rules: Vec<String>,
+    // values that end a trajectory [default: 1]
+    stop: Option<Vec<u64>>,
+}
+
+// Rule n -> (factor * n + offset) / divisor of one residue class
+struct Rule {
+    text: String,
+    factor: u32,
+    offset: i64,
+    divisor: u32,
+}
+
+impl Rule {
+    // Parse "3n+1", "n/2", "2n/3" or "(4n-1)/3"; whitespace and "*" are ignored
+    fn parse(text: &str) -> Result<Rule, String> {
+        let invalid = || {
+            format!(
+                "Invalid rule '{}'. Use the form an+b, an/d or (an+b)/d, e.g. 3n+1, n/2 or (4n-1)/3",
+                text
+            )
+        };
+        let compact: String = text
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '*')
+            .collect();
+        let pattern = Regex::new(r"^(\()?([0-9]*)n(?:([+-])([0-9]+))?(\))?(?:/([0-9]+))?$")
+            .expect("valid regex");
+        let captures = pattern.captures(&compact).ok_or_else(invalid)?;
+        let (open, close) = (captures.get(1).is_some(), captures.get(5).is_some());
+        let (offset, divisor) = (captures.get(4), captures.get(6));
+        // parentheses around the numerator exactly when it has an offset and is divided
+        if open != close || open != (offset.is_some() && divisor.is_some()) {
+            return Err(invalid());
+        }
+        let number = |text: Option<&str>, default: u32| match text {
+            None | Some("") => Ok(default),
+            Some(text) => text.parse::<u32>().map_err(|_| invalid()),
+        };
+        let offset = i64::from(number(offset.map(|m| m.as_str()), 0)?);
+        let rule = Rule {
+            text: text.trim().to_string(),
+            factor: number(captures.get(2).map(|m| m.as_str()), 1)?,
+            offset: if captures.get(3).is_some_and(|m| m.as_str() == "-") {
+                -offset
+            } else {
+                offset
+            },
+            divisor: number(divisor.map(|m| m.as_str()), 1)?,
+        };
+        if rule.factor == 0 || rule.divisor == 0 {
+            return Err(invalid());
+        }
+        Ok(rule)
+    }
+
+    fn apply(&self, n: &BigInt) -> BigInt {
+        (n * self.factor + self.offset) / self.divisor
+    }
+
+    // Change of log2 n per step with this rule, for large n
+    fn growth(&self) -> f64 {
+        (f64::from(self.factor) / f64::from(self.divisor)).log2()
+    }
+}
+
+/// A map of the positive integers with a rule `n -> (a n + b) / d` for each residue of n mod m,
+/// like the 3n+1 map (m = 2) or the original function of Collatz (m = 3), read from a TOML file.
+pub struct ResidueMap {
+    name: String,
+    modulus: u32,
+    rules: Vec<Rule>,
+    stop: Vec<BigInt>,
+}
+
+impl ResidueMap {
+    /// Reads the definition of a map from the TOML file `path` and checks that every rule maps
+    /// each integer of its residue class to an integer.
+    pub fn load(path: &Path) -> Result<ResidueMap, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+        let definition: Definition = toml::from_str(&content)
+            .map_err(|err| format!("Invalid map {}: {}", path.display(), err))?;
+        if definition.modulus < 2 {
+            return Err(format!(
+                "Invalid map {}: the modulus must be at least 2",
+                path.display()
+            ));
+        }
+        if definition.rules.len() != definition.modulus as usize {
+            return Err(format!(
+                "Invalid map {}: {} rules for modulus {}, one per residue 0 to {} is needed",
+                path.display(),
+                definition.rules.len(),
+                definition.modulus,
+                definition.modulus - 1
+            ));
+        }
+        let mut rules = Vec::with_capacity(definition.rules.len());
+        for (residue, text) in definition.rules.iter().enumerate() {
+            let rule = Rule::parse(text)
+                .map_err(|err| format!("Invalid map {}: {}", path.display(), err))?;
+            // n = r + k m gives a n + b = (a r + b) + a k m, divisible by d for every k
+            // exactly when both terms are
+            let divisor = i128::from(rule.divisor);
+            let base = i128::from(rule.factor) * residue as i128 + i128::from(rule.offset);
+            let step = i128::from(rule.factor) * i128::from(definition.modulus);
+            if base % divisor != 0 || step % divisor != 0 {
+                return Err(format!(
+                    "Invalid map {}: the rule {} of residue {} does not give an integer for every n = {} mod {}",
+                    path.display(),
+                    rule.text,
+                    residue,
+                    residue,
+                    definition.modulus
+                ));
+            }
+            rules.push(rule);
+        }
+        Ok(ResidueMap {
+            name: definition
+                .name
+                .unwrap_or_else(|| path.display().to_string()),
+            modulus: definition.modulus,
+            rules,
+            stop: definition
+                .stop
+                .unwrap_or_else(|| vec![1])
+                .into_iter()
+                .map(BigInt::from)
+                .collect(),
+        })
+    }
+}
+
+impl fmt::Display for ResidueMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (mod {}:", self.name, self.modulus)?;
+        for (residue, rule) in self.rules.iter().enumerate() {
+            let separator = if residue == 0 { "" } else { "," };
+            write!(f, "{} {} -> {}", separator, residue, rule.text)?;
+        }
+        write!(f, ")")
+    }
+}
+
+// Why a trajectory of a generalized map ended
+enum End {
+    Stop,
+    // length and smallest value of the cycle the trajectory entered
+    Cycle(usize, BigInt),
+    Truncated(Truncation),
+}
+
+/// Iterates `map` from each seed until it reaches one of the stop values of the map, enters a
+/// cycle or hits a limit, and prints the steps, the largest value and how often each rule was
+/// applied.
+///
+/// Cycles are detected with Brent's algorithm, which keeps a single earlier value, so a cycle is
+/// reported some steps after the trajectory entered it. Without `--max-steps` or
+/// `--timeout` a trajectory stops after a million steps, as generalized maps need not reach a
+/// stop value.
+pub fn run(
+    map_path: &Path,
+    seeds: &[String],
+    limits: &Limits,
+    number_format: &NumberFormat,
+) -> Result<(), String> {
+    let map = ResidueMap::load(map_path)?;
+    let max_steps = match (limits.max_steps, limits.timeout) {
+        (None, None) => Some(DEFAULT_MAX_STEPS),
+        (max_steps, _) => max_steps,
+    };
+    let modulus = BigInt::from(map.modulus);
+    println!("map: {}", map);
+    for seed in seeds {
+        let start = crate::parse_input(seed.to_string(), limits.max_bits.unwrap_or(u64::MAX))
+            .map_err(|err| format!("{}: {}", seed, err))?;
+        let deadline = limits
+            .timeout
+            .map(|timeout| (Instant::now() + timeout, timeout));
+        let mut n = start.clone();
+        let mut steps = 0;
+        let mut counts = vec![0usize; map.rules.len()];
+        let (mut max_value, mut max_index) = (start.clone(), 0);
+        // Brent's cycle detection: the value at the last power of two of steps
+        let (mut saved, mut power, mut since_saved) = (start.clone(), 1, 0);
+        let end = loop {
+            if map.stop.contains(&n) {
+                break End::Stop;
+            }
+            if max_steps.is_some_and(|max_steps| steps >= max_steps) {
+                break End::Truncated(Truncation::MaxSteps(steps));
+            }
+            if let Some((deadline, timeout)) = deadline {
+                if steps % TIMEOUT_CHECK_INTERVAL == 0 && Instant::now() >= deadline {
+                    break End::Truncated(Truncation::Timeout(timeout));
+                }
+            }
+            let residue = usize::try_from(&n % &modulus).expect("the residue is below the modulus");
+            let rule = &map.rules[residue];
+            // a n + b has at most as many bits as n and a together, plus one for b
+            if let Some(max_bits) = limits.max_bits.filter(|&max_bits| {
+                n.bits() + u64::from(32 - rule.factor.leading_zeros()) + 1 > max_bits
+            }) {
+                break End::Truncated(Truncation::MaxBits(max_bits));
+            }
+            let next = rule.apply(&n);
+            if !next.is_positive() {
+                return Err(format!(
+                    "{}: step {} maps {} to {} with the rule {} of residue {}, outside the positive integers",
+                    seed,
+                    steps + 1,
+                    n,
+                    next,
+                    rule.text,
+                    residue
+                ));
+            }
+            n = next;
+            steps += 1;
+            counts[residue] += 1;
+            if n > max_value {
+                max_value = n.clone();
+                max_index = steps;
+            }
+            since_saved += 1;
+            if n == saved {
+                break End::Cycle(since_saved, cycle_min(&map, &n, &modulus, since_saved));
+            }
+            if since_saved == power {
+                saved = n.clone();
+                power *= 2;
+                since_saved = 0;
+            }
+        };
+
+        println!();
+        println!("seed: {}", seed);
+        match &end {
+            End::Stop => println!("steps: {}, reached the stop value {}", steps, n),
+            End::Cycle(length, min) => println!(
+                "steps: {}, entered a cycle of length {} through {}",
+                steps,
+                length,
+                number_format.format(min)
+            ),
+            End::Truncated(truncation) => println!("steps: {} ({})", steps, truncation),
+        }
+        println!(
+            "max value: {} at step {}",
+            number_format.format(&max_value),
+            max_index
+        );
+        if !matches!(end, End::Stop) {
+            println!("last value: {} bits", n.bits());
+        }
+        if steps > 0 {
+            println!(
+                "growth: {:+.4} bits per step",
+                (drift::log2(&n) - drift::log2(&start)) / steps as f64
+            );
+        }
+        println!("residue  rule         growth  steps       share");
+        for (residue, (rule, count)) in map.rules.iter().zip(&counts).enumerate() {
+            println!(
+                "{:>7}  {:<11} {:>+7.3}  {:<10} {:>5.1}%",
+                residue,
+                rule.text,
+                rule.growth(),
+                count,
+                100.0 * *count as f64 / steps.max(1) as f64
+            );
+        }
+    }
+    Ok(())
+}
+
+// Smallest value of the cycle of `length` through n
+fn cycle_min(map: &ResidueMap, n: &BigInt, modulus: &BigInt, length: usize) -> BigInt {
+    let mut value = n.clone();
+    let mut min = n.clone();
+    for _ in 0..length {
+        let residue = usize::try_from(&value % modulus).expect("the residue is below the modulus");
+        value = map.rules[residue].apply(&value);
+        if value < min {
+            min = value.clone();
+        }
+    }
+    min
+}
@@ -0,0 +1,213 @@
+use crate::metadata;
+use crate::parts;
+use crate::scan;
+use num_bigint::BigInt;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+
+// State of an existing sequence file, recovered to continue the computation
+pub struct ResumeState {
+    // file the computation continues in: the file itself or the last part of a split sequence
+    pub append_path: PathBuf,
+    // part files before the one appended to (empty for a single file)
+    pub previous_parts: Vec<PathBuf>,
+    // seed expression and parsed value from the metadata header, if the file has one
+    pub seed: Option<String>,
+    pub parsed: Option<BigInt>,
+    pub last_value: BigInt,
+    pub steps: usize,
+    pub max_value: BigInt,
+    pub max_index: usize,
+    // the file already has a footer, nothing is left to compute
+    pub complete: bool,
+    // value lines carry their step and rule (--annotate)
+    pub annotated: bool,
+    // bytes of an incomplete last line that were cut off
+    pub repaired_bytes: u64,
+}
+
+/// Scans an existing sequence file (or the parts listed in a manifest) and prepares it for
+/// appending. A trailing partial line left by an interrupted run is truncated. Invalid lines
+/// elsewhere are only accepted in a complete file, which is just read back for its statistics
+/// and reports them then; a file that would be continued has to be intact.
+pub fn prepare(path: &Path) -> Result<ResumeState, String> {
+    let files = parts::sequence_files(path)
+        .map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+    let mut state = ResumeState {
+        append_path: files.last().cloned().unwrap_or_else(|| path.to_path_buf()),
+        previous_parts: files[..files.len().saturating_sub(1)].to_vec(),
+        seed: None,
+        parsed: None,
+        last_value: BigInt::from(0),
+        steps: 0,
+        max_value: BigInt::from(0),
+        max_index: 0,
+        complete: false,
+        annotated: false,
+        repaired_bytes: 0,
+    };
+    // first invalid line before the end: file, line number, byte offset and text
+    let mut invalid: Option<(PathBuf, usize, u64, String)> = None;
+
+    for file in &files {
+        let mut reader = BufReader::new(
+            File::open(file)
+                .map_err(|err| format!("Failed to open {}: {}", file.display(), err))?,
+        );
+        // offset after the last complete and valid line
+        let mut valid_len: u64 = 0;
+        let mut line = Vec::new();
+        let mut line_num = 0;
+        loop {
+            line.clear();
+            let read = reader
+                .read_until(b'\n', &mut line)
+                .map_err(|err| format!("Failed to read {}: {}", file.display(), err))?;
+            if read == 0 {
+                break;
+            }
+            line_num += 1;
+            if line_num == 1 && *file == files[0] && line.starts_with(scan::BOM) {
+                line.drain(..scan::BOM.len());
+            }
+            let text = String::from_utf8_lossy(&line);
+            if !text.ends_with('\n') {
+                // partial line of an interrupted write
+                if *file != state.append_path {
+                    return Err(format!(
+                        "Part file {} ends with an incomplete line",
+                        file.display()
+                    ));
+                }
+                break;
+            }
+            if let Some((key, value)) = metadata::parse_comment(&text) {
+                match key {
+                    "seed" => state.seed = Some(value.to_string()),
+                    "parsed" => state.parsed = value.parse().ok(),
+                    "stopping time" => state.complete = true,
+                    "annotated" => state.annotated = value == "true",
+                    "sample every" => {
+                        return Err(format!(
+                            "{} only holds a sample of the sequence (--sample-every) and cannot be resumed",
+                            file.display()
+                        ))
+                    }
+                    "max disk" => {
+                        return Err(format!(
+                            "{} writes {} to stay within --max-disk and cannot be resumed",
+                            file.display(),
+                            value
+                        ))
+                    }
+                    "filter" => {
+                        return Err(format!(
+                            "{} only holds the values kept by --filter {} and cannot be resumed",
+                            file.display(),
+                            value
+                        ))
+                    }
+                    "radix" if value != "10" => {
+                        return Err(format!(
+                            "{} holds values in base {} (--radix) and cannot be resumed",
+                            file.display(),
+                            value
+                        ))
+                    }
+                    "terms" if value == metadata::ODD_TERMS => {
+                        return Err(format!(
+                            "{} only holds the odd terms (--odd-only) and cannot be resumed",
+                            file.display()
+                        ))
+                    }
+                    _ => {}
+                }
+            } else {
+                match String::from_utf8_lossy(scan::value_part(text.trim().as_bytes()))
+                    .parse::<BigInt>()
+                {
+                    Ok(value) => {
+                        state.steps += 1;
+                        if value > state.max_value {
+                            state.max_value = value.clone();
+                            state.max_index = state.steps;
+                        }
+                        state.last_value = value;
+                    }
+                    Err(_) => {
+                        // an unparseable last line is cut off, anything else is real corruption
+                        let at_end = reader
+                            .fill_buf()
+                            .map(|rest| rest.is_empty())
+                            .unwrap_or(false);
+                        if at_end && *file == state.append_path {
+                            break;
+                        }
+                        // counted as a step, like the analysis pass does
+                        state.steps += 1;
+                        invalid.get_or_insert_with(|| {
+                            (file.clone(), line_num, valid_len, text.trim().to_string())
+                        });
+                    }
+                }
+            }
+            valid_len += read as u64;
+        }
+
+        if *file == state.append_path {
+            let file_len = file
+                .metadata()
+                .map_err(|err| format!("Failed to read {}: {}", file.display(), err))?
+                .len();
+            if valid_len < file_len {
+                state.repaired_bytes = file_len - valid_len;
+                OpenOptions::new()
+                    .write(true)
+                    .open(file)
+                    .and_then(|handle| handle.set_len(valid_len))
+                    .map_err(|err| format!("Failed to repair {}: {}", file.display(), err))?;
+            }
+        }
+    }
+
+    if state.steps == 0 {
+        // nothing written yet: continue from the seed of the header
+        match &state.parsed {
+            Some(parsed) => state.last_value = parsed.clone(),
+            None => {
+                return Err(format!(
+                    "{} contains no values to resume from",
+                    path.display()
+                ))
+            }
+        }
+    }
+    if state.last_value == BigInt::from(1) {
+        state.complete = true;
+    }
+    match invalid {
+        Some((file, line_num, offset, text)) if !state.complete => Err(format!(
+            "Invalid line '{}' in {} (line {}, byte {}). Only the last line of a sequence can be repaired",
+            text,
+            file.display(),
+            line_num,
+            offset
+        )),
+        _ => Ok(state),
+    }
+}
+
+// Function to open the file of a resumed sequence in append mode
+pub fn open_append(state: &ResumeState) -> Result<File, String> {
+    OpenOptions::new()
+        .append(true)
+        .open(&state.append_path)
+        .map_err(|err| {
+            format!(
+                "Failed to open {} for appending: {}",
+                state.append_path.display(),
+                err
+            )
+        })
+}
@@ -0,0 +1,247 @@
+use crate::metadata;
+use collatz_bigint_file::warn;
+use num_bigint::{BigInt, Sign};
+use std::cmp::Ordering;
+use std::io::{self, BufRead};
+
+/// Reads the lines of a sequence file into a reused buffer, without a `String` per line.
+///
+/// The analysis pass only needs the parity, the position of the maximum and the values that end
+/// up in the terminal. All of that can be read off the decimal digits, so values are only
+/// converted to `BigInt` when they are printed or turn out to be the maximum.
+///
+/// Files edited by other tools are read as well: a byte order mark before the first line and
+/// carriage returns before the newlines are ignored, and lines that are not valid UTF-8 come
+/// back as [`Line::Invalid`] with the byte offset of the line available from [`Scanner::offset`].
+pub struct Scanner<R> {
+    reader: R,
+    line: Vec<u8>,
+    // canonical digits of lines that are not written by this program, e.g. "+0027"
+    fallback: Vec<u8>,
+    line_num: usize,
+    // byte offset of the current line and of the next one
+    offset: u64,
+    next_offset: u64,
+    // base of the values, 10 unless the file was written with --radix
+    radix: u32,
+}
+
+// A line of the sequence file
+pub enum Line<'a> {
+    // metadata header or footer
+    Comment,
+    // digits of a positive value, without sign or leading zeros
+    Value(&'a [u8]),
+    Invalid(String),
+}
+
+impl<R: BufRead> Scanner<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_radix(reader, 10)
+    }
+
+    // Scanner of a file whose values are written in another base
+    pub fn with_radix(reader: R, radix: u32) -> Self {
+        Scanner {
+            reader,
+            line: Vec::new(),
+            fallback: Vec::new(),
+            line_num: 0,
+            offset: 0,
+            next_offset: 0,
+            radix,
+        }
+    }
+
+    // Number of the line returned by the last call to `next_line`, starting at 1
+    pub fn line_num(&self) -> usize {
+        self.line_num
+    }
+
+    // Byte offset of the line returned by the last call to `next_line`, from the start of the
+    // input (of the first part for split files)
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn next_line(&mut self) -> io::Result<Option<Line<'_>>> {
+        if !self.read_line()? {
+            return Ok(None);
+        }
+        Ok(Some(self.classify()))
+    }
+
+    // Like `next_line`, but skips the metadata header and footer
+    pub fn next_value(&mut self) -> io::Result<Option<Line<'_>>> {
+        loop {
+            if !self.read_line()? {
+                return Ok(None);
+            }
+            if !self.is_comment() {
+                return Ok(Some(self.classify()));
+            }
+        }
+    }
+
+    fn read_line(&mut self) -> io::Result<bool> {
+        self.line.clear();
+        let read = self.reader.read_until(b'\n', &mut self.line)?;
+        if read == 0 {
+            return Ok(false);
+        }
+        if self.line_num == 0 && self.line.starts_with(BOM) {
+            self.line.drain(..BOM.len());
+        }
+        self.line_num += 1;
+        self.offset = self.next_offset;
+        self.next_offset += read as u64;
+        Ok(true)
+    }
+
+    fn is_comment(&self) -> bool {
+        self.line.trim_ascii().first() == Some(&(metadata::COMMENT_PREFIX as u8))
+    }
+
+    fn classify(&mut self) -> Line<'_> {
+        if self.is_comment() {
+            return Line::Comment;
+        }
+        let line = value_part(self.line.trim_ascii());
+        if let Some(digits) = canonical_digits(line, self.radix) {
+            return Line::Value(digits);
+        }
+        if std::str::from_utf8(line).is_err() {
+            return Line::Invalid("not valid UTF-8".to_string());
+        }
+        if self.radix != 10 {
+            return match BigInt::parse_bytes(line, self.radix) {
+                Some(value) if value.sign() == Sign::Plus => {
+                    self.fallback = value.to_str_radix(self.radix).into_bytes();
+                    Line::Value(&self.fallback)
+                }
+                _ => Line::Invalid(format!(
+                    "Invalid value in base {}: {:?}",
+                    self.radix,
+                    String::from_utf8_lossy(line)
+                )),
+            };
+        }
+        // anything else goes through the regular parser, which also reports the error
+        let text = String::from_utf8_lossy(line);
+        match crate::parse_bigint(&text) {
+            Ok(value) => {
+                self.fallback = value.to_string().into_bytes();
+                Line::Value(&self.fallback)
+            }
+            Err(err) => Line::Invalid(err),
+        }
+    }
+}
+
+// Byte order mark some editors put before the first line of a UTF-8 file
+pub const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+// Lines reported one by one before only the count goes on
+const MALFORMED_REPORTED: usize = 3;
+
+/// Tally of the lines of a file that hold no value, reported once after reading instead of one
+/// warning per line: the first few with their line number and byte offset, then the count.
+#[derive(Default)]
+pub struct Malformed {
+    count: u64,
+    // line number, byte offset and reason of the first lines
+    first: Vec<(usize, u64, String)>,
+}
+
+impl Malformed {
+    pub fn push(&mut self, line_num: usize, offset: u64, err: String) {
+        self.count += 1;
+        if self.first.len() < MALFORMED_REPORTED {
+            self.first.push((line_num, offset, err));
+        }
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    // Log the lines as warnings, `source` naming the file they were read from
+    pub fn report(&self, source: &str) {
+        if self.count == 0 {
+            return;
+        }
+        for (line_num, offset, err) in &self.first {
+            warn!(
+                "Skipped line {} (byte {}) of {}: {}",
+                line_num, offset, source, err
+            );
+        }
+        if self.count > self.first.len() as u64 {
+            warn!(
+                "Skipped {} unparseable lines of {} in total, {} more than listed",
+                self.count,
+                source,
+                self.count - self.first.len() as u64
+            );
+        }
+    }
+}
+
+// Value of a line written with --annotate ("77: 3n+1 -> 9232"): the part after the last "-> ",
+// or the whole line if there is none
+pub fn value_part(line: &[u8]) -> &[u8] {
+    line.windows(3)
+        .rposition(|window| window == b"-> ")
+        .map_or(line, |i| &line[i + 3..])
+}
+
+// Strip leading zeros from a line of plain ASCII digits. Returns None for anything else and for 0.
+fn canonical_digits(line: &[u8], radix: u32) -> Option<&[u8]> {
+    if line.is_empty()
+        || !line
+            .iter()
+            .all(|digit| digit_value(*digit).is_some_and(|value| value < radix))
+    {
+        return None;
+    }
+    let start = line.iter().position(|digit| *digit != b'0')?;
+    Some(&line[start..])
+}
+
+pub fn is_even(digits: &[u8]) -> bool {
+    digits.last().is_some_and(|digit| digit % 2 == 0)
+}
+
+// Parity in any base: the last digit decides in even bases, the digit sum in odd ones
+pub fn is_even_in(digits: &[u8], radix: u32) -> bool {
+    let value = |digit: &u8| digit_value(*digit).unwrap_or(0);
+    if radix.is_multiple_of(2) {
+        digits
+            .last()
+            .is_some_and(|digit| value(digit).is_multiple_of(2))
+    } else {
+        digits.iter().map(value).sum::<u32>().is_multiple_of(2)
+    }
+}
+
+// Value of a lowercase digit as written by `to_str_radix`
+fn digit_value(digit: u8) -> Option<u32> {
+    match digit {
+        b'0'..=b'9' => Some(u32::from(digit - b'0')),
+        b'a'..=b'z' => Some(u32::from(digit - b'a') + 10),
+        _ => None,
+    }
+}
+
+// Compare two canonical digit strings by value: more digits is larger, otherwise lexicographic
+pub fn compare(a: &[u8], b: &[u8]) -> Ordering {
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+pub fn to_bigint(digits: &[u8]) -> BigInt {
+    to_bigint_in(digits, 10)
+}
+
+pub fn to_bigint_in(digits: &[u8], radix: u32) -> BigInt {
+    BigInt::parse_bytes(digits, radix).unwrap_or_default()
+}
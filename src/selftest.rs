@@ -0,0 +1,272 @@
+use collatz_bigint_file::digits::DigitWriter;
+use collatz_bigint_file::hash::{self, Sha256};
+use collatz_bigint_file::sequence::{self, collatz, collatz_fast_io, Generated, Limits, Map};
+use collatz_bigint_file::stats::{self, Parity};
+use colored::Colorize;
+use num_bigint::BigInt;
+use num_traits::One;
+use std::io::Write;
+use std::ops::ControlFlow;
+
+// OEIS A006577, the number of steps of n = 1 to 100 to reach 1
+const A006577: [usize; 100] = [
+    0, 1, 7, 2, 5, 8, 16, 3, 19, 6, 14, 9, 9, 17, 17, 4, 12, 20, 20, 7, 7, 15, 15, 10, 23, 10, 111,
+    18, 18, 18, 106, 5, 26, 13, 13, 21, 21, 21, 34, 8, 109, 8, 29, 16, 16, 16, 104, 11, 24, 24, 24,
+    11, 11, 112, 112, 19, 32, 19, 32, 19, 19, 107, 107, 6, 27, 27, 27, 14, 14, 14, 102, 22, 115,
+    22, 14, 22, 22, 35, 35, 9, 22, 110, 110, 9, 9, 30, 30, 17, 30, 17, 92, 17, 17, 105, 105, 12,
+    118, 25, 25, 25,
+];
+// OEIS A025586, the largest value in the trajectory of n = 1 to 100, the seed included
+const A025586: [u64; 100] = [
+    1, 2, 16, 4, 16, 16, 52, 8, 52, 16, 52, 16, 40, 52, 160, 16, 52, 52, 88, 20, 64, 52, 160, 24,
+    88, 40, 9232, 52, 88, 160, 9232, 32, 100, 52, 160, 52, 112, 88, 304, 40, 9232, 64, 196, 52,
+    136, 160, 9232, 48, 148, 88, 232, 52, 160, 9232, 9232, 56, 196, 88, 304, 160, 184, 9232, 9232,
+    64, 196, 100, 304, 68, 208, 160, 9232, 72, 9232, 112, 340, 88, 232, 304, 808, 80, 244, 9232,
+    9232, 84, 256, 196, 592, 88, 304, 136, 9232, 160, 280, 9232, 9232, 96, 9232, 148, 448, 100,
+];
+// OEIS A006877, the seeds below 10^6 that take more steps than every smaller seed, with their
+// terms of A006577 and A025586
+const RECORDS: [(u64, usize, u64); 44] = [
+    (1, 0, 1),
+    (2, 1, 2),
+    (3, 7, 16),
+    (6, 8, 16),
+    (7, 16, 52),
+    (9, 19, 52),
+    (18, 20, 52),
+    (25, 23, 88),
+    (27, 111, 9232),
+    (54, 112, 9232),
+    (73, 115, 9232),
+    (97, 118, 9232),
+    (129, 121, 9232),
+    (171, 124, 9232),
+    (231, 127, 9232),
+    (313, 130, 9232),
+    (327, 143, 9232),
+    (649, 144, 9232),
+    (703, 170, 250504),
+    (871, 178, 190996),
+    (1161, 181, 190996),
+    (2223, 182, 250504),
+    (2463, 208, 250504),
+    (2919, 216, 250504),
+    (3711, 237, 481624),
+    (6171, 261, 975400),
+    (10971, 267, 975400),
+    (13255, 275, 497176),
+    (17647, 278, 11003416),
+    (23529, 281, 11003416),
+    (26623, 307, 106358020),
+    (34239, 310, 18976192),
+    (35655, 323, 41163712),
+    (52527, 339, 106358020),
+    (77031, 350, 21933016),
+    (106239, 353, 104674192),
+    (142587, 374, 593279152),
+    (156159, 382, 41163712),
+    (216367, 385, 11843332),
+    (230631, 442, 76778008),
+    (410011, 448, 76778008),
+    (511935, 469, 76778008),
+    (626331, 508, 7222283188),
+    (837799, 524, 2974984576),
+];
+// Bits of 2^k-1, the seed of the cross-check above the threshold of the pipelined writer
+const LARGE_SEED_BITS: usize = 4100;
+
+// Steps to reach 1 and, if the backend knows it, the largest value of a trajectory
+type Outcome = Result<(usize, Option<BigInt>), String>;
+type Backend = fn(&BigInt) -> Outcome;
+
+// The ways the program computes a trajectory, each checked against the reference; the two
+// writers come first, the cross-check compares their files directly
+const BACKENDS: [(&str, Backend); 6] = [
+    ("sequence file writer", file_writer),
+    ("sequence file writer --fast-io", fast_io_writer),
+    ("in memory (batch, compare, server)", in_memory),
+    ("trajectory hash (verify)", hashed),
+    ("jumps over halvings (at, first)", jumps),
+    ("shortcut map (repl, art)", shortcut),
+];
+
+/// Checks every way the program computes trajectories against reference values of the OEIS:
+/// the total stopping times (A006577) and largest values (A025586) of the seeds 1 to 100 and of
+/// the stopping time records below 10^6 (A006877, among them 27, 97, 871, 6171 and 837799).
+/// A seed of 4100 bits then checks that the writers, which switch to a pipelined computation
+/// for large values, agree byte for byte with each other and on the steps with the others.
+///
+/// The limits of the command line do not apply, every trajectory is computed to 1.
+pub fn run() -> Result<(), String> {
+    let seeds: Vec<(u64, usize, u64)> = (1..=100u64)
+        .map(|n| (n, A006577[n as usize - 1], A025586[n as usize - 1]))
+        .chain(RECORDS)
+        .collect();
+    println!(
+        "reference: OEIS A006577 and A025586 for n = 1 to 100 and the {} records of A006877 below 10^6",
+        RECORDS.len()
+    );
+    println!();
+    println!(
+        "{}",
+        format!("{:<36} {:>5}  result", "backend", "seeds").bold()
+    );
+    let mut failed = 0;
+    for (name, backend) in BACKENDS {
+        let mismatch = seeds.iter().find_map(|&(seed, steps, max)| {
+            let seed_value = BigInt::from(seed);
+            match backend(&seed_value) {
+                Ok((computed, _)) if computed != steps => {
+                    Some(format!("{}: {} steps instead of {}", seed, computed, steps))
+                }
+                Ok((_, Some(computed))) if computed != BigInt::from(max) => Some(format!(
+                    "{}: max value {} instead of {}",
+                    seed, computed, max
+                )),
+                Ok(_) => None,
+                Err(err) => Some(format!("{}: {}", seed, err)),
+            }
+        });
+        let result = match &mismatch {
+            None => "OK".green().to_string(),
+            Some(mismatch) => format!("{} {}", "MISMATCH".red(), mismatch),
+        };
+        println!("{:<36} {:>5}  {}", name, seeds.len(), result);
+        failed += usize::from(mismatch.is_some());
+    }
+
+    let large = (BigInt::one() << LARGE_SEED_BITS) - 1;
+    let cross_check = cross_check(&large);
+    let result = match &cross_check {
+        Ok(steps) => format!("{} ({} steps)", "OK".green(), steps),
+        Err(err) => format!("{} {}", "MISMATCH".red(), err),
+    };
+    println!(
+        "{:<36} {:>5}  {}",
+        format!("all backends on 2^{}-1", LARGE_SEED_BITS),
+        1,
+        result
+    );
+    failed += usize::from(cross_check.is_err());
+
+    println!();
+    match failed {
+        0 => {
+            println!("OK: every backend matches the reference");
+            Ok(())
+        }
+        failed => Err(format!(
+            "MISMATCH: {} of {} checks failed",
+            failed,
+            BACKENDS.len() + 1
+        )),
+    }
+}
+
+// The writers agree byte for byte on a large seed, with the hash of `verify`, and every backend
+// on its steps
+fn cross_check(seed: &BigInt) -> Result<usize, String> {
+    let plain = write_values(seed, false);
+    if plain != write_values(seed, true) {
+        return Err("the files written with and without --fast-io differ".to_string());
+    }
+    if !plain.ends_with(b"\n1\n") {
+        return Err("the sequence file does not end with 1".to_string());
+    }
+    let steps = plain.iter().filter(|&&byte| byte == b'\n').count();
+    let mut hasher = Sha256::default();
+    DigitWriter::default()
+        .write_line(seed, &mut hasher)
+        .and_then(|_| hasher.write_all(&plain))
+        .expect("hashing does not fail");
+    let (hash, _) = hash::trajectory_hash(seed, &Limits::default());
+    if hasher.finish_hex() != hash {
+        return Err("the hash of the sequence file differs from the one of verify".to_string());
+    }
+    for (name, backend) in &BACKENDS[2..] {
+        let (computed, _) = backend(seed)?;
+        if computed != steps {
+            return Err(format!(
+                "{}: {} steps, the sequence file has {}",
+                name, computed, steps
+            ));
+        }
+    }
+    Ok(steps)
+}
+
+fn in_memory(seed: &BigInt) -> Outcome {
+    let row = stats::trajectory(seed.to_string(), seed, &Limits::default(), Map::Standard);
+    Ok((row.stopping_time, Some(row.max_value)))
+}
+
+fn file_writer(seed: &BigInt) -> Outcome {
+    read_values(seed, &write_values(seed, false))
+}
+
+fn fast_io_writer(seed: &BigInt) -> Outcome {
+    read_values(seed, &write_values(seed, true))
+}
+
+fn hashed(seed: &BigInt) -> Outcome {
+    let (_, generated) = hash::trajectory_hash(seed, &Limits::default());
+    Ok((generated.steps, Some(generated.max_value.max(seed.clone()))))
+}
+
+fn jumps(seed: &BigInt) -> Outcome {
+    let mut n = seed.clone();
+    let (steps, _) = sequence::advance(&mut n, usize::MAX, &Limits::default(), None);
+    if !n.is_one() {
+        return Err(format!("stopped at {} after {} steps", n, steps));
+    }
+    Ok((steps, None))
+}
+
+// A step (3n+1)/2 of the shortcut map stands for two steps of the standard map
+fn shortcut(seed: &BigInt) -> Outcome {
+    let mut odd_steps = 0;
+    let row = stats::observe_trajectory(
+        seed.to_string(),
+        seed,
+        &Limits::default(),
+        Map::Shortcut,
+        |_, _, parity| {
+            if parity == Parity::Odd {
+                odd_steps += 1;
+            }
+            ControlFlow::Continue(())
+        },
+    );
+    Ok((row.stopping_time + odd_steps, None))
+}
+
+// The value lines of the sequence file of `seed`, without header and footer
+fn write_values(seed: &BigInt, fast_io: bool) -> Vec<u8> {
+    let mut output = Vec::new();
+    let (seed, limits) = (seed.clone(), Limits::default());
+    if fast_io {
+        collatz_fast_io(seed, &mut output, Generated::default(), &limits);
+    } else {
+        collatz(seed, &mut output, Generated::default(), &limits);
+    }
+    output
+}
+
+// Steps and largest value of the value lines written for `seed`
+fn read_values(seed: &BigInt, values: &[u8]) -> Outcome {
+    let text = String::from_utf8_lossy(values);
+    let mut max = seed.clone();
+    let mut last = seed.clone();
+    let mut steps = 0;
+    for line in text.lines() {
+        last = line
+            .parse::<BigInt>()
+            .map_err(|_| format!("invalid line {} '{}'", steps + 1, line))?;
+        max = max.max(last.clone());
+        steps += 1;
+    }
+    if !last.is_one() {
+        return Err(format!("the file ends with {} after {} steps", last, steps));
+    }
+    Ok((steps, Some(max)))
+}
@@ -0,0 +1,791 @@
+use crate::condition::{Condition, Vars};
+use crate::digits::{DecimalValue, DigitWriter};
+use crate::progress;
+use crate::value::{Backend, Value};
+use num_bigint::BigInt;
+use num_traits::{One, Zero};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+use std::fmt;
+use std::io::Write;
+use std::mem;
+use std::ops::ControlFlow;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Interval of the debug progress messages, in steps
+pub const PROGRESS_INTERVAL: usize = 100_000;
+// Steps between two looks at the clock for the estimate of the time left, and the time between
+// two estimates
+const ETA_CHECK_INTERVAL: usize = 64;
+const ETA_INTERVAL: Duration = Duration::from_secs(10);
+// Start values of at least this many bits are computed and written on separate threads: from
+// a few thousand digits on, converting a value to decimal takes much longer than the step that
+// produced it
+const PIPELINE_MIN_BITS: u64 = 4096;
+// Bits of the values sent to the writer at once, and batches on the way at most
+const PIPELINE_BATCH_BITS: u64 = 1 << 20;
+const PIPELINE_DEPTH: usize = 4;
+
+// Rule applied to odd values
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Map {
+    // n -> 3n+1
+    #[default]
+    Standard,
+    // n -> (3n+1)/2, merging the halving that always follows 3n+1 into the same step
+    Shortcut,
+}
+
+impl Map {
+    // Name of the map as written in the metadata header
+    pub fn name(self) -> &'static str {
+        match self {
+            Map::Standard => "3n+1",
+            Map::Shortcut => "(3n+1)/2",
+        }
+    }
+}
+
+impl std::str::FromStr for Map {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.trim() {
+            "standard" | "3n+1" => Ok(Map::Standard),
+            "shortcut" | "(3n+1)/2" => Ok(Map::Shortcut),
+            other => Err(format!(
+                "Unknown map '{}'. Use 'standard' (3n+1) or 'shortcut' ((3n+1)/2)",
+                other
+            )),
+        }
+    }
+}
+
+// Value the computation runs down to
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Until {
+    // the end of the sequence
+    #[default]
+    One,
+    // the first value below the start value, the glide
+    BelowSeed,
+    // the first value after the seed at or below this one
+    Value(BigInt),
+}
+
+impl Until {
+    // Whether the computation from `start` stops at `n`
+    pub fn reached(&self, n: &BigInt, start: &BigInt) -> bool {
+        match self {
+            Until::One => false,
+            Until::BelowSeed => n < start,
+            Until::Value(value) => n <= value,
+        }
+    }
+
+    pub fn target(&self) -> Option<Target> {
+        match self {
+            Until::One => None,
+            Until::BelowSeed => Some(Target::BelowSeed),
+            Until::Value(_) => Some(Target::Value),
+        }
+    }
+}
+
+impl std::str::FromStr for Until {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.trim() {
+            "one" => Ok(Until::One),
+            "below-seed" => Ok(Until::BelowSeed),
+            other => other
+                .strip_prefix("value:")
+                .and_then(|value| value.trim().parse::<BigInt>().ok())
+                .filter(|value| *value >= BigInt::one())
+                .map(Until::Value)
+                .ok_or_else(|| {
+                    format!(
+                        "Invalid stop value '{}'. Use 'one', 'below-seed' or 'value:N' with a positive decimal N",
+                        other
+                    )
+                }),
+        }
+    }
+}
+
+// Limits that stop the computation before the sequence reaches 1, and the representation of its
+// values
+#[derive(Clone, Debug, Default)]
+pub struct Limits {
+    // total steps, including those of a resumed file
+    pub max_steps: Option<usize>,
+    // wall-clock time of this call
+    pub timeout: Option<Duration>,
+    // bit length no value may exceed, guards against running out of memory
+    pub max_bits: Option<u64>,
+    // bytes of the value lines written by this call, guards against filling the disk; only the
+    // functions that write the sequence check it
+    pub max_bytes: Option<u64>,
+    // stop early at the glide or a threshold
+    pub until: Until,
+    // stop early once this condition holds, checked after every step
+    pub stop_when: Option<Condition>,
+    // u128 for small values or BigInt for all; the results are the same
+    pub backend: Backend,
+}
+
+// Stop value of `Until` that ended a computation
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    BelowSeed,
+    Value,
+}
+
+// Why a computation stopped before the sequence reached 1
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Truncation {
+    MaxSteps(usize),
+    Timeout(Duration),
+    MaxBits(u64),
+    MaxBytes(u64),
+    // stopped by the caller, e.g. because the receiver of the values went away
+    Cancelled,
+    // reached the stop value of `Limits::until`
+    Until(Target),
+    // the condition of `Limits::stop_when` held
+    Condition,
+}
+
+impl fmt::Display for Truncation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Truncation::MaxSteps(max_steps) => write!(f, "step limit of {} reached", max_steps),
+            Truncation::Timeout(timeout) => {
+                write!(f, "timeout of {}s reached", timeout.as_secs_f64())
+            }
+            Truncation::MaxBits(max_bits) => {
+                write!(f, "next value would exceed the limit of {} bits", max_bits)
+            }
+            Truncation::MaxBytes(max_bytes) => {
+                write!(f, "output limit of {} bytes reached", max_bytes)
+            }
+            Truncation::Cancelled => write!(f, "cancelled"),
+            Truncation::Until(Target::BelowSeed) => write!(f, "dropped below the seed"),
+            Truncation::Until(Target::Value) => write!(f, "reached the stop value"),
+            Truncation::Condition => write!(f, "stop condition met"),
+        }
+    }
+}
+
+// Parses the text written by `Display`, e.g. the truncated column of batch statistics
+impl std::str::FromStr for Truncation {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let input = input.trim();
+        let number = |prefix: &str, suffix: &str| {
+            input
+                .strip_prefix(prefix)
+                .and_then(|rest| rest.strip_suffix(suffix))
+        };
+        let parsed = if let Some(max_steps) = number("step limit of ", " reached") {
+            max_steps.parse().ok().map(Truncation::MaxSteps)
+        } else if let Some(seconds) = number("timeout of ", "s reached") {
+            seconds
+                .parse()
+                .ok()
+                .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                .map(Truncation::Timeout)
+        } else if let Some(max_bits) = number("next value would exceed the limit of ", " bits") {
+            max_bits.parse().ok().map(Truncation::MaxBits)
+        } else if let Some(max_bytes) = number("output limit of ", " bytes reached") {
+            max_bytes.parse().ok().map(Truncation::MaxBytes)
+        } else {
+            match input {
+                "cancelled" => Some(Truncation::Cancelled),
+                "dropped below the seed" => Some(Truncation::Until(Target::BelowSeed)),
+                "reached the stop value" => Some(Truncation::Until(Target::Value)),
+                "stop condition met" => Some(Truncation::Condition),
+                _ => None,
+            }
+        };
+        parsed.ok_or_else(|| format!("Unknown truncation '{}'", input))
+    }
+}
+
+/// Expected change of log2 n per step of the heuristic model.
+///
+/// An odd value n goes to (3n+1)/2 ≈ 3n/2 and an even one to n/2; with parities behaving like
+/// fair coin flips a step of the shortcut map changes log2 n by log2(3/4)/2 ≈ -0.2075 on
+/// average. The standard map spends an extra step on every odd value, 3 steps per 2 shortcut
+/// steps, so it drifts by log2(3/4)/3 per step.
+pub fn drift_per_step(map: Map) -> f64 {
+    match map {
+        Map::Standard => (0.75f64).log2() / 3.0,
+        Map::Shortcut => (0.75f64).log2() / 2.0,
+    }
+}
+
+/// Steps the heuristic model expects from a value of `log2` bits down to 1.
+pub fn expected_steps(log2: f64, map: Map) -> f64 {
+    -log2.max(0.0) / drift_per_step(map)
+}
+
+/// Calculates the Collatz sequence for a given starting value.
+///
+/// The Collatz sequence is a series of numbers where each number is derived from the previous
+/// number using the following rules:
+///
+/// - If the number is even, divide it by 2.
+/// - If the number is odd, multiply it by 3 and add 1.
+///
+/// The sequence continues until the number reaches 1.
+///
+/// For start values of 4096 bits or more the values are computed on a second thread and
+/// converted to decimal and written on the calling one, so the arithmetic does not wait for the
+/// output.
+///
+/// # Arguments
+///
+/// - `n`: The starting value for the Collatz sequence.
+/// - `output_file`: A mutable reference to the writer of the sequence file.
+/// - `generated`: Steps and maximum so far, non-zero when a previous run is resumed.
+/// - `limits`: Step limit, timeout, value size limit, output limit and stop value; when one is hit
+///   the computation stops early and `generated.truncated` records why.
+///
+/// Returns the stopping time and the largest value written, used for the file footer and to
+/// scale the magnitude coloring.
+pub fn collatz(
+    n: BigInt,
+    output_file: &mut impl Write,
+    generated: Generated,
+    limits: &Limits,
+) -> Generated {
+    if n.bits() >= PIPELINE_MIN_BITS {
+        return collatz_pipelined(n, output_file, generated, limits);
+    }
+    let mut digits = DigitWriter::default();
+    let mut written = 0;
+    let mut generated = generate(n, generated, limits, |n| {
+        written += digits
+            .write_line(n, output_file)
+            .expect("Failed to write to file") as u64;
+        ControlFlow::Continue(())
+    });
+    generated.digits += written;
+    generated
+}
+
+/// Like [`collatz`], but keeps the decimal digits of the current value and updates them with
+/// each step instead of converting every value to decimal anew (`--fast-io`), see
+/// [`DecimalValue`]. The file is the same byte for byte; the time per value grows linearly with
+/// its length instead of quadratically.
+pub fn collatz_fast_io(
+    n: BigInt,
+    output_file: &mut impl Write,
+    generated: Generated,
+    limits: &Limits,
+) -> Generated {
+    let mut decimal = DecimalValue::new(&n);
+    let mut written = 0;
+    let mut generated = generate(n, generated, limits, |_| {
+        decimal.step();
+        written += decimal
+            .write_line(output_file)
+            .expect("Failed to write to file") as u64;
+        ControlFlow::Continue(())
+    });
+    generated.digits += written;
+    generated
+}
+
+/// Like [`collatz`], but writes the values in `radix`, a power of two, straight from their
+/// binary form (`--digits-only`): no value is ever converted to decimal, which takes time
+/// quadratic in its length, so the time per value stays linear. `observe` gets every value
+/// after the start value, e.g. to collect the statistics that would otherwise be read from the
+/// decimal digits.
+pub fn collatz_in_radix(
+    n: BigInt,
+    output_file: &mut impl Write,
+    generated: Generated,
+    limits: &Limits,
+    radix: u32,
+    mut observe: impl FnMut(&BigInt),
+) -> Generated {
+    debug_assert!(
+        radix.is_power_of_two(),
+        "only powers of two avoid the division"
+    );
+    let mut written = 0;
+    let mut generated = generate(n, generated, limits, |n| {
+        observe(n);
+        let digits = n.to_str_radix(radix);
+        output_file
+            .write_all(digits.as_bytes())
+            .and_then(|()| output_file.write_all(b"\n"))
+            .expect("Failed to write to file");
+        written += digits.len() as u64 + 1;
+        ControlFlow::Continue(())
+    });
+    generated.digits += written;
+    generated
+}
+
+// Computes the values on a second thread and writes them on this one. The values go over in
+// batches through a bounded channel, so the arithmetic runs ahead of the digit conversion by at
+// most PIPELINE_DEPTH batches and the memory held by the values stays bounded.
+fn collatz_pipelined(
+    n: BigInt,
+    output_file: &mut impl Write,
+    generated: Generated,
+    limits: &Limits,
+) -> Generated {
+    let (sender, receiver) = mpsc::sync_channel::<Vec<BigInt>>(PIPELINE_DEPTH);
+    thread::scope(|scope| {
+        let computation = scope.spawn(move || {
+            let mut batch = Vec::new();
+            let mut batch_bits = 0;
+            let generated = generate(n, generated, limits, |n| {
+                batch.push(n.clone());
+                batch_bits += n.bits();
+                if batch_bits >= PIPELINE_BATCH_BITS {
+                    batch_bits = 0;
+                    // the writer is gone only if writing failed
+                    if sender.send(mem::take(&mut batch)).is_err() {
+                        return ControlFlow::Break(());
+                    }
+                }
+                ControlFlow::Continue(())
+            });
+            // a failed write shows up on the writing side
+            let _ = sender.send(batch);
+            generated
+        });
+
+        let mut digits = DigitWriter::default();
+        let mut written = 0;
+        for batch in receiver {
+            for n in &batch {
+                written += digits
+                    .write_line(n, output_file)
+                    .expect("Failed to write to file") as u64;
+            }
+        }
+        let mut generated = computation
+            .join()
+            .expect("the computation of the sequence panicked");
+        generated.digits += written;
+        generated
+    })
+}
+
+// The sequence loop; `emit` gets every value after the start value and stops the computation
+// by returning `ControlFlow::Break`.
+fn generate(
+    n: BigInt,
+    mut generated: Generated,
+    limits: &Limits,
+    mut emit: impl FnMut(&BigInt) -> ControlFlow<()>,
+) -> Generated {
+    let deadline = limits.timeout.map(|timeout| Instant::now() + timeout);
+    // the start value is only needed to detect the glide and for the stop condition
+    let start = match (&limits.until, &limits.stop_when) {
+        (Until::BelowSeed, _) | (_, Some(_)) => n.clone(),
+        _ => BigInt::zero(),
+    };
+    // time and step of the last estimate of the time left and of the last progress event
+    let started = Instant::now();
+    let mut last_eta = (started, generated.steps);
+    let mut last_event = (started, generated.steps);
+    // upper bound of the bytes of the value lines emitted so far, checked against max_bytes
+    let mut bytes = 0;
+    generated.profile.start(n.bits(), generated.steps);
+    let mut n = Value::new(n, limits.backend);
+    while !n.is_one() {
+        if limits
+            .max_steps
+            .is_some_and(|max_steps| generated.steps >= max_steps)
+        {
+            generated.truncated = limits.max_steps.map(Truncation::MaxSteps);
+            break;
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            generated.truncated = limits.timeout.map(Truncation::Timeout);
+            break;
+        }
+
+        // the next value has at most two more bits than n, so at most this many decimal digits
+        // plus the newline; counted from the bits because the digits are not known here
+        let line = ((n.bits() + 2) as f64 * std::f64::consts::LOG10_2) as u64 + 2;
+        if limits
+            .max_bytes
+            .is_some_and(|max_bytes| bytes + line > max_bytes)
+        {
+            generated.truncated = limits.max_bytes.map(Truncation::MaxBytes);
+            break;
+        }
+        bytes += line;
+
+        let rose = n.is_odd();
+        if rose {
+            // 3n+1 has at most two more bits than n
+            if limits
+                .max_bits
+                .is_some_and(|max_bits| n.bits() + 2 > max_bits)
+            {
+                generated.truncated = limits.max_bits.map(Truncation::MaxBits);
+                break;
+            }
+            n.triple_plus_one();
+        } else {
+            n.halve();
+        }
+        generated.steps += 1;
+        if rose {
+            generated.peaks.push(n.get(), generated.steps);
+        }
+        if *n.get() > generated.max_value {
+            generated.max_value = n.get().clone();
+            generated.max_index = generated.steps;
+        }
+        if emit(n.get()).is_break() {
+            generated.truncated = Some(Truncation::Cancelled);
+            break;
+        }
+        if generated.profile.due(generated.steps) {
+            generated.profile.record(n.bits(), generated.steps);
+        }
+        if generated.steps.is_multiple_of(PROGRESS_INTERVAL) {
+            crate::debug!(
+                "step {}: current value has {} bits, max {} bits at step {}",
+                generated.steps,
+                n.bits(),
+                generated.max_value.bits(),
+                generated.max_index
+            );
+        }
+        if generated.steps.is_multiple_of(ETA_CHECK_INTERVAL) {
+            if crate::log::enabled(crate::log::Level::Info) && last_eta.0.elapsed() >= ETA_INTERVAL
+            {
+                log_eta(n.get(), generated.steps, last_eta);
+                last_eta = (Instant::now(), generated.steps);
+            }
+            if progress::enabled() && last_event.0.elapsed() >= progress::INTERVAL {
+                let rate =
+                    (generated.steps - last_event.1) as f64 / last_event.0.elapsed().as_secs_f64();
+                progress::emit(&format!(
+                    "{{\"event\":\"progress\",\"step\":{},\"bits\":{},\"max_bits\":{},\"rate\":{:.1},\"elapsed\":{:.3}}}",
+                    generated.steps,
+                    n.bits(),
+                    generated.max_value.bits(),
+                    rate,
+                    started.elapsed().as_secs_f64()
+                ));
+                last_event = (Instant::now(), generated.steps);
+            }
+        }
+        if !n.is_one() && limits.until.reached(n.get(), &start) {
+            generated.truncated = limits.until.target().map(Truncation::Until);
+            break;
+        }
+        // the max of a file run leaves out the seed, the condition sees it like a batch
+        let max = (&generated.max_value).max(&start);
+        if !n.is_one() && stop_condition(limits, n.get(), &start, generated.steps, max) {
+            generated.truncated = Some(Truncation::Condition);
+            break;
+        }
+    }
+    // the last chunk, shorter than the others
+    generated.profile.record(n.bits(), generated.steps);
+    if progress::enabled() {
+        // the reasons are plain ASCII, so their debug form is a valid JSON string
+        let truncated = generated
+            .truncated
+            .map_or("null".to_string(), |truncation| {
+                format!("{:?}", truncation.to_string())
+            });
+        progress::emit(&format!(
+            "{{\"event\":\"done\",\"steps\":{},\"max_bits\":{},\"truncated\":{},\"elapsed\":{:.3}}}",
+            generated.steps,
+            generated.max_value.bits(),
+            truncated,
+            started.elapsed().as_secs_f64()
+        ));
+    }
+    generated
+}
+
+/// Whether the condition of `limits.stop_when` holds for `n` at `step` of the trajectory from
+/// `seed`, with `max` the largest value so far; false without a condition.
+pub fn stop_condition(
+    limits: &Limits,
+    n: &BigInt,
+    seed: &BigInt,
+    step: usize,
+    max: &BigInt,
+) -> bool {
+    limits
+        .stop_when
+        .as_ref()
+        .is_some_and(|condition| condition.holds(&Vars { n, seed, step, max }))
+}
+
+// Log the steps and time the heuristic drift model expects until n reaches 1, at the rate of the
+// steps since `last`
+fn log_eta(n: &BigInt, steps: usize, last: (Instant, usize)) {
+    let left = expected_steps(n.bits() as f64, Map::Standard);
+    let rate = (steps - last.1) as f64 / last.0.elapsed().as_secs_f64();
+    // the cost of a step grows with the size of n, which the model lets fall linearly to 0, so
+    // the remaining steps take half as long on average as the current ones
+    let seconds = left / rate / 2.0;
+    let eta = match seconds as u64 {
+        seconds if seconds >= 3600 => format!("{}h {:02}m", seconds / 3600, seconds / 60 % 60),
+        seconds if seconds >= 60 => format!("{}m {:02}s", seconds / 60, seconds % 60),
+        seconds => format!("{}s", seconds),
+    };
+    crate::info!(
+        "step {}: {} bits, about {:.0} steps and {} left (heuristic estimate, {:.0} steps/s)",
+        steps,
+        n.bits(),
+        left,
+        eta,
+        rate
+    );
+}
+
+/// Applies up to `steps` steps of the standard map to `n` in place, without producing the values
+/// in between, and returns the steps applied: fewer than `steps` if n reached 1 first.
+///
+/// A run of halvings is applied as one shift by the trailing zeros of n, so only the 3n+1 steps,
+/// about a third of all steps, cost a multiplication. `limits.timeout` counts from `deadline`
+/// and `limits.max_bits` is checked before each 3n+1; when one is hit, the truncation is returned
+/// with n at the step where it stopped. `limits.max_steps` and `limits.until` do not apply.
+pub fn advance(
+    n: &mut BigInt,
+    steps: usize,
+    limits: &Limits,
+    deadline: Option<Instant>,
+) -> (usize, Option<Truncation>) {
+    let mut applied = 0;
+    let mut rises = 0u64;
+    while applied < steps && !n.is_one() {
+        match n.trailing_zeros() {
+            Some(zeros) if zeros > 0 => {
+                let run = (zeros as usize).min(steps - applied);
+                *n >>= run;
+                applied += run;
+            }
+            _ => {
+                rises += 1;
+                if rises.is_multiple_of(ETA_CHECK_INTERVAL as u64)
+                    && deadline.is_some_and(|deadline| Instant::now() >= deadline)
+                {
+                    return (applied, limits.timeout.map(Truncation::Timeout));
+                }
+                if limits
+                    .max_bits
+                    .is_some_and(|max_bits| n.bits() + 2 > max_bits)
+                {
+                    return (applied, limits.max_bits.map(Truncation::MaxBits));
+                }
+                *n = BigInt::from(3) * &*n + 1;
+                applied += 1;
+            }
+        }
+    }
+    (applied, None)
+}
+
+// Summary of the generation pass
+#[derive(Default)]
+pub struct Generated {
+    pub steps: usize,
+    pub max_value: BigInt,
+    pub max_index: usize,
+    // digits written in this session, excluding the part of a resumed file
+    pub digits: u64,
+    // set if a limit stopped the computation before reaching 1
+    pub truncated: Option<Truncation>,
+    // highest peaks of the steps computed in this session, none unless asked for
+    pub peaks: Peaks,
+    // time of the steps computed in this session by bit length, none unless asked for
+    pub profile: Profile,
+}
+
+/// The `k` highest peaks of a trajectory with their steps, tracked while it is computed.
+///
+/// A peak is a local maximum, a value above both of its neighbors. In the Collatz map these are
+/// exactly the values of the 3n+1 steps: each is larger than the odd value before it and is
+/// followed by a halving. Only a value that makes it onto the list is cloned, so tracking a few
+/// peaks costs nothing next to the arithmetic.
+#[derive(Clone, Debug, Default)]
+pub struct Peaks {
+    k: usize,
+    // min-heap, so the lowest peak is dropped first
+    heap: BinaryHeap<Reverse<(BigInt, usize)>>,
+}
+
+impl Peaks {
+    /// Tracks the `k` highest peaks; with 0 nothing is tracked.
+    pub fn new(k: usize) -> Self {
+        Peaks {
+            k,
+            heap: BinaryHeap::with_capacity(k + 1),
+        }
+    }
+
+    /// Records the peak `value` at `step` if it is among the `k` highest so far.
+    pub fn push(&mut self, value: &BigInt, step: usize) {
+        if self.heap.len() < self.k {
+            self.heap.push(Reverse((value.clone(), step)));
+        } else if self
+            .heap
+            .peek()
+            .is_some_and(|Reverse((lowest, _))| value > lowest)
+        {
+            self.heap.pop();
+            self.heap.push(Reverse((value.clone(), step)));
+        }
+    }
+
+    /// The peaks from the highest down, as (step, value).
+    pub fn ranked(&self) -> Vec<(usize, &BigInt)> {
+        let mut peaks: Vec<_> = self
+            .heap
+            .iter()
+            .map(|Reverse((value, step))| (*step, value))
+            .collect();
+        peaks.sort_by(|a, b| b.1.cmp(a.1));
+        peaks
+    }
+}
+
+/// Time spent on the steps of a trajectory by the bit length of the values, for `--profile`.
+///
+/// The steps are timed in chunks of `interval`; each chunk counts towards the bucket of the bit
+/// length of its first value, a power of two range like 4096-8191 bits, and towards the rising
+/// or falling part of the bucket depending on whether its values grew. The clock is read once
+/// per chunk, so profiling costs nothing next to the arithmetic.
+#[derive(Clone, Debug, Default)]
+pub struct Profile {
+    interval: usize,
+    // start of the current chunk: time, bits of its first value and step
+    chunk: Option<(Instant, u64, usize)>,
+    // (bucket, rising) -> chunks, steps and time
+    buckets: BTreeMap<(u32, bool), (u64, u64, Duration)>,
+}
+
+/// Time of the steps in one bucket of a [`Profile`].
+#[derive(Clone, Debug)]
+pub struct ProfileEntry {
+    // bit lengths of the bucket, both included
+    pub bits: (u64, u64),
+    pub rising: bool,
+    pub chunks: u64,
+    pub steps: u64,
+    pub time: Duration,
+}
+
+impl Profile {
+    /// Times the steps in chunks of `interval`; with 0 nothing is timed.
+    pub fn new(interval: usize) -> Self {
+        Profile {
+            interval,
+            ..Profile::default()
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.interval > 0
+    }
+
+    /// Starts a chunk at `step` with a value of `bits` bits.
+    pub fn start(&mut self, bits: u64, step: usize) {
+        if self.enabled() {
+            self.chunk = Some((Instant::now(), bits, step));
+        }
+    }
+
+    /// Whether a chunk ends at `step`.
+    pub fn due(&self, step: usize) -> bool {
+        self.enabled() && step.is_multiple_of(self.interval)
+    }
+
+    /// Ends the current chunk at `step` with a value of `bits` bits and starts the next one.
+    pub fn record(&mut self, bits: u64, step: usize) {
+        let Some((started, first_bits, first_step)) = self.chunk else {
+            return;
+        };
+        if step > first_step {
+            let bucket = u64::BITS - first_bits.leading_zeros();
+            let entry = self.buckets.entry((bucket, bits > first_bits)).or_default();
+            entry.0 += 1;
+            entry.1 += (step - first_step) as u64;
+            entry.2 += started.elapsed();
+        }
+        self.start(bits, step);
+    }
+
+    /// The buckets with steps, from the smallest values up, falling before rising.
+    pub fn entries(&self) -> Vec<ProfileEntry> {
+        self.buckets
+            .iter()
+            .map(|(&(bucket, rising), &(chunks, steps, time))| ProfileEntry {
+                bits: match bucket {
+                    0 => (0, 0),
+                    bucket => (1 << (bucket - 1), (1 << bucket) - 1),
+                },
+                rising,
+                chunks,
+                steps,
+                time,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The file, steps, maximum and truncation of the sequence of `seed`
+    fn write(
+        seed: &BigInt,
+        limits: &Limits,
+    ) -> (Vec<u8>, usize, BigInt, usize, Option<Truncation>) {
+        let mut file = Vec::new();
+        let generated = collatz(seed.clone(), &mut file, Generated::default(), limits);
+        (
+            file,
+            generated.steps,
+            generated.max_value,
+            generated.max_index,
+            generated.truncated,
+        )
+    }
+
+    #[test]
+    fn backends_write_the_same_file_around_2_to_the_128() {
+        for bits in [126u32, 127, 128] {
+            let power = BigInt::one() << bits;
+            for seed in [&power - 1u32, &power + 1u32, &power / 3u32 + 1u32] {
+                for limits in [
+                    Limits::default(),
+                    Limits {
+                        max_bits: Some(129),
+                        ..Limits::default()
+                    },
+                ] {
+                    let big = Limits {
+                        backend: Backend::Big,
+                        ..limits.clone()
+                    };
+                    assert_eq!(write(&seed, &limits), write(&seed, &big), "{}", seed);
+                }
+            }
+        }
+    }
+}
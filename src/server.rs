@@ -0,0 +1,326 @@
+use crate::batch::{self, StatsFormat};
+use crate::format::NumberFormat;
+use crate::metrics::{self, METRICS};
+use crate::websocket;
+use collatz_bigint_file::digits::DigitWriter;
+use collatz_bigint_file::report::json_string;
+use collatz_bigint_file::sequence::{collatz, Generated, Limits, Map};
+use collatz_bigint_file::stats;
+use collatz_bigint_file::{info, warn};
+use num_bigint::BigInt;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::net::{TcpListener, TcpStream};
+use std::ops::ControlFlow;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// How often buffered WebSocket events are pushed to the client
+const EVENT_FLUSH_INTERVAL: Duration = Duration::from_millis(100);
+
+// Settings shared by all connections
+struct State {
+    limits: Limits,
+    number_format: NumberFormat,
+}
+
+/// Serves the HTTP API until the process is stopped. Every connection gets its own thread.
+///
+/// - `GET /collatz/{seed}?stats=true`: statistics as a JSON object
+/// - `GET /collatz/{seed}`: the trajectory as `text/plain`, one value per line, streamed while
+///   it is computed
+/// - `GET /ws/collatz/{seed}`: a WebSocket that streams one `{"step","bits","parity"}` event
+///   per step, then a summary
+/// - `GET /metrics`: counters and records of all requests in the Prometheus text format
+///
+/// The seed can be an expression like `2^199-1` (URL encoded `2%5E199-1`). The limits
+/// `--max-steps`, `--timeout` and `--max-value-size` apply to every request.
+pub fn run(bind: &str, port: u16, limits: Limits, number_format: NumberFormat) -> io::Result<()> {
+    let listener = TcpListener::bind((bind, port))?;
+    metrics::start();
+    println!("Listening on http://{}", listener.local_addr()?);
+    let state = Arc::new(State {
+        limits,
+        number_format,
+    });
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                warn!("Failed to accept connection: {}", err);
+                continue;
+            }
+        };
+        let state = Arc::clone(&state);
+        thread::spawn(move || {
+            if let Err(err) = handle(stream, &state) {
+                warn!("Connection failed: {}", err);
+            }
+        });
+    }
+    Ok(())
+}
+
+// A parsed request line, e.g. "GET /collatz/27?stats=true HTTP/1.1"
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    query: Vec<(String, String)>,
+    headers: Vec<(String, String)>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn query_flag(&self, key: &str) -> bool {
+        self.query
+            .iter()
+            .any(|(k, v)| k == key && matches!(v.as_str(), "" | "true" | "1" | "yes"))
+    }
+}
+
+pub fn read_request(reader: &mut impl BufRead) -> io::Result<Option<Request>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    let mut headers = Vec::new();
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && header.trim_end() != "" {
+        if let Some((name, value)) = header.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+        header.clear();
+    }
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Ok(None);
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    Ok(Some(Request {
+        method: method.to_string(),
+        path: percent_decode(path),
+        query: query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (percent_decode(key), percent_decode(value))
+            })
+            .collect(),
+        headers,
+    }))
+}
+
+// Decode %XX escapes (and '+' as space) of a URL component
+pub fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match (escaped, bytes[i]) {
+            (Some(byte), _) => {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+            (None, b'+') => decoded.push(b' '),
+            (None, byte) => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+pub fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &str,
+) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+pub fn error(stream: &mut TcpStream, status: &str, message: &str) -> io::Result<()> {
+    let body = format!("{{\"error\":{}}}\n", json_string(message));
+    respond(stream, status, "application/json", &body)
+}
+
+fn handle(mut stream: TcpStream, state: &State) -> io::Result<()> {
+    let Some(request) = read_request(&mut BufReader::new(stream.try_clone()?))? else {
+        return Ok(());
+    };
+    info!("{} {}", request.method, request.path);
+    if request.method != "GET" {
+        return error(
+            &mut stream,
+            "405 Method Not Allowed",
+            "Only GET is supported",
+        );
+    }
+    if request.path == "/metrics" {
+        return respond(
+            &mut stream,
+            "200 OK",
+            metrics::CONTENT_TYPE,
+            &METRICS.render(),
+        );
+    }
+    let (seed, websocket) = match request.path.strip_prefix("/ws/collatz/") {
+        Some(seed) => (seed, true),
+        None => match request.path.strip_prefix("/collatz/") {
+            Some(seed) => (seed, false),
+            None => {
+                return error(
+                    &mut stream,
+                    "404 Not Found",
+                    "Unknown path. Use /collatz/{seed}, /collatz/{seed}?stats=true, /ws/collatz/{seed} or /metrics",
+                )
+            }
+        },
+    };
+    let max_bits = state.limits.max_bits.unwrap_or(u64::MAX);
+    let start = match crate::parse_input(seed.to_string(), max_bits) {
+        Ok(start) => start,
+        Err(err) => {
+            METRICS.record_invalid();
+            return error(&mut stream, "400 Bad Request", &err);
+        }
+    };
+
+    if websocket {
+        let Some(key) = request
+            .header("Upgrade")
+            .filter(|upgrade| upgrade.eq_ignore_ascii_case("websocket"))
+            .and(request.header("Sec-WebSocket-Key"))
+        else {
+            return error(
+                &mut stream,
+                "426 Upgrade Required",
+                "/ws/collatz/{seed} needs a WebSocket handshake",
+            );
+        };
+        websocket::accept(&mut stream, key)?;
+        return stream_events(&mut stream, seed, &start, &state.limits);
+    }
+
+    if request.query_flag("stats") {
+        let row = stats::trajectory(seed.to_string(), &start, &state.limits, Map::Standard);
+        METRICS.record(&row);
+        let mut body = Vec::new();
+        batch::write_row(&mut body, &row, StatsFormat::Json, &state.number_format)?;
+        return respond(
+            &mut stream,
+            "200 OK",
+            "application/json",
+            &String::from_utf8_lossy(&body),
+        );
+    }
+
+    // stream the trajectory with chunked transfer encoding while it is computed
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nTransfer-Encoding: chunked\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n"
+    )?;
+    let mut body = BufWriter::with_capacity(64 << 10, Chunked(&mut stream));
+    DigitWriter::default().write_line(&start, &mut body)?;
+    let generated = collatz(start, &mut body, Generated::default(), &state.limits);
+    METRICS.record_steps(generated.steps, generated.truncated.is_some());
+    if let Some(truncation) = generated.truncated {
+        writeln!(body, "# truncated: {}", truncation)?;
+    }
+    body.flush()?;
+    drop(body);
+    stream.write_all(b"0\r\n\r\n")
+}
+
+// Send one text message per step, {"step":1,"bits":5,"parity":0}, then a summary message and a
+// close frame. A client that goes away cancels the computation.
+fn stream_events(
+    stream: &mut TcpStream,
+    seed: &str,
+    start: &BigInt,
+    limits: &Limits,
+) -> io::Result<()> {
+    let mut events = BufWriter::with_capacity(64 << 10, &mut *stream);
+    let mut failure = None;
+    let mut step = 0usize;
+    let mut last_flush = Instant::now();
+    let row = stats::visit_trajectory(seed.to_string(), start, limits, Map::Standard, |n| {
+        step += 1;
+        let event = format!(
+            "{{\"step\":{},\"bits\":{},\"parity\":{}}}",
+            step,
+            n.bits(),
+            u8::from(n.bit(0))
+        );
+        // flush regularly so that slow computations still show progress
+        let result = websocket::write_text(&mut events, &event).and_then(|()| {
+            if last_flush.elapsed() >= EVENT_FLUSH_INTERVAL {
+                last_flush = Instant::now();
+                events.flush()?;
+            }
+            Ok(())
+        });
+        match result {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(err) => {
+                failure = Some(err);
+                ControlFlow::Break(())
+            }
+        }
+    });
+    METRICS.record(&row);
+    if let Some(err) = failure {
+        return Err(err);
+    }
+    let truncated = match &row.truncated {
+        Some(truncation) => json_string(&truncation.to_string()),
+        None => "null".to_string(),
+    };
+    let summary = format!(
+        "{{\"done\":true,\"stopping_time\":{},\"max_bits\":{},\"max_index\":{},\"truncated\":{}}}",
+        row.stopping_time,
+        row.max_value.bits(),
+        row.max_index,
+        truncated
+    );
+    websocket::write_text(&mut events, &summary)?;
+    websocket::write_close(&mut events)?;
+    events.flush()
+}
+
+// Writer that frames everything written as HTTP/1.1 chunks
+struct Chunked<W>(W);
+
+impl<W: Write> Write for Chunked<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        write!(self.0, "{:x}\r\n", buf.len())?;
+        self.0.write_all(buf)?;
+        self.0.write_all(b"\r\n")?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
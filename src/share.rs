@@ -0,0 +1,120 @@
+use crate::websocket::{base64, unbase64};
+use collatz_bigint_file::hash;
+
+// Version of the layout, the first byte of a share string
+const VERSION: u8 = 1;
+
+// Bytes of the trajectory hash kept in a share string, 128 of its 256 bits
+const DIGEST_LEN: usize = 16;
+
+/// A result as passed on with `--share`: the seed as given and what its trajectory must yield.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Shared {
+    pub seed: String,
+    pub steps: usize,
+    pub max_index: usize,
+    pub max_bits: u64,
+    // leading bytes of the trajectory hash
+    pub digest: [u8; DIGEST_LEN],
+}
+
+impl Shared {
+    /// The result of a complete run, None if its hash is unknown.
+    pub fn new(
+        seed: &str,
+        steps: usize,
+        max_index: usize,
+        max_bits: u64,
+        hash: &str,
+    ) -> Option<Shared> {
+        Some(Shared {
+            seed: seed.trim().to_string(),
+            steps,
+            max_index,
+            max_bits,
+            digest: digest(hash)?,
+        })
+    }
+
+    /// Whether `hash`, printed like "sha256:...", starts with the shared digest.
+    pub fn matches(&self, hash: &str) -> bool {
+        digest(hash) == Some(self.digest)
+    }
+
+    /// The share string: base64 without padding of the version, the steps, max position and
+    /// max bits as LEB128 numbers, the digest and the seed expression.
+    pub fn encode(&self) -> String {
+        let mut bytes = vec![VERSION];
+        for number in [self.steps as u64, self.max_index as u64, self.max_bits] {
+            push_number(&mut bytes, number);
+        }
+        bytes.extend_from_slice(&self.digest);
+        bytes.extend_from_slice(self.seed.as_bytes());
+        base64(&bytes).trim_end_matches('=').to_string()
+    }
+
+    /// Reads a share string written by [`Shared::encode`].
+    pub fn decode(text: &str) -> Result<Shared, String> {
+        let invalid = |reason: &str| format!("Invalid share string: {}", reason);
+        let bytes = unbase64(text.trim()).ok_or_else(|| invalid("not base64"))?;
+        let (&version, mut rest) = bytes.split_first().ok_or_else(|| invalid("empty"))?;
+        if version != VERSION {
+            return Err(invalid(&format!(
+                "version {} is not known, this program reads version {}",
+                version, VERSION
+            )));
+        }
+        let mut numbers = [0u64; 3];
+        for number in &mut numbers {
+            *number = take_number(&mut rest).ok_or_else(|| invalid("cut off"))?;
+        }
+        if rest.len() < DIGEST_LEN {
+            return Err(invalid("cut off"));
+        }
+        let (digest, seed) = rest.split_at(DIGEST_LEN);
+        let seed = std::str::from_utf8(seed).map_err(|_| invalid("the seed is not UTF-8"))?;
+        if seed.trim().is_empty() {
+            return Err(invalid("no seed"));
+        }
+        let too_large = |_| invalid("a count is too large");
+        Ok(Shared {
+            seed: seed.to_string(),
+            steps: usize::try_from(numbers[0]).map_err(too_large)?,
+            max_index: usize::try_from(numbers[1]).map_err(too_large)?,
+            max_bits: numbers[2],
+            digest: digest.try_into().expect("split at the digest length"),
+        })
+    }
+}
+
+// Leading bytes of a hash printed like "sha256:...", None if it is not one
+fn digest(hash: &str) -> Option<[u8; DIGEST_LEN]> {
+    let hex = hash.trim().strip_prefix(hash::PREFIX)?;
+    let mut digest = [0; DIGEST_LEN];
+    for (i, byte) in digest.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(2 * i..2 * i + 2)?, 16).ok()?;
+    }
+    Some(digest)
+}
+
+// Seven bits per byte, the lowest first, with the top bit set on all but the last
+fn push_number(bytes: &mut Vec<u8>, mut number: u64) {
+    while number >= 0x80 {
+        bytes.push(number as u8 | 0x80);
+        number >>= 7;
+    }
+    bytes.push(number as u8);
+}
+
+fn take_number(bytes: &mut &[u8]) -> Option<u64> {
+    let mut number = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = bytes.split_first()?;
+        *bytes = rest;
+        number |= u64::from(byte & 0x7f).checked_shl(shift)?;
+        if byte & 0x80 == 0 {
+            return Some(number);
+        }
+    }
+    None
+}
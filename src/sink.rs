@@ -0,0 +1,956 @@
+use crate::drift::{self, DriftAnalysis};
+use crate::metadata;
+use crate::parity::ParityAnalysis;
+use crate::parity_vector::ParityVector;
+use crate::parts::PartWriter;
+use crate::scan;
+use crate::sonify::Sonification;
+use collatz_bigint_file::hash::Sha256;
+use collatz_bigint_file::info;
+use collatz_bigint_file::sequence::{expected_steps, Map};
+use collatz_bigint_file::stats::{PowerOfTwoEntry, Valuations};
+use num_bigint::BigInt;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, BufWriter, Write};
+use std::net::TcpStream;
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Destination of the sequence written by a run.
+///
+/// Only the file sink can be read back for the analysis pass. For the others the statistics and
+/// the hash are collected while the sequence is written, see [`Tally`].
+pub trait SequenceSink: Write {
+    /// Flushes the output and returns the path to read the sequence back from, None if the
+    /// sink cannot be read back.
+    fn finish(self: Box<Self>) -> io::Result<Option<PathBuf>>;
+
+    /// Bytes of the sequence so far, with the lines a wrapper still holds back; 0 for the sinks
+    /// other than files, which `--max-disk` does not apply to.
+    fn size(&self) -> u64;
+
+    /// The format a sink switched to during the run to stay within `--max-disk`, e.g. "only the
+    /// odd terms from step 43", None if it kept the format it started with.
+    fn fallback(&self) -> Option<String> {
+        None
+    }
+}
+
+impl SequenceSink for PartWriter {
+    fn finish(self: Box<Self>) -> io::Result<Option<PathBuf>> {
+        PartWriter::finish(*self).map(Some)
+    }
+
+    fn size(&self) -> u64 {
+        self.total()
+    }
+}
+
+// stdout and sockets
+impl<W: Write> SequenceSink for BufWriter<W> {
+    fn finish(mut self: Box<Self>) -> io::Result<Option<PathBuf>> {
+        self.flush()?;
+        Ok(None)
+    }
+
+    fn size(&self) -> u64 {
+        0
+    }
+}
+
+impl SequenceSink for io::Sink {
+    fn finish(self: Box<Self>) -> io::Result<Option<PathBuf>> {
+        Ok(None)
+    }
+
+    fn size(&self) -> u64 {
+        0
+    }
+}
+
+// Where the sequence goes, selected with --sink
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Sink {
+    // the sequence file in --output-dir, split by --max-part-size
+    #[default]
+    File,
+    Stdout,
+    // "host:port"
+    Tcp(String),
+    Unix(PathBuf),
+    // discard the values, e.g. to measure the computation alone
+    Null,
+}
+
+impl FromStr for Sink {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.trim() {
+            "file" => Ok(Sink::File),
+            "stdout" | "-" => Ok(Sink::Stdout),
+            "null" => Ok(Sink::Null),
+            other => {
+                if let Some(address) = other.strip_prefix("tcp://") {
+                    Ok(Sink::Tcp(address.to_string()))
+                } else if let Some(path) = other.strip_prefix("unix://") {
+                    Ok(Sink::Unix(PathBuf::from(path)))
+                } else {
+                    Err(format!(
+                        "Unknown sink '{}'. Use file, stdout, null, tcp://host:port or unix:///path",
+                        other
+                    ))
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Sink {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Sink::File => write!(f, "file"),
+            Sink::Stdout => write!(f, "stdout"),
+            Sink::Tcp(address) => write!(f, "tcp://{}", address),
+            Sink::Unix(path) => write!(f, "unix://{}", path.display()),
+            Sink::Null => write!(f, "null"),
+        }
+    }
+}
+
+// Function to open one of the sinks that are not a file; files are created by `def_output`
+pub fn open(sink: &Sink, buffer_size: usize) -> Result<Box<dyn SequenceSink>, String> {
+    match sink {
+        Sink::File => unreachable!("file sinks are created with the output path"),
+        Sink::Stdout => Ok(Box::new(BufWriter::with_capacity(
+            buffer_size,
+            io::stdout(),
+        ))),
+        Sink::Null => Ok(Box::new(io::sink())),
+        Sink::Tcp(address) => {
+            let stream = TcpStream::connect(address)
+                .map_err(|err| format!("Failed to connect to {}: {}", sink, err))?;
+            Ok(Box::new(BufWriter::with_capacity(buffer_size, stream)))
+        }
+        #[cfg(unix)]
+        Sink::Unix(path) => {
+            let stream = UnixStream::connect(path)
+                .map_err(|err| format!("Failed to connect to {}: {}", sink, err))?;
+            Ok(Box::new(BufWriter::with_capacity(buffer_size, stream)))
+        }
+        #[cfg(not(unix))]
+        Sink::Unix(_) => Err("Unix sockets are not supported on this platform".to_string()),
+    }
+}
+
+/// Writer that passes the values of a sequence through to a sink while hashing them and
+/// counting the odd ones and their valuations, for sinks that cannot be read back.
+pub struct Tally<'a, W> {
+    inner: W,
+    hasher: Sha256,
+    odd: usize,
+    valuations: Valuations,
+    parity: Option<ParityAnalysis>,
+    drift: Option<DriftAnalysis>,
+    parities: Option<&'a mut ParityVector>,
+    sonification: Option<Sonification>,
+    // decimal digits of the seed until a value drops below it, at the glide
+    seed: Vec<u8>,
+    steps: usize,
+    glide: Option<usize>,
+    power_of_two: PowerOfTwoEntry,
+    // digits of the current line, only kept for the drift analysis, the sonification and the glide
+    line: Vec<u8>,
+    // last byte written, a digit right before a newline decides the parity of the value
+    last: u8,
+}
+
+// Statistics collected by a `Tally`
+#[derive(Default)]
+pub struct Tallied {
+    pub odd: usize,
+    // hash of the trajectory
+    pub hash: String,
+    pub valuations: Valuations,
+    pub parity: Option<ParityAnalysis>,
+    pub drift: Option<DriftAnalysis>,
+    // stopping time below the seed
+    pub glide: Option<usize>,
+    pub power_of_two: PowerOfTwoEntry,
+    pub sonification: Option<Sonification>,
+}
+
+impl<'a, W: Write> Tally<'a, W> {
+    // the canonical encoding of the trajectory starts with the seed
+    pub fn new(
+        inner: W,
+        seed: Option<&BigInt>,
+        parity: Option<ParityAnalysis>,
+        drift: Option<DriftAnalysis>,
+        parities: Option<&'a mut ParityVector>,
+        sonification: Option<Sonification>,
+    ) -> Self {
+        let mut hasher = Sha256::default();
+        if let Some(seed) = seed {
+            hasher.update(seed.to_string().as_bytes());
+            hasher.update(b"\n");
+        }
+        Tally {
+            inner,
+            hasher,
+            odd: 0,
+            valuations: Valuations::new(seed.is_some_and(|seed| seed.bit(0))),
+            parity,
+            drift,
+            parities,
+            sonification,
+            seed: seed.map_or(Vec::new(), |seed| seed.to_string().into_bytes()),
+            steps: 0,
+            glide: None,
+            power_of_two: PowerOfTwoEntry::new(seed.map(|seed| seed.bit(0))),
+            line: Vec::new(),
+            last: b'\n',
+        }
+    }
+
+    pub fn finish(self) -> Tallied {
+        Tallied {
+            odd: self.odd,
+            hash: self.hasher.finish_hex(),
+            valuations: self.valuations,
+            parity: self.parity,
+            drift: self.drift,
+            glide: self.glide,
+            power_of_two: self.power_of_two,
+            sonification: self.sonification,
+        }
+    }
+}
+
+impl<W: Write> Write for Tally<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        let buf = &buf[..written];
+        self.hasher.update(buf);
+        for &byte in buf {
+            if byte == b'\n' {
+                let odd = matches!(self.last, b'1' | b'3' | b'5' | b'7' | b'9');
+                if odd {
+                    self.odd += 1;
+                }
+                self.valuations.push(!odd);
+                self.power_of_two.push(!odd);
+                if let Some(parity) = self.parity.as_mut() {
+                    parity.push(!odd);
+                }
+                if let Some(drift) = self.drift.as_mut() {
+                    drift.push(drift::log2_digits(&self.line, 10));
+                }
+                if let Some(parities) = self.parities.as_mut() {
+                    parities.push(!odd)?;
+                }
+                if let Some(sonification) = self.sonification.as_mut() {
+                    sonification.push(drift::log2_digits(&self.line, 10), !odd);
+                }
+                self.steps += 1;
+                if !self.seed.is_empty() && scan::compare(&self.line, &self.seed).is_lt() {
+                    self.glide = Some(self.steps);
+                    self.seed.clear();
+                }
+                self.line.clear();
+            } else if self.drift.is_some() || self.sonification.is_some() || !self.seed.is_empty() {
+                self.line.push(byte);
+            }
+            self.last = byte;
+        }
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Sink wrapper keeping only the odd terms of a sequence (the Syracuse subsequence).
+///
+/// Every value line becomes `<halvings> <odd term>`: the number of halvings since the previous
+/// odd term (or the seed), then the odd term. Replaying the trajectory from the seed of the
+/// header, apply 3n+1 if n is odd, then halve `halvings` times to get the odd term, so the full
+/// sequence can always be reconstructed. Halvings after the last odd term of a truncated
+/// sequence are kept in a `# trailing halvings` line (the value after the last odd term counts as
+/// its 3n+1 step). Comment lines pass through unchanged.
+pub struct Condensed {
+    inner: Box<dyn SequenceSink>,
+    line: Vec<u8>,
+    halvings: u64,
+    // whether values were written after the last odd term
+    pending: bool,
+    // whether the previous value was even, so that the current one is a halving of it
+    previous_even: bool,
+}
+
+impl Condensed {
+    pub fn new(inner: Box<dyn SequenceSink>, seed: &BigInt) -> Self {
+        Condensed::after(inner, !seed.bit(0))
+    }
+
+    // Condensed output that takes over after a value written in full, even or odd as given; the
+    // halvings count from that value
+    pub fn after(inner: Box<dyn SequenceSink>, previous_even: bool) -> Self {
+        Condensed {
+            inner,
+            line: Vec::new(),
+            halvings: 0,
+            pending: false,
+            previous_even,
+        }
+    }
+
+    pub fn into_inner(self) -> Box<dyn SequenceSink> {
+        self.inner
+    }
+
+    fn end_line(&mut self) -> io::Result<()> {
+        if self.line.first() == Some(&b'#') {
+            if self.pending {
+                writeln!(self.inner, "# trailing halvings: {}", self.halvings)?;
+                self.halvings = 0;
+                self.pending = false;
+            }
+            self.inner.write_all(&self.line)?;
+        } else {
+            if self.previous_even {
+                self.halvings += 1;
+            }
+            // the line ends with its newline
+            let even = !matches!(
+                self.line.iter().rev().nth(1),
+                Some(b'1' | b'3' | b'5' | b'7' | b'9')
+            );
+            if even {
+                self.pending = true;
+            } else {
+                write!(self.inner, "{} ", self.halvings)?;
+                self.inner.write_all(&self.line)?;
+                self.halvings = 0;
+                self.pending = false;
+            }
+            self.previous_even = even;
+        }
+        self.line.clear();
+        Ok(())
+    }
+}
+
+impl Write for Condensed {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for part in buf.split_inclusive(|&byte| byte == b'\n') {
+            self.line.extend_from_slice(part);
+            if part.ends_with(b"\n") {
+                self.end_line()?;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl SequenceSink for Condensed {
+    fn finish(self: Box<Self>) -> io::Result<Option<PathBuf>> {
+        self.inner.finish()
+    }
+
+    fn size(&self) -> u64 {
+        self.inner.size()
+    }
+}
+
+/// Sink wrapper decimating a sequence for plotting.
+///
+/// Every value line becomes `<step> <value>` and only some are kept: every k-th term, the largest
+/// term of each window of k terms so that no peak is lost, and the last k terms at full
+/// resolution for the final descent. The last k lines are held back until the footer shows
+/// where the sequence ends; a truncated sequence has no final descent and its last k terms are
+/// sampled like the others. Comment lines pass through unchanged.
+pub struct Sampled {
+    inner: Box<dyn SequenceSink>,
+    every: u64,
+    line: Vec<u8>,
+    step: u64,
+    // whether the last `every` values are written in full
+    keep_tail: bool,
+    // the last `every` values with their steps, not decided yet
+    tail: VecDeque<(u64, Vec<u8>)>,
+    // largest value of the current window, written when the window ends
+    window_max: Option<(u64, Vec<u8>)>,
+    // bytes of the lines of the tail and the window maximum, once written
+    held: u64,
+}
+
+impl Sampled {
+    pub fn new(inner: Box<dyn SequenceSink>, every: u64) -> Self {
+        Sampled {
+            inner,
+            every,
+            line: Vec::new(),
+            step: 0,
+            keep_tail: true,
+            tail: VecDeque::new(),
+            window_max: None,
+            held: 0,
+        }
+    }
+
+    // A sample that takes over after `step` values were written otherwise, for --max-disk. The
+    // last values are sampled like the others: a budget that needs a sample cannot hold them
+    pub fn from_step(inner: Box<dyn SequenceSink>, every: u64, step: u64) -> Self {
+        Sampled {
+            step,
+            keep_tail: false,
+            ..Sampled::new(inner, every)
+        }
+    }
+
+    pub fn into_inner(self) -> Box<dyn SequenceSink> {
+        self.inner
+    }
+
+    fn end_line(&mut self) -> io::Result<()> {
+        if self.line.first() == Some(&b'#') {
+            if self.line.starts_with(b"# truncated:") {
+                while let Some((step, value)) = self.pop_tail() {
+                    self.sample(step, value)?;
+                }
+            }
+            if let Some((step, value)) = self.take_window_max() {
+                self.write_value(step, &value)?;
+            }
+            while let Some((step, value)) = self.pop_tail() {
+                self.write_value(step, &value)?;
+            }
+            self.inner.write_all(&self.line)?;
+            self.line.clear();
+        } else {
+            self.step += 1;
+            let value = std::mem::take(&mut self.line);
+            if self.keep_tail {
+                self.held += line_size(self.step, &value);
+                self.tail.push_back((self.step, value));
+                if self.tail.len() as u64 > self.every {
+                    let (step, value) = self.pop_tail().expect("the tail is not empty");
+                    self.sample(step, value)?;
+                }
+            } else {
+                self.sample(self.step, value)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn pop_tail(&mut self) -> Option<(u64, Vec<u8>)> {
+        let (step, value) = self.tail.pop_front()?;
+        self.held -= line_size(step, &value);
+        Some((step, value))
+    }
+
+    fn take_window_max(&mut self) -> Option<(u64, Vec<u8>)> {
+        let (step, value) = self.window_max.take()?;
+        self.held -= line_size(step, &value);
+        Some((step, value))
+    }
+
+    // Function to keep a value that left the tail if it is a sample point or its window's maximum
+    fn sample(&mut self, step: u64, value: Vec<u8>) -> io::Result<()> {
+        let larger = match &self.window_max {
+            // values are written without leading zeros: more digits means larger
+            Some((_, max)) => (value.len(), &value) > (max.len(), max),
+            None => true,
+        };
+        if step.is_multiple_of(self.every) {
+            if let Some((max_step, max)) = self.take_window_max() {
+                if !larger {
+                    self.write_value(max_step, &max)?;
+                }
+            }
+            self.write_value(step, &value)
+        } else {
+            if larger {
+                self.take_window_max();
+                self.held += line_size(step, &value);
+                self.window_max = Some((step, value));
+            }
+            Ok(())
+        }
+    }
+
+    fn write_value(&mut self, step: u64, value: &[u8]) -> io::Result<()> {
+        write!(self.inner, "{} ", step)?;
+        self.inner.write_all(value)
+    }
+}
+
+// Bytes of the line `<step> <value>`, the value with its newline
+fn line_size(step: u64, value: &[u8]) -> u64 {
+    u64::from(step.checked_ilog10().unwrap_or(0)) + 2 + value.len() as u64
+}
+
+impl Write for Sampled {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for part in buf.split_inclusive(|&byte| byte == b'\n') {
+            self.line.extend_from_slice(part);
+            if part.ends_with(b"\n") {
+                self.end_line()?;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl SequenceSink for Sampled {
+    fn finish(self: Box<Self>) -> io::Result<Option<PathBuf>> {
+        self.inner.finish()
+    }
+
+    fn size(&self) -> u64 {
+        self.inner.size() + self.held
+    }
+}
+
+/// Sink wrapper writing the values of a sequence in another base, e.g. binary or hexadecimal.
+///
+/// The last number of every value line is converted, so it also works below [`Condensed`] and
+/// [`Sampled`], which put a count or a step in front. Comment lines pass through unchanged.
+pub struct Radix {
+    inner: Box<dyn SequenceSink>,
+    radix: u32,
+    line: Vec<u8>,
+}
+
+impl Radix {
+    pub fn new(inner: Box<dyn SequenceSink>, radix: u32) -> Self {
+        Radix {
+            inner,
+            radix,
+            line: Vec::new(),
+        }
+    }
+
+    fn end_line(&mut self) -> io::Result<()> {
+        let line = self.line.trim_ascii_end();
+        let start = line
+            .iter()
+            .rposition(|&byte| byte == b' ')
+            .map_or(0, |i| i + 1);
+        match BigInt::parse_bytes(&line[start..], 10) {
+            Some(value) if line.first() != Some(&b'#') => {
+                self.inner.write_all(&line[..start])?;
+                writeln!(self.inner, "{}", value.to_str_radix(self.radix))?;
+            }
+            _ => self.inner.write_all(&self.line)?,
+        }
+        self.line.clear();
+        Ok(())
+    }
+}
+
+impl Write for Radix {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for part in buf.split_inclusive(|&byte| byte == b'\n') {
+            self.line.extend_from_slice(part);
+            if part.ends_with(b"\n") {
+                self.end_line()?;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl SequenceSink for Radix {
+    fn finish(self: Box<Self>) -> io::Result<Option<PathBuf>> {
+        self.inner.finish()
+    }
+
+    fn size(&self) -> u64 {
+        self.inner.size()
+    }
+}
+
+/// Sink wrapper that annotates every value line with its step and the rule that produced it,
+/// e.g. `77: 3n+1 -> 9232` or `78: /2 -> 4616`. Readers take the value after the last `-> `.
+pub struct Annotated {
+    inner: Box<dyn SequenceSink>,
+    line: Vec<u8>,
+    step: usize,
+    // whether the value before the current line is odd, so 3n+1 produced it
+    previous_odd: bool,
+}
+
+impl Annotated {
+    // `step` values are already written, the last of them (or the seed) has parity `last_is_odd`
+    pub fn new(inner: Box<dyn SequenceSink>, step: usize, last_is_odd: bool) -> Self {
+        Annotated {
+            inner,
+            line: Vec::new(),
+            step,
+            previous_odd: last_is_odd,
+        }
+    }
+
+    pub fn into_inner(self) -> Box<dyn SequenceSink> {
+        self.inner
+    }
+
+    fn end_line(&mut self) -> io::Result<()> {
+        let value = self.line.trim_ascii_end();
+        if value.first() == Some(&b'#') || value.is_empty() {
+            self.inner.write_all(&self.line)?;
+        } else {
+            self.step += 1;
+            let rule = if self.previous_odd { "3n+1" } else { "/2" };
+            write!(self.inner, "{}: {} -> ", self.step, rule)?;
+            self.inner.write_all(&self.line)?;
+            self.previous_odd = matches!(value.last(), Some(b'1' | b'3' | b'5' | b'7' | b'9'));
+        }
+        self.line.clear();
+        Ok(())
+    }
+}
+
+impl Write for Annotated {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for part in buf.split_inclusive(|&byte| byte == b'\n') {
+            self.line.extend_from_slice(part);
+            if part.ends_with(b"\n") {
+                self.end_line()?;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl SequenceSink for Annotated {
+    fn finish(self: Box<Self>) -> io::Result<Option<PathBuf>> {
+        self.inner.finish()
+    }
+
+    fn size(&self) -> u64 {
+        self.inner.size()
+    }
+}
+
+// Bytes kept free for the footer besides its max value, and for the lines that mark a switch
+const FOOTER_BYTES: u64 = 100;
+const SWITCH_BYTES: u64 = 80;
+
+/// Values a sequence file holds, from all of them to none; `--max-disk` moves down this list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Coverage {
+    /// Every value, with its step and rule if `annotated`.
+    All { annotated: bool },
+    /// The odd terms with the halvings before them, see [`Condensed`].
+    OddTerms,
+    /// Every k-th value and the largest of every k, see [`Sampled`].
+    Sample(u64),
+    /// No more values, only the footer.
+    None,
+}
+
+impl Coverage {
+    // The next coverage to try if this one does not fit, None after the last
+    fn thinner(self, steps_left: f64) -> Option<Coverage> {
+        match self {
+            Coverage::All { .. } => Some(Coverage::OddTerms),
+            // the first sample that keeps fewer lines than the odd terms
+            Coverage::OddTerms => Some(Coverage::Sample(8)),
+            // a sample of more than the steps left writes at most one more line
+            Coverage::Sample(every) if (every as f64) < steps_left => {
+                Some(Coverage::Sample(every.saturating_mul(2)))
+            }
+            Coverage::Sample(_) => Some(Coverage::None),
+            Coverage::None => None,
+        }
+    }
+}
+
+impl fmt::Display for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Coverage::All { annotated: false } => write!(f, "every value"),
+            Coverage::All { annotated: true } => write!(f, "every value with its step and rule"),
+            Coverage::OddTerms => write!(f, "only the odd terms"),
+            Coverage::Sample(every) => write!(f, "every {}th value", every),
+            Coverage::None => write!(f, "no more values"),
+        }
+    }
+}
+
+// The wrapper writing the values of a coverage, over the file and its --radix conversion
+enum Stage {
+    All(Box<dyn SequenceSink>),
+    Annotated(Annotated),
+    OddTerms(Condensed),
+    Sample(Sampled),
+    // drops the value lines, passes the comments
+    None(Box<dyn SequenceSink>),
+}
+
+impl Stage {
+    fn sink(&mut self) -> &mut dyn SequenceSink {
+        match self {
+            Stage::All(sink) | Stage::None(sink) => sink.as_mut(),
+            Stage::Annotated(annotated) => annotated,
+            Stage::OddTerms(condensed) => condensed,
+            Stage::Sample(sampled) => sampled,
+        }
+    }
+
+    fn size(&self) -> u64 {
+        match self {
+            Stage::All(sink) | Stage::None(sink) => sink.size(),
+            Stage::Annotated(annotated) => annotated.size(),
+            Stage::OddTerms(condensed) => condensed.size(),
+            Stage::Sample(sampled) => sampled.size(),
+        }
+    }
+
+    fn into_sink(self) -> Box<dyn SequenceSink> {
+        match self {
+            Stage::All(sink) | Stage::None(sink) => sink,
+            Stage::Annotated(annotated) => Box::new(annotated),
+            Stage::OddTerms(condensed) => Box::new(condensed),
+            Stage::Sample(sampled) => Box::new(sampled),
+        }
+    }
+
+    fn into_base(self) -> Box<dyn SequenceSink> {
+        match self {
+            Stage::All(sink) | Stage::None(sink) => sink,
+            Stage::Annotated(annotated) => annotated.into_inner(),
+            Stage::OddTerms(condensed) => condensed.into_inner(),
+            Stage::Sample(sampled) => sampled.into_inner(),
+        }
+    }
+}
+
+/// Sink wrapper keeping a new sequence file within `--max-disk`.
+///
+/// The format is chosen before the run from an estimate, which single trajectories can exceed
+/// by far. Before each value this checks the bytes written so far, with the lines the wrappers
+/// hold back, plus what the drift model expects for the rest of the trajectory from the current
+/// value and room for the footer. If that exceeds the budget, the file goes on with only the odd
+/// terms from this value on, then with a sample of every k-th value, k doubling as often as
+/// needed, and once not even that fits, with no more values. Each switch is marked by a
+/// `# max disk:` line with the step, followed by the `# terms` or `# sample every` line of the
+/// new format. The computation itself runs to the end, so the footer holds the whole trajectory.
+/// Expects decimal lines; the conversion to `--radix` goes below it.
+pub struct Budgeted {
+    // None only during a switch
+    stage: Option<Stage>,
+    coverage: Coverage,
+    budget: u64,
+    // digits in the base of the file per decimal digit
+    digit_ratio: f64,
+    max_steps: Option<u64>,
+    line: Vec<u8>,
+    step: u64,
+    previous_even: bool,
+    // digits of the longest value so far, for the max value line of the footer
+    max_digits: u64,
+    // the coverages switched to, with the first step of each
+    switches: Vec<(u64, Coverage)>,
+}
+
+impl Budgeted {
+    pub fn new(
+        base: Box<dyn SequenceSink>,
+        coverage: Coverage,
+        seed: &BigInt,
+        budget: u64,
+        radix: u32,
+        max_steps: Option<usize>,
+    ) -> Self {
+        let stage = match coverage {
+            Coverage::All { annotated: false } => Stage::All(base),
+            Coverage::All { annotated: true } => {
+                Stage::Annotated(Annotated::new(base, 0, seed.bit(0)))
+            }
+            Coverage::OddTerms => Stage::OddTerms(Condensed::new(base, seed)),
+            Coverage::Sample(every) => Stage::Sample(Sampled::new(base, every)),
+            Coverage::None => Stage::None(base),
+        };
+        Budgeted {
+            stage: Some(stage),
+            coverage,
+            budget,
+            digit_ratio: std::f64::consts::LN_10 / f64::from(radix).ln(),
+            max_steps: max_steps.map(|steps| steps as u64),
+            line: Vec::new(),
+            step: 0,
+            previous_even: !seed.bit(0),
+            max_digits: 0,
+            switches: Vec::new(),
+        }
+    }
+
+    fn stage(&self) -> &Stage {
+        self.stage
+            .as_ref()
+            .expect("a stage is set outside of switches")
+    }
+
+    fn end_line(&mut self) -> io::Result<()> {
+        if self.line.first() != Some(&b'#') {
+            self.step += 1;
+            let digits = self.line.trim_ascii_end();
+            let (length, log2) = (digits.len() as u64, drift::log2_digits(digits, 10));
+            let even = !matches!(digits.last(), Some(b'1' | b'3' | b'5' | b'7' | b'9'));
+            self.max_digits = self.max_digits.max(length);
+            self.fit(length, log2)?;
+            self.previous_even = even;
+            if self.coverage == Coverage::None {
+                self.line.clear();
+                return Ok(());
+            }
+        }
+        self.stage
+            .as_mut()
+            .expect("a stage is set outside of switches")
+            .sink()
+            .write_all(&self.line)?;
+        self.line.clear();
+        Ok(())
+    }
+
+    // Switch to a thinner coverage if the rest of the file would not fit into the budget in the
+    // current one, for a value of `digits` decimal digits and `log2` bits at the current step.
+    // The new coverage has to fit into half of the room left, so the model has to be off by a
+    // factor of two before the next switch
+    fn fit(&mut self, digits: u64, log2: f64) -> io::Result<()> {
+        let mut steps_left = expected_steps(log2, Map::Standard).max(1.0);
+        if let Some(max_steps) = self.max_steps {
+            steps_left = steps_left.min((max_steps + 1).saturating_sub(self.step) as f64);
+        }
+        let used = self.stage().size() + FOOTER_BYTES + self.max_digits + SWITCH_BYTES;
+        let room = self.budget.saturating_sub(used) as f64;
+        if self.projected(self.coverage, digits, steps_left) <= room {
+            return Ok(());
+        }
+        let mut coverage = self.coverage;
+        while self.projected(coverage, digits, steps_left) > room / 2.0 {
+            match coverage.thinner(steps_left) {
+                Some(thinner) => coverage = thinner,
+                None => break,
+            }
+        }
+        self.switch(coverage)
+    }
+
+    // Bytes the drift model expects for `steps` more lines in `coverage` from a value of
+    // `digits` decimal digits, which falls linearly to 1, so half as long on average
+    fn projected(&self, coverage: Coverage, digits: u64, steps: f64) -> f64 {
+        let value = digits as f64 * self.digit_ratio / 2.0 + 1.0;
+        let step = (self.step as f64 + steps).log10().floor() + 1.0;
+        let (lines, prefix) = match coverage {
+            Coverage::All { annotated: false } => (steps, 0.0),
+            Coverage::All { annotated: true } => (steps, step + ": 3n+1 -> ".len() as f64),
+            Coverage::OddTerms => (steps / 3.0, "2 ".len() as f64),
+            Coverage::Sample(every) => (2.0 * steps / every as f64, step + 1.0),
+            Coverage::None => (0.0, 0.0),
+        };
+        lines * (value + prefix)
+    }
+
+    fn switch(&mut self, coverage: Coverage) -> io::Result<()> {
+        let mut stage = self
+            .stage
+            .take()
+            .expect("a stage is set outside of switches");
+        // a comment makes the wrappers write the lines they hold back
+        writeln!(
+            stage.sink(),
+            "# max disk: {} from step {}",
+            coverage,
+            self.step
+        )?;
+        let mut base = stage.into_base();
+        self.stage = Some(match coverage {
+            Coverage::OddTerms => {
+                writeln!(base, "# terms: {}", metadata::ODD_TERMS)?;
+                Stage::OddTerms(Condensed::after(base, self.previous_even))
+            }
+            Coverage::Sample(every) => {
+                writeln!(base, "# sample every: {}", every)?;
+                Stage::Sample(Sampled::from_step(base, every, self.step - 1))
+            }
+            Coverage::None => Stage::None(base),
+            Coverage::All { .. } => unreachable!("a switch only goes to a thinner coverage"),
+        });
+        info!(
+            "Writing {} from step {} to stay within --max-disk",
+            coverage, self.step
+        );
+        self.coverage = coverage;
+        self.switches.push((self.step, coverage));
+        Ok(())
+    }
+}
+
+impl Write for Budgeted {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for part in buf.split_inclusive(|&byte| byte == b'\n') {
+            self.line.extend_from_slice(part);
+            if part.ends_with(b"\n") {
+                self.end_line()?;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stage
+            .as_mut()
+            .expect("a stage is set outside of switches")
+            .sink()
+            .flush()
+    }
+}
+
+impl SequenceSink for Budgeted {
+    fn finish(self: Box<Self>) -> io::Result<Option<PathBuf>> {
+        self.stage
+            .expect("a stage is set outside of switches")
+            .into_sink()
+            .finish()
+    }
+
+    fn size(&self) -> u64 {
+        self.stage().size()
+    }
+
+    fn fallback(&self) -> Option<String> {
+        let switches: Vec<String> = self
+            .switches
+            .iter()
+            .map(|(step, coverage)| format!("{} from step {}", coverage, step))
+            .collect();
+        (!switches.is_empty()).then(|| switches.join(", then "))
+    }
+}
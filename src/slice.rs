@@ -0,0 +1,149 @@
+use crate::metadata;
+use crate::parts;
+use crate::scan::{self, Line, Scanner};
+use clap::ValueEnum;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+// Format of the extracted values
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum SliceFormat {
+    /// One `step N: value` line per value
+    #[default]
+    Text,
+    /// Comma separated `step,value` with a header line
+    Csv,
+    /// One JSON object `{"step":N,"value":V}` per line
+    Json,
+}
+
+/// Prints the values of steps `from` to `to` (both included, the first value after the seed is
+/// step 1) of a sequence file or the parts of a split sequence, in decimal, without computing
+/// anything.
+///
+/// Annotated files and files written with `--radix` are read as they are. Files that only hold
+/// the odd terms or a sample of the sequence have no value for every step and are refused. Reading
+/// stops after `to`.
+pub fn run(
+    path: &Path,
+    from: usize,
+    to: Option<usize>,
+    format: SliceFormat,
+    buffer_size: usize,
+) -> Result<(), String> {
+    if to.is_some_and(|to| to < from) {
+        return Err(format!(
+            "The window is empty: --to {} is before --from {}",
+            to.unwrap_or_default(),
+            from
+        ));
+    }
+    let read_error = |err: io::Error| format!("Failed to read {}: {}", path.display(), err);
+    let radix = header_radix(path, buffer_size)?;
+    let mut scanner =
+        Scanner::with_radix(parts::open(path, buffer_size).map_err(read_error)?, radix);
+
+    let stdout = io::stdout();
+    let mut output = io::BufWriter::new(stdout.lock());
+    let write_error = |err: io::Error| format!("Failed to write the slice: {}", err);
+    if format == SliceFormat::Csv {
+        writeln!(output, "step,value").map_err(write_error)?;
+    }
+    let mut step = 0;
+    let mut written = 0;
+    // comments after the first value are the footer, or mark where --max-disk thinned the file
+    let mut after_comment = false;
+    while let Some(line) = scanner.next_line().map_err(read_error)? {
+        if let Line::Comment = line {
+            after_comment = step > 0;
+            continue;
+        }
+        if after_comment {
+            return Err(format!(
+                "{} changes its format at line {} to stay within --max-disk and cannot be sliced past step {}",
+                path.display(),
+                scanner.line_num(),
+                step
+            ));
+        }
+        step += 1;
+        if step < from {
+            continue;
+        }
+        if to.is_some_and(|to| step > to) {
+            break;
+        }
+        let value = match line {
+            Line::Value(digits) if radix == 10 => String::from_utf8_lossy(digits).into_owned(),
+            Line::Value(digits) => scan::to_bigint_in(digits, radix).to_string(),
+            Line::Invalid(err) => {
+                return Err(format!(
+                    "Invalid value at step {} (line {}): {}",
+                    step,
+                    scanner.line_num(),
+                    err
+                ))
+            }
+            Line::Comment => unreachable!("comments are skipped above"),
+        };
+        match format {
+            SliceFormat::Text => writeln!(output, "step {}: {}", step, value),
+            SliceFormat::Csv => writeln!(output, "{},{}", step, value),
+            SliceFormat::Json => writeln!(output, "{{\"step\":{},\"value\":{}}}", step, value),
+        }
+        .map_err(write_error)?;
+        written += 1;
+    }
+    output.flush().map_err(write_error)?;
+    if written == 0 {
+        return Err(format!(
+            "{} ends at step {}, before step {}",
+            path.display(),
+            step,
+            from
+        ));
+    }
+    Ok(())
+}
+
+// Base of the values from the metadata header; errors for files without a value per step
+fn header_radix(path: &Path, buffer_size: usize) -> Result<u32, String> {
+    let reader = parts::open(path, buffer_size)
+        .map_err(|err| format!("Failed to open {}: {}", path.display(), err))?;
+    let mut radix = 10;
+    for line in reader.lines() {
+        let line = line.map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+        let Some((key, value)) = metadata::parse_comment(&line) else {
+            // the header ends at the first value
+            break;
+        };
+        match key {
+            "radix" => {
+                radix = value
+                    .parse()
+                    .map_err(|_| format!("Invalid radix '{}' in {}", value, path.display()))?
+            }
+            "sample every" => {
+                return Err(format!(
+                    "{} only holds a sample of the sequence (--sample-every) and cannot be sliced by step",
+                    path.display()
+                ))
+            }
+            "filter" => {
+                return Err(format!(
+                    "{} only holds the values kept by --filter {} and cannot be sliced by step",
+                    path.display(),
+                    value
+                ))
+            }
+            "terms" if value == metadata::ODD_TERMS => {
+                return Err(format!(
+                    "{} only holds the odd terms (--odd-only) and cannot be sliced by step",
+                    path.display()
+                ))
+            }
+            _ => {}
+        }
+    }
+    Ok(radix)
+}
@@ -0,0 +1,100 @@
+use crate::drift;
+use crate::output;
+use num_bigint::BigInt;
+use std::io::Write;
+use std::path::Path;
+
+// Notes kept at most; longer trajectories are cut off after them
+pub const MAX_NOTES: usize = 20_000;
+// Pitch range the values are spread over, C2 to C7
+const LOWEST_PITCH: f32 = 36.0;
+const HIGHEST_PITCH: f32 = 96.0;
+// Ticks per quarter note and length of every note, an eighth
+const TICKS_PER_QUARTER: u16 = 480;
+const NOTE_TICKS: u32 = 240;
+// General MIDI programs of the even and odd values: acoustic grand piano and marimba
+const EVEN_PROGRAM: u8 = 0;
+const ODD_PROGRAM: u8 = 12;
+const VELOCITY: u8 = 96;
+
+/// Hailstone sonification: one note per value of the trajectory, starting with the seed.
+///
+/// The pitch follows log2 n, spread over the range from C2 to C7 between 1 and the largest
+/// value, and the parity picks the instrument: even values on channel 1 (piano), odd values on
+/// channel 2 (marimba). The notes are collected while the trajectory is read and written as a
+/// standard MIDI file of format 0 at 120 beats per minute, two notes per beat.
+pub struct Sonification {
+    // log2 n and parity of every value
+    notes: Vec<(f32, bool)>,
+    // values after the first `MAX_NOTES`
+    dropped: u64,
+}
+
+impl Sonification {
+    pub fn new(seed: &BigInt) -> Self {
+        Sonification {
+            notes: vec![(drift::log2(seed) as f32, !seed.bit(0))],
+            dropped: 0,
+        }
+    }
+
+    pub fn push(&mut self, log2: f64, is_even: bool) {
+        if self.notes.len() < MAX_NOTES {
+            self.notes.push((log2 as f32, is_even));
+        } else {
+            self.dropped += 1;
+        }
+    }
+
+    // Number of values that did not fit into the sonification
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    pub fn write(&self, path: &Path, force: bool) -> Result<(), String> {
+        let mut file = output::create(path, force)?;
+        file.write_all(&self.to_midi())
+            .map_err(|err| format!("Failed to write {}: {}", path.display(), err))
+    }
+
+    fn to_midi(&self) -> Vec<u8> {
+        let highest = self.notes.iter().map(|(log2, _)| *log2).fold(1.0, f32::max);
+        let mut track = Vec::new();
+        // tempo of 500000 µs per quarter note, 120 bpm
+        track.extend_from_slice(&[0x00, 0xff, 0x51, 0x03, 0x07, 0xa1, 0x20]);
+        track.extend_from_slice(&[0x00, 0xc0, EVEN_PROGRAM, 0x00, 0xc1, ODD_PROGRAM]);
+        for &(log2, is_even) in &self.notes {
+            let channel = if is_even { 0 } else { 1 };
+            let pitch =
+                (LOWEST_PITCH + log2 / highest * (HIGHEST_PITCH - LOWEST_PITCH)).round() as u8;
+            track.extend_from_slice(&[0x00, 0x90 | channel, pitch, VELOCITY]);
+            write_variable_length(&mut track, NOTE_TICKS);
+            track.extend_from_slice(&[0x80 | channel, pitch, 0x40]);
+        }
+        // end of track
+        track.extend_from_slice(&[0x00, 0xff, 0x2f, 0x00]);
+
+        let mut midi = Vec::with_capacity(track.len() + 22);
+        midi.extend_from_slice(b"MThd");
+        midi.extend_from_slice(&6u32.to_be_bytes());
+        // format 0, one track
+        midi.extend_from_slice(&0u16.to_be_bytes());
+        midi.extend_from_slice(&1u16.to_be_bytes());
+        midi.extend_from_slice(&TICKS_PER_QUARTER.to_be_bytes());
+        midi.extend_from_slice(b"MTrk");
+        midi.extend_from_slice(&(track.len() as u32).to_be_bytes());
+        midi.extend_from_slice(&track);
+        midi
+    }
+}
+
+// Append a delta time as a MIDI variable-length quantity, 7 bits per byte, most significant first
+fn write_variable_length(output: &mut Vec<u8>, mut value: u32) {
+    let mut bytes = vec![(value & 0x7f) as u8];
+    value >>= 7;
+    while value > 0 {
+        bytes.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    output.extend(bytes.iter().rev());
+}
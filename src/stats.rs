@@ -0,0 +1,376 @@
+//! Trajectory statistics computed in memory: stopping time, glide, maximum, odd steps and the
+//! 2-adic valuations of the odd steps.
+
+use crate::sequence::{stop_condition, Limits, Map, Truncation};
+use crate::value::Value;
+use num_bigint::BigInt;
+use std::ops::ControlFlow;
+use std::time::Instant;
+
+/// Statistics of one trajectory, computed in memory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Row {
+    pub seed: String,
+    // total stopping time: steps until the trajectory reaches 1, or the steps computed if it
+    // was truncated
+    pub stopping_time: usize,
+    // stopping time in the narrow sense, the glide: steps until the trajectory first drops
+    // below the seed, None if it never does
+    pub glide: Option<usize>,
+    pub max_value: BigInt,
+    pub max_index: usize,
+    // odd values after the seed; the other steps are even values
+    pub odd: usize,
+    // halvings after each odd step, ν₂(3n+1)
+    pub valuations: Valuations,
+    pub truncated: Option<Truncation>,
+}
+
+impl Row {
+    /// The stopping time of the trajectory by one of the two definitions, None if it is unknown
+    /// because the computation was truncated first, or the trajectory never drops below its seed.
+    pub fn stopping_time_by(&self, definition: StoppingTime) -> Option<usize> {
+        match definition {
+            StoppingTime::Total => self.truncated.is_none().then_some(self.stopping_time),
+            StoppingTime::BelowSeed => self.glide,
+        }
+    }
+}
+
+/// The two definitions of the stopping time of a trajectory.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StoppingTime {
+    /// Steps until the trajectory reaches 1, the total stopping time (also called delay).
+    #[default]
+    Total,
+    /// Steps until the trajectory first drops below its seed, the stopping time in the narrow
+    /// sense (also called glide).
+    BelowSeed,
+}
+
+impl StoppingTime {
+    /// Label of the definition in reports, e.g. "total stopping time".
+    pub fn label(self) -> &'static str {
+        match self {
+            StoppingTime::Total => "total stopping time",
+            StoppingTime::BelowSeed => "stopping time below the seed (glide)",
+        }
+    }
+}
+
+impl std::str::FromStr for StoppingTime {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.trim() {
+            "total" | "delay" => Ok(StoppingTime::Total),
+            "below-seed" | "glide" => Ok(StoppingTime::BelowSeed),
+            other => Err(format!(
+                "Unknown stopping time definition '{}'. Use 'total' (steps to reach 1) or 'below-seed' (steps to drop below the seed)",
+                other
+            )),
+        }
+    }
+}
+
+/// Distribution of ν₂(3n+1), the number of halvings that follow each odd step.
+///
+/// Heuristically 3n+1 is divisible by 2^k with probability 2^-k, so the mean approaches 2 for
+/// long trajectories.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Valuations {
+    // counts[k] is the number of odd steps followed by exactly k halvings
+    counts: Vec<u64>,
+    // halvings since the last odd value, None before the first one
+    run: Option<u32>,
+}
+
+impl Valuations {
+    /// Starts a distribution that is fed value by value with [`Valuations::push`]; the halvings
+    /// after an odd seed count too.
+    pub fn new(seed_is_odd: bool) -> Self {
+        Valuations {
+            counts: Vec::new(),
+            run: seed_is_odd.then_some(0),
+        }
+    }
+
+    /// Records the valuation of one odd step.
+    pub fn record(&mut self, valuation: u32) {
+        let valuation = valuation as usize;
+        if self.counts.len() <= valuation {
+            self.counts.resize(valuation + 1, 0);
+        }
+        self.counts[valuation] += 1;
+    }
+
+    /// Feeds the parity of the next value of a trajectory without its 3n+1 values at hand: the
+    /// run of even values after an odd one is its valuation, recorded at the next odd value.
+    /// The run after the last odd value of a truncated trajectory is left out.
+    pub fn push(&mut self, is_even: bool) {
+        match (is_even, self.run) {
+            (true, Some(run)) => self.run = Some(run + 1),
+            (true, None) => {}
+            (false, Some(run)) => {
+                self.record(run);
+                self.run = Some(0);
+            }
+            (false, None) => self.run = Some(0),
+        }
+    }
+
+    /// Number of odd steps with each valuation, indexed by the valuation.
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    /// Mean valuation, None without odd steps.
+    pub fn mean(&self) -> Option<f64> {
+        let total: u64 = self.counts.iter().sum();
+        let sum: u64 = self
+            .counts
+            .iter()
+            .enumerate()
+            .map(|(valuation, count)| valuation as u64 * count)
+            .sum();
+        (total > 0).then(|| sum as f64 / total as f64)
+    }
+}
+
+/// Entry of a trajectory into its final descent through the powers of two.
+///
+/// Once a trajectory hits 2^k it only halves down to 1, and no value before can be a power of
+/// two, so the first power of two is the value right after the last odd one (or the seed itself).
+/// That is tracked from the parities alone, like [`Valuations`].
+#[derive(Clone, Debug, Default)]
+pub struct PowerOfTwoEntry {
+    steps: usize,
+    // step of the first power of two so far, None while it depends on an unknown seed
+    entry: Option<usize>,
+    previous_odd: bool,
+}
+
+impl PowerOfTwoEntry {
+    /// Starts at the seed, whose parity is unknown for resumed files without a header.
+    pub fn new(seed_is_odd: Option<bool>) -> Self {
+        PowerOfTwoEntry {
+            steps: 0,
+            entry: seed_is_odd.map(|_| 0),
+            previous_odd: seed_is_odd.unwrap_or(false),
+        }
+    }
+
+    /// Feeds the parity of the next value of the trajectory.
+    pub fn push(&mut self, is_even: bool) {
+        self.steps += 1;
+        if self.previous_odd {
+            self.entry = Some(self.steps);
+        }
+        self.previous_odd = !is_even;
+    }
+
+    /// Step of the first power of two and its exponent k, for a trajectory that reached 1.
+    pub fn entry(&self) -> Option<(usize, usize)> {
+        self.entry.map(|entry| (entry, self.steps - entry))
+    }
+}
+
+/// The statistics of the trajectory of `seed` under the standard map without limits, labeled
+/// with the seed in decimal: the same on every machine and run, to compare against in tests.
+pub fn canonical(seed: &BigInt) -> Row {
+    trajectory(seed.to_string(), seed, &Limits::default(), Map::Standard)
+}
+
+/// Computes a trajectory in memory, without writing it anywhere.
+pub fn trajectory(seed: String, start: &BigInt, limits: &Limits, map: Map) -> Row {
+    visit_trajectory(seed, start, limits, map, |_| ControlFlow::Continue(()))
+}
+
+/// Like [`trajectory`], but `visit` sees every value after the seed and can cancel the
+/// computation by returning [`ControlFlow::Break`], recorded as [`Truncation::Cancelled`].
+pub fn visit_trajectory(
+    seed: String,
+    start: &BigInt,
+    limits: &Limits,
+    map: Map,
+    mut visit: impl FnMut(&BigInt) -> ControlFlow<()>,
+) -> Row {
+    observe_trajectory(seed, start, limits, map, |_, n, _| visit(n))
+}
+
+/// Parity of the value a step starts from, which decides the rule it applies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Parity {
+    /// n/2
+    Even,
+    /// 3n+1, or (3n+1)/2 with [`Map::Shortcut`]
+    Odd,
+}
+
+/// Like [`visit_trajectory`], but `observe` also gets the number of the step (the first value
+/// after the seed is step 1) and the parity of the value it started from, so embedders can stop
+/// on conditions of their own, write the values wherever they like or drive a live display.
+/// `examples/observer.rs` shows all three.
+pub fn observe_trajectory(
+    seed: String,
+    start: &BigInt,
+    limits: &Limits,
+    map: Map,
+    mut observe: impl FnMut(usize, &BigInt, Parity) -> ControlFlow<()>,
+) -> Row {
+    let mut row = Row {
+        seed,
+        stopping_time: 0,
+        glide: None,
+        max_value: start.clone(),
+        max_index: 0,
+        odd: 0,
+        valuations: Valuations::default(),
+        truncated: None,
+    };
+    let deadline = limits.timeout.map(|timeout| Instant::now() + timeout);
+    let mut n = Value::new(start.clone(), limits.backend);
+    while !n.is_one() {
+        if limits
+            .max_steps
+            .is_some_and(|max_steps| row.stopping_time >= max_steps)
+        {
+            row.truncated = limits.max_steps.map(Truncation::MaxSteps);
+            break;
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            row.truncated = limits.timeout.map(Truncation::Timeout);
+            break;
+        }
+        let parity = if !n.is_odd() {
+            n.halve();
+            Parity::Even
+        } else {
+            if limits
+                .max_bits
+                .is_some_and(|max_bits| n.bits() + 2 > max_bits)
+            {
+                row.truncated = limits.max_bits.map(Truncation::MaxBits);
+                break;
+            }
+            n.triple_plus_one();
+            row.valuations.record(n.trailing_zeros() as u32);
+            if map == Map::Shortcut {
+                n.halve();
+            }
+            Parity::Odd
+        };
+        row.stopping_time += 1;
+        if observe(row.stopping_time, n.get(), parity).is_break() {
+            row.truncated = Some(Truncation::Cancelled);
+            break;
+        }
+        if n.is_odd() {
+            row.odd += 1;
+        }
+        if *n.get() > row.max_value {
+            row.max_value = n.get().clone();
+            row.max_index = row.stopping_time;
+        }
+        if row.glide.is_none() && n.get() < start {
+            row.glide = Some(row.stopping_time);
+        }
+        if !n.is_one() && limits.until.reached(n.get(), start) {
+            row.truncated = limits.until.target().map(Truncation::Until);
+            break;
+        }
+        if !n.is_one() && stop_condition(limits, n.get(), start, row.stopping_time, &row.max_value)
+        {
+            row.truncated = Some(Truncation::Condition);
+            break;
+        }
+    }
+    row
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::value::Backend;
+    use num_traits::One;
+
+    // Seeds on both sides of the bounds of the u128 fast path, whose trajectories cross them
+    // in both directions
+    fn boundary_seeds() -> Vec<BigInt> {
+        let mut seeds = Vec::new();
+        for bits in [120u32, 126, 127, 128, 129] {
+            let power = BigInt::one() << bits;
+            for offset in [-27i32, -3, -1, 1, 3, 27] {
+                seeds.push(&power + offset);
+            }
+            seeds.push(&power / 3);
+            seeds.push(&power / 3 + 1);
+        }
+        seeds
+    }
+
+    // The statistics and every value of a trajectory
+    fn run(seed: &BigInt, limits: &Limits, map: Map) -> (Row, Vec<BigInt>) {
+        let mut values = Vec::new();
+        let row = visit_trajectory(seed.to_string(), seed, limits, map, |n| {
+            values.push(n.clone());
+            ControlFlow::Continue(())
+        });
+        (row, values)
+    }
+
+    fn assert_same_on_both_backends(limits: Limits) {
+        let big = Limits {
+            backend: Backend::Big,
+            ..limits.clone()
+        };
+        for seed in boundary_seeds() {
+            for map in [Map::Standard, Map::Shortcut] {
+                assert_eq!(
+                    run(&seed, &limits, map),
+                    run(&seed, &big, map),
+                    "{} with {:?}",
+                    seed,
+                    map
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn backends_agree_around_2_to_the_127() {
+        assert_same_on_both_backends(Limits::default());
+    }
+
+    #[test]
+    fn backends_agree_on_truncated_trajectories() {
+        // a value limit just above the fast path stops on the BigInt side of the boundary
+        assert_same_on_both_backends(Limits {
+            max_bits: Some(129),
+            ..Limits::default()
+        });
+        assert_same_on_both_backends(Limits {
+            max_steps: Some(50),
+            ..Limits::default()
+        });
+    }
+
+    #[test]
+    fn backends_agree_on_small_seeds() {
+        for seed in 1..5000 {
+            let seed = BigInt::from(seed);
+            assert_eq!(
+                trajectory(String::new(), &seed, &Limits::default(), Map::Standard),
+                trajectory(
+                    String::new(),
+                    &seed,
+                    &Limits {
+                        backend: Backend::Big,
+                        ..Limits::default()
+                    },
+                    Map::Standard
+                )
+            );
+        }
+    }
+}
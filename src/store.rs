@@ -0,0 +1,363 @@
+use collatz_bigint_file::report::{SequenceStats, Value};
+use collatz_bigint_file::stats::Row;
+use num_bigint::BigInt;
+use std::path::PathBuf;
+
+// Results are committed in transactions of this many seeds
+const COMMIT_INTERVAL: usize = 10_000;
+
+// Tables of a store: `results` has a column per field of SequenceStats after the id
+fn schema() -> String {
+    let columns: Vec<String> = SequenceStats::schema()
+        .iter()
+        .map(|field| {
+            let kind = match field.value {
+                Value::Int(_) => "INTEGER",
+                Value::Float(_) => "REAL",
+                Value::Text(_) => "TEXT",
+            };
+            match field.name {
+                "seed" => "seed TEXT NOT NULL".to_string(),
+                name => format!("{} {}", name, kind),
+            }
+        })
+        .collect();
+    format!(
+        "CREATE TABLE IF NOT EXISTS results (
+    id INTEGER PRIMARY KEY,
+    {}
+);
+{}",
+        columns.join(",\n    "),
+        VALUES_SCHEMA
+    )
+}
+
+const VALUES_SCHEMA: &str = "CREATE TABLE IF NOT EXISTS trajectory_values (
+    result_id INTEGER NOT NULL REFERENCES results(id),
+    step INTEGER NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (result_id, step)
+) WITHOUT ROWID;
+";
+
+// Helper function to get the database path of a store URL like "sqlite://results.db"
+pub fn parse_url(url: &str) -> Result<PathBuf, String> {
+    match url.trim().strip_prefix("sqlite://") {
+        Some(path) if !path.is_empty() => Ok(PathBuf::from(path)),
+        _ => Err(format!(
+            "Invalid store '{}'. Expected e.g. 'sqlite://results.db'",
+            url
+        )),
+    }
+}
+
+/// SQLite database receiving one row per seed of a batch, and optionally every value.
+///
+/// `results` holds the statistics, `trajectory_values` the values after the seed keyed by
+/// `(result_id, step)`. Values larger than 64 bits are stored as decimal text.
+pub struct Store {
+    insert_seed: sqlite::Statement,
+    update_result: sqlite::Statement,
+    insert_value: sqlite::Statement,
+    connection: sqlite::Connection,
+    store_values: bool,
+    uncommitted: usize,
+}
+
+impl Store {
+    pub fn open(url: &str, store_values: bool) -> Result<Store, String> {
+        let path = parse_url(url)?;
+        let connection = sqlite::Connection::open(&path)?;
+        connection.execute_batch(&schema())?;
+        connection.execute_batch("BEGIN")?;
+        Ok(Store {
+            insert_seed: connection.prepare("INSERT INTO results (seed) VALUES (?1)")?,
+            update_result: connection.prepare(&update_result())?,
+            insert_value: connection.prepare(
+                "INSERT INTO trajectory_values (result_id, step, value) VALUES (?1, ?2, ?3)",
+            )?,
+            connection,
+            store_values,
+            uncommitted: 0,
+        })
+    }
+
+    pub fn store_values(&self) -> bool {
+        self.store_values
+    }
+
+    /// Adds a row for `seed` and returns its id; the statistics follow with [`Store::finish_result`]
+    /// once the trajectory is computed, the values in between with [`Store::insert_value`].
+    pub fn begin_result(&mut self, seed: &str) -> Result<i64, String> {
+        self.insert_seed.execute(&[sqlite::Value::Text(seed)])?;
+        Ok(self.connection.last_insert_rowid())
+    }
+
+    pub fn insert_value(&mut self, id: i64, step: usize, value: &BigInt) -> Result<(), String> {
+        self.insert_value.execute(&[
+            sqlite::Value::Int(id),
+            sqlite::Value::Int(step as i64),
+            sqlite::Value::Text(&value.to_string()),
+        ])
+    }
+
+    pub fn finish_result(&mut self, id: i64, row: &Row) -> Result<(), String> {
+        let stats = SequenceStats::new(row, row.max_value.to_string());
+        let values: Vec<Value> = stats
+            .fields()
+            .filter(|field| field.name != "seed")
+            .map(|field| field.value)
+            .collect();
+        let mut params = vec![sqlite::Value::Int(id)];
+        params.extend(values.iter().map(|value| match value {
+            Value::Int(Some(value)) => sqlite::Value::Int(*value as i64),
+            Value::Float(Some(value)) => sqlite::Value::Real(*value),
+            Value::Text(Some(value)) => sqlite::Value::Text(value),
+            Value::Int(None) | Value::Float(None) | Value::Text(None) => sqlite::Value::Null,
+        }));
+        self.update_result.execute(&params)?;
+        self.uncommitted += 1;
+        if self.uncommitted >= COMMIT_INTERVAL {
+            self.connection.execute_batch("COMMIT; BEGIN")?;
+            self.uncommitted = 0;
+        }
+        Ok(())
+    }
+
+    pub fn close(self) -> Result<(), String> {
+        self.connection.execute_batch("COMMIT")
+    }
+}
+
+// Statement setting the statistics of the result ?1, the seed is inserted with the row
+fn update_result() -> String {
+    let columns: Vec<String> = SequenceStats::names()
+        .into_iter()
+        .filter(|&name| name != "seed")
+        .zip(2..)
+        .map(|(name, param)| format!("{} = ?{}", name, param))
+        .collect();
+    format!("UPDATE results SET {} WHERE id = ?1", columns.join(", "))
+}
+
+// Minimal binding of the system libsqlite3, linked with the `sqlite` feature
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use std::ffi::{c_char, c_int, c_void, CStr, CString};
+    use std::path::Path;
+    use std::ptr;
+
+    const SQLITE_OK: c_int = 0;
+    const SQLITE_DONE: c_int = 101;
+    // SQLITE_TRANSIENT: sqlite copies bound text before the call returns
+    const SQLITE_TRANSIENT: isize = -1;
+
+    #[repr(C)]
+    struct Sqlite3 {
+        _private: [u8; 0],
+    }
+
+    #[repr(C)]
+    struct Sqlite3Stmt {
+        _private: [u8; 0],
+    }
+
+    #[link(name = "sqlite3")]
+    extern "C" {
+        fn sqlite3_open(filename: *const c_char, db: *mut *mut Sqlite3) -> c_int;
+        fn sqlite3_close(db: *mut Sqlite3) -> c_int;
+        fn sqlite3_errmsg(db: *mut Sqlite3) -> *const c_char;
+        fn sqlite3_exec(
+            db: *mut Sqlite3,
+            sql: *const c_char,
+            callback: *const c_void,
+            arg: *mut c_void,
+            errmsg: *mut *mut c_char,
+        ) -> c_int;
+        fn sqlite3_prepare_v2(
+            db: *mut Sqlite3,
+            sql: *const c_char,
+            len: c_int,
+            stmt: *mut *mut Sqlite3Stmt,
+            tail: *mut *const c_char,
+        ) -> c_int;
+        fn sqlite3_bind_int64(stmt: *mut Sqlite3Stmt, index: c_int, value: i64) -> c_int;
+        fn sqlite3_bind_double(stmt: *mut Sqlite3Stmt, index: c_int, value: f64) -> c_int;
+        fn sqlite3_bind_text(
+            stmt: *mut Sqlite3Stmt,
+            index: c_int,
+            text: *const c_char,
+            len: c_int,
+            destructor: isize,
+        ) -> c_int;
+        fn sqlite3_bind_null(stmt: *mut Sqlite3Stmt, index: c_int) -> c_int;
+        fn sqlite3_step(stmt: *mut Sqlite3Stmt) -> c_int;
+        fn sqlite3_reset(stmt: *mut Sqlite3Stmt) -> c_int;
+        fn sqlite3_finalize(stmt: *mut Sqlite3Stmt) -> c_int;
+        fn sqlite3_last_insert_rowid(db: *mut Sqlite3) -> i64;
+    }
+
+    pub enum Value<'a> {
+        Int(i64),
+        Real(f64),
+        Text(&'a str),
+        Null,
+    }
+
+    pub struct Connection(*mut Sqlite3);
+
+    // Statements have to be dropped before their connection
+    pub struct Statement {
+        db: *mut Sqlite3,
+        raw: *mut Sqlite3Stmt,
+    }
+
+    fn error_message(db: *mut Sqlite3) -> String {
+        unsafe { CStr::from_ptr(sqlite3_errmsg(db)) }
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn c_string(value: &str) -> Result<CString, String> {
+        CString::new(value).map_err(|_| format!("'{}' contains a NUL byte", value))
+    }
+
+    impl Connection {
+        pub fn open(path: &Path) -> Result<Connection, String> {
+            let filename = c_string(&path.to_string_lossy())?;
+            let mut db = ptr::null_mut();
+            let status = unsafe { sqlite3_open(filename.as_ptr(), &mut db) };
+            let connection = Connection(db);
+            if status != SQLITE_OK {
+                return Err(format!(
+                    "Failed to open {}: {}",
+                    path.display(),
+                    error_message(db)
+                ));
+            }
+            Ok(connection)
+        }
+
+        pub fn execute_batch(&self, sql: &str) -> Result<(), String> {
+            let sql = c_string(sql)?;
+            let status = unsafe {
+                sqlite3_exec(
+                    self.0,
+                    sql.as_ptr(),
+                    ptr::null(),
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                )
+            };
+            match status {
+                SQLITE_OK => Ok(()),
+                _ => Err(format!("SQLite error: {}", error_message(self.0))),
+            }
+        }
+
+        pub fn prepare(&self, sql: &str) -> Result<Statement, String> {
+            let sql = c_string(sql)?;
+            let mut raw = ptr::null_mut();
+            let status =
+                unsafe { sqlite3_prepare_v2(self.0, sql.as_ptr(), -1, &mut raw, ptr::null_mut()) };
+            match status {
+                SQLITE_OK => Ok(Statement { db: self.0, raw }),
+                _ => Err(format!("SQLite error: {}", error_message(self.0))),
+            }
+        }
+
+        pub fn last_insert_rowid(&self) -> i64 {
+            unsafe { sqlite3_last_insert_rowid(self.0) }
+        }
+    }
+
+    impl Drop for Connection {
+        fn drop(&mut self) {
+            unsafe { sqlite3_close(self.0) };
+        }
+    }
+
+    impl Statement {
+        // Bind the parameters ?1, ?2, ... and run a statement that returns no rows
+        pub fn execute(&mut self, params: &[Value]) -> Result<(), String> {
+            for (index, value) in (1..).zip(params) {
+                let status = unsafe {
+                    match value {
+                        Value::Int(value) => sqlite3_bind_int64(self.raw, index, *value),
+                        Value::Real(value) => sqlite3_bind_double(self.raw, index, *value),
+                        Value::Text(text) => sqlite3_bind_text(
+                            self.raw,
+                            index,
+                            text.as_ptr().cast(),
+                            text.len() as c_int,
+                            SQLITE_TRANSIENT,
+                        ),
+                        Value::Null => sqlite3_bind_null(self.raw, index),
+                    }
+                };
+                if status != SQLITE_OK {
+                    return Err(format!("SQLite error: {}", error_message(self.db)));
+                }
+            }
+            let status = unsafe { sqlite3_step(self.raw) };
+            unsafe { sqlite3_reset(self.raw) };
+            match status {
+                SQLITE_DONE => Ok(()),
+                _ => Err(format!("SQLite error: {}", error_message(self.db))),
+            }
+        }
+    }
+
+    impl Drop for Statement {
+        fn drop(&mut self) {
+            unsafe { sqlite3_finalize(self.raw) };
+        }
+    }
+}
+
+// Without the `sqlite` feature every store fails to open
+#[cfg(not(feature = "sqlite"))]
+mod sqlite {
+    use std::path::Path;
+
+    // same interface as the real binding, the values are never bound
+    #[allow(dead_code)]
+    pub enum Value<'a> {
+        Int(i64),
+        Real(f64),
+        Text(&'a str),
+        Null,
+    }
+
+    pub struct Connection;
+
+    pub struct Statement;
+
+    impl Connection {
+        pub fn open(_path: &Path) -> Result<Connection, String> {
+            Err(
+                "--store needs SQLite support, build with `cargo build --features sqlite`"
+                    .to_string(),
+            )
+        }
+
+        pub fn execute_batch(&self, _sql: &str) -> Result<(), String> {
+            unreachable!()
+        }
+
+        pub fn prepare(&self, _sql: &str) -> Result<Statement, String> {
+            unreachable!()
+        }
+
+        pub fn last_insert_rowid(&self) -> i64 {
+            unreachable!()
+        }
+    }
+
+    impl Statement {
+        pub fn execute(&mut self, _params: &[Value]) -> Result<(), String> {
+            unreachable!()
+        }
+    }
+}
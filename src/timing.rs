@@ -0,0 +1,110 @@
+use std::time::Duration;
+
+// Wall-clock time of the phases of a run
+pub struct Timing {
+    pub generation: Duration,
+    pub analysis: Duration,
+    // steps computed and digits written by this run (a resumed run only counts the new part)
+    pub steps: usize,
+    pub digits: u64,
+}
+
+impl Timing {
+    pub fn total(&self) -> Duration {
+        self.generation + self.analysis
+    }
+
+    pub fn print(&self) {
+        let generation = self.generation.as_secs_f64();
+        println!(
+            "{}",
+            tr!(
+                "total time: {}s (generation {}s, analysis {}s)",
+                format!("{:.3}", self.total().as_secs_f64()),
+                format!("{:.3}", generation),
+                format!("{:.3}", self.analysis.as_secs_f64())
+            )
+        );
+        println!(
+            "{}",
+            tr!(
+                "steps/s: {}",
+                format!("{:.0}", per_second(self.steps as f64, generation))
+            )
+        );
+        println!(
+            "{}",
+            tr!(
+                "digits/s: {} ({} digits written)",
+                format!("{:.0}", per_second(self.digits as f64, generation)),
+                self.digits
+            )
+        );
+    }
+}
+
+// Print mean and standard deviation of the timings of repeated runs
+pub fn print_bench(timings: &[Timing]) {
+    println!();
+    println!("benchmark over {} runs (mean ± stddev):", timings.len());
+    let phases = [
+        (
+            "total",
+            timings.iter().map(Timing::total).collect::<Vec<_>>(),
+        ),
+        ("generation", timings.iter().map(|t| t.generation).collect()),
+        ("analysis", timings.iter().map(|t| t.analysis).collect()),
+    ];
+    for (name, durations) in phases {
+        let samples: Vec<f64> = durations.iter().map(Duration::as_secs_f64).collect();
+        let (mean, stddev) = mean_stddev(&samples);
+        println!("{}: {:.3}s ± {:.3}s", name, mean, stddev);
+    }
+}
+
+// Mean and sample standard deviation, 0 for a single sample
+fn mean_stddev(samples: &[f64]) -> (f64, f64) {
+    let count = samples.len() as f64;
+    let mean = samples.iter().sum::<f64>() / count;
+    if samples.len() < 2 {
+        return (mean, 0.0);
+    }
+    let variance = samples
+        .iter()
+        .map(|sample| (sample - mean).powi(2))
+        .sum::<f64>()
+        / (count - 1.0);
+    (mean, variance.sqrt())
+}
+
+fn per_second(count: f64, seconds: f64) -> f64 {
+    if seconds > 0.0 {
+        count / seconds
+    } else {
+        0.0
+    }
+}
+
+// Helper function to parse a duration like "90", "90s", "15m", "2h" or "1d" (plain numbers are seconds)
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input = input.trim();
+    let (number, unit) = match input.char_indices().last() {
+        Some(_) if input.ends_with("ms") => (&input[..input.len() - 2], 0.001),
+        Some((i, 's')) => (&input[..i], 1.0),
+        Some((i, 'm')) => (&input[..i], 60.0),
+        Some((i, 'h')) => (&input[..i], 3600.0),
+        Some((i, 'd')) => (&input[..i], 86400.0),
+        _ => (input, 1.0),
+    };
+    number
+        .parse::<f64>()
+        .ok()
+        .filter(|value| value.is_finite() && *value > 0.0)
+        .map(|value| Duration::from_secs_f64(value * unit))
+        .ok_or_else(|| {
+            format!(
+                "Invalid duration '{}'. Expected e.g. '90s', '15m' or '2h'",
+                input
+            )
+        })
+}
@@ -0,0 +1,255 @@
+//! The current value of a trajectory, held in a `u128` while it is small and in a `BigInt`
+//! otherwise.
+//!
+//! Most steps of most trajectories, and every step of the seeds of a typical batch, work on
+//! values of a few dozen bits, where the allocations of `BigInt` arithmetic cost far more than
+//! the arithmetic itself. The value moves to a `BigInt` once 3n+1 could overflow the `u128`,
+//! and back only once it has fallen well below that, so a trajectory hovering around the
+//! boundary does not convert at every step.
+
+use num_bigint::{BigInt, Sign};
+use num_traits::{One, ToPrimitive};
+use std::fmt;
+
+// A u128 holds values of at most this many bits: 3n+1 of them stays below 2^128
+const PROMOTE_BITS: u64 = 126;
+// A BigInt moves back to a u128 at this many bits or less
+const DEMOTE_BITS: u64 = 120;
+
+/// Representation of the values in the sequence loops (`--force-backend`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Backend {
+    /// A `u128` while the value fits, a `BigInt` above.
+    #[default]
+    Auto,
+    /// A `BigInt` for every value, e.g. to rule out the fast path when comparing results.
+    Big,
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Backend::Auto => "auto",
+            Backend::Big => "big",
+        })
+    }
+}
+
+impl std::str::FromStr for Backend {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.trim() {
+            "auto" => Ok(Backend::Auto),
+            "big" => Ok(Backend::Big),
+            other => Err(format!(
+                "Unknown backend '{}'. Use 'auto' (u128 for small values) or 'big' (BigInt for all)",
+                other
+            )),
+        }
+    }
+}
+
+/// The current value of a trajectory, stepped in place.
+///
+/// The `BigInt` is kept up to date on both paths, since the callers of the sequence loops see
+/// every value as one; on the fast path it is rewritten in place without allocating.
+pub struct Value {
+    // the value while it is held in a u128, None while the BigInt does the arithmetic
+    small: Option<u128>,
+    big: BigInt,
+    backend: Backend,
+}
+
+impl Value {
+    pub fn new(n: BigInt, backend: Backend) -> Self {
+        let mut value = Value {
+            small: None,
+            big: n,
+            backend,
+        };
+        if backend == Backend::Auto && value.big.bits() <= PROMOTE_BITS {
+            value.small = value.big.to_u128();
+        }
+        value
+    }
+
+    pub fn get(&self) -> &BigInt {
+        &self.big
+    }
+
+    pub fn is_one(&self) -> bool {
+        match self.small {
+            Some(n) => n == 1,
+            None => self.big.is_one(),
+        }
+    }
+
+    pub fn is_odd(&self) -> bool {
+        match self.small {
+            Some(n) => n & 1 == 1,
+            None => self.big.bit(0),
+        }
+    }
+
+    pub fn bits(&self) -> u64 {
+        match self.small {
+            Some(n) => u64::from(128 - n.leading_zeros()),
+            None => self.big.bits(),
+        }
+    }
+
+    pub fn trailing_zeros(&self) -> u64 {
+        match self.small {
+            Some(n) => u64::from(n.trailing_zeros()),
+            None => self.big.trailing_zeros().unwrap_or(0),
+        }
+    }
+
+    /// n/2
+    pub fn halve(&mut self) {
+        match &mut self.small {
+            Some(n) => {
+                *n >>= 1;
+                self.sync();
+            }
+            None => self.big >>= 1,
+        }
+        self.settle();
+    }
+
+    /// 3n+1
+    pub fn triple_plus_one(&mut self) {
+        match &mut self.small {
+            // below 2^PROMOTE_BITS, so this cannot overflow
+            Some(n) => {
+                *n = 3 * *n + 1;
+                self.sync();
+            }
+            None => {
+                self.big *= 3u32;
+                self.big += 1u32;
+            }
+        }
+        self.settle();
+    }
+
+    // Whether the fast path holds the value, for the tests of the boundary
+    #[cfg(test)]
+    fn is_small(&self) -> bool {
+        self.small.is_some()
+    }
+
+    // Copy the u128 into the BigInt, reusing its digits
+    fn sync(&mut self) {
+        let n = self.small.expect("only called on the fast path");
+        let digits = [
+            n as u32,
+            (n >> 32) as u32,
+            (n >> 64) as u32,
+            (n >> 96) as u32,
+        ];
+        self.big.assign_from_slice(Sign::Plus, &digits);
+    }
+
+    // Promote a u128 that 3n+1 could overflow, demote a BigInt that fell below DEMOTE_BITS
+    fn settle(&mut self) {
+        match self.small {
+            Some(_) if self.bits() > PROMOTE_BITS => self.small = None,
+            None if self.backend == Backend::Auto && self.big.bits() <= DEMOTE_BITS => {
+                self.small = self.big.to_u128();
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn power_of_two(bits: u32) -> BigInt {
+        BigInt::one() << bits
+    }
+
+    #[test]
+    fn starts_on_the_fast_path_up_to_the_promotion_bound() {
+        assert!(Value::new(BigInt::from(27), Backend::Auto).is_small());
+        assert!(Value::new(power_of_two(126) - 1u32, Backend::Auto).is_small());
+        assert!(!Value::new(power_of_two(126), Backend::Auto).is_small());
+        assert!(!Value::new(power_of_two(127) + 1u32, Backend::Auto).is_small());
+        assert!(!Value::new(BigInt::from(27), Backend::Big).is_small());
+    }
+
+    #[test]
+    fn promotes_before_3n_plus_1_can_overflow() {
+        // the largest odd value of the fast path: 3n+1 is just below 3 * 2^126 < 2^128
+        let n = power_of_two(126) - 1u32;
+        let mut value = Value::new(n.clone(), Backend::Auto);
+        value.triple_plus_one();
+        assert_eq!(*value.get(), BigInt::from(3) * &n + 1);
+        assert!(!value.is_small());
+    }
+
+    #[test]
+    fn demotes_only_well_below_the_promotion_bound() {
+        let mut value = Value::new(power_of_two(127), Backend::Auto);
+        while value.bits() > DEMOTE_BITS + 1 {
+            value.halve();
+            assert!(!value.is_small(), "demoted at {} bits", value.bits());
+        }
+        value.halve();
+        assert_eq!(value.bits(), DEMOTE_BITS);
+        assert!(value.is_small());
+        assert_eq!(*value.get(), power_of_two(DEMOTE_BITS as u32 - 1));
+        // and stays there until it outgrows the promotion bound again
+        while value.bits() <= PROMOTE_BITS {
+            assert!(value.is_small());
+            value.triple_plus_one();
+        }
+        assert!(!value.is_small());
+    }
+
+    #[test]
+    fn big_backend_never_takes_the_fast_path() {
+        let mut value = Value::new(power_of_two(127) + 1u32, Backend::Big);
+        while !value.is_one() {
+            if value.is_odd() {
+                value.triple_plus_one();
+            } else {
+                value.halve();
+            }
+            assert!(!value.is_small());
+        }
+    }
+
+    #[test]
+    fn both_backends_step_through_the_same_values() {
+        for seed in [
+            power_of_two(126) - 1u32,
+            power_of_two(127) - 1u32,
+            power_of_two(127) + 1u32,
+            power_of_two(128) - 1u32,
+            power_of_two(128) + 1u32,
+            (power_of_two(128) - 1u32) / 3u32,
+        ] {
+            let mut auto = Value::new(seed.clone(), Backend::Auto);
+            let mut big = Value::new(seed, Backend::Big);
+            while !big.is_one() {
+                assert!(!auto.is_one());
+                assert_eq!(auto.is_odd(), big.is_odd());
+                assert_eq!(auto.trailing_zeros(), big.trailing_zeros());
+                if big.is_odd() {
+                    auto.triple_plus_one();
+                    big.triple_plus_one();
+                } else {
+                    auto.halve();
+                    big.halve();
+                }
+                assert_eq!(auto.get(), big.get());
+                assert_eq!(auto.bits(), big.bits());
+            }
+            assert!(auto.is_one());
+        }
+    }
+}
@@ -0,0 +1,308 @@
+use crate::format;
+use crate::metadata;
+use crate::scan;
+use crate::share::Shared;
+use collatz_bigint_file::digits::DigitWriter;
+use collatz_bigint_file::hash::{self, Sha256};
+use collatz_bigint_file::sequence::Limits;
+use num_bigint::{BigInt, Sign};
+use num_traits::One;
+use std::fmt;
+use std::io::BufRead;
+
+/// Recomputes the trajectory of `seed` in memory and prints its hash, checked against `expect`
+/// if given.
+pub fn run(seed: &str, expect: Option<&str>, limits: &Limits) -> Result<(), String> {
+    let parsed = crate::parse_input(seed.to_string(), limits.max_bits.unwrap_or(u64::MAX))?;
+    let (hash, generated) = hash::trajectory_hash(&parsed, limits);
+    if let Some(truncation) = generated.truncated {
+        return Err(format!(
+            "Cannot verify {}: {} after {} steps",
+            seed.trim(),
+            truncation,
+            generated.steps
+        ));
+    }
+    println!("seed: {}", seed.trim());
+    println!("total stopping time: {}", generated.steps);
+    println!("hash: {}", hash);
+    check_hash(expect, &hash, "recomputed")
+}
+
+/// Recomputes the trajectory of the seed in a share string written with `--share` and checks
+/// that its total stopping time, max position and bits and hash are the shared ones.
+pub fn share(text: &str, limits: &Limits) -> Result<(), String> {
+    let shared = Shared::decode(text)?;
+    let parsed = crate::parse_input(shared.seed.clone(), limits.max_bits.unwrap_or(u64::MAX))?;
+    let (hash, generated) = hash::trajectory_hash(&parsed, limits);
+    if let Some(truncation) = generated.truncated {
+        return Err(format!(
+            "Cannot verify {}: {} after {} steps",
+            shared.seed, truncation, generated.steps
+        ));
+    }
+    println!("seed: {}", shared.seed);
+    println!("total stopping time: {}", generated.steps);
+    println!("hash: {}", hash);
+    let max_bits = generated.max_value.bits();
+    let mismatch = if generated.steps != shared.steps {
+        Some((
+            "total stopping time",
+            shared.steps.to_string(),
+            generated.steps.to_string(),
+        ))
+    } else if generated.max_index != shared.max_index {
+        Some((
+            "max pos",
+            shared.max_index.to_string(),
+            generated.max_index.to_string(),
+        ))
+    } else if max_bits != shared.max_bits {
+        Some((
+            "max value bits",
+            shared.max_bits.to_string(),
+            max_bits.to_string(),
+        ))
+    } else if !shared.matches(&hash) {
+        let digest: String = shared
+            .digest
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        Some((
+            "hash",
+            format!("{}{}...", hash::PREFIX, digest),
+            hash.clone(),
+        ))
+    } else {
+        None
+    };
+    match mismatch {
+        None => {
+            println!("OK: the recomputed trajectory matches the shared result");
+            Ok(())
+        }
+        Some((what, shared, recomputed)) => Err(format!(
+            "MISMATCH: shared {} {}, recomputed {}",
+            what, shared, recomputed
+        )),
+    }
+}
+
+// Layout of the value lines of a trajectory read from stdin
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    // one value per line
+    Plain,
+    // `<step>: <rule> -> <value>`, as written with --annotate
+    Annotated,
+    // the value in the last column, separated by commas, semicolons, tabs or spaces
+    Columns,
+}
+
+impl Format {
+    fn detect(line: &str) -> Format {
+        if line.contains("-> ") {
+            Format::Annotated
+        } else if line.contains([',', ';', '\t', ' ']) {
+            Format::Columns
+        } else {
+            Format::Plain
+        }
+    }
+
+    fn value<'a>(&self, line: &'a str) -> &'a str {
+        match self {
+            Format::Plain => line,
+            Format::Annotated => {
+                std::str::from_utf8(scan::value_part(line.as_bytes())).unwrap_or(line)
+            }
+            Format::Columns => line
+                .rsplit([',', ';', '\t', ' '])
+                .next()
+                .unwrap_or(line)
+                .trim_matches('"'),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Format::Plain => write!(f, "plain"),
+            Format::Annotated => write!(f, "annotated"),
+            Format::Columns => write!(f, "columns"),
+        }
+    }
+}
+
+/// Checks a trajectory produced by any program, read line by line from `input`: every value
+/// must follow from the one before by the Collatz map, and the last one must be 1.
+///
+/// The layout is detected from the first value line: one value per line, the annotated lines
+/// of `--annotate`, or columns like CSV with the value in the last one (a header line before
+/// the first number is skipped). Values are decimal unless a `# radix:` header says otherwise;
+/// blank lines and other `#` comments are ignored. The seed is `seed`, else the `# seed:` header
+/// of a sequence file, else the first value; a first value equal to the seed counts as the seed
+/// itself. The hash covers the seed and every value, so it matches the one of a run of this
+/// program and can be checked with `expect`. Values are dropped once checked, so trajectories of
+/// any length can be streamed through. A byte order mark and CRLF line ends are accepted; a line
+/// that is no value, e.g. one that is not valid UTF-8, fails the check with its line number and
+/// byte offset.
+pub fn stream(
+    seed: Option<&str>,
+    expect: Option<&str>,
+    input: impl BufRead,
+    max_bits: u64,
+) -> Result<(), String> {
+    let mut seed = seed
+        .map(|seed| crate::parse_input(seed.to_string(), max_bits))
+        .transpose()?;
+    let mut detected = None;
+    // base of the values, from the `# radix:` header of a file written with --radix
+    let mut radix = 10;
+    let mut n: Option<BigInt> = None;
+    let mut step = 0;
+    let mut hasher = Sha256::default();
+    let mut digits = DigitWriter::default();
+    let mut hash_line = |value: &BigInt, hasher: &mut Sha256| {
+        digits
+            .write_line(value, hasher)
+            .expect("hashing does not fail")
+    };
+
+    let mut input = input;
+    let mut buffer = Vec::new();
+    let (mut line_num, mut offset) = (0, 0u64);
+    loop {
+        buffer.clear();
+        let read = input
+            .read_until(b'\n', &mut buffer)
+            .map_err(|err| format!("Failed to read stdin: {}", err))?;
+        if read == 0 {
+            break;
+        }
+        let bytes = match line_num {
+            0 => buffer.strip_prefix(scan::BOM).unwrap_or(&buffer),
+            _ => &buffer,
+        };
+        line_num += 1;
+        let line_offset = offset;
+        offset += read as u64;
+        let Ok(line) = std::str::from_utf8(bytes) else {
+            // comments are skipped whatever their encoding, values must be readable
+            if bytes.trim_ascii_start().starts_with(b"#") {
+                continue;
+            }
+            return Err(format!(
+                "Invalid value in line {} (byte {}): not valid UTF-8",
+                line_num, line_offset
+            ));
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some((key, value)) = metadata::parse_comment(line) {
+            match key {
+                "seed" if seed.is_none() && n.is_none() => {
+                    seed = Some(
+                        crate::parse_input(value.to_string(), max_bits)
+                            .map_err(|err| format!("Invalid seed in line {}: {}", line_num, err))?,
+                    )
+                }
+                "radix" => {
+                    radix = format::parse_radix(value)
+                        .map_err(|err| format!("Invalid radix in line {}: {}", line_num, err))?
+                }
+                _ => {}
+            }
+            continue;
+        }
+        let layout = *detected.get_or_insert_with(|| Format::detect(line));
+        let text = layout.value(line).trim();
+        let value = match BigInt::parse_bytes(text.as_bytes(), radix) {
+            Some(value) => value,
+            // a header line of the columns before the first value
+            None if layout == Format::Columns && n.is_none() => continue,
+            None => {
+                return Err(format!(
+                    "Invalid value in line {} (byte {}): {:?}",
+                    line_num, line_offset, line
+                ))
+            }
+        };
+
+        let previous = match n.take() {
+            Some(previous) => previous,
+            None => {
+                let start = seed.clone().unwrap_or_else(|| value.clone());
+                if start.sign() != Sign::Plus {
+                    return Err(format!("The seed {} is not a positive integer", start));
+                }
+                hash_line(&start, &mut hasher);
+                seed = Some(start.clone());
+                if value == start {
+                    // the first line is the seed itself
+                    n = Some(start);
+                    continue;
+                }
+                start
+            }
+        };
+        if previous.is_one() {
+            return Err(format!(
+                "MISMATCH: line {} continues after the trajectory reached 1 at step {}",
+                line_num, step
+            ));
+        }
+        let expected = if previous.bit(0) {
+            BigInt::from(3) * &previous + 1
+        } else {
+            &previous >> 1
+        };
+        step += 1;
+        if value != expected {
+            return Err(format!(
+                "MISMATCH: step {} (line {}) is {}, but {} follows {}",
+                step, line_num, value, expected, previous
+            ));
+        }
+        hash_line(&value, &mut hasher);
+        n = Some(value);
+    }
+
+    let (Some(seed), Some(last)) = (seed, n) else {
+        return Err("No values on stdin".to_string());
+    };
+    let hash = hasher.finish_hex();
+    println!("format: {}", detected.unwrap_or(Format::Plain));
+    println!("seed: {}", seed);
+    if !last.is_one() {
+        return Err(format!(
+            "INCOMPLETE: the trajectory ends at step {} with {}, before reaching 1",
+            step, last
+        ));
+    }
+    println!("total stopping time: {}", step);
+    println!("hash: {}", hash);
+    println!("OK: every step follows the Collatz map");
+    check_hash(expect, &hash, "read")
+}
+
+// Compare a hash with the expected one, if any
+fn check_hash(expect: Option<&str>, hash: &str, origin: &str) -> Result<(), String> {
+    match expect {
+        None => Ok(()),
+        Some(expected) if expected.trim().eq_ignore_ascii_case(hash) => {
+            println!("OK: the {} trajectory matches", origin);
+            Ok(())
+        }
+        Some(expected) => Err(format!(
+            "MISMATCH: expected {}, {} {}",
+            expected.trim(),
+            origin,
+            hash
+        )),
+    }
+}
@@ -0,0 +1,283 @@
+use num_bigint::BigInt;
+use std::cmp::Reverse;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BinaryHeap, HashSet};
+use std::fs::{self, File};
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+// Bytes per key of the in-memory tier, with the overhead of the hash table
+const ENTRY_BYTES: u64 = 16;
+// Keys per block of a run file, one fence key per block is kept in memory
+const BLOCK_KEYS: usize = 512;
+// Runs on disk before they are merged into one
+const MAX_RUNS: usize = 8;
+// Probes of the Bloom filter per key
+const BLOOM_PROBES: u64 = 7;
+
+/// Key of a value in a [`VisitedSet`]: values below 2^63 are their own key, so scans of ranges
+/// of seeds are exact; larger values are reduced to a hash with the top bit set, where two of
+/// them could collide.
+pub fn key(n: &BigInt) -> u64 {
+    match u64::try_from(n) {
+        Ok(value) if value >> 63 == 0 => value,
+        _ => {
+            let mut hasher = DefaultHasher::new();
+            n.hash(&mut hasher);
+            hasher.finish() | 1 << 63
+        }
+    }
+}
+
+// Sorted keys spilled to a file, with the first key of every block
+struct Run {
+    path: PathBuf,
+    file: File,
+    len: u64,
+    fences: Vec<u64>,
+}
+
+impl Run {
+    // Write sorted, distinct `keys` to a new run file
+    fn write(path: PathBuf, keys: impl IntoIterator<Item = io::Result<u64>>) -> io::Result<Run> {
+        let mut output = BufWriter::new(File::create(&path)?);
+        let (mut len, mut fences) = (0, Vec::new());
+        for key in keys {
+            let key = key?;
+            if len % BLOCK_KEYS as u64 == 0 {
+                fences.push(key);
+            }
+            output.write_all(&key.to_le_bytes())?;
+            len += 1;
+        }
+        output
+            .into_inner()
+            .map_err(|err| err.into_error())?
+            .sync_all()?;
+        Ok(Run {
+            file: File::open(&path)?,
+            path,
+            len,
+            fences,
+        })
+    }
+
+    // Whether the run holds `key`: one block read, found by the fences
+    fn contains(&self, key: u64) -> io::Result<bool> {
+        let block = match self.fences.partition_point(|&fence| fence <= key) {
+            0 => return Ok(false),
+            after => after - 1,
+        };
+        let start = (block * BLOCK_KEYS) as u64;
+        let keys = (self.len - start).min(BLOCK_KEYS as u64) as usize;
+        let mut buffer = [0u8; BLOCK_KEYS * 8];
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(start * 8))?;
+        file.read_exact(&mut buffer[..keys * 8])?;
+        let block: Vec<u64> = buffer[..keys * 8]
+            .chunks_exact(8)
+            .map(|bytes| u64::from_le_bytes(bytes.try_into().expect("8 bytes")))
+            .collect();
+        Ok(block.binary_search(&key).is_ok())
+    }
+
+    // The keys of the run in order
+    fn keys(&self) -> io::Result<impl Iterator<Item = io::Result<u64>>> {
+        let mut input = BufReader::new(File::open(&self.path)?);
+        Ok((0..self.len).map(move |_| {
+            let mut bytes = [0u8; 8];
+            input
+                .read_exact(&mut bytes)
+                .map(|_| u64::from_le_bytes(bytes))
+        }))
+    }
+}
+
+/// Counters of a [`VisitedSet`] for the summary of a scan.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct VisitedStats {
+    pub in_memory: u64,
+    pub on_disk: u64,
+    pub runs: usize,
+    pub spills: u64,
+    // lookups the Bloom filter passed on to the runs, and those of them that found nothing
+    pub disk_lookups: u64,
+    pub false_positives: u64,
+}
+
+/// Set of visited values with a memory ceiling, for scans of more seeds than fit in memory.
+///
+/// Keys are kept in a hash table until it holds its share of the ceiling; then they are sorted
+/// and spilled to a run file in `dir`, and added to a Bloom filter that takes the other half of
+/// the ceiling. A lookup that misses the table only reads from disk if the filter has the key,
+/// one block of each run found by the fence keys kept in memory. Runs are merged once there are
+/// more than 8, and removed when the set is dropped. The filter is sized for a tenth of its bits
+/// in keys; beyond that it passes more lookups to disk, which makes them slower but not wrong.
+pub struct VisitedSet {
+    memory: HashSet<u64>,
+    capacity: usize,
+    bloom: Vec<u64>,
+    runs: Vec<Run>,
+    dir: PathBuf,
+    // number of the next run file
+    next_run: u64,
+    stats: VisitedStats,
+}
+
+impl VisitedSet {
+    /// An empty set using about `memory` bytes, spilling to files in `dir`.
+    pub fn new(memory: u64, dir: &Path) -> Result<VisitedSet, String> {
+        fs::create_dir_all(dir)
+            .map_err(|err| format!("Failed to create {}: {}", dir.display(), err))?;
+        let capacity = (memory / 2 / ENTRY_BYTES).max(1024) as usize;
+        let bloom_words = (memory / 2 / 8).max(1) as usize;
+        Ok(VisitedSet {
+            memory: HashSet::with_capacity(capacity),
+            capacity,
+            bloom: vec![0; bloom_words],
+            runs: Vec::new(),
+            dir: dir.to_path_buf(),
+            next_run: 0,
+            stats: VisitedStats::default(),
+        })
+    }
+
+    /// Whether `key` was inserted before.
+    pub fn contains(&mut self, key: u64) -> Result<bool, String> {
+        if self.memory.contains(&key) {
+            return Ok(true);
+        }
+        if self.runs.is_empty() || !self.bloom_contains(key) {
+            return Ok(false);
+        }
+        self.stats.disk_lookups += 1;
+        for run in &self.runs {
+            if run
+                .contains(key)
+                .map_err(|err| format!("Failed to read {}: {}", run.path.display(), err))?
+            {
+                return Ok(true);
+            }
+        }
+        self.stats.false_positives += 1;
+        Ok(false)
+    }
+
+    /// Adds `key`, which must not be in the set yet, and spills the in-memory keys to disk once
+    /// they reach their share of the memory.
+    pub fn insert(&mut self, key: u64) -> Result<(), String> {
+        self.memory.insert(key);
+        if self.memory.len() >= self.capacity {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    pub fn stats(&self) -> VisitedStats {
+        VisitedStats {
+            in_memory: self.memory.len() as u64,
+            on_disk: self.runs.iter().map(|run| run.len).sum(),
+            runs: self.runs.len(),
+            ..self.stats
+        }
+    }
+
+    // Sort the in-memory keys into a new run and merge the runs if there are too many
+    fn spill(&mut self) -> Result<(), String> {
+        let mut keys: Vec<u64> = self.memory.drain().collect();
+        keys.sort_unstable();
+        for &key in &keys {
+            self.bloom_insert(key);
+        }
+        let path = self.run_path();
+        let run = Run::write(path.clone(), keys.into_iter().map(Ok))
+            .map_err(|err| format!("Failed to write {}: {}", path.display(), err))?;
+        self.runs.push(run);
+        self.stats.spills += 1;
+        if self.runs.len() > MAX_RUNS {
+            self.merge_runs()?;
+        }
+        Ok(())
+    }
+
+    // Merge all runs into one with a k-way merge, streamed from and to disk; the runs are
+    // disjoint, as only keys not in the set are inserted
+    fn merge_runs(&mut self) -> Result<(), String> {
+        let path = self.run_path();
+        let merged = (|| -> io::Result<Run> {
+            let mut sources = Vec::with_capacity(self.runs.len());
+            for run in &self.runs {
+                sources.push(run.keys()?);
+            }
+            let mut heap = BinaryHeap::new();
+            for (i, source) in sources.iter_mut().enumerate() {
+                if let Some(key) = source.next().transpose()? {
+                    heap.push(Reverse((key, i)));
+                }
+            }
+            let merged = std::iter::from_fn(|| {
+                let Reverse((key, i)) = heap.pop()?;
+                Some(match sources[i].next().transpose() {
+                    Ok(Some(next)) => {
+                        heap.push(Reverse((next, i)));
+                        Ok(key)
+                    }
+                    Ok(None) => Ok(key),
+                    Err(err) => Err(err),
+                })
+            });
+            Run::write(path.clone(), merged)
+        })()
+        .map_err(|err| format!("Failed to merge the runs into {}: {}", path.display(), err))?;
+        for run in self.runs.drain(..) {
+            let _ = fs::remove_file(&run.path);
+        }
+        self.runs.push(merged);
+        Ok(())
+    }
+
+    fn run_path(&mut self) -> PathBuf {
+        self.next_run += 1;
+        self.dir.join(format!(
+            "collatz-visited-{}-{}.run",
+            std::process::id(),
+            self.next_run
+        ))
+    }
+
+    // Bit positions of `key` in the filter, by double hashing of a mixed key
+    fn bloom_bits(&self, key: u64) -> impl Iterator<Item = u64> {
+        let bits = self.bloom.len() as u64 * 64;
+        let first = mix(key);
+        let second = mix(first) | 1;
+        (0..BLOOM_PROBES).map(move |i| first.wrapping_add(i.wrapping_mul(second)) % bits)
+    }
+
+    fn bloom_insert(&mut self, key: u64) {
+        for bit in self.bloom_bits(key).collect::<Vec<_>>() {
+            self.bloom[(bit / 64) as usize] |= 1 << (bit % 64);
+        }
+    }
+
+    fn bloom_contains(&self, key: u64) -> bool {
+        self.bloom_bits(key)
+            .all(|bit| self.bloom[(bit / 64) as usize] & 1 << (bit % 64) != 0)
+    }
+}
+
+impl Drop for VisitedSet {
+    fn drop(&mut self) {
+        for run in &self.runs {
+            let _ = fs::remove_file(&run.path);
+        }
+    }
+}
+
+// The splitmix64 finalizer, spreads consecutive keys over the whole filter
+fn mix(key: u64) -> u64 {
+    let mut z = key.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,79 @@
+//! `wasm-bindgen` wrapper for `wasm32-unknown-unknown`, so the same engine powers the demo page
+//! in `web/`.
+//!
+//! `wasm-bindgen --target web` generates the JavaScript module from the build, see the "Browser
+//! demo" section of the README. There is no filesystem and no clock in the browser, so the values
+//! go to a callback and `--timeout` has no equivalent here.
+
+use crate::digits::DigitWriter;
+use crate::input;
+use crate::report::SequenceStats;
+use crate::sequence::{Limits, Map};
+use crate::stats;
+use js_sys::{Function, JSON};
+use num_bigint::BigInt;
+use std::ops::ControlFlow;
+use wasm_bindgen::prelude::*;
+
+/// Computes the trajectory of `seed`, a decimal integer or an expression like `2^100-1`, and
+/// returns its statistics as an object with the fields of `--stats-format json`.
+///
+/// `on_value` is called with every value as a decimal string, starting with the seed; an
+/// exception it throws stops the computation and is passed on. `max_steps` and `max_bits` of 0
+/// mean no limit, `shortcut` selects the `(3n+1)/2` map.
+#[wasm_bindgen]
+pub fn run(
+    seed: &str,
+    max_steps: u32,
+    max_bits: u32,
+    shortcut: bool,
+    on_value: Option<Function>,
+) -> Result<JsValue, JsValue> {
+    let limits = Limits {
+        max_steps: (max_steps > 0).then_some(max_steps as usize),
+        timeout: None,
+        max_bits: (max_bits > 0).then_some(u64::from(max_bits)),
+        ..Limits::default()
+    };
+    let start = input::parse_seed(seed, limits.max_bits.unwrap_or(u64::MAX))
+        .map_err(|_| JsError::new(&format!("Invalid seed: {}", seed)))?;
+    let map = if shortcut {
+        Map::Shortcut
+    } else {
+        Map::Standard
+    };
+
+    let mut digits = DigitWriter::default();
+    let mut line = Vec::new();
+    let mut emit = |n: &BigInt| -> Result<(), JsValue> {
+        let Some(on_value) = &on_value else {
+            return Ok(());
+        };
+        line.clear();
+        digits
+            .write_line(n, &mut line)
+            .expect("writing to a Vec never fails");
+        let value = std::str::from_utf8(&line[..line.len() - 1]).expect("digits are ASCII");
+        on_value.call1(&JsValue::NULL, &JsValue::from_str(value))?;
+        Ok(())
+    };
+    emit(&start)?;
+    let mut failure = None;
+    let row = stats::visit_trajectory(
+        seed.trim().to_string(),
+        &start,
+        &limits,
+        map,
+        |n| match emit(n) {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(err) => {
+                failure = Some(err);
+                ControlFlow::Break(())
+            }
+        },
+    );
+    if let Some(err) = failure {
+        return Err(err);
+    }
+    JSON::parse(&SequenceStats::new(&row, row.max_value.to_string()).json())
+}
@@ -0,0 +1,175 @@
+use crate::batch::{self, Normalizer, StatsFormat, Unresolved};
+use crate::Settings;
+use collatz_bigint_file::{error, info};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+// Subfolders of the watched directory for the processed inputs and those that failed
+const DONE_DIR: &str = "done";
+const FAILED_DIR: &str = "failed";
+// Marks the result files, which are not seed lists themselves
+const RESULTS_MARK: &str = ".stats.";
+
+/// Watches `dir` for seed lists and computes every new one like a batch on stdin, until killed.
+///
+/// Every `interval` the directory is scanned; a file counts as complete once its size and
+/// modification time did not change since the previous scan, so files still being copied are
+/// left alone. The statistics of `seeds.txt` go to `seeds.stats.txt` (`.csv` or `.jsonl` with
+/// `--stats-format`) next to it, written under a hidden name and renamed when complete, and the
+/// input is then moved to `done/`; an input that cannot be processed is moved to `failed/`.
+/// Hidden files and result files are skipped. With `once`, the files present are processed
+/// right away and the function returns.
+pub fn run(dir: &Path, interval: Duration, once: bool, settings: &Settings) -> Result<(), String> {
+    if !dir.is_dir() {
+        return Err(format!("{} is not a directory", dir.display()));
+    }
+    if !once {
+        info!(
+            "Watching {} for seed files every {:.1}s",
+            dir.display(),
+            interval.as_secs_f64()
+        );
+    }
+    // size and modification time of the files at the previous scan
+    let mut seen: HashMap<PathBuf, (u64, SystemTime)> = HashMap::new();
+    loop {
+        let mut ready = Vec::new();
+        let mut current = HashMap::new();
+        let entries = fs::read_dir(dir)
+            .map_err(|err| format!("Failed to read {}: {}", dir.display(), err))?;
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if !metadata.is_file() || name.starts_with('.') || name.contains(RESULTS_MARK) {
+                continue;
+            }
+            let state = (
+                metadata.len(),
+                metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            );
+            let path = entry.path();
+            if once || seen.get(&path) == Some(&state) {
+                ready.push(path);
+            } else {
+                current.insert(path, state);
+            }
+        }
+        ready.sort();
+        for input in ready {
+            let moved_to = match process(&input, settings) {
+                Ok((seeds, results)) => {
+                    info!(
+                        "{}: {} seeds, statistics in {}",
+                        input.display(),
+                        seeds,
+                        results.display()
+                    );
+                    DONE_DIR
+                }
+                Err(err) => {
+                    error!("{}: {}", input.display(), err);
+                    FAILED_DIR
+                }
+            };
+            move_into(&input, &dir.join(moved_to))?;
+        }
+        if once {
+            return Ok(());
+        }
+        seen = current;
+        thread::sleep(interval);
+    }
+}
+
+// Compute the statistics of the seed list `input` into its result file; returns the number of
+// seeds and the path of the results
+fn process(input: &Path, settings: &Settings) -> Result<(u64, PathBuf), String> {
+    let extension = match settings.stats_format {
+        StatsFormat::Text => "txt",
+        StatsFormat::Csv => "csv",
+        StatsFormat::Json => "jsonl",
+        StatsFormat::Markdown => "md",
+        StatsFormat::Html => "html",
+    };
+    let stem = input
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let name = format!("{}{}{}", stem, RESULTS_MARK, extension);
+    let results = input.with_file_name(&name);
+    let partial = input.with_file_name(format!(".{}.part", name));
+
+    let computed = (|| -> Result<u64, String> {
+        let write_error = |err| format!("Failed to write {}: {}", partial.display(), err);
+        let seeds = File::open(input).map_err(|err| format!("Failed to open: {}", err))?;
+        let mut output = BufWriter::new(File::create(&partial).map_err(write_error)?);
+        let mut unresolved = Unresolved::new(None, false)?;
+        let mut normalizer = Normalizer::new(&settings.normalize);
+        let distribution = batch::run(
+            BufReader::new(seeds),
+            &mut output,
+            settings.stats_format,
+            &settings.limits,
+            &settings.number_format,
+            None,
+            None,
+            settings.stopping_time_definition,
+            None,
+            &mut unresolved,
+            &mut normalizer,
+        )
+        .map_err(|err| format!("Failed to process seeds: {}", err))?;
+        // CSV and JSON lines stay machine readable, the summary only follows text
+        if settings.stats_format == StatsFormat::Text {
+            distribution
+                .write_summary(&mut output)
+                .and_then(|_| normalizer.write_summary(&mut output))
+                .and_then(|_| unresolved.write_summary(&mut output))
+                .map_err(write_error)?;
+        }
+        output
+            .into_inner()
+            .map_err(|err| err.into_error())
+            .and_then(|file| file.sync_all())
+            .map_err(write_error)?;
+        Ok(distribution.seeds())
+    })();
+    match computed {
+        Ok(seeds) => {
+            fs::rename(&partial, &results)
+                .map_err(|err| format!("Failed to rename {}: {}", partial.display(), err))?;
+            Ok((seeds, results))
+        }
+        Err(err) => {
+            let _ = fs::remove_file(&partial);
+            Err(err)
+        }
+    }
+}
+
+// Move `file` into the directory `target`, with a numbered name if it holds one of the same name
+fn move_into(file: &Path, target: &Path) -> Result<(), String> {
+    fs::create_dir_all(target)
+        .map_err(|err| format!("Failed to create {}: {}", target.display(), err))?;
+    let name = file.file_name().unwrap_or_default().to_string_lossy();
+    let mut destination = target.join(&*name);
+    let mut copy = 0;
+    while destination.exists() {
+        copy += 1;
+        destination = target.join(format!("{}.{}", name, copy));
+    }
+    fs::rename(file, &destination).map_err(|err| {
+        format!(
+            "Failed to move {} to {}: {}",
+            file.display(),
+            destination.display(),
+            err
+        )
+    })
+}
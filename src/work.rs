@@ -0,0 +1,620 @@
+use crate::ledger::Ledger;
+use crate::server;
+use collatz_bigint_file::sequence::{Limits, Truncation};
+use collatz_bigint_file::{debug, info, warn};
+use num_bigint::BigInt;
+use num_traits::{One, ToPrimitive, Zero};
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// First line of a report, with the version of the format
+pub const REPORT_HEADER: &str = "# collatz_bigint_file report 1";
+
+// Extension of the reports saved by `coordinator --listen`
+const REPORT_EXTENSION: &str = "report";
+
+// Bit length of a range bound at most, checked before a power is computed: ranges also arrive
+// in the reports posted to `coordinator --listen`
+const MAX_BOUND_BITS: u64 = 1 << 20;
+
+// Seeds between two progress messages of a worker
+const PROGRESS_INTERVAL: u64 = 1_000_000;
+
+/// Residue classes `r mod 2^k` as `(r, k)` whose seeds all drop below themselves within k steps,
+/// so they are counted instead of computed: even seeds and seeds ≡ 1 mod 4.
+pub const SIEVE: [(u64, u32); 2] = [(0, 1), (1, 2)];
+
+/// Half-open range of seeds `start..end`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Range {
+    pub start: BigInt,
+    pub end: BigInt,
+}
+
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+// Helper function to parse a range like "10^12..10^12+10^9"; the end is excluded
+pub fn parse_range(input: &str) -> Result<Range, String> {
+    let (start, end) = input.split_once("..").ok_or_else(|| {
+        format!(
+            "Invalid range '{}'. Expected e.g. '10^12..10^12+10^9'",
+            input
+        )
+    })?;
+    let range = Range {
+        start: parse_bound(start)?,
+        end: parse_bound(end)?,
+    };
+    if range.start < BigInt::one() || range.end <= range.start {
+        return Err(format!(
+            "Invalid range '{}'. The start has to be positive and below the end",
+            input
+        ));
+    }
+    Ok(range)
+}
+
+// Expand seed arguments, expressions like 2^100-1 or ranges like 1..10000 with the end excluded,
+// into labeled seeds; fails beyond `max_seeds`
+pub fn expand_seeds(
+    args: &[String],
+    max_bits: u64,
+    max_seeds: usize,
+) -> Result<Vec<(String, BigInt)>, String> {
+    let mut seeds = Vec::new();
+    for arg in args {
+        if arg.contains("..") {
+            let range = parse_range(arg)?;
+            let mut seed = range.start;
+            while seed < range.end && seeds.len() <= max_seeds {
+                seeds.push((seed.to_string(), seed.clone()));
+                seed += 1;
+            }
+        } else {
+            let seed = crate::parse_input(arg.clone(), max_bits)
+                .map_err(|err| format!("{}: {}", arg, err))?;
+            seeds.push((arg.clone(), seed));
+        }
+        if seeds.len() > max_seeds {
+            return Err(format!(
+                "Too many seeds, at most {} are supported",
+                max_seeds
+            ));
+        }
+    }
+    Ok(seeds)
+}
+
+// A sum or difference of integers and powers, e.g. "10^12+10^9-1"
+fn parse_bound(input: &str) -> Result<BigInt, String> {
+    let invalid = || format!("Invalid range bound '{}'", input.trim());
+    let mut total = BigInt::zero();
+    let mut term_start = 0;
+    let mut sign = 1;
+    let input = input.trim();
+    for (i, c) in input.char_indices().chain([(input.len(), '+')]) {
+        if c != '+' && c != '-' {
+            continue;
+        }
+        let term = input[term_start..i].trim();
+        let value = match term.split_once('^') {
+            Some((base, exponent)) => {
+                let base = base.trim().parse::<BigInt>().map_err(|_| invalid())?;
+                let exponent = exponent.trim().parse::<u32>().map_err(|_| invalid())?;
+                let bits = f64::from(exponent) * crate::drift::log2(&base);
+                if bits > MAX_BOUND_BITS as f64 {
+                    return Err(format!(
+                        "Invalid range bound '{}': about {} bits, more than the {} a bound may have",
+                        input,
+                        bits as u64,
+                        MAX_BOUND_BITS
+                    ));
+                }
+                num_traits::pow(base, exponent as usize)
+            }
+            None => term.parse::<BigInt>().map_err(|_| invalid())?,
+        };
+        total += sign * value;
+        sign = if c == '-' { -1 } else { 1 };
+        term_start = i + 1;
+    }
+    Ok(total)
+}
+
+/// Splits `range` into `units` consecutive work units of equal size; the first ones get one
+/// more seed if the range does not divide evenly. The same range and count always give the
+/// same units, so workers can be handed out by number.
+pub fn split(range: &Range, units: u64) -> Vec<Range> {
+    let len = &range.end - &range.start;
+    let units = BigInt::from(units).min(len.clone());
+    let size = &len / &units;
+    let remainder = &len % &units;
+    let mut start = range.start.clone();
+    let mut unit_ranges = Vec::new();
+    let mut i = BigInt::zero();
+    while i < units {
+        let mut end = &start + &size;
+        if i < remainder {
+            end += 1;
+        }
+        unit_ranges.push(Range {
+            start: start.clone(),
+            end: end.clone(),
+        });
+        start = end;
+        i += 1;
+    }
+    unit_ranges
+}
+
+/// Result of verifying a range: every seed has to drop below its start value, so that all
+/// seeds are covered by induction once the ranges below are verified as well.
+pub struct Report {
+    pub range: Range,
+    // seeds whose trajectory was computed
+    pub computed: u64,
+    // seeds that drop below themselves by their residue mod 4 alone
+    pub sieved: u64,
+    // seed with the longest glide (steps until the trajectory drops below the seed)
+    pub max_glide: Option<(BigInt, usize)>,
+    // seed reaching the largest value, and that value
+    pub max_value: Option<(BigInt, BigInt)>,
+    // seeds stopped by a limit before they dropped below their start value
+    pub unverified: Vec<(BigInt, String)>,
+    pub elapsed: Duration,
+    // glides of the computed seeds in order, only kept for a certificate and not written
+    pub glides: Vec<u32>,
+}
+
+impl Report {
+    pub fn write(&self, output: &mut impl Write) -> io::Result<()> {
+        writeln!(output, "{}", REPORT_HEADER)?;
+        writeln!(output, "range: {}", self.range)?;
+        writeln!(output, "computed: {}", self.computed)?;
+        writeln!(output, "sieved: {}", self.sieved)?;
+        if let Some((seed, glide)) = &self.max_glide {
+            writeln!(output, "max glide: {} {}", seed, glide)?;
+        }
+        if let Some((seed, value)) = &self.max_value {
+            writeln!(output, "max value: {} {}", seed, value)?;
+        }
+        for (seed, reason) in &self.unverified {
+            writeln!(output, "unverified: {} {}", seed, reason)?;
+        }
+        writeln!(output, "elapsed: {:.3}s", self.elapsed.as_secs_f64())
+    }
+
+    pub fn parse(input: &str) -> Result<Report, String> {
+        let mut lines = input.lines();
+        if lines.next().map(str::trim) != Some(REPORT_HEADER) {
+            return Err(format!("Not a report, expected '{}'", REPORT_HEADER));
+        }
+        let mut range = None;
+        let mut report = Report {
+            range: Range {
+                start: BigInt::zero(),
+                end: BigInt::zero(),
+            },
+            computed: 0,
+            sieved: 0,
+            max_glide: None,
+            max_value: None,
+            unverified: Vec::new(),
+            elapsed: Duration::ZERO,
+            glides: Vec::new(),
+        };
+        for line in lines {
+            let invalid = || format!("Invalid report line '{}'", line);
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            let mut words = value.splitn(2, ' ');
+            let mut number = || {
+                words
+                    .next()
+                    .and_then(|word| word.parse::<BigInt>().ok())
+                    .ok_or_else(invalid)
+            };
+            match key.trim() {
+                "range" => range = Some(parse_range(value)?),
+                "computed" => report.computed = value.parse().map_err(|_| invalid())?,
+                "sieved" => report.sieved = value.parse().map_err(|_| invalid())?,
+                "max glide" => {
+                    let seed = number()?;
+                    let glide = number()?.to_usize().ok_or_else(invalid)?;
+                    report.max_glide = Some((seed, glide));
+                }
+                "max value" => report.max_value = Some((number()?, number()?)),
+                "unverified" => {
+                    let seed = number()?;
+                    let reason = words.next().unwrap_or_default().to_string();
+                    report.unverified.push((seed, reason));
+                }
+                "elapsed" => {
+                    let seconds = value.trim_end_matches('s').parse::<f64>();
+                    report.elapsed = Duration::from_secs_f64(seconds.map_err(|_| invalid())?);
+                }
+                _ => {}
+            }
+        }
+        report.range = range.ok_or("Report without a range")?;
+        Ok(report)
+    }
+
+    fn add(&mut self, other: Report) {
+        self.range.end = other.range.end;
+        self.computed += other.computed;
+        self.sieved += other.sieved;
+        if other.max_glide.as_ref().map(|(_, glide)| glide)
+            > self.max_glide.as_ref().map(|(_, glide)| glide)
+        {
+            self.max_glide = other.max_glide;
+        }
+        if other.max_value.as_ref().map(|(_, value)| value)
+            > self.max_value.as_ref().map(|(_, value)| value)
+        {
+            self.max_value = other.max_value;
+        }
+        self.unverified.extend(other.unverified);
+        self.elapsed += other.elapsed;
+        self.glides.extend(other.glides);
+    }
+}
+
+// Follow the trajectory of `seed` until it drops below the seed. Returns the glide and the
+// largest value, or the limit that stopped it first.
+fn descend(seed: &BigInt, limits: &Limits) -> Result<(usize, BigInt), Truncation> {
+    let mut n = seed.clone();
+    let mut max_value = seed.clone();
+    let mut steps = 0;
+    while n >= *seed {
+        if limits.max_steps.is_some_and(|max_steps| steps >= max_steps) {
+            return Err(Truncation::MaxSteps(steps));
+        }
+        if n.bit(0) {
+            if limits
+                .max_bits
+                .is_some_and(|max_bits| n.bits() + 2 > max_bits)
+            {
+                return Err(Truncation::MaxBits(limits.max_bits.unwrap_or_default()));
+            }
+            n = BigInt::from(3) * n + 1;
+            max_value = max_value.max(n.clone());
+        } else {
+            n >>= 1;
+        }
+        steps += 1;
+    }
+    Ok((steps, max_value))
+}
+
+/// Whether `seed` is in the residue class `residue mod 2^bits`, for `bits` below 64.
+pub fn in_class(seed: &BigInt, residue: u64, bits: u32) -> bool {
+    seed.iter_u64_digits().next().unwrap_or(0) & ((1 << bits) - 1) == residue
+}
+
+/// Verifies every seed of `range`. The seeds of the [`SIEVE`] classes drop below themselves
+/// within three steps and are only counted; the others are computed until they drop, and their
+/// glides are kept in the report if `keep_glides` is set. Seeds stopped by `--max-steps` or
+/// `--max-value-size` are listed as unverified.
+pub fn verify_range(range: &Range, limits: &Limits, keep_glides: bool) -> Report {
+    let started = Instant::now();
+    let mut report = Report {
+        range: range.clone(),
+        computed: 0,
+        sieved: 0,
+        max_glide: None,
+        max_value: None,
+        unverified: Vec::new(),
+        elapsed: Duration::ZERO,
+        glides: Vec::new(),
+    };
+    let mut seed = range.start.clone();
+    while seed < range.end {
+        // 1 is the end of every trajectory, nothing to verify
+        if seed.is_one()
+            || SIEVE
+                .iter()
+                .any(|&(residue, bits)| in_class(&seed, residue, bits))
+        {
+            report.sieved += 1;
+        } else {
+            report.computed += 1;
+            match descend(&seed, limits) {
+                Ok((glide, max_value)) => {
+                    if keep_glides {
+                        report.glides.push(glide as u32);
+                    }
+                    if report
+                        .max_glide
+                        .as_ref()
+                        .is_none_or(|(_, max)| glide > *max)
+                    {
+                        report.max_glide = Some((seed.clone(), glide));
+                    }
+                    if report
+                        .max_value
+                        .as_ref()
+                        .is_none_or(|(_, max)| max_value > *max)
+                    {
+                        report.max_value = Some((seed.clone(), max_value));
+                    }
+                }
+                Err(truncation) => {
+                    warn!("Could not verify {}: {}", seed, truncation);
+                    report
+                        .unverified
+                        .push((seed.clone(), truncation.to_string()));
+                }
+            }
+        }
+        let done = report.computed + report.sieved;
+        if done.is_multiple_of(PROGRESS_INTERVAL) {
+            debug!("{} seeds done, at {}", done, seed);
+        }
+        seed += 1;
+    }
+    report.elapsed = started.elapsed();
+    report
+}
+
+/// Cuts `range` into consecutive units of `size` seeds, the last one possibly shorter.
+pub fn units(range: &Range, size: &BigInt) -> Vec<Range> {
+    let mut units = Vec::new();
+    let mut start = range.start.clone();
+    while start < range.end {
+        let end = (&start + size).min(range.end.clone());
+        units.push(Range {
+            start: start.clone(),
+            end: end.clone(),
+        });
+        start = end;
+    }
+    units
+}
+
+/// Verifies `range` in units of `unit_size` seeds on `jobs` threads and merges their reports,
+/// with the glides of the computed seeds if `keep_glides` is set.
+///
+/// With a `ledger`, the units it lists as completed are skipped and every unit is recorded in it
+/// as soon as it is done, in whatever order the threads finish them. Only whole units are
+/// recorded and merging rejects overlaps, so a restarted scan never counts a seed twice.
+pub fn verify_units(
+    range: &Range,
+    unit_size: &BigInt,
+    jobs: usize,
+    mut ledger: Option<Ledger>,
+    limits: &Limits,
+    keep_glides: bool,
+) -> Result<Report, String> {
+    let units = units(range, unit_size);
+    let mut reports = ledger.as_mut().map(Ledger::take_done).unwrap_or_default();
+    for report in &reports {
+        if !units.contains(&report.range) {
+            return Err(format!(
+                "The ledger lists {}, which is not a unit of {}",
+                report.range, range
+            ));
+        }
+    }
+    let pending: Vec<&Range> = units
+        .iter()
+        .filter(|unit| !reports.iter().any(|report| report.range == **unit))
+        .collect();
+    if !reports.is_empty() {
+        info!(
+            "{} of {} units are done already, {} left",
+            reports.len(),
+            units.len(),
+            pending.len()
+        );
+    }
+
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|scope| -> Result<(), String> {
+        for _ in 0..jobs.min(pending.len()) {
+            let (sender, next, pending) = (sender.clone(), &next, &pending);
+            scope.spawn(move || {
+                while let Some(unit) = pending.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    // the receiver is gone after an error, stop with the current unit
+                    if sender
+                        .send(verify_range(unit, limits, keep_glides))
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+        for report in receiver {
+            if let Some(ledger) = ledger.as_mut() {
+                ledger.record(&report)?;
+            }
+            debug!("Unit {} done", report.range);
+            reports.push(report);
+        }
+        Ok(())
+    })?;
+    merge(reports)
+}
+
+/// Merges the reports of adjacent work units into the report of the whole range. Gaps and
+/// overlaps between the units are errors, so a merged report always covers its range.
+pub fn merge(mut reports: Vec<Report>) -> Result<Report, String> {
+    reports.sort_by(|a, b| a.range.start.cmp(&b.range.start));
+    let mut reports = reports.into_iter();
+    let mut merged = reports.next().ok_or("No reports to merge")?;
+    for report in reports {
+        if report.range.start > merged.range.end {
+            return Err(format!(
+                "Gap between the reports: {}..{} is missing",
+                merged.range.end, report.range.start
+            ));
+        }
+        if report.range.start < merged.range.end {
+            return Err(format!(
+                "Reports overlap: {} starts before {}",
+                report.range, merged.range.end
+            ));
+        }
+        merged.add(report);
+    }
+    Ok(merged)
+}
+
+pub fn read_report(path: &Path) -> Result<Report, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read {}: {}", path.display(), err))?;
+    Report::parse(&content).map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+/// Sends a report to a coordinator with an HTTP POST, e.g. to `http://host:8090/report`.
+pub fn post_report(url: &str, report: &Report) -> Result<(), String> {
+    let mut body = Vec::new();
+    report
+        .write(&mut body)
+        .expect("Writing to memory cannot fail");
+    post(url, "the report", "text/plain", &body)
+}
+
+/// Sends `body` to an `http://` URL with a POST request and checks for a 2xx status; `what`
+/// names the body in the errors, e.g. "the report".
+pub fn post(url: &str, what: &str, content_type: &str, body: &[u8]) -> Result<(), String> {
+    let rest = url.strip_prefix("http://").ok_or_else(|| {
+        format!(
+            "Invalid URL '{}' for {}. Only http:// is supported",
+            url, what
+        )
+    })?;
+    let (address, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    let address = if address.contains(':') {
+        address.to_string()
+    } else {
+        format!("{}:80", address)
+    };
+
+    let send = || -> io::Result<String> {
+        let mut stream = TcpStream::connect(&address)?;
+        write!(
+            stream,
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            path,
+            address,
+            content_type,
+            body.len()
+        )?;
+        stream.write_all(body)?;
+        let mut status = String::new();
+        BufReader::new(stream).read_line(&mut status)?;
+        Ok(status)
+    };
+    let status = send().map_err(|err| format!("Failed to send {} to {}: {}", what, url, err))?;
+    match status.split_whitespace().nth(1) {
+        Some(code) if code.starts_with('2') => Ok(()),
+        _ => Err(format!("{} rejected {}: {}", url, what, status.trim_end())),
+    }
+}
+
+/// Receives reports posted by workers to `/report` and saves them in `dir`. After every
+/// report, the merged coverage of all reports in `dir` is printed.
+pub fn collect(bind: &str, port: u16, dir: &Path) -> Result<(), String> {
+    fs::create_dir_all(dir)
+        .map_err(|err| format!("Failed to create {}: {}", dir.display(), err))?;
+    let listener = TcpListener::bind((bind, port))
+        .map_err(|err| format!("Failed to listen on {}:{}: {}", bind, port, err))?;
+    println!(
+        "Collecting reports on http://{}/report into {}",
+        listener.local_addr().map_err(|err| err.to_string())?,
+        dir.display()
+    );
+    for stream in listener.incoming() {
+        let result = stream.and_then(|stream| receive(stream, dir));
+        match result {
+            Ok(Some(path)) => {
+                info!("Saved {}", path.display());
+                print_coverage(dir);
+            }
+            Ok(None) => {}
+            Err(err) => warn!("Connection failed: {}", err),
+        }
+    }
+    Ok(())
+}
+
+fn receive(mut stream: TcpStream, dir: &Path) -> io::Result<Option<PathBuf>> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let Some(request) = server::read_request(&mut reader)? else {
+        return Ok(None);
+    };
+    if request.method != "POST" || request.path != "/report" {
+        server::error(&mut stream, "404 Not Found", "POST reports to /report")?;
+        return Ok(None);
+    }
+    let len = request
+        .header("Content-Length")
+        .and_then(|len| len.parse::<u64>().ok())
+        .unwrap_or(0);
+    let mut body = String::new();
+    reader.take(len).read_to_string(&mut body)?;
+    let report = match Report::parse(&body) {
+        Ok(report) => report,
+        Err(err) => {
+            server::error(&mut stream, "400 Bad Request", &err)?;
+            return Ok(None);
+        }
+    };
+    let path = dir.join(format!(
+        "{}-{}.{}",
+        report.range.start, report.range.end, REPORT_EXTENSION
+    ));
+    fs::write(&path, &body)?;
+    println!("Received {} from {}", report.range, stream.peer_addr()?);
+    server::respond(&mut stream, "201 Created", "text/plain", "saved\n")?;
+    Ok(Some(path))
+}
+
+// Print the contiguous ranges covered by the reports in `dir`
+fn print_coverage(dir: &Path) {
+    let mut reports = Vec::new();
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        if path
+            .extension()
+            .is_some_and(|extension| extension == REPORT_EXTENSION)
+        {
+            match read_report(&path) {
+                Ok(report) => reports.push(report),
+                Err(err) => warn!("{}", err),
+            }
+        }
+    }
+    reports.sort_by(|a, b| a.range.start.cmp(&b.range.start));
+    let mut covered: Vec<Range> = Vec::new();
+    for report in reports {
+        match covered.last_mut() {
+            Some(last) if last.end >= report.range.start => {
+                last.end = last.end.clone().max(report.range.end)
+            }
+            _ => covered.push(report.range),
+        }
+    }
+    let covered: Vec<String> = covered.iter().map(Range::to_string).collect();
+    println!("Covered: {}", covered.join(", "));
+}
@@ -0,0 +1,21 @@
+// Wrapper around the wasm-bindgen module of src/wasm.rs, generated into web/pkg/, see the
+// "Browser demo" section of the README.
+//
+//   const collatz = await Collatz.load();
+//   const stats = collatz.run("27", { onValue: (value) => console.log(value) });
+
+import init, { run } from "./pkg/collatz_bigint_file.js";
+
+export class Collatz {
+  static async load() {
+    await init();
+    return new Collatz();
+  }
+
+  // Computes the trajectory of `seed` (a decimal string or an expression like "2^100-1") and
+  // returns its statistics. `onValue` receives every value as a decimal string, starting with
+  // the seed.
+  run(seed, { onValue = null, maxSteps = 0, maxBits = 0, shortcut = false } = {}) {
+    return run(seed, maxSteps, maxBits, shortcut, onValue ?? undefined);
+  }
+}